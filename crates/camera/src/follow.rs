@@ -0,0 +1,46 @@
+use crate::{CameraController, FlightKey, FreeFly, Pose};
+use cgmath::Vector3;
+
+/// Free flight relative to a moving target, so the target stays put on screen unless the camera
+/// is moved or turned.
+pub struct Follow {
+    input: FreeFly,
+    target: Vector3<f32>,
+    offset: Vector3<f32>,
+}
+
+impl Follow {
+    pub fn new(target: Vector3<f32>) -> Self {
+        Self {
+            input: FreeFly::new(),
+            target,
+            offset: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl CameraController for Follow {
+    fn name(&self) -> &'static str {
+        "follow"
+    }
+    fn attach(&mut self, pose: &Pose) {
+        self.offset = pose.position - self.target;
+    }
+    fn step(&mut self, pose: &mut Pose, dt: f32) {
+        pose.position = self.target + self.offset;
+        self.input.step(pose, dt);
+        self.offset = pose.position - self.target;
+    }
+    fn flight_input(&mut self, key: FlightKey, pressed: bool, slow_mode: bool) {
+        self.input.flight_input(key, pressed, slow_mode);
+    }
+    fn mouse_input(&mut self, dx: f64, dy: f64) {
+        self.input.mouse_input(dx, dy);
+    }
+    fn level(&mut self, up: Vector3<f32>) {
+        self.input.level(up);
+    }
+    fn track(&mut self, target: Vector3<f32>) {
+        self.target = target;
+    }
+}
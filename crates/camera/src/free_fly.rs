@@ -0,0 +1,138 @@
+use crate::{CameraController, FlightKey, Pose};
+use cgmath::{prelude::*, Quaternion, Rad, Vector3};
+
+const SPEED: f32 = 2.0;
+const SLOW_SPEED: f32 = 0.4;
+const ROLL_RATE: f32 = 1.0;
+const SENSITIVITY: f32 = 0.001;
+/// Time constant in seconds of leveling, which removes roll exponentially so that it slows down
+/// as the horizon comes level
+const LEVEL_TIME: f32 = 0.15;
+/// The slowest leveling rolls, in radians per second, so that it ends rather than creeping
+const LEVEL_MIN_RATE: f32 = 0.2;
+
+/// Six degrees of freedom flight: WASD, shift and space to move, Q and E to roll, and the mouse
+/// to look around, with the keys at those positions on other layouts, see [`FlightKey`].
+pub struct FreeFly {
+    slow_mode: bool,
+    forwards: bool,
+    backwards: bool,
+    right: bool,
+    left: bool,
+    down: bool,
+    up: bool,
+    roll_right: bool,
+    roll_left: bool,
+    pitch_up: f32,
+    yaw_right: f32,
+    /// The up direction being leveled to, if leveling
+    leveling: Option<Vector3<f32>>,
+}
+
+impl FreeFly {
+    pub fn new() -> Self {
+        Self {
+            slow_mode: false,
+            forwards: false,
+            backwards: false,
+            right: false,
+            left: false,
+            down: false,
+            up: false,
+            roll_right: false,
+            roll_left: false,
+            pitch_up: 0.0,
+            yaw_right: 0.0,
+            leveling: None,
+        }
+    }
+    /// The camera-space velocity requested by the held keys
+    pub(crate) fn velocity(&self) -> Vector3<f32> {
+        let mut velocity = Vector3::zero();
+        if self.forwards {
+            velocity += Vector3::unit_z();
+        }
+        if self.backwards {
+            velocity -= Vector3::unit_z();
+        }
+        if self.right {
+            velocity += Vector3::unit_x();
+        }
+        if self.left {
+            velocity -= Vector3::unit_x();
+        }
+        if self.down {
+            velocity += Vector3::unit_y();
+        }
+        if self.up {
+            velocity -= Vector3::unit_y();
+        }
+        velocity * if self.slow_mode { SLOW_SPEED } else { SPEED }
+    }
+    /// Applies roll, leveling and the mouse movement accumulated since the last step
+    pub(crate) fn rotate(&mut self, pose: &mut Pose, dt: f32) {
+        let mut roll =
+            if self.roll_right { 1.0 } else { 0.0 } + if self.roll_left { -1.0 } else { 0.0 };
+        roll *= ROLL_RATE * dt;
+        if let Some(up) = self.leveling {
+            match pose.roll(up) {
+                Some(off) => {
+                    let step =
+                        (off.abs() * (1.0 - (-dt / LEVEL_TIME).exp())).max(LEVEL_MIN_RATE * dt);
+                    if step >= off.abs() {
+                        roll -= off;
+                        self.leveling = None;
+                    } else {
+                        roll -= step.copysign(off);
+                    }
+                }
+                None => self.leveling = None,
+            }
+        }
+        pose.rotation = pose.rotation
+            * Quaternion::from_axis_angle(Vector3::unit_z(), Rad(roll))
+            * Quaternion::from_axis_angle(Vector3::unit_x(), Rad(self.pitch_up))
+            * Quaternion::from_axis_angle(Vector3::unit_y(), Rad(self.yaw_right));
+        self.pitch_up = 0.0;
+        self.yaw_right = 0.0;
+    }
+}
+impl Default for FreeFly {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CameraController for FreeFly {
+    fn name(&self) -> &'static str {
+        "free fly"
+    }
+    fn step(&mut self, pose: &mut Pose, dt: f32) {
+        pose.position += pose.rotation.rotate_vector(self.velocity() * dt);
+        self.rotate(pose, dt);
+    }
+    fn flight_input(&mut self, key: FlightKey, pressed: bool, slow_mode: bool) {
+        self.slow_mode = slow_mode;
+        match key {
+            FlightKey::Forwards => self.forwards = pressed,
+            FlightKey::Backwards => self.backwards = pressed,
+            FlightKey::Right => self.right = pressed,
+            FlightKey::Left => self.left = pressed,
+            FlightKey::Down => self.down = pressed,
+            FlightKey::Up => self.up = pressed,
+            FlightKey::RollRight => self.roll_right = pressed,
+            FlightKey::RollLeft => self.roll_left = pressed,
+        }
+        // Rolling by hand takes over from leveling
+        if pressed && matches!(key, FlightKey::RollRight | FlightKey::RollLeft) {
+            self.leveling = None;
+        }
+    }
+    fn mouse_input(&mut self, dx: f64, dy: f64) {
+        self.pitch_up -= SENSITIVITY * (dy as f32);
+        self.yaw_right += SENSITIVITY * (dx as f32);
+    }
+    fn level(&mut self, up: Vector3<f32>) {
+        self.leveling = Some(up);
+    }
+}
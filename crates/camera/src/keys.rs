@@ -0,0 +1,98 @@
+use std::sync::Mutex;
+use winit::event::{KeyboardInput, VirtualKeyCode};
+
+/// The keys flying the camera, found by where they are on the keyboard rather than by what they
+/// type, so that they fall under the same fingers on any layout. They are where W, S, D, A,
+/// shift, space, E and Q are on a US QWERTY keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlightKey {
+    Forwards,
+    Backwards,
+    Right,
+    Left,
+    Down,
+    Up,
+    RollRight,
+    RollLeft,
+}
+
+/// What the keys at the positions of the flight keys were last seen to type, in the order of
+/// [`FlightKey::ALL`]
+static SEEN_KEYCODES: Mutex<[Option<VirtualKeyCode>; 8]> = Mutex::new([None; 8]);
+
+impl FlightKey {
+    pub const ALL: [Self; 8] = [
+        Self::Forwards,
+        Self::Backwards,
+        Self::Right,
+        Self::Left,
+        Self::Down,
+        Self::Up,
+        Self::RollRight,
+        Self::RollLeft,
+    ];
+    /// The flight key at the position of a pressed or released key, if any.
+    pub fn of(input: &KeyboardInput) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        let key = Self::ALL
+            .into_iter()
+            .find(|key| Some(key.qwerty_keycode()) == input.virtual_keycode)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let key = Self::ALL
+            .into_iter()
+            .find(|key| key.scancode() == input.scancode)?;
+        if input.virtual_keycode.is_some() {
+            SEEN_KEYCODES.lock().unwrap()[key as usize] = input.virtual_keycode;
+        }
+        Some(key)
+    }
+    /// The key at this position on a US QWERTY keyboard. On the web, winit takes keycodes from
+    /// the position of keys already, so this is the key regardless of layout.
+    pub fn qwerty_keycode(self) -> VirtualKeyCode {
+        match self {
+            Self::Forwards => VirtualKeyCode::W,
+            Self::Backwards => VirtualKeyCode::S,
+            Self::Right => VirtualKeyCode::D,
+            Self::Left => VirtualKeyCode::A,
+            Self::Down => VirtualKeyCode::LShift,
+            Self::Up => VirtualKeyCode::Space,
+            Self::RollRight => VirtualKeyCode::E,
+            Self::RollLeft => VirtualKeyCode::Q,
+        }
+    }
+    /// The name of the key to press, as it types on the layout in use once it has been pressed,
+    /// and as on a US QWERTY keyboard until then.
+    pub fn label(self) -> String {
+        let keycode = SEEN_KEYCODES.lock().unwrap()[self as usize];
+        format!("{:?}", keycode.unwrap_or(self.qwerty_keycode()))
+    }
+    /// The scancode of the key at this position, as reported by winit on Linux, the BSDs and
+    /// Windows, whose scancodes agree for these keys.
+    #[cfg(not(any(target_arch = "wasm32", target_os = "macos")))]
+    fn scancode(self) -> u32 {
+        match self {
+            Self::Forwards => 17,
+            Self::Backwards => 31,
+            Self::Right => 32,
+            Self::Left => 30,
+            Self::Down => 42,
+            Self::Up => 57,
+            Self::RollRight => 18,
+            Self::RollLeft => 16,
+        }
+    }
+    /// The virtual keycode of macOS of the key at this position.
+    #[cfg(target_os = "macos")]
+    fn scancode(self) -> u32 {
+        match self {
+            Self::Forwards => 0x0d,
+            Self::Backwards => 0x01,
+            Self::Right => 0x02,
+            Self::Left => 0x00,
+            Self::Down => 0x38,
+            Self::Up => 0x31,
+            Self::RollRight => 0x0e,
+            Self::RollLeft => 0x0c,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use cgmath::{prelude::*, Matrix3, Matrix4, Quaternion, Rad, Vector3};
+use std::time::Duration;
+use winit::event::{ElementState, KeyboardInput};
+
+mod follow;
+mod free_fly;
+mod keys;
+mod orbit;
+mod path;
+pub use follow::Follow;
+pub use free_fly::FreeFly;
+pub use keys::FlightKey;
+pub use orbit::Orbit;
+pub use path::PathPlayback;
+
+pub const CAMERA_DELTA_TIME: Duration = Duration::from_micros(100);
+/// The world direction shown as up on screen by the default pose and by [`Pose::looking_at`]
+pub const WORLD_UP: Vector3<f32> = Vector3::new(0.0, -1.0, 0.0);
+/// How close to looking straight along an up direction roll is left undefined, in the cosine of
+/// the angle between them
+const VERTICAL_COSINE: f32 = 0.99;
+
+/// Where the camera is and where it looks. The camera looks along its local +z axis, with +y
+/// pointing down on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pose {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+impl Pose {
+    pub fn forward(&self) -> Vector3<f32> {
+        self.rotation.rotate_vector(Vector3::unit_z())
+    }
+    pub fn world_to_camera(&self) -> Matrix4<f32> {
+        let trans = Matrix4::from_translation(-self.position);
+        let rot = Matrix4::from(self.rotation.conjugate());
+        rot * trans
+    }
+    /// At `position`, looking at `target`, with world +y as far down on screen as possible.
+    pub fn looking_at(position: Vector3<f32>, target: Vector3<f32>) -> Pose {
+        let forward = (target - position).normalize();
+        let down = if forward.y.abs() < 0.99 {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_z()
+        };
+        let right = down.cross(forward).normalize();
+        Pose {
+            position,
+            rotation: Matrix3::from_cols(right, forward.cross(right), forward).into(),
+        }
+    }
+    /// Looking at a sphere from the direction of this pose, from the distance at which it just
+    /// fits the view. `half_extent` is the shortest distance from the middle of the view to its
+    /// edge, at a distance of one along the view direction.
+    pub fn framing(&self, center: Vector3<f32>, radius: f32, half_extent: f32) -> Pose {
+        let direction = center - self.position;
+        let direction = if direction.magnitude2() > 0.0 {
+            direction.normalize()
+        } else {
+            self.forward()
+        };
+        let half_angle = half_extent.atan();
+        let distance = radius / half_angle.sin();
+        Pose::looking_at(center - distance * direction, center)
+    }
+    /// How far the camera is rolled clockwise from having `up` as far up on screen as possible,
+    /// in radians, or nothing when looking almost straight along `up`.
+    pub fn roll(&self, up: Vector3<f32>) -> Option<f32> {
+        let forward = self.forward();
+        if forward.dot(up).abs() >= VERTICAL_COSINE {
+            return None;
+        }
+        let level_right = forward.cross(up).normalize();
+        let level_down = forward.cross(level_right);
+        let right = self.rotation.rotate_vector(Vector3::unit_x());
+        Some(right.dot(level_down).atan2(right.dot(level_right)))
+    }
+    /// How far above the plane normal to `up` the camera looks, in radians.
+    pub fn pitch(&self, up: Vector3<f32>) -> f32 {
+        self.forward().dot(up).clamp(-1.0, 1.0).asin()
+    }
+    /// Linear in position and spherical in rotation, with `t` in `[0, 1]`.
+    pub fn interpolate(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+        }
+    }
+}
+impl Default for Pose {
+    fn default() -> Self {
+        Self {
+            position: -2.0f32 * Vector3::unit_x(),
+            rotation: Quaternion::from_angle_y(Rad(std::f32::consts::PI / 2.0)),
+        }
+    }
+}
+
+/// Moves a [`Pose`] in fixed [`CAMERA_DELTA_TIME`] steps in response to input.
+pub trait CameraController {
+    fn name(&self) -> &'static str;
+    /// Called when the controller takes over the camera.
+    fn attach(&mut self, pose: &Pose) {
+        let _ = pose;
+    }
+    fn step(&mut self, pose: &mut Pose, dt: f32);
+    fn flight_input(&mut self, key: FlightKey, pressed: bool, slow_mode: bool) {
+        let _ = (key, pressed, slow_mode);
+    }
+    fn mouse_input(&mut self, dx: f64, dy: f64) {
+        let _ = (dx, dy);
+    }
+    /// Whether the controller is done, and the camera should be handed back to flying.
+    fn finished(&self) -> bool {
+        false
+    }
+    /// Whether the controller plays back a recorded path, rather than transitioning along one
+    /// or following input.
+    fn is_playing_path(&self) -> bool {
+        false
+    }
+    /// Rolls the camera smoothly until `up` is as far up on screen as possible, if the controller
+    /// can roll.
+    fn level(&mut self, up: Vector3<f32>) {
+        let _ = up;
+    }
+    /// Informs the controller of the current world position of whatever it is centered on, if
+    /// anything.
+    fn track(&mut self, target: Vector3<f32>) {
+        let _ = target;
+    }
+}
+
+pub struct Camera {
+    pose: Pose,
+    controller: Box<dyn CameraController>,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            pose: Pose::default(),
+            controller: Box::new(FreeFly::new()),
+        }
+    }
+    pub fn set_controller(&mut self, mut controller: Box<dyn CameraController>) {
+        controller.attach(&self.pose);
+        self.controller = controller;
+    }
+    pub fn controller_name(&self) -> &'static str {
+        self.controller.name()
+    }
+    pub fn controller_finished(&self) -> bool {
+        self.controller.finished()
+    }
+    /// Whether the camera is playing back a recorded path.
+    pub fn is_playing_path(&self) -> bool {
+        self.controller.is_playing_path()
+    }
+    pub fn update_return_stepped(&mut self, mut dt: Duration) -> Duration {
+        let mut stepped = Duration::ZERO;
+        while dt >= CAMERA_DELTA_TIME {
+            dt -= CAMERA_DELTA_TIME;
+            stepped += CAMERA_DELTA_TIME;
+            self.controller
+                .step(&mut self.pose, CAMERA_DELTA_TIME.as_secs_f32());
+        }
+        stepped
+    }
+    /// Passes on flight keys, ignoring other keys.
+    pub fn key_input(&mut self, key: KeyboardInput, slow_mode: bool) {
+        if let Some(flight_key) = FlightKey::of(&key) {
+            let pressed = key.state == ElementState::Pressed;
+            self.flight_input(flight_key, pressed, slow_mode);
+        }
+    }
+    pub fn flight_input(&mut self, key: FlightKey, pressed: bool, slow_mode: bool) {
+        self.controller.flight_input(key, pressed, slow_mode);
+    }
+    pub fn mouse_input(&mut self, dx: f64, dy: f64) {
+        self.controller.mouse_input(dx, dy);
+    }
+    pub fn track(&mut self, target: Vector3<f32>) {
+        self.controller.track(target);
+    }
+    pub fn level(&mut self, up: Vector3<f32>) {
+        self.controller.level(up);
+    }
+    pub fn world_to_camera(&self) -> Matrix4<f32> {
+        self.pose.world_to_camera()
+    }
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+    /// Jumps to a pose, letting the controller adapt to it.
+    pub fn set_pose(&mut self, pose: Pose) {
+        self.pose = pose;
+        self.controller.attach(&self.pose);
+    }
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.pose.rotation
+    }
+    pub fn position(&self) -> Vector3<f32> {
+        self.pose.position
+    }
+    pub fn forward(&self) -> Vector3<f32> {
+        self.pose.forward()
+    }
+}
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
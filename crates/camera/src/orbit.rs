@@ -0,0 +1,48 @@
+use crate::{CameraController, FlightKey, FreeFly, Pose};
+use cgmath::{prelude::*, Vector3};
+
+const MIN_DISTANCE: f32 = 0.05;
+
+/// Circles a center point, looking at it: the mouse orbits, W and S zoom, and Q and E roll.
+pub struct Orbit {
+    input: FreeFly,
+    center: Vector3<f32>,
+    distance: f32,
+}
+
+impl Orbit {
+    pub fn new(center: Vector3<f32>) -> Self {
+        Self {
+            input: FreeFly::new(),
+            center,
+            distance: 1.0,
+        }
+    }
+}
+
+impl CameraController for Orbit {
+    fn name(&self) -> &'static str {
+        "orbit"
+    }
+    fn attach(&mut self, pose: &Pose) {
+        self.distance = (pose.position - self.center).magnitude().max(MIN_DISTANCE);
+    }
+    fn step(&mut self, pose: &mut Pose, dt: f32) {
+        self.input.rotate(pose, dt);
+        let zoom = self.input.velocity().z;
+        self.distance = (self.distance * (-zoom * dt).exp()).max(MIN_DISTANCE);
+        pose.position = self.center - self.distance * pose.forward();
+    }
+    fn flight_input(&mut self, key: FlightKey, pressed: bool, slow_mode: bool) {
+        self.input.flight_input(key, pressed, slow_mode);
+    }
+    fn mouse_input(&mut self, dx: f64, dy: f64) {
+        self.input.mouse_input(dx, dy);
+    }
+    fn level(&mut self, up: Vector3<f32>) {
+        self.input.level(up);
+    }
+    fn track(&mut self, target: Vector3<f32>) {
+        self.center = target;
+    }
+}
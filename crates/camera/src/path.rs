@@ -0,0 +1,80 @@
+use crate::{CameraController, Pose};
+use std::time::Duration;
+
+/// Replays a sequence of timed keyframes, interpolating between them. Input is ignored.
+pub struct PathPlayback {
+    keyframes: Vec<(f32, Pose)>,
+    time: f32,
+    looping: bool,
+    /// Whether this is a transition between two poses, which eases in and out
+    transition: bool,
+}
+
+impl PathPlayback {
+    /// Keyframe times are measured from the start of playback and must be increasing.
+    pub fn new(keyframes: Vec<(Duration, Pose)>, looping: bool) -> Self {
+        assert!(!keyframes.is_empty());
+        assert!(keyframes.windows(2).all(|w| w[0].0 < w[1].0));
+        Self {
+            keyframes: keyframes
+                .into_iter()
+                .map(|(time, pose)| (time.as_secs_f32(), pose))
+                .collect(),
+            time: 0.0,
+            looping,
+            transition: false,
+        }
+    }
+    /// Moves smoothly from one pose to another, starting and stopping gently.
+    pub fn transition(from: Pose, to: Pose, duration: Duration) -> Self {
+        Self {
+            transition: true,
+            ..Self::new(vec![(Duration::ZERO, from), (duration, to)], false)
+        }
+    }
+    fn duration(&self) -> f32 {
+        self.keyframes.last().unwrap().0
+    }
+    fn pose_at(&self, time: f32) -> Pose {
+        let next = self.keyframes.partition_point(|&(t, _)| t <= time);
+        match (
+            self.keyframes.get(next.wrapping_sub(1)),
+            self.keyframes.get(next),
+        ) {
+            (Some((t0, p0)), Some((t1, p1))) => {
+                let t = (time - t0) / (t1 - t0);
+                let t = if self.transition {
+                    t * t * (3.0 - 2.0 * t)
+                } else {
+                    t
+                };
+                p0.interpolate(p1, t)
+            }
+            (Some((_, p)), None) | (None, Some((_, p))) => *p,
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+impl CameraController for PathPlayback {
+    fn name(&self) -> &'static str {
+        if self.transition {
+            "transition"
+        } else {
+            "path playback"
+        }
+    }
+    fn step(&mut self, pose: &mut Pose, dt: f32) {
+        self.time += dt;
+        if self.looping && self.duration() > 0.0 {
+            self.time %= self.duration();
+        }
+        *pose = self.pose_at(self.time);
+    }
+    fn finished(&self) -> bool {
+        !self.looping && self.time >= self.duration()
+    }
+    fn is_playing_path(&self) -> bool {
+        !self.transition
+    }
+}
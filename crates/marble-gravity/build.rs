@@ -19,6 +19,7 @@ fn main() {
         glob("**/*.vert").unwrap(),
         glob("**/*.frag").unwrap(),
         glob("**/*.comp").unwrap(),
+        glob("**/*.wgsl").unwrap(),
     ]
     .into_iter()
     .flatten()
@@ -40,31 +41,40 @@ fn main() {
         let name = shader.path.to_str().unwrap();
         println!("cargo:warning=Compiling shader {name}");
 
-        let module = parser
-            .parse(
-                &naga::front::glsl::Options {
-                    stage: shader.kind,
-                    defines: naga::FastHashMap::default(),
-                },
-                &shader.source,
-            )
-            .unwrap();
-        let compiled = naga::back::wgsl::write_string(
-            &module,
-            &validator.validate(&module).unwrap(),
-            naga::back::wgsl::WriterFlags::empty(),
-        )
-        .unwrap();
-        let extension = match shader.kind {
-            ShaderStage::Vertex => "vert",
-            ShaderStage::Fragment => "frag",
-            ShaderStage::Compute => "comp",
+        let (compiled, extension) = match shader.kind {
+            ShaderKind::Glsl(stage) => {
+                let module = parser
+                    .parse(
+                        &naga::front::glsl::Options {
+                            stage,
+                            defines: naga::FastHashMap::default(),
+                        },
+                        &shader.source,
+                    )
+                    .unwrap();
+                let compiled = naga::back::wgsl::write_string(
+                    &module,
+                    &validator.validate(&module).unwrap(),
+                    naga::back::wgsl::WriterFlags::empty(),
+                )
+                .unwrap();
+                let extension = match stage {
+                    ShaderStage::Vertex => "vert.wgsl",
+                    ShaderStage::Fragment => "frag.wgsl",
+                    ShaderStage::Compute => "comp.wgsl",
+                };
+                (compiled, extension)
+            }
+            ShaderKind::Wgsl => {
+                // Native WGSL is only validated, and then copied verbatim to keep comments and
+                // formatting intact for wgpu error messages.
+                let module = naga::front::wgsl::parse_str(&shader.source)
+                    .unwrap_or_else(|error| panic!("{}", error.emit_to_string(&shader.source)));
+                validator.validate(&module).unwrap();
+                (shader.source.clone(), "wgsl")
+            }
         };
-        fs::write(
-            shader.path.with_extension(format!("{extension}.wgsl")),
-            compiled.as_bytes(),
-        )
-        .unwrap();
+        fs::write(shader.path.with_extension(extension), compiled.as_bytes()).unwrap();
     }
 
     // Remember compiled
@@ -74,7 +84,15 @@ fn main() {
 struct ShaderData {
     source: String,
     path: PathBuf,
-    kind: ShaderStage,
+    kind: ShaderKind,
+}
+
+/// GLSL shaders hold a single stage and are translated to WGSL, while WGSL shaders may hold any
+/// number of entry points and are used as is.
+#[derive(Clone, Copy)]
+enum ShaderKind {
+    Glsl(ShaderStage),
+    Wgsl,
 }
 
 impl ShaderData {
@@ -88,9 +106,10 @@ impl ShaderData {
             .to_str()
             .expect("Extension cannot be converted to &str");
         let kind = match extension {
-            "vert" => ShaderStage::Vertex,
-            "frag" => ShaderStage::Fragment,
-            "comp" => ShaderStage::Compute,
+            "vert" => ShaderKind::Glsl(ShaderStage::Vertex),
+            "frag" => ShaderKind::Glsl(ShaderStage::Fragment),
+            "comp" => ShaderKind::Glsl(ShaderStage::Compute),
+            "wgsl" => ShaderKind::Wgsl,
             _ => panic!("Unsupported shader: {}", path.display()),
         };
 
@@ -0,0 +1,550 @@
+//! What inputs do. Keys, gamepad buttons and touch gestures are bound to [`Action`]s in an
+//! [`InputMap`], and `run.rs` carries out the actions, so that every way of giving input and the
+//! help overlay agree on what does what. Flight keys are bound by their position, see
+//! [`FlightKey`], and other keys by what they type, with or without Ctrl and Shift.
+//!
+//! Winit has no gamepad support, so gamepads are read from the Gamepad API of the web, in its
+//! standard mapping, and are unavailable natively. Touches are told apart by [`Touches`]: a tap,
+//! a drag of one finger looking around and two fingers held down.
+
+use camera::FlightKey;
+use instant::Instant;
+use std::time::Duration;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{KeyboardInput, ModifiersState, Touch, TouchPhase, VirtualKeyCode},
+};
+
+/// The longest touch counting as a tap
+const TAP_TIME: Duration = Duration::from_millis(300);
+/// The furthest a touch may move in pixels and still count as a tap
+const TAP_DISTANCE: f64 = 10.0;
+/// How far a stick is tilted before it counts as pressed, or is read as looking around
+const STICK_DEADZONE: f64 = 0.3;
+/// Look rate of the right stick tilted fully, in mouse movement per second
+const STICK_LOOK_RATE: f64 = 800.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Held to fly the camera
+    Fly(FlightKey),
+    OpenGallery,
+    ToggleLog,
+    /// Opens the developer console, which takes typed commands
+    ToggleConsole,
+    NextLogFilter,
+    ToggleCheckpoints,
+    ToggleHelp,
+    /// Shows energy and momentum in the overlay
+    ToggleConservation,
+    /// Captures the next frame for debugging, with the `renderdoc` feature
+    CaptureFrame,
+    ReleaseMouse,
+    MoreRaySplits,
+    FewerRaySplits,
+    ShorterTicks,
+    LongerTicks,
+    WeakerGravity,
+    StrongerGravity,
+    NextBackend,
+    ToggleLensing,
+    ToggleAccretionDisk,
+    ToggleTracers,
+    PlayPause,
+    /// Pauses and advances the simulation by a single tick
+    Step,
+    /// Ends a time-lapse or camera path playback
+    Stop,
+    NextStreamlines,
+    DenserMedium,
+    ThinnerMedium,
+    MoreExtinction,
+    LessExtinction,
+    NextLabels,
+    ToggleFps,
+    Undo,
+    Redo,
+    /// Copies the selected bodies, or else the camera pose
+    Copy,
+    Paste,
+    SpawnMarble,
+    /// Removes the selected bodies, or else the body under the crosshair
+    Remove,
+    Measure,
+    /// Cycles between flying freely, orbiting the center of mass and following a body
+    NextCameraTarget,
+    /// Rolls the camera until the horizon is level
+    Level,
+    ToggleAttitude,
+    /// Shows the world axes and the direction of the sun
+    ToggleCompass,
+    /// Flies to where every body is in view
+    FrameAll,
+    /// Flies to where the selected bodies fill the view
+    FrameSelection,
+    RecordKeyframe,
+    PlayPath,
+    /// Replaces the bodies with those of the next initial-condition preset
+    NextPreset,
+    StartTutorial,
+    StartChallenge,
+    ToggleGolf,
+    ToggleSplitScreen,
+    ToggleTimeLapse,
+    TogglePhotoMode,
+    NextCaptureSize,
+    CapturePanorama,
+    CaptureStereoPanorama,
+    CapturePhoto,
+    SelectSphere,
+    FreezeSelection,
+    RecolorSelection,
+    KickSelection,
+    ExportSelection,
+}
+
+/// A button of a gamepad in the standard mapping, or a direction of its left stick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+}
+impl GamepadButton {
+    pub const ALL: [Self; 18] = [
+        Self::South,
+        Self::East,
+        Self::West,
+        Self::North,
+        Self::LeftBumper,
+        Self::RightBumper,
+        Self::LeftTrigger,
+        Self::RightTrigger,
+        Self::Select,
+        Self::Start,
+        Self::DpadUp,
+        Self::DpadDown,
+        Self::DpadLeft,
+        Self::DpadRight,
+        Self::LeftStickUp,
+        Self::LeftStickDown,
+        Self::LeftStickLeft,
+        Self::LeftStickRight,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// A short touch of one finger, barely moving
+    Tap,
+    /// Two or more fingers held down
+    TwoFingerHold,
+}
+
+/// Something that can be bound to an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// A key by what it types, along with whether Ctrl and Shift must be held
+    Key {
+        key: VirtualKeyCode,
+        ctrl: bool,
+        shift: bool,
+    },
+    /// A key by its position
+    Position(FlightKey),
+    Button(GamepadButton),
+    Gesture(Gesture),
+}
+impl Input {
+    /// A key without modifiers.
+    const fn key(key: VirtualKeyCode) -> Self {
+        Self::Key {
+            key,
+            ctrl: false,
+            shift: false,
+        }
+    }
+    const fn ctrl(key: VirtualKeyCode) -> Self {
+        Self::Key {
+            key,
+            ctrl: true,
+            shift: false,
+        }
+    }
+    const fn shift(key: VirtualKeyCode) -> Self {
+        Self::Key {
+            key,
+            ctrl: false,
+            shift: true,
+        }
+    }
+    pub fn label(self) -> String {
+        match self {
+            Self::Key { key, ctrl, shift } => format!(
+                "{}{}{key:?}",
+                if ctrl { "Ctrl+" } else { "" },
+                if shift { "Shift+" } else { "" }
+            ),
+            Self::Position(key) => key.label(),
+            Self::Button(button) => format!("{button:?}"),
+            Self::Gesture(gesture) => format!("{gesture:?}"),
+        }
+    }
+}
+
+/// A change of input, before it is looked up in the [`InputMap`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    /// An input pressed or released
+    Input(Input, bool),
+    /// Looking around, in mouse movement
+    Look(f64, f64),
+}
+
+/// The bindings of inputs to actions
+pub struct InputMap {
+    bindings: Vec<(Input, Action)>,
+}
+impl InputMap {
+    pub fn new() -> Self {
+        use Action as A;
+        use GamepadButton as B;
+        use VirtualKeyCode as K;
+
+        let mut bindings: Vec<_> = FlightKey::ALL
+            .into_iter()
+            .map(|key| (Input::Position(key), A::Fly(key)))
+            .collect();
+        bindings.extend([
+            (Input::key(K::F1), A::OpenGallery),
+            (Input::key(K::F2), A::ToggleLog),
+            (Input::key(K::F3), A::NextLogFilter),
+            (Input::key(K::F4), A::CaptureFrame),
+            (Input::key(K::F5), A::ToggleCheckpoints),
+            (Input::key(K::F6), A::ToggleHelp),
+            (Input::key(K::F7), A::NextPreset),
+            (Input::key(K::F8), A::ToggleConservation),
+            (Input::key(K::F9), A::ToggleCompass),
+            (Input::key(K::Grave), A::ToggleConsole),
+            (Input::key(K::Escape), A::ReleaseMouse),
+            (Input::key(K::Up), A::MoreRaySplits),
+            (Input::key(K::Down), A::FewerRaySplits),
+            (Input::key(K::Comma), A::ShorterTicks),
+            (Input::key(K::Period), A::LongerTicks),
+            (Input::key(K::Minus), A::WeakerGravity),
+            (Input::key(K::Equals), A::StrongerGravity),
+            (Input::key(K::R), A::NextBackend),
+            (Input::key(K::I), A::ToggleLensing),
+            (Input::key(K::U), A::ToggleAccretionDisk),
+            (Input::key(K::X), A::ToggleTracers),
+            (Input::key(K::Pause), A::PlayPause),
+            (Input::key(K::PlayPause), A::PlayPause),
+            (Input::key(K::Right), A::Step),
+            (Input::key(K::MediaStop), A::Stop),
+            (Input::key(K::Semicolon), A::NextStreamlines),
+            (Input::key(K::RBracket), A::DenserMedium),
+            (Input::key(K::LBracket), A::ThinnerMedium),
+            (Input::shift(K::RBracket), A::MoreExtinction),
+            (Input::shift(K::LBracket), A::LessExtinction),
+            (Input::key(K::L), A::NextLabels),
+            (Input::key(K::F), A::ToggleFps),
+            (Input::ctrl(K::Z), A::Undo),
+            (
+                Input::Key {
+                    key: K::Z,
+                    ctrl: true,
+                    shift: true,
+                },
+                A::Redo,
+            ),
+            (Input::ctrl(K::Y), A::Redo),
+            (Input::ctrl(K::C), A::Copy),
+            (Input::ctrl(K::V), A::Paste),
+            (Input::key(K::B), A::SpawnMarble),
+            (Input::key(K::Delete), A::Remove),
+            (Input::key(K::M), A::Measure),
+            (Input::key(K::C), A::NextCameraTarget),
+            (Input::key(K::Y), A::Level),
+            (Input::shift(K::Y), A::ToggleAttitude),
+            (Input::key(K::Home), A::FrameAll),
+            (Input::shift(K::Home), A::FrameSelection),
+            (Input::key(K::K), A::RecordKeyframe),
+            (Input::key(K::P), A::PlayPath),
+            (Input::key(K::T), A::StartTutorial),
+            (Input::key(K::G), A::StartChallenge),
+            (Input::key(K::O), A::ToggleGolf),
+            (Input::key(K::J), A::ToggleSplitScreen),
+            (Input::key(K::N), A::ToggleTimeLapse),
+            (Input::key(K::H), A::TogglePhotoMode),
+            (Input::key(K::F11), A::NextCaptureSize),
+            (Input::key(K::F10), A::CapturePanorama),
+            (Input::shift(K::F10), A::CaptureStereoPanorama),
+            (Input::key(K::F12), A::CapturePhoto),
+            (Input::key(K::V), A::SelectSphere),
+            (Input::key(K::Key1), A::FreezeSelection),
+            (Input::key(K::Key2), A::RecolorSelection),
+            (Input::key(K::Key3), A::KickSelection),
+            (Input::key(K::Key4), A::ExportSelection),
+            (Input::Button(B::LeftStickUp), A::Fly(FlightKey::Forwards)),
+            (
+                Input::Button(B::LeftStickDown),
+                A::Fly(FlightKey::Backwards),
+            ),
+            (Input::Button(B::LeftStickRight), A::Fly(FlightKey::Right)),
+            (Input::Button(B::LeftStickLeft), A::Fly(FlightKey::Left)),
+            (Input::Button(B::RightTrigger), A::Fly(FlightKey::Up)),
+            (Input::Button(B::LeftTrigger), A::Fly(FlightKey::Down)),
+            (Input::Button(B::RightBumper), A::Fly(FlightKey::RollRight)),
+            (Input::Button(B::LeftBumper), A::Fly(FlightKey::RollLeft)),
+            (Input::Button(B::South), A::SpawnMarble),
+            (Input::Button(B::East), A::TogglePhotoMode),
+            (Input::Button(B::West), A::SelectSphere),
+            (Input::Button(B::North), A::NextCameraTarget),
+            (Input::Button(B::Start), A::PlayPause),
+            (Input::Button(B::Select), A::ToggleHelp),
+            (Input::Button(B::DpadUp), A::StrongerGravity),
+            (Input::Button(B::DpadDown), A::WeakerGravity),
+            (Input::Button(B::DpadLeft), A::ShorterTicks),
+            (Input::Button(B::DpadRight), A::LongerTicks),
+            (Input::Gesture(Gesture::Tap), A::SpawnMarble),
+            (
+                Input::Gesture(Gesture::TwoFingerHold),
+                A::Fly(FlightKey::Forwards),
+            ),
+        ]);
+        Self { bindings }
+    }
+    pub fn action(&self, input: Input) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == input)
+            .map(|&(_, action)| action)
+    }
+    /// The action of a key. Keys bound with Ctrl win over flight keys, which win over other keys,
+    /// and modifiers the key is not bound with are ignored.
+    pub fn key(&self, input: &KeyboardInput, modifiers: ModifiersState) -> Option<Action> {
+        let with = |ctrl, shift| {
+            let key = input.virtual_keycode?;
+            self.action(Input::Key { key, ctrl, shift })
+        };
+        let (ctrl, shift) = (modifiers.ctrl(), modifiers.shift());
+        let ctrl_bound = || {
+            if ctrl {
+                with(true, shift).or_else(|| with(true, false))
+            } else {
+                None
+            }
+        };
+        ctrl_bound()
+            .or_else(|| self.action(Input::Position(FlightKey::of(input)?)))
+            .or_else(|| with(false, shift))
+            .or_else(|| with(false, false))
+    }
+    /// The name of the first key bound to an action, for showing in help.
+    pub fn key_label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|&&(input, bound)| {
+                bound == action && matches!(input, Input::Key { .. } | Input::Position(_))
+            })
+            .map_or_else(|| "nothing".to_owned(), |&(input, _)| input.label())
+    }
+}
+
+/// The buttons of the first gamepad, polled once per frame
+pub struct Gamepad {
+    held: [bool; GamepadButton::ALL.len()],
+    connected: bool,
+    last_poll: Instant,
+}
+impl Gamepad {
+    pub fn new() -> Self {
+        Self {
+            held: [false; GamepadButton::ALL.len()],
+            connected: false,
+            last_poll: Instant::now(),
+        }
+    }
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+    /// The buttons pressed and released since the last poll, and the right stick looking around.
+    pub fn poll(&mut self, now: Instant) -> Vec<InputEvent> {
+        let frame = now.duration_since(self.last_poll);
+        self.last_poll = now;
+        let read = read_gamepad();
+        self.connected = read.is_some();
+        let Some((held, (x, y))) = read else {
+            // Release everything when the gamepad is disconnected
+            return self.update([false; GamepadButton::ALL.len()]);
+        };
+        let mut events = self.update(held);
+        let look = |axis: f64| {
+            if axis.abs() < STICK_DEADZONE {
+                0.0
+            } else {
+                STICK_LOOK_RATE * frame.as_secs_f64() * axis
+            }
+        };
+        if x.abs() >= STICK_DEADZONE || y.abs() >= STICK_DEADZONE {
+            events.push(InputEvent::Look(look(x), look(y)));
+        }
+        events
+    }
+    fn update(&mut self, held: [bool; GamepadButton::ALL.len()]) -> Vec<InputEvent> {
+        let events = GamepadButton::ALL
+            .into_iter()
+            .filter(|&button| held[button as usize] != self.held[button as usize])
+            .map(|button| InputEvent::Input(Input::Button(button), held[button as usize]))
+            .collect();
+        self.held = held;
+        events
+    }
+}
+
+/// The held buttons of the first gamepad in the standard mapping, in the order of
+/// [`GamepadButton::ALL`], and the tilt of its right stick.
+#[cfg(target_arch = "wasm32")]
+fn read_gamepad() -> Option<([bool; GamepadButton::ALL.len()], (f64, f64))> {
+    use js_sys::{Array, Reflect};
+    use wasm_bindgen::JsCast;
+
+    let navigator = Reflect::get(&js_sys::global(), &"navigator".into()).ok()?;
+    let get_gamepads = Reflect::get(&navigator, &"getGamepads".into())
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    // Older browsers return an array-like list rather than an array
+    let gamepads = Array::from(&get_gamepads.call0(&navigator).ok()?);
+    let gamepad = gamepads.iter().find(|gamepad| {
+        gamepad.is_object()
+            && Reflect::get(gamepad, &"mapping".into())
+                .ok()
+                .and_then(|mapping| mapping.as_string())
+                .as_deref()
+                == Some("standard")
+    })?;
+    let buttons = Array::from(&Reflect::get(&gamepad, &"buttons".into()).ok()?);
+    let axes = Array::from(&Reflect::get(&gamepad, &"axes".into()).ok()?);
+    let pressed = |index| {
+        Reflect::get(&buttons.get(index), &"pressed".into())
+            .ok()
+            .and_then(|pressed| pressed.as_bool())
+            .unwrap_or(false)
+    };
+    let axis = |index| axes.get(index).as_f64().unwrap_or(0.0);
+    let mut held = [false; GamepadButton::ALL.len()];
+    for button in GamepadButton::ALL {
+        held[button as usize] = match button {
+            GamepadButton::LeftStickUp => axis(1) <= -STICK_DEADZONE,
+            GamepadButton::LeftStickDown => axis(1) >= STICK_DEADZONE,
+            GamepadButton::LeftStickLeft => axis(0) <= -STICK_DEADZONE,
+            GamepadButton::LeftStickRight => axis(0) >= STICK_DEADZONE,
+            // The index of the button in the standard mapping, which skips the stick buttons
+            button if button as u32 >= GamepadButton::DpadUp as u32 => pressed(button as u32 + 2),
+            button => pressed(button as u32),
+        };
+    }
+    Some((held, (axis(2), axis(3))))
+}
+/// The gamepad, which natively is never read.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_gamepad() -> Option<([bool; GamepadButton::ALL.len()], (f64, f64))> {
+    None
+}
+
+struct Finger {
+    id: u64,
+    down: Instant,
+    origin: PhysicalPosition<f64>,
+    last: PhysicalPosition<f64>,
+    /// Whether it moved too far to be a tap
+    moved: bool,
+}
+
+/// The fingers on the screen, told apart into gestures
+pub struct Touches {
+    fingers: Vec<Finger>,
+    /// Whether several fingers have been down since the screen was last untouched
+    several: bool,
+}
+impl Touches {
+    pub fn new() -> Self {
+        Self {
+            fingers: Vec::new(),
+            several: false,
+        }
+    }
+    pub fn touch(&mut self, touch: &Touch) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let position = self.fingers.iter().position(|finger| finger.id == touch.id);
+        match (touch.phase, position) {
+            (TouchPhase::Started, _) => {
+                self.fingers.push(Finger {
+                    id: touch.id,
+                    down: Instant::now(),
+                    origin: touch.location,
+                    last: touch.location,
+                    moved: false,
+                });
+                if self.fingers.len() == 2 {
+                    self.several = true;
+                    let hold = Input::Gesture(Gesture::TwoFingerHold);
+                    events.push(InputEvent::Input(hold, true));
+                }
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                let alone = self.fingers.len() == 1;
+                let finger = &mut self.fingers[index];
+                let (dx, dy) = (
+                    touch.location.x - finger.last.x,
+                    touch.location.y - finger.last.y,
+                );
+                finger.last = touch.location;
+                let (x, y) = (
+                    touch.location.x - finger.origin.x,
+                    touch.location.y - finger.origin.y,
+                );
+                finger.moved |= x.hypot(y) > TAP_DISTANCE;
+                if alone && finger.moved {
+                    events.push(InputEvent::Look(dx, dy));
+                }
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, Some(index)) => {
+                let finger = self.fingers.remove(index);
+                if self.fingers.len() == 1 {
+                    let hold = Input::Gesture(Gesture::TwoFingerHold);
+                    events.push(InputEvent::Input(hold, false));
+                }
+                let tap = touch.phase == TouchPhase::Ended
+                    && !self.several
+                    && !finger.moved
+                    && finger.down.elapsed() < TAP_TIME;
+                if tap {
+                    let tap = Input::Gesture(Gesture::Tap);
+                    events.extend([InputEvent::Input(tap, true), InputEvent::Input(tap, false)]);
+                }
+                if self.fingers.is_empty() {
+                    self.several = false;
+                }
+            }
+            (TouchPhase::Moved | TouchPhase::Ended | TouchPhase::Cancelled, None) => {}
+        }
+        events
+    }
+}
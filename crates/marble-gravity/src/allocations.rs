@@ -0,0 +1,66 @@
+//! A global allocator for tests that counts the allocations made by each thread, to check that
+//! code run every frame does not allocate once warmed up.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct Counting;
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_one();
+        // SAFETY: Forwarded with the caller's guarantees
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: Forwarded with the caller's guarantees
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_one();
+        // SAFETY: Forwarded with the caller's guarantees
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn count_one() {
+    // Threads being torn down have no counter left
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// How many times `f` allocates or reallocates on the current thread.
+pub fn count(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+mod tests {
+    use super::*;
+    use camera::Pose;
+    use physics::{Physics, PhysicsConfig};
+    use spheretree::SphereTreeBuilder;
+
+    #[test]
+    fn rebuilding_the_sphere_tree_does_not_allocate() {
+        let physics = Physics::seeded(1);
+        let (bodies, groups) = (physics.bodies(), &PhysicsConfig::default().groups);
+        let pose = Pose::default();
+        let mut builder = SphereTreeBuilder::new();
+        builder.build(bodies, groups, &pose);
+        assert_eq!(
+            count(|| {
+                builder.build(bodies, groups, &pose);
+            }),
+            0
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! A small attitude indicator, like the artificial horizon of an aircraft: a line across a circle
+//! tilts against the roll of the camera and sinks as it pitches up, relative to
+//! [`camera::WORLD_UP`]. Its placement and whether it is shown are in [`OverlayLayout`].
+//!
+//! [`OverlayLayout`]: crate::overlay::OverlayLayout
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use camera::WORLD_UP;
+
+/// Radius of the indicator in pixels
+const RADIUS: f32 = 32.0;
+const RIM_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+const HORIZON_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+/// The fixed mark showing the level of the camera itself
+const MARK_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.7];
+
+/// The attitude indicator
+pub struct Indicator;
+impl Subscriber for Indicator {
+    /// Draws the indicator for the next frame only, unless it is hidden.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let pose = &frame.pose;
+        let widget = graphics.overlay_layout().attitude;
+        if !widget.visible {
+            return;
+        }
+        let (x, y) = widget.top_left((2.0 * RADIUS, 2.0 * RADIUS), graphics.window_size());
+        let center = (x + RADIUS, y + RADIUS);
+        let at = |(dx, dy): (f32, f32)| (center.0 + dx, center.1 + dy);
+        graphics.overlay_circle(center, RADIUS, RIM_COLOR);
+        if let Some(roll) = pose.roll(WORLD_UP) {
+            // Rolling clockwise turns the horizon counterclockwise on screen, where y is down
+            let (sin, cos) = (-roll).sin_cos();
+            let offset = RADIUS * pose.pitch(WORLD_UP).sin();
+            let half_chord = (RADIUS * RADIUS - offset * offset).max(0.0).sqrt();
+            let (mid_x, mid_y) = (-sin * offset, cos * offset);
+            graphics.overlay_segment(
+                at((mid_x - half_chord * cos, mid_y - half_chord * sin)),
+                at((mid_x + half_chord * cos, mid_y + half_chord * sin)),
+                HORIZON_COLOR,
+            );
+        }
+        for side in [-1.0, 1.0] {
+            graphics.overlay_segment(
+                at((side * 0.6 * RADIUS, 0.0)),
+                at((side * 0.2 * RADIUS, 0.0)),
+                MARK_COLOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,581 @@
+//! `--audio <file>` turns the simulation into a music visualizer: the loudness of the bass, mids
+//! and highs of a PCM WAV file, played from the start of the run and looped, drives settings
+//! through the developer console, see [`CommandLine`]. `--audio mic` listens to the default
+//! microphone instead, through the first recorder of the platform that runs, see [`RECORDERS`].
+//! `--audio -` reads a WAV stream from standard input, from any other source.
+//!
+//! `--audio-map <file>` says what drives what, one mapping per line, such as `bass gravity 5 40`,
+//! which sets gravity from 5 to 40 as the bass goes from silent to its recent loudest. The bands
+//! are `bass`, `mids`, `highs` and `level`, the loudness overall. Besides the settings of the
+//! console, such as `emission` to make bodies glow, `spawn` spawns bodies at up to that many per
+//! second and `shake` shakes the view by up to that many degrees. Lines starting with `#` are
+//! comments. Without a map, the bass shakes the view and the highs thicken the medium.
+//!
+//! The audio is analyzed but not played. Not supported on the web.
+//!
+//! [`CommandLine`]: crate::command_line::CommandLine
+
+use crate::tools::{self, Pipe};
+use camera::Pose;
+use cgmath::{Deg, Euler, Quaternion};
+use instant::Instant;
+use std::{
+    collections::VecDeque,
+    f32::consts::TAU,
+    io::{self, Read},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Recorders writing the default microphone to stdout as a WAV stream, tried in order
+const RECORDERS: &[(&str, &[&str])] = &[
+    (
+        "arecord",
+        &["-q", "-f", "S16_LE", "-c", "1", "-r", "44100", "-t", "wav"],
+    ),
+    ("rec", &["-q", "-c", "1", "-t", "wav", "-"]),
+];
+
+/// Samples analyzed at once, about 23 ms at 44.1 kHz
+const WINDOW: usize = 1024;
+/// The bands and the frequencies in hertz they span, after which comes the overall level
+const BANDS: [(&str, f32, f32); 3] = [
+    ("bass", 20.0, 250.0),
+    ("mids", 250.0, 2000.0),
+    ("highs", 2000.0, 8000.0),
+];
+const LEVEL: usize = BANDS.len();
+/// Time constant in seconds of the fall of a band after it got louder, which it follows at once
+const RELEASE: f32 = 0.15;
+/// Time constant in seconds of the fall of the loudest a band has recently been, by which it is
+/// normalized
+const PEAK_RELEASE: f32 = 8.0;
+/// Quieter bands are taken to be silent rather than normalized up to full loudness
+const SILENCE: f32 = 1e-3;
+/// Settings are only changed by at least this fraction of their range, rather than every frame
+const SETTING_RESOLUTION: f32 = 0.01;
+const DEFAULT_MAP: &str = "bass shake 0 1\nhighs density 0 0.2";
+
+enum Source {
+    /// Mono samples, played from `start` and looped
+    File {
+        samples: Vec<f32>,
+        rate: f32,
+        start: Instant,
+    },
+    /// The latest [`WINDOW`] mono samples of a stream read by another thread
+    Stream {
+        window: Arc<Mutex<VecDeque<f32>>>,
+        rate: f32,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    Setting(String),
+    /// Bodies per second
+    Spawn,
+    /// Degrees
+    Shake,
+}
+
+struct Mapping {
+    band: usize,
+    target: Target,
+    range: (f32, f32),
+    /// The value last set, for settings
+    last: Option<f32>,
+}
+
+pub struct Audio {
+    source: Option<Source>,
+    mappings: Vec<Mapping>,
+    /// The loudness of each band from 0 to 1
+    levels: [f32; LEVEL + 1],
+    peaks: [f32; LEVEL + 1],
+    last_update: Option<Instant>,
+    /// Fractions of bodies to be spawned later
+    spawn_debt: f32,
+    /// The amplitude of the shake in degrees, and how far into the run it is
+    shake: (f32, f32),
+}
+impl Audio {
+    /// Starts analyzing the audio requested on the command line, if any.
+    pub fn requested() -> Self {
+        let mut audio = Self {
+            source: None,
+            mappings: Vec::new(),
+            levels: [0.0; LEVEL + 1],
+            peaks: [0.0; LEVEL + 1],
+            last_update: None,
+            spawn_debt: 0.0,
+            shake: (0.0, 0.0),
+        };
+        let Some((name, path)) = crate::requested("audio") else {
+            return audio;
+        };
+        let source = match path.as_str() {
+            "-" => open_stream(io::stdin()),
+            "mic" => open_microphone(),
+            path => std::fs::File::open(path).and_then(|file| open_file(io::BufReader::new(file))),
+        };
+        match source {
+            Ok(source) => audio.source = Some(source),
+            Err(err) => {
+                log::error!("Failed to read audio from {name} {path}: {err}");
+                return audio;
+            }
+        }
+        let map = match crate::requested("audio-map") {
+            Some((name, path)) => std::fs::read_to_string(&path).unwrap_or_else(|err| {
+                log::error!("Failed to read {name} {path}: {err}");
+                String::new()
+            }),
+            None => DEFAULT_MAP.to_owned(),
+        };
+        audio.mappings = parse_map(&map);
+        log::info!("Visualizing {path} with {} mappings", audio.mappings.len());
+        audio
+    }
+    /// Analyzes the audio playing at `now`, returning the console lines that follow from it.
+    /// Spawns at most `room` bodies.
+    pub fn update(&mut self, now: Instant, room: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let Some(source) = &self.source else {
+            return lines;
+        };
+        let dt = self
+            .last_update
+            .replace(now)
+            .map_or(0.0, |last| (now - last).as_secs_f32());
+        let (samples, rate): (Vec<f32>, f32) = match source {
+            Source::File {
+                samples,
+                rate,
+                start,
+            } => {
+                let end = ((now - *start).as_secs_f64() * f64::from(*rate)) as usize;
+                let window = (end.saturating_sub(WINDOW)..end)
+                    .map(|i| samples[i % samples.len()])
+                    .collect();
+                (window, *rate)
+            }
+            Source::Stream { window, rate } => {
+                (window.lock().unwrap().iter().copied().collect(), *rate)
+            }
+        };
+        let loudness = loudness(&samples, rate);
+        let fall = |time_constant: f32| (-dt / time_constant).exp();
+        for ((level, peak), loudness) in self.levels.iter_mut().zip(&mut self.peaks).zip(loudness) {
+            *peak = (*peak * fall(PEAK_RELEASE)).max(loudness);
+            let normalized = if *peak < SILENCE {
+                0.0
+            } else {
+                loudness / *peak
+            };
+            *level = normalized.max(*level * fall(RELEASE));
+        }
+        self.shake.0 = 0.0;
+        self.shake.1 += dt;
+        for mapping in &mut self.mappings {
+            let (min, max) = mapping.range;
+            let value = min + (max - min) * self.levels[mapping.band];
+            match &mapping.target {
+                Target::Setting(name) => {
+                    let resolution = SETTING_RESOLUTION * (max - min).abs();
+                    if mapping
+                        .last
+                        .is_some_and(|last| (value - last).abs() < resolution)
+                    {
+                        continue;
+                    }
+                    mapping.last = Some(value);
+                    lines.push(format!("set {name} {value}"));
+                }
+                Target::Spawn => self.spawn_debt += value * dt,
+                Target::Shake => self.shake.0 += value,
+            }
+        }
+        let spawned = (self.spawn_debt as usize).min(room);
+        self.spawn_debt = self.spawn_debt.fract();
+        if spawned > 0 {
+            lines.push(format!("spawn {spawned}"));
+        }
+        lines
+    }
+    /// The pose shaken by the audio, for drawing the view from.
+    pub fn shake(&self, pose: Pose) -> Pose {
+        let (amplitude, t) = self.shake;
+        if amplitude == 0.0 {
+            return pose;
+        }
+        // Incommensurate frequencies, so that the shake does not repeat
+        let angle = |frequency: f32, phase: f32| Deg(amplitude * (frequency * t + phase).sin());
+        let jolt = Quaternion::from(Euler {
+            x: angle(31.0, 0.0),
+            y: angle(37.0, 1.0),
+            z: angle(23.0, 2.0),
+        });
+        Pose {
+            rotation: pose.rotation * jolt,
+            ..pose
+        }
+    }
+}
+
+fn parse_map(text: &str) -> Vec<Mapping> {
+    let mut mappings = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let [band, target, min, max] = words[..] else {
+            log::warn!("Ignoring audio mapping {line}, expected such as bass gravity 5 40");
+            continue;
+        };
+        let Some(band) = BANDS
+            .iter()
+            .map(|&(name, ..)| name)
+            .chain(["level"])
+            .position(|name| name == band)
+        else {
+            log::warn!("Ignoring audio mapping {line}, expected bass, mids, highs or level");
+            continue;
+        };
+        let (Ok(min), Ok(max)) = (min.parse::<f32>(), max.parse::<f32>()) else {
+            log::warn!("Ignoring audio mapping {line}, expected numbers after {target}");
+            continue;
+        };
+        let target = match target {
+            "spawn" => Target::Spawn,
+            "shake" => Target::Shake,
+            name => Target::Setting(name.to_owned()),
+        };
+        mappings.push(Mapping {
+            band,
+            target,
+            range: (min, max),
+            last: None,
+        });
+    }
+    mappings
+}
+
+/// The loudness of each band and overall of a window of samples, in arbitrary units.
+fn loudness(samples: &[f32], rate: f32) -> [f32; LEVEL + 1] {
+    let mut loudness = [0.0; LEVEL + 1];
+    if samples.len() < WINDOW {
+        return loudness;
+    }
+    let samples = &samples[samples.len() - WINDOW..];
+    let power = power_spectrum(samples);
+    let bin_width = rate / WINDOW as f32;
+    for (band, &(_, low, high)) in loudness.iter_mut().zip(&BANDS) {
+        let bins = (low / bin_width).ceil() as usize..(high / bin_width).ceil() as usize;
+        *band = power[bins.start.min(power.len())..bins.end.min(power.len())]
+            .iter()
+            .sum::<f32>()
+            .sqrt();
+    }
+    loudness[LEVEL] = (samples.iter().map(|x| x * x).sum::<f32>() / WINDOW as f32).sqrt();
+    loudness
+}
+
+/// The squared magnitudes of the frequencies up to half the sample rate, with a Hann window, by
+/// a radix-2 fast Fourier transform. The number of samples must be a power of two.
+fn power_spectrum(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, x)| x * (0.5 - 0.5 * (TAU * i as f32 / n as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; n];
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+        }
+    }
+    let mut size = 2;
+    while size <= n {
+        let (sin, cos) = (-TAU / size as f32).sin_cos();
+        for start in (0..n).step_by(size) {
+            let (mut w_re, mut w_im) = (1.0, 0.0);
+            for a in start..start + size / 2 {
+                let b = a + size / 2;
+                let t_re = w_re * re[b] - w_im * im[b];
+                let t_im = w_re * im[b] + w_im * re[b];
+                (re[b], im[b]) = (re[a] - t_re, im[a] - t_im);
+                (re[a], im[a]) = (re[a] + t_re, im[a] + t_im);
+                (w_re, w_im) = (w_re * cos - w_im * sin, w_re * sin + w_im * cos);
+            }
+        }
+        size *= 2;
+    }
+    (0..n / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Format {
+    channels: usize,
+    rate: f32,
+    /// Bytes per sample of a channel
+    width: usize,
+    float: bool,
+}
+impl Format {
+    /// The mono samples of whole frames of interleaved channels.
+    fn decode(self, bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+        bytes
+            .chunks_exact(self.channels * self.width)
+            .map(move |frame| {
+                let sum: f32 = frame
+                    .chunks_exact(self.width)
+                    .map(|sample| match (self.width, self.float) {
+                        (4, true) => f32::from_le_bytes(sample.try_into().unwrap()),
+                        (1, _) => (f32::from(sample[0]) - 128.0) / 128.0,
+                        // Little-endian signed integers, read as the top bytes of an i32
+                        (width, _) => {
+                            let mut bytes = [0; 4];
+                            bytes[4 - width..].copy_from_slice(sample);
+                            i32::from_le_bytes(bytes) as f32 / 2_f32.powi(31)
+                        }
+                    })
+                    .sum();
+                sum / self.channels as f32
+            })
+    }
+}
+
+/// Reads the header of a WAV file up to its samples, returning their format and the number of
+/// bytes of them, if known.
+fn read_header(reader: &mut impl Read) -> io::Result<(Format, Option<usize>)> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut riff = [0; 12];
+    reader.read_exact(&mut riff)?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(invalid("Expected a WAV file"));
+    }
+    let mut format = None;
+    loop {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let size = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if &header[..4] == b"data" {
+            let format = format.ok_or_else(|| invalid("Expected a format before the samples"))?;
+            // Streams do not know their length up front
+            let size = (size != 0 && size != u32::MAX).then_some(size as usize);
+            return Ok((format, size));
+        }
+        // Chunks are padded to an even length
+        let mut chunk = vec![0; size as usize + size as usize % 2];
+        reader.read_exact(&mut chunk)?;
+        if &header[..4] == b"fmt " && chunk.len() >= 16 {
+            let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+            // Extensible formats give the actual one at the start of their subformat
+            let tag = if u16_at(0) == 0xFFFE && chunk.len() >= 26 {
+                u16_at(24)
+            } else {
+                u16_at(0)
+            };
+            let width = usize::from(u16_at(14)) / 8;
+            let float = match (tag, width) {
+                (1, 1..=4) => false,
+                (3, 4) => true,
+                _ => {
+                    return Err(invalid(
+                        "Expected 8 to 32 bit integer or 32 bit float samples",
+                    ))
+                }
+            };
+            format = Some(Format {
+                channels: usize::from(u16_at(2)).max(1),
+                rate: u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as f32,
+                width,
+                float,
+            });
+        }
+    }
+}
+
+fn open_file(mut reader: impl Read) -> io::Result<Source> {
+    let (format, size) = read_header(&mut reader)?;
+    let mut bytes = Vec::new();
+    match size {
+        Some(size) => reader.take(size as u64).read_to_end(&mut bytes)?,
+        None => reader.read_to_end(&mut bytes)?,
+    };
+    let samples: Vec<f32> = format.decode(&bytes).collect();
+    if samples.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Expected samples",
+        ));
+    }
+    Ok(Source::File {
+        samples,
+        rate: format.rate,
+        start: Instant::now(),
+    })
+}
+
+/// Reads from the first recorder that runs and writes a WAV header, so that one which runs but
+/// cannot record, such as without a microphone, falls through to the next.
+fn open_microphone() -> io::Result<Source> {
+    tools::run_first(RECORDERS, Pipe::Stdout, |program, child| {
+        let source = open_stream(child.stdout.take().unwrap())?;
+        log::info!("Listening to the microphone with {program}");
+        Ok(source)
+    })
+}
+
+fn open_stream(mut reader: impl Read + Send + 'static) -> io::Result<Source> {
+    let (format, _) = read_header(&mut reader)?;
+    let window = Arc::new(Mutex::new(VecDeque::with_capacity(WINDOW)));
+    let shared = Arc::clone(&window);
+    thread::Builder::new()
+        .name("audio".to_owned())
+        .spawn(move || {
+            // A few milliseconds at a time, so that the analysis keeps up with the stream
+            let mut chunk = vec![0; 128 * format.channels * format.width];
+            loop {
+                if let Err(err) = reader.read_exact(&mut chunk) {
+                    log::warn!("Stopped reading audio: {err}");
+                    return;
+                }
+                let mut window = shared.lock().unwrap();
+                window.extend(format.decode(&chunk));
+                let excess = window.len().saturating_sub(WINDOW);
+                window.drain(..excess);
+            }
+        })?;
+    Ok(Source::Stream {
+        window,
+        rate: format.rate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, rate: f32) -> Vec<f32> {
+        (0..WINDOW)
+            .map(|i| (TAU * frequency * i as f32 / rate).sin())
+            .collect()
+    }
+
+    /// A WAV header with the given format chunk, an odd-sized chunk to skip, and the size of the
+    /// samples.
+    fn wav(format: &[u8], data_size: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend(b"fmt ");
+        bytes.extend((format.len() as u32).to_le_bytes());
+        bytes.extend(format);
+        bytes.extend(b"LIST\x03\0\0\0abc\0");
+        bytes.extend(b"data");
+        bytes.extend(data_size.to_le_bytes());
+        bytes
+    }
+
+    fn format_chunk(tag: u16, channels: u16, bits: u16) -> Vec<u8> {
+        let mut chunk = Vec::new();
+        chunk.extend(tag.to_le_bytes());
+        chunk.extend(channels.to_le_bytes());
+        chunk.extend(48000_u32.to_le_bytes());
+        chunk.extend((48000 * u32::from(channels * bits / 8)).to_le_bytes());
+        chunk.extend((channels * bits / 8).to_le_bytes());
+        chunk.extend(bits.to_le_bytes());
+        chunk
+    }
+
+    #[test]
+    fn power_spectrum_peaks_at_the_frequency() {
+        let power = power_spectrum(&sine(64.0, WINDOW as f32));
+        assert_eq!(power.len(), WINDOW / 2);
+        let peak = (0..power.len())
+            .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+            .unwrap();
+        assert_eq!(peak, 64);
+        // The Hann window spreads a bin-centred sine over its neighbours only
+        let leaked: f32 = power
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| k.abs_diff(64) > 1)
+            .map(|(_, p)| p)
+            .sum();
+        assert!(leaked < 1e-6 * power[64], "{leaked}");
+    }
+
+    #[test]
+    fn loudness_falls_in_the_band_of_the_frequency() {
+        for (band, frequency) in [(0, 100.0), (1, 1000.0), (2, 4000.0)] {
+            let levels = loudness(&sine(frequency, 44100.0), 44100.0);
+            let loudest = (0..LEVEL)
+                .max_by(|&a, &b| levels[a].total_cmp(&levels[b]))
+                .unwrap();
+            assert_eq!(loudest, band, "{frequency} {levels:?}");
+            assert!((levels[LEVEL] - 0.5_f32.sqrt()).abs() < 0.01, "{levels:?}");
+        }
+        assert_eq!(loudness(&[1.0; WINDOW - 1], 44100.0), [0.0; LEVEL + 1]);
+    }
+
+    #[test]
+    fn reads_integer_headers() {
+        let bytes = wav(&format_chunk(1, 2, 16), 400);
+        let expected = Format {
+            channels: 2,
+            rate: 48000.0,
+            width: 2,
+            float: false,
+        };
+        assert_eq!(read_header(&mut &bytes[..]).unwrap(), (expected, Some(400)));
+        let unsupported = wav(&format_chunk(2, 1, 16), 400);
+        assert!(read_header(&mut &unsupported[..]).is_err());
+        assert!(read_header(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn reads_extensible_headers() {
+        let mut chunk = format_chunk(0xFFFE, 1, 32);
+        // The size of the extension, the valid bits, the channel mask and the subformat
+        chunk.extend(22_u16.to_le_bytes());
+        chunk.extend(32_u16.to_le_bytes());
+        chunk.extend(4_u32.to_le_bytes());
+        chunk.extend(3_u16.to_le_bytes());
+        chunk.extend([0; 14]);
+        let (format, _) = read_header(&mut &wav(&chunk, 400)[..]).unwrap();
+        assert!(format.float && format.width == 4, "{format:?}");
+    }
+
+    #[test]
+    fn streams_have_unknown_lengths() {
+        for size in [0, u32::MAX] {
+            let bytes = wav(&format_chunk(1, 1, 8), size);
+            assert_eq!(read_header(&mut &bytes[..]).unwrap().1, None);
+        }
+    }
+
+    #[test]
+    fn parses_maps() {
+        let mappings = parse_map(
+            "# A comment\n\nbass gravity 5 40\n  highs shake 0 2  \nlevel spawn 0 10\n\
+             treble gravity 0 1\nmids gravity\nmids gravity a b\n",
+        );
+        let parsed: Vec<_> = mappings
+            .iter()
+            .map(|mapping| (mapping.band, &mapping.target, mapping.range))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                (0, &Target::Setting("gravity".to_owned()), (5.0, 40.0)),
+                (2, &Target::Shake, (0.0, 2.0)),
+                (LEVEL, &Target::Spawn, (0.0, 10.0)),
+            ]
+        );
+    }
+}
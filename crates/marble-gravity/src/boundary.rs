@@ -0,0 +1,81 @@
+//! Faint outlines of the walls that contain the bodies, for the sphere and periodic boundaries,
+//! see [`Boundary`]. `set boundary-outline off` in the developer console hides them.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use physics::Boundary;
+
+const COLOR: [f32; 4] = [0.7, 0.85, 1.0, 0.12];
+/// Of each circle outlining the sphere, and of each edge of the cube, so that little is left
+/// out of the view from inside
+const SEGMENTS: usize = 64;
+
+/// The outline of the boundary
+pub struct Outline;
+impl Subscriber for Outline {
+    /// Outlines the boundary for the next frame only, where shown.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (config, world_to_camera) = (frame.physics.config(), frame.world_to_camera());
+        if !graphics.boundary_outline() {
+            return;
+        }
+        let radius = config.boundary_radius;
+        let mut segment = |from: Vector3<f32>, to: Vector3<f32>| {
+            let view = |pos: Vector3<f32>| (world_to_camera * pos.extend(1.0)).truncate();
+            // Segments partly behind the camera are left out rather than clipped
+            if let (Some(from), Some(to)) =
+                (graphics.project(view(from)), graphics.project(view(to)))
+            {
+                graphics.overlay_segment(from, to, COLOR);
+            }
+        };
+        match config.boundary {
+            Boundary::Damped | Boundary::Unbounded => {}
+            Boundary::Sphere => {
+                // The equator and two meridians, with latitudes at 45 degrees
+                let circle = |i: usize, center: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>| {
+                    let (sin, cos) = (std::f32::consts::TAU * i as f32 / SEGMENTS as f32).sin_cos();
+                    center + cos * u + sin * v
+                };
+                let (x, y, z) = (Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z());
+                let latitude = radius * std::f32::consts::FRAC_1_SQRT_2;
+                let circles = [
+                    (Vector3::zero(), radius * x, radius * z),
+                    (Vector3::zero(), radius * x, radius * y),
+                    (Vector3::zero(), radius * z, radius * y),
+                    (latitude * y, latitude * x, latitude * z),
+                    (-latitude * y, latitude * x, latitude * z),
+                ];
+                for (center, u, v) in circles {
+                    for i in 0..SEGMENTS {
+                        segment(circle(i, center, u, v), circle(i + 1, center, u, v));
+                    }
+                }
+            }
+            Boundary::Periodic => {
+                // The twelve edges of the cube, along each axis from the four corners at its start
+                for axis in 0..3 {
+                    for corner in 0..4 {
+                        let mut from = Vector3::new(radius, radius, radius);
+                        let others = [(axis + 1) % 3, (axis + 2) % 3];
+                        for (bit, other) in others.into_iter().enumerate() {
+                            if corner & (1 << bit) != 0 {
+                                from[other] = -radius;
+                            }
+                        }
+                        from[axis] = -radius;
+                        let mut to = from;
+                        to[axis] = radius;
+                        let at = |i: usize| from.lerp(to, i as f32 / SEGMENTS as f32);
+                        for i in 0..SEGMENTS {
+                            segment(at(i), at(i + 1));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
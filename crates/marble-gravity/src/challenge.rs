@@ -0,0 +1,238 @@
+//! A game layer of timed objectives, scored by how quickly and with how few commands they are
+//! completed.
+
+use crate::{
+    events::{AppEvent, EventBus, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use physics::{parse_scenario, Physics, ToF32};
+use std::{ops::Range, time::Duration};
+
+const ZONE_COLOR: [f32; 4] = [0.9, 0.15, 0.1, 0.8];
+const SCORE_PER_SECOND_LEFT: u32 = 10;
+const SCORE_PER_COMMAND: u32 = 25;
+const SCORE_FOR_COMPLETION: u32 = 500;
+
+pub enum Objective {
+    /// Keep one body within a distance band of another
+    KeepInOrbit {
+        body: usize,
+        around: usize,
+        band: Range<f32>,
+        duration: Duration,
+    },
+    /// Leave no body with its center inside a sphere, the red zone
+    ClearZone { center: Vector3<f32>, radius: f32 },
+}
+
+pub struct Challenge {
+    pub name: &'static str,
+    /// Bodies to start from, or the current bodies if none
+    pub scenario: Option<&'static str>,
+    pub objective: Objective,
+    pub time_limit: Duration,
+}
+
+pub const CHALLENGES: &[Challenge] = &[
+    Challenge {
+        name: "Keep the small marble in orbit for 60 seconds",
+        scenario: Some(include_str!("../assets/scenarios/stable-orbit.scenario")),
+        objective: Objective::KeepInOrbit {
+            body: 1,
+            around: 0,
+            band: 0.4..2.0,
+            duration: Duration::from_secs(60),
+        },
+        time_limit: Duration::from_secs(120),
+    },
+    Challenge {
+        name: "Clear all marbles from the red zone",
+        scenario: None,
+        objective: Objective::ClearZone {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            radius: 0.6,
+        },
+        time_limit: Duration::from_secs(120),
+    },
+];
+
+enum State {
+    Idle,
+    /// Waiting for physics to be idle, so that the scenario can be loaded
+    Starting(usize),
+    Playing {
+        challenge: usize,
+        started: Duration,
+        /// The simulated time from which the objective has been met, for objectives with a
+        /// duration
+        holding_since: Option<Duration>,
+        commands: u32,
+    },
+    Results {
+        challenge: usize,
+        score: Option<u32>,
+    },
+}
+
+pub struct ChallengeMode {
+    state: State,
+    /// The simulated time up to which physics has been seen, to show the time left between
+    /// updates
+    latest_time: Duration,
+}
+impl ChallengeMode {
+    pub fn new() -> Self {
+        Self {
+            state: State::Idle,
+            latest_time: Duration::ZERO,
+        }
+    }
+    /// Starts the challenge after the current or last one.
+    pub fn start_next(&mut self) {
+        let next = match self.state {
+            State::Idle => 0,
+            State::Starting(challenge)
+            | State::Playing { challenge, .. }
+            | State::Results { challenge, .. } => (challenge + 1) % CHALLENGES.len(),
+        };
+        self.state = State::Starting(next);
+    }
+    /// Must only be called while no worker is advancing `physics`, or loaded bodies would be
+    /// lost.
+    pub fn update(&mut self, physics: &mut Physics, bus: &mut EventBus) {
+        let now = physics.simulated_time();
+        self.latest_time = now;
+        match &mut self.state {
+            State::Idle | State::Results { .. } => {}
+            &mut State::Starting(challenge) => {
+                let Challenge { name, scenario, .. } = &CHALLENGES[challenge];
+                if let Some(scenario) = scenario {
+                    let scenario = parse_scenario(scenario).expect("built-in scenarios are valid");
+                    bus.publish(AppEvent::ScenarioLoaded(scenario.settings));
+                    physics.load_scenario(scenario);
+                }
+                log::info!("Challenge started: {name}");
+                self.state = State::Playing {
+                    challenge,
+                    started: now,
+                    holding_since: None,
+                    commands: 0,
+                };
+            }
+            State::Playing {
+                challenge,
+                started,
+                holding_since,
+                commands,
+            } => {
+                let Challenge {
+                    objective,
+                    time_limit,
+                    ..
+                } = &CHALLENGES[*challenge];
+                let elapsed = now - *started;
+                let completed = match objective {
+                    Objective::KeepInOrbit {
+                        body,
+                        around,
+                        band,
+                        duration,
+                    } => {
+                        let bodies = physics.bodies();
+                        let in_orbit = match (bodies.get(*body), bodies.get(*around)) {
+                            (Some(a), Some(b)) => band.contains(&a.pos.distance(b.pos).to_f32()),
+                            _ => false,
+                        };
+                        if in_orbit {
+                            let since = *holding_since.get_or_insert(now);
+                            now - since >= *duration
+                        } else {
+                            *holding_since = None;
+                            false
+                        }
+                    }
+                    Objective::ClearZone { center, radius } => physics
+                        .bodies()
+                        .iter()
+                        .all(|body| body.pos.to_f32().distance2(*center) > radius * radius),
+                };
+                let score = if completed {
+                    let seconds_left = time_limit.saturating_sub(elapsed).as_secs() as u32;
+                    Some(
+                        (SCORE_FOR_COMPLETION + SCORE_PER_SECOND_LEFT * seconds_left)
+                            .saturating_sub(SCORE_PER_COMMAND * *commands),
+                    )
+                } else if elapsed >= *time_limit {
+                    None
+                } else {
+                    return;
+                };
+                log::info!("Challenge ended with score {score:?}");
+                self.state = State::Results {
+                    challenge: *challenge,
+                    score,
+                };
+            }
+        }
+    }
+}
+impl Subscriber for ChallengeMode {
+    fn notify(&mut self, event: &AppEvent) {
+        if let (State::Playing { commands, .. }, AppEvent::CommandExecuted(_)) =
+            (&mut self.state, event)
+        {
+            *commands += 1;
+        }
+    }
+    /// Shows the timer or results in the overlay, and outlines the red zone.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let world_to_camera = frame.world_to_camera();
+        match self.state {
+            State::Idle | State::Starting(_) => {}
+            State::Playing {
+                challenge,
+                started,
+                holding_since,
+                commands,
+            } => {
+                let Challenge {
+                    name,
+                    objective,
+                    time_limit,
+                    ..
+                } = &CHALLENGES[challenge];
+                let elapsed = self.latest_time.saturating_sub(started);
+                graphics.overlay_line(format_args!("{name}"));
+                graphics.overlay_line(format_args!(
+                    "{:.0} seconds left, {commands} commands used",
+                    time_limit.saturating_sub(elapsed).as_secs_f32()
+                ));
+                match objective {
+                    Objective::KeepInOrbit { duration, .. } => {
+                        let held = holding_since.map_or(Duration::ZERO, |since| {
+                            self.latest_time.saturating_sub(since)
+                        });
+                        graphics.overlay_line(format_args!(
+                            "in orbit for {:.0} of {:.0} seconds",
+                            held.as_secs_f32(),
+                            duration.as_secs_f32()
+                        ));
+                    }
+                    Objective::ClearZone { center, radius } => {
+                        let view_center = (world_to_camera * center.extend(1.0)).truncate();
+                        graphics.overlay_sphere(view_center, *radius, ZONE_COLOR);
+                    }
+                }
+            }
+            State::Results { challenge, score } => {
+                graphics.overlay_line(format_args!("{}", CHALLENGES[challenge].name));
+                match score {
+                    Some(score) => graphics.overlay_line(format_args!("Completed, score {score}")),
+                    None => graphics.overlay_line(format_args!("Out of time")),
+                }
+                graphics.overlay_line(format_args!("Press G for the next challenge"));
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Copying and pasting text, such as camera poses and bodies in the scenario format. Natively,
+//! this goes through the clipboard tools of the platform, the first of which that runs is used.
+//! On the web, it goes through the asynchronous clipboard API, which may ask for permission.
+
+use std::cell::RefCell;
+
+thread_local! {
+    /// Text read from the clipboard, not yet taken
+    static PASTED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Commands reading the clipboard from stdin
+#[cfg(not(target_arch = "wasm32"))]
+const COPY_COMMANDS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip", &[]),
+];
+/// Commands writing the clipboard to stdout
+#[cfg(not(target_arch = "wasm32"))]
+const PASTE_COMMANDS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+    ("powershell", &["-NoProfile", "-Command", "Get-Clipboard"]),
+];
+
+/// Puts text on the clipboard in the background.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy(text: String) {
+    use crate::tools::{self, Pipe};
+    use std::io::Write;
+
+    std::thread::spawn(move || {
+        let copied = tools::run_first(COPY_COMMANDS, Pipe::Stdin, |_, child| {
+            child.stdin.take().unwrap().write_all(text.as_bytes())?;
+            tools::wait_success(child)
+        });
+        if let Err(err) = copied {
+            log::error!("Failed to copy, no clipboard tool worked: {err}");
+        }
+    });
+}
+
+/// Reads the clipboard, to be taken by [`take_pasted`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn request_paste() {
+    use crate::tools::{self, Pipe};
+    use std::io::Read;
+
+    let pasted = tools::run_first(PASTE_COMMANDS, Pipe::Stdout, |_, child| {
+        let mut bytes = Vec::new();
+        child.stdout.take().unwrap().read_to_end(&mut bytes)?;
+        tools::wait_success(child)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    });
+    match pasted {
+        Ok(text) => PASTED.with(|pasted| *pasted.borrow_mut() = Some(text)),
+        Err(err) => log::error!("Failed to paste, no clipboard tool worked: {err}"),
+    }
+}
+
+/// Puts text on the clipboard in the background.
+#[cfg(target_arch = "wasm32")]
+pub fn copy(text: String) {
+    let promise = clipboard_call("writeText", &js_sys::Array::of1(&text.into()));
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = async { wasm_bindgen_futures::JsFuture::from(promise?).await }.await {
+            log::error!("Failed to copy: {err:?}");
+        }
+    });
+}
+
+/// Starts reading the clipboard, to be taken by [`take_pasted`] once done.
+#[cfg(target_arch = "wasm32")]
+pub fn request_paste() {
+    let promise = clipboard_call("readText", &js_sys::Array::new());
+    wasm_bindgen_futures::spawn_local(async move {
+        match async { wasm_bindgen_futures::JsFuture::from(promise?).await }.await {
+            Ok(text) => PASTED.with(|pasted| *pasted.borrow_mut() = text.as_string()),
+            Err(err) => log::error!("Failed to paste: {err:?}"),
+        }
+    });
+}
+
+/// Calls a method of `navigator.clipboard`, which is missing outside of secure contexts.
+#[cfg(target_arch = "wasm32")]
+fn clipboard_call(
+    method: &str,
+    args: &js_sys::Array,
+) -> Result<js_sys::Promise, wasm_bindgen::JsValue> {
+    use js_sys::Reflect;
+    use wasm_bindgen::JsCast;
+
+    let navigator = Reflect::get(&js_sys::global(), &"navigator".into())?;
+    let clipboard = Reflect::get(&navigator, &"clipboard".into())?;
+    let method: js_sys::Function = Reflect::get(&clipboard, &method.into())?.dyn_into()?;
+    method.apply(&clipboard, args)?.dyn_into()
+}
+
+/// Takes the text read by the latest [`request_paste`], once it is done.
+pub fn take_pasted() -> Option<String> {
+    PASTED.with(|pasted| pasted.borrow_mut().take())
+}
@@ -0,0 +1,596 @@
+//! A developer console dropping down over the view: typed commands such as `spawn 10` or
+//! `set gravity 20` go through the [`CommandHistory`], so that they can be undone like any other
+//! edit. Up and down recall earlier commands, and tab completes names. `help` lists the commands.
+
+use crate::{
+    actions::Action,
+    commands::{Command, CommandHistory},
+    comparison::{Comparison, ComparisonView, Variant},
+    events::{AppEvent, EventBus, Frame, Subscriber},
+    graphics::{Backend, Graphics, Medium, Sun, MAX_RAY_SPLITS, RADIUS_SCALES},
+};
+use camera::Camera;
+use cgmath::{prelude::*, Vector3};
+use physics::{
+    Body, Boundary, Integrator, Physics, Scalar, BODIES, MAX_DELTA_TIME, MIN_DELTA_TIME,
+};
+use std::{collections::VecDeque, time::Duration};
+use winit::event::VirtualKeyCode;
+
+/// Lines of output kept, of which the newest [`OUTPUT_LINES`] are shown
+const OUTPUT_CAPACITY: usize = 100;
+const OUTPUT_LINES: usize = 10;
+const HISTORY_LENGTH: usize = 50;
+/// Spawned bodies fill a ball of this radius, this far in front of the camera
+const SPAWN_DISTANCE: f32 = 0.5;
+const SPAWN_RADIUS: f32 = 0.15;
+
+/// The commands, their arguments and what they do, for `help` and completion
+const COMMANDS: [(&str, &str, &str); 15] = [
+    ("help", "", "lists the commands"),
+    ("spawn", "[count]", "spawns bodies in front of the camera"),
+    ("set", "[setting] [value]", "shows or changes settings"),
+    (
+        "save",
+        "snapshot <name>",
+        "saves the bodies and camera to the gallery",
+    ),
+    ("undo", "", "undoes the last edit"),
+    ("redo", "", "redoes the last undone edit"),
+    ("pause", "", "pauses or resumes the simulation"),
+    ("step", "", "advances the simulation by a single tick"),
+    ("frame", "", "flies to where every body is in view"),
+    ("level", "", "levels the horizon"),
+    (
+        "keyframe",
+        "",
+        "records the camera pose as a keyframe of the path",
+    ),
+    ("path", "", "flies the camera along the recorded path"),
+    (
+        "compare",
+        "[integrator <name>|dt <ms>|split|overlay|off]",
+        "compares with a second simulation",
+    ),
+    ("clear", "", "clears this output"),
+    ("close", "", "closes the console"),
+];
+const SETTINGS: [&str; 19] = [
+    "gravity",
+    "softening",
+    "warm-up",
+    "integrator",
+    "boundary",
+    "boundary-radius",
+    "boundary-outline",
+    "dt",
+    "speed",
+    "splits",
+    "backend",
+    "lensing",
+    "disk",
+    "density",
+    "extinction",
+    "sun-azimuth",
+    "sun-elevation",
+    "radius-scale",
+    "emission",
+];
+
+/// What the commands of the console act on
+pub struct Context<'a> {
+    pub commands: &'a mut CommandHistory,
+    pub graphics: &'a mut Graphics,
+    pub physics: &'a Physics,
+    /// How many times as fast as real time the simulation runs
+    pub speed: &'a mut f32,
+    pub camera: &'a Camera,
+    /// Actions to carry out as if their inputs were pressed
+    pub actions: &'a mut VecDeque<(Action, bool)>,
+    pub comparison: &'a mut Comparison,
+    pub bus: &'a mut EventBus,
+}
+
+pub struct CommandLine {
+    open: bool,
+    input: String,
+    /// Earlier commands, oldest first
+    history: VecDeque<String>,
+    /// The index in `history` of the command recalled into `input`, if any
+    recalled: Option<usize>,
+    output: VecDeque<String>,
+}
+impl CommandLine {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            history: VecDeque::new(),
+            recalled: None,
+            output: VecDeque::new(),
+        }
+    }
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    /// Types a character, unless it is a control character or the backtick opening the console.
+    pub fn character(&mut self, c: char) {
+        if self.open && !c.is_control() && c != '`' {
+            self.input.push(c);
+            self.recalled = None;
+        }
+    }
+    /// Edits the input by a key press while open, returning a command to run if it was entered.
+    pub fn key_input(&mut self, key: VirtualKeyCode) -> Option<String> {
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                let line = std::mem::take(&mut self.input);
+                self.recalled = None;
+                self.print(format!("> {line}"));
+                if !line.trim().is_empty() && self.history.back() != Some(&line) {
+                    self.history.push_back(line.clone());
+                    if self.history.len() > HISTORY_LENGTH {
+                        self.history.pop_front();
+                    }
+                }
+                return Some(line);
+            }
+            VirtualKeyCode::Back => {
+                self.input.pop();
+            }
+            VirtualKeyCode::Tab => self.complete(),
+            VirtualKeyCode::Up => {
+                let index = match self.recalled {
+                    Some(index) => index.saturating_sub(1),
+                    None => self.history.len().checked_sub(1)?,
+                };
+                self.recall(Some(index));
+            }
+            VirtualKeyCode::Down => match self.recalled {
+                Some(index) if index + 1 < self.history.len() => self.recall(Some(index + 1)),
+                Some(_) => self.recall(None),
+                None => {}
+            },
+            VirtualKeyCode::Escape | VirtualKeyCode::Grave => self.open = false,
+            _ => {}
+        }
+        None
+    }
+    /// Runs a command, printing what it did or what was wrong with it.
+    pub fn run(&mut self, line: &str, context: &mut Context<'_>) {
+        match self.execute(line, context) {
+            Ok(text) => text.lines().for_each(|line| self.print(line.to_owned())),
+            Err(err) => self.print(format!("Error: {err}")),
+        }
+    }
+    /// Runs a command, returning what it did or what was wrong with it.
+    pub fn execute(&mut self, line: &str, context: &mut Context<'_>) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => Ok(String::new()),
+            ["help"] => Ok(COMMANDS
+                .map(|(name, arguments, description)| {
+                    format!(
+                        "{}: {description}",
+                        format!("{name} {arguments}").trim_end()
+                    )
+                })
+                .join("\n")),
+            ["spawn"] => spawn(1, context),
+            ["spawn", count] => match count.parse() {
+                Ok(count) if (1..=BODIES).contains(&count) => spawn(count, context),
+                _ => Err(format!("Expected a count from 1 to {BODIES}, not {count}")),
+            },
+            ["set"] => Ok(SETTINGS
+                .map(|name| format!("{name} {}", setting(name, context).unwrap_or_default()))
+                .join("\n")),
+            ["set", name] => setting(name, context)
+                .map(|value| format!("{name} {value}"))
+                .ok_or_else(|| unknown_setting(name)),
+            ["set", name, value] => set(name, value, context, CommandHistory::push),
+            ["save", "snapshot", name] => save_snapshot(name, context),
+            ["compare", ..] => compare(&words[1..], context),
+            ["undo"] => {
+                context.commands.undo();
+                Ok(String::new())
+            }
+            ["redo"] => {
+                context.commands.redo();
+                Ok(String::new())
+            }
+            ["pause" | "step" | "frame" | "level" | "keyframe" | "path"] => {
+                let action = match words[0] {
+                    "pause" => Action::PlayPause,
+                    "step" => Action::Step,
+                    "frame" => Action::FrameAll,
+                    "level" => Action::Level,
+                    "keyframe" => Action::RecordKeyframe,
+                    _ => Action::PlayPath,
+                };
+                context.actions.push_back((action, true));
+                Ok(String::new())
+            }
+            ["clear"] => {
+                self.output.clear();
+                Ok(String::new())
+            }
+            ["close"] => {
+                self.open = false;
+                Ok(String::new())
+            }
+            [name, ..] => match COMMANDS.iter().find(|&&(command, ..)| command == name) {
+                Some((_, arguments, _)) => Err(format!("Expected {name} {arguments}")),
+                None => Err(format!("Unknown command {name}, see help")),
+            },
+        }
+    }
+    /// Runs a line from a continuous source, such as audio or a fader. Unlike
+    /// [`CommandLine::execute`], the settings it changes bypass the undo history, see
+    /// [`CommandHistory::modulate`].
+    pub fn modulate(&mut self, line: &str, context: &mut Context<'_>) -> Result<String, String> {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["set", name, value] => set(name, value, context, CommandHistory::modulate),
+            _ => self.execute(line, context),
+        }
+    }
+    fn print(&mut self, line: String) {
+        self.output.push_back(line);
+        if self.output.len() > OUTPUT_CAPACITY {
+            self.output.pop_front();
+        }
+    }
+    fn recall(&mut self, index: Option<usize>) {
+        self.recalled = index;
+        self.input = index.map_or_else(String::new, |index| self.history[index].clone());
+    }
+    /// Completes the last word as far as it is unambiguous, listing the candidates otherwise.
+    fn complete(&mut self) {
+        let start = self.input.rfind(' ').map_or(0, |space| space + 1);
+        let (done, word) = self.input.split_at(start);
+        let words: Vec<&str> = done.split_whitespace().collect();
+        let matches: Vec<&str> = candidates(&words)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+        match matches[..] {
+            [] => {}
+            [only] => self.input = format!("{done}{only} "),
+            [first, ..] => {
+                let common = matches.iter().fold(first.len(), |common, candidate| {
+                    first
+                        .bytes()
+                        .zip(candidate.bytes())
+                        .take(common)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                let line = matches.join(" ");
+                self.input = format!("{done}{}", &first[..common]);
+                self.print(line);
+            }
+        }
+    }
+}
+impl Subscriber for CommandLine {
+    /// Shows the output and the input in the overlay, while open.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if !self.open {
+            return;
+        }
+        for line in self
+            .output
+            .iter()
+            .skip(self.output.len().saturating_sub(OUTPUT_LINES))
+        {
+            graphics.overlay_line(format_args!("{line}"));
+        }
+        graphics.overlay_line(format_args!("> {}_", self.input));
+    }
+}
+
+fn unknown_setting(name: &str) -> String {
+    format!(
+        "Unknown setting {name}, expected one of {}",
+        SETTINGS.join(", ")
+    )
+}
+
+/// What may follow the given words
+fn candidates(words: &[&str]) -> Vec<&'static str> {
+    match words {
+        [] => COMMANDS.map(|(name, ..)| name).to_vec(),
+        ["set"] => SETTINGS.to_vec(),
+        ["set" | "compare", "integrator"] => Integrator::ALL.map(Integrator::name).to_vec(),
+        ["set", "backend"] => Backend::ALL.map(Backend::name).to_vec(),
+        ["set", "boundary"] => Boundary::ALL.map(Boundary::name).to_vec(),
+        ["set", "lensing" | "disk" | "boundary-outline"] => vec!["on", "off"],
+        ["save"] => vec!["snapshot"],
+        ["compare"] => vec!["integrator", "dt", "split", "overlay", "off"],
+        _ => Vec::new(),
+    }
+}
+
+/// Spawns bodies at rest, spread evenly through a ball in front of the camera.
+fn spawn(count: usize, context: &mut Context<'_>) -> Result<String, String> {
+    let room = BODIES - context.physics.bodies().len();
+    if count > room {
+        return Err(format!("Room for only {room} more bodies"));
+    }
+    let center = context.camera.position() + SPAWN_DISTANCE * context.camera.forward();
+    // Directions along a Fibonacci spiral, and radii from a golden ratio sequence, which spread
+    // the bodies evenly through the ball without a random generator
+    let golden_ratio = (5f32.sqrt() - 1.0) / 2.0;
+    for i in 0..count {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+        let (sin, cos) = (std::f32::consts::TAU * golden_ratio * i as f32).sin_cos();
+        let ring = (1.0 - y * y).sqrt();
+        let fraction = ((i as f32 + 0.5) * golden_ratio).fract();
+        let offset = SPAWN_RADIUS * fraction.cbrt() * Vector3::new(ring * cos, y, ring * sin);
+        let position = if count == 1 { center } else { center + offset };
+        context.commands.push(Command::SpawnBody(Body::at(
+            position.map(Scalar::from),
+            Vector3::zero(),
+        )));
+    }
+    Ok(format!("Spawned {count} bodies"))
+}
+
+fn setting(name: &str, context: &Context<'_>) -> Option<String> {
+    let config = context.physics.config();
+    let graphics = &context.graphics;
+    let on_off = |on| if on { "on" } else { "off" }.to_owned();
+    Some(match name {
+        "gravity" => config.gravity.to_string(),
+        "softening" => config.softening.to_string(),
+        "warm-up" => format!("{} s", config.warm_up),
+        "integrator" => config.integrator.name().to_owned(),
+        "boundary" => config.boundary.name().to_owned(),
+        "boundary-radius" => config.boundary_radius.to_string(),
+        "boundary-outline" => on_off(graphics.boundary_outline()),
+        "dt" => format!("{} ms", context.physics.delta_time().as_secs_f64() * 1e3),
+        "speed" => context.speed.to_string(),
+        "splits" => graphics.ray_splits().to_string(),
+        "backend" => graphics.backend().name().to_owned(),
+        "lensing" => on_off(graphics.lensing()),
+        "disk" => on_off(graphics.accretion_disk()),
+        "density" => graphics.medium().density.to_string(),
+        "extinction" => graphics.medium().extinction.to_string(),
+        "sun-azimuth" => graphics.sun().azimuth.to_string(),
+        "sun-elevation" => graphics.sun().elevation.to_string(),
+        "radius-scale" => graphics.radius_scale().to_string(),
+        "emission" => graphics.emission().to_string(),
+        _ => return None,
+    })
+}
+
+/// Changes a setting, passing the commands of those that are undoable to `push`.
+fn set(
+    name: &str,
+    value: &str,
+    context: &mut Context<'_>,
+    push: fn(&mut CommandHistory, Command),
+) -> Result<String, String> {
+    let number = || {
+        value
+            .parse::<f32>()
+            .ok()
+            .filter(|number| number.is_finite() && *number >= 0.0)
+            .ok_or_else(|| format!("Expected a number of at least zero, not {value}"))
+    };
+    let on_off = || match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("Expected on or off, not {value}")),
+    };
+    let mut config = *context.physics.config();
+    let graphics = &mut *context.graphics;
+    match name {
+        "gravity" => config.gravity = number()?,
+        "softening" => config.softening = number()?,
+        "warm-up" => config.warm_up = number()?,
+        "integrator" => {
+            config.integrator = Integrator::from_name(value)
+                .ok_or_else(|| format!("Unknown integrator {value}"))?;
+        }
+        "boundary" => {
+            config.boundary =
+                Boundary::from_name(value).ok_or_else(|| format!("Unknown boundary {value}"))?;
+        }
+        "boundary-radius" => match number()? {
+            radius if radius > 0.0 => config.boundary_radius = radius,
+            _ => return Err("Expected a radius above zero".to_owned()),
+        },
+        "boundary-outline" => graphics.set_boundary_outline(on_off()?),
+        "dt" => {
+            let delta_time = Duration::try_from_secs_f32(number()? / 1e3)
+                .ok()
+                .filter(|delta_time| (MIN_DELTA_TIME..=MAX_DELTA_TIME).contains(delta_time))
+                .ok_or_else(|| format!("Expected from {MIN_DELTA_TIME:?} to {MAX_DELTA_TIME:?}"))?;
+            push(context.commands, Command::SetDeltaTime(delta_time));
+        }
+        "speed" => match number()? {
+            speed if speed <= crate::MAX_SPEED => *context.speed = speed,
+            _ => return Err(format!("Expected from 0 to {}", crate::MAX_SPEED)),
+        },
+        "splits" => match value.parse() {
+            Ok(ray_splits) if ray_splits <= MAX_RAY_SPLITS => {
+                push(context.commands, Command::SetRaySplits(ray_splits));
+            }
+            _ => return Err(format!("Expected from 0 to {MAX_RAY_SPLITS}, not {value}")),
+        },
+        "backend" => graphics.set_backend(
+            Backend::ALL
+                .into_iter()
+                .find(|backend| backend.name() == value)
+                .ok_or_else(|| format!("Unknown backend {value}"))?,
+        ),
+        "lensing" => graphics.set_lensing(on_off()?),
+        "disk" => graphics.set_accretion_disk(on_off()?),
+        "density" => graphics.set_medium(Medium {
+            density: number()?,
+            ..graphics.medium()
+        }),
+        "extinction" => graphics.set_medium(Medium {
+            extinction: number()?,
+            ..graphics.medium()
+        }),
+        "sun-azimuth" | "sun-elevation" => {
+            let degrees = value
+                .parse::<f32>()
+                .ok()
+                .filter(|degrees| degrees.is_finite())
+                .ok_or_else(|| format!("Expected an angle in degrees, not {value}"))?;
+            let sun = graphics.sun();
+            graphics.set_sun(if name == "sun-azimuth" {
+                Sun {
+                    azimuth: degrees,
+                    ..sun
+                }
+            } else {
+                Sun {
+                    elevation: degrees,
+                    ..sun
+                }
+            });
+        }
+        "radius-scale" => match number()? {
+            scale if RADIUS_SCALES.contains(&scale) => graphics.set_radius_scale(scale),
+            _ => {
+                return Err(format!(
+                    "Expected from {} to {}",
+                    RADIUS_SCALES.start(),
+                    RADIUS_SCALES.end()
+                ))
+            }
+        },
+        "emission" => graphics.set_emission(number()?),
+        _ => return Err(unknown_setting(name)),
+    }
+    if config != *context.physics.config() {
+        push(
+            context.commands,
+            Command::SetPhysicsConfig(Box::new(config)),
+        );
+    }
+    // Settings changed by commands are published as the commands are executed
+    let by_command = matches!(
+        name,
+        "gravity"
+            | "softening"
+            | "warm-up"
+            | "integrator"
+            | "boundary"
+            | "boundary-radius"
+            | "dt"
+            | "splits"
+    );
+    if let Some(&name) = SETTINGS.iter().find(|&&setting| setting == name) {
+        if !by_command {
+            context.bus.publish(AppEvent::SettingChanged(name));
+        }
+    }
+    Ok(format!("Set {name} to {value}"))
+}
+
+/// Starts, shows or stops a second simulation to compare against, see [`Comparison`].
+fn compare(arguments: &[&str], context: &mut Context<'_>) -> Result<String, String> {
+    let comparison = &mut *context.comparison;
+    let variant = match *arguments {
+        [] => {
+            return Ok(match comparison.divergence(context.physics) {
+                Some(divergence) => format!(
+                    "{:.3e} apart on average, {:.3e} at most",
+                    divergence.rms, divergence.max
+                ),
+                None => "Not comparing".to_owned(),
+            });
+        }
+        ["off"] => {
+            if comparison.is_running() && comparison.view() == ComparisonView::Split {
+                context.graphics.set_split_screen(false);
+            }
+            comparison.stop();
+            return Ok("Stopped comparing".to_owned());
+        }
+        ["split" | "overlay"] => {
+            let view = if arguments[0] == "split" {
+                ComparisonView::Split
+            } else {
+                ComparisonView::Overlay
+            };
+            comparison.set_view(view);
+            if comparison.is_running() {
+                context
+                    .graphics
+                    .set_split_screen(view == ComparisonView::Split);
+            }
+            return Ok(format!("Showing the comparison {}", arguments[0]));
+        }
+        ["integrator", name] => Variant::Integrator(
+            Integrator::from_name(name).ok_or_else(|| format!("Unknown integrator {name}"))?,
+        ),
+        ["dt", milliseconds] => {
+            let delta_time = milliseconds
+                .parse::<f32>()
+                .ok()
+                .and_then(|number| Duration::try_from_secs_f32(number / 1e3).ok())
+                .filter(|delta_time| (MIN_DELTA_TIME..=MAX_DELTA_TIME).contains(delta_time))
+                .ok_or_else(|| {
+                    format!("Expected from {MIN_DELTA_TIME:?} to {MAX_DELTA_TIME:?} in ms")
+                })?;
+            Variant::DeltaTime(delta_time)
+        }
+        _ => {
+            return Err("Expected compare [integrator <name>|dt <ms>|split|overlay|off]".to_owned())
+        }
+    };
+    comparison.start(variant, context.physics)?;
+    context
+        .graphics
+        .set_split_screen(comparison.view() == ComparisonView::Split);
+    Ok(format!("Comparing with {variant}"))
+}
+
+/// Writes the bodies, the fields, the camera pose and the settings of the simulation as a
+/// scenario, to a file in the gallery natively and to the clipboard on the web.
+fn save_snapshot(name: &str, context: &Context<'_>) -> Result<String, String> {
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Expected letters, digits, - and _ in the name, not {name}"
+        ));
+    }
+    let position = context.camera.position();
+    let mut text = physics::write_settings(&physics::ScenarioSettings {
+        camera: Some(physics::ScenarioCamera {
+            position,
+            target: position + context.camera.forward(),
+        }),
+        delta_time: Some(context.physics.delta_time()),
+        ray_splits: Some(context.graphics.ray_splits()),
+    });
+    let fields = &context.physics.config().fields;
+    text.push_str(&physics::write_fields(fields.iter().flatten()));
+    text.push_str(&physics::write_scenario(context.physics.bodies().iter()));
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path =
+            std::path::Path::new(crate::gallery::USER_SCENARIOS).join(format!("{name}.scenario"));
+        std::fs::create_dir_all(crate::gallery::USER_SCENARIOS)
+            .and_then(|()| std::fs::write(&path, text))
+            .map_err(|err| format!("Failed to save {}: {err}", path.display()))?;
+        Ok(format!("Saved {}", path.display()))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::clipboard::copy(text);
+        Ok(format!(
+            "Copied snapshot {name}, as files cannot be saved on the web"
+        ))
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    events::{AppEvent, EventBus},
+    graphics::Graphics,
+    selection::Selection,
+    PhysicsSystem,
+};
+use cgmath::Vector3;
+use physics::{Body, BodySet, Generator, Physics, PhysicsConfig, Scalar};
+use std::{collections::VecDeque, fmt, time::Duration};
+
+const HISTORY_LENGTH: usize = 100;
+
+/// An interactive mutation of the simulation or its presentation.
+#[derive(Clone, Debug)]
+pub enum Command {
+    SetRaySplits(u32),
+    /// Sets the duration of each physics tick
+    SetDeltaTime(Duration),
+    /// Sets the constants of the simulation, such as the strength of gravity
+    SetPhysicsConfig(Box<PhysicsConfig>),
+    SpawnBody(Body),
+    RemoveBody(usize),
+    RemoveBodies(Selection),
+    /// Freezes the selected bodies, or thaws them if all are frozen already
+    ToggleFrozen(Selection),
+    Recolor(Selection, u32),
+    AddVelocity(Selection, Vector3<f32>),
+    /// Replaces the bodies with those drawn by a generator under a master seed, which become the
+    /// generator and seed of the simulation
+    Generate(Generator, u64),
+}
+
+/// What is needed to revert a command. Body edits store a snapshot of all bodies, which is
+/// cheap next to a single physics tick.
+enum Undo {
+    RaySplits(u32),
+    DeltaTime(Duration),
+    PhysicsConfig(Box<PhysicsConfig>),
+    Bodies(Box<BodySet>),
+    Generated {
+        bodies: Box<BodySet>,
+        generator: Generator,
+        seed: u64,
+    },
+}
+
+enum Request {
+    Do(Command),
+    /// A setting driven continuously, which bypasses the undo history
+    Modulate(Command),
+    Undo,
+    Redo,
+}
+
+pub struct CommandHistory {
+    queued: VecDeque<Request>,
+    undo: VecDeque<(Command, Undo)>,
+    redo: Vec<Command>,
+}
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self {
+            queued: VecDeque::new(),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+    pub fn push(&mut self, command: Command) {
+        self.queued.push_back(Request::Do(command));
+    }
+    /// Queues a change of a setting driven continuously, such as by audio or a fader. It is
+    /// applied like any other command, but kept out of the undo history and logged at debug, so
+    /// that a change every frame neither evicts the edits before it nor floods the log.
+    pub fn modulate(&mut self, command: Command) {
+        self.queued.push_back(Request::Modulate(command));
+    }
+    pub fn undo(&mut self) {
+        self.queued.push_back(Request::Undo);
+    }
+    pub fn redo(&mut self) {
+        self.queued.push_back(Request::Redo);
+    }
+    /// Must only be called while no worker is advancing the physics of `system`, or the edits
+    /// would be lost.
+    pub fn apply_queued(
+        &mut self,
+        graphics: &mut Graphics,
+        system: &mut PhysicsSystem,
+        bus: &mut EventBus,
+    ) {
+        while let Some(request) = self.queued.pop_front() {
+            match request {
+                Request::Do(command) => {
+                    self.redo.clear();
+                    self.execute(command, graphics, system, bus);
+                }
+                Request::Modulate(command) => {
+                    let physics = &mut *system.physics;
+                    match command {
+                        Command::SetRaySplits(ray_splits) => {
+                            graphics.set_ray_splits(ray_splits);
+                            bus.publish(AppEvent::SettingChanged("splits"));
+                        }
+                        Command::SetDeltaTime(delta_time) => {
+                            physics.set_delta_time(delta_time);
+                            bus.publish(AppEvent::SettingChanged("dt"));
+                        }
+                        Command::SetPhysicsConfig(config) => {
+                            set_config(physics, &config, bus, log::Level::Debug);
+                        }
+                        // Edits of bodies are undoable however they were requested
+                        command => self.execute(command, graphics, system, bus),
+                    }
+                }
+                Request::Undo => match self.undo.pop_back() {
+                    Some((command, undo)) => {
+                        log::info!("Undoing {command:?}");
+                        let physics = &mut *system.physics;
+                        match undo {
+                            Undo::RaySplits(ray_splits) => {
+                                graphics.set_ray_splits(ray_splits);
+                                bus.publish(AppEvent::SettingChanged("splits"));
+                            }
+                            Undo::DeltaTime(delta_time) => {
+                                physics.set_delta_time(delta_time);
+                                bus.publish(AppEvent::SettingChanged("dt"));
+                            }
+                            Undo::PhysicsConfig(config) => {
+                                set_config(physics, &config, bus, log::Level::Info);
+                            }
+                            Undo::Bodies(bodies) => {
+                                *physics.bodies_mut() = *bodies;
+                                bus.publish(AppEvent::BodiesReplaced);
+                            }
+                            Undo::Generated {
+                                bodies,
+                                generator,
+                                seed,
+                            } => {
+                                *physics.bodies_mut() = *bodies;
+                                (system.generator, system.seed) = (generator, seed);
+                                bus.publish(AppEvent::BodiesReplaced);
+                            }
+                        }
+                        self.redo.push(command);
+                    }
+                    None => log::info!("Nothing to undo"),
+                },
+                Request::Redo => match self.redo.pop() {
+                    Some(command) => {
+                        log::info!("Redoing {command:?}");
+                        self.execute(command, graphics, system, bus);
+                    }
+                    None => log::info!("Nothing to redo"),
+                },
+            }
+        }
+    }
+    fn execute(
+        &mut self,
+        command: Command,
+        graphics: &mut Graphics,
+        system: &mut PhysicsSystem,
+        bus: &mut EventBus,
+    ) {
+        let physics = &mut *system.physics;
+        let undo = match command {
+            Command::SetRaySplits(ray_splits) => {
+                let undo = Undo::RaySplits(graphics.ray_splits());
+                graphics.set_ray_splits(ray_splits);
+                bus.publish(AppEvent::SettingChanged("splits"));
+                undo
+            }
+            Command::SetDeltaTime(delta_time) => {
+                let undo = Undo::DeltaTime(physics.delta_time());
+                physics.set_delta_time(delta_time);
+                bus.publish(AppEvent::SettingChanged("dt"));
+                let delta_time = physics.delta_time();
+                log::info!("Physics ticks are now {}µs", delta_time.as_micros());
+                if let Some(stable) = physics.stable_delta_time().filter(|&s| s < delta_time) {
+                    log::warn!(
+                        "Ticks longer than about {}µs may make collisions blow up",
+                        stable.as_micros()
+                    );
+                }
+                undo
+            }
+            Command::SetPhysicsConfig(ref config) => {
+                let undo = Undo::PhysicsConfig(Box::new(*physics.config()));
+                set_config(physics, config, bus, log::Level::Info);
+                undo
+            }
+            Command::SpawnBody(body) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                if physics.bodies_mut().push(body).is_err() {
+                    log::warn!("Cannot spawn more than {} bodies", BodySet::capacity());
+                    return;
+                }
+                bus.publish(AppEvent::BodyAdded(physics.bodies().len() - 1));
+                undo
+            }
+            Command::RemoveBody(index) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                if index >= physics.bodies().len() {
+                    return;
+                }
+                physics.bodies_mut().swap_remove(index);
+                bus.publish(AppEvent::BodyRemoved(index));
+                undo
+            }
+            Command::RemoveBodies(selection) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                let bodies = physics.bodies_mut();
+                // In decreasing order, so that bodies moved by `swap_remove` are never selected
+                for index in selection.iter().rev() {
+                    if index < bodies.len() {
+                        bodies.swap_remove(index);
+                        bus.publish(AppEvent::BodyRemoved(index));
+                    }
+                }
+                undo
+            }
+            Command::ToggleFrozen(selection) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                let bodies = physics.bodies_mut();
+                let selected = || selection.iter().filter_map(|i| bodies.get(i));
+                let freeze = !selected().all(Body::is_frozen);
+                for index in selection.iter() {
+                    if let Some(body) = bodies.get_mut(index) {
+                        if freeze {
+                            body.flags |= Body::FROZEN;
+                        } else {
+                            body.flags &= !Body::FROZEN;
+                        }
+                    }
+                }
+                undo
+            }
+            Command::Recolor(selection, color) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                let bodies = physics.bodies_mut();
+                for index in selection.iter() {
+                    if let Some(body) = bodies.get_mut(index) {
+                        body.color = color;
+                    }
+                }
+                undo
+            }
+            Command::AddVelocity(selection, velocity) => {
+                let undo = Undo::Bodies(Box::new(*physics.bodies()));
+                let bodies = physics.bodies_mut();
+                for index in selection.iter() {
+                    if let Some(body) = bodies.get_mut(index) {
+                        body.vel += velocity.map(Scalar::from);
+                    }
+                }
+                undo
+            }
+            Command::Generate(generator, seed) => {
+                let undo = Undo::Generated {
+                    bodies: Box::new(*physics.bodies()),
+                    generator: system.generator,
+                    seed: system.seed,
+                };
+                physics.replace_bodies(generator.cluster(seed, physics.config()));
+                (system.generator, system.seed) = (generator, seed);
+                bus.publish(AppEvent::BodiesReplaced);
+                undo
+            }
+        };
+        if self.undo.len() == HISTORY_LENGTH {
+            self.undo.pop_front();
+        }
+        bus.publish(AppEvent::CommandExecuted(command.clone()));
+        self.undo.push_back((command, undo));
+    }
+}
+
+/// Replaces the config, logging at `level` and publishing a change of each setting of the
+/// console that differs.
+fn set_config(
+    physics: &mut Physics,
+    config: &PhysicsConfig,
+    bus: &mut EventBus,
+    level: log::Level,
+) {
+    let old = *physics.config();
+    physics.set_config(*config);
+    let mut changed = |name, differs, value: &dyn fmt::Display| {
+        if differs {
+            log::log!(level, "Set {name} to {value}");
+            bus.publish(AppEvent::SettingChanged(name));
+        }
+    };
+    changed("gravity", old.gravity != config.gravity, &config.gravity);
+    changed(
+        "softening",
+        old.softening != config.softening,
+        &config.softening,
+    );
+    changed("warm-up", old.warm_up != config.warm_up, &config.warm_up);
+    changed(
+        "integrator",
+        old.integrator != config.integrator,
+        &config.integrator.name(),
+    );
+    changed(
+        "boundary",
+        old.boundary != config.boundary,
+        &config.boundary.name(),
+    );
+    changed(
+        "boundary-radius",
+        old.boundary_radius != config.boundary_radius,
+        &config.boundary_radius,
+    );
+}
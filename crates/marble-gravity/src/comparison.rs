@@ -0,0 +1,216 @@
+//! A second simulation running from the same bodies with another integrator or tick duration,
+//! shown beside the first in a split screen or as tinted outlines over it, along with how far the
+//! two have drifted apart. `compare integrator rk4` or `compare dt 4` in the developer console
+//! starts it, `compare split` and `compare overlay` choose how it is shown and `compare off`
+//! stops it, see [`CommandLine`]. The second starts over from the bodies of the first whenever
+//! those are edited. Not supported on the web, where physics runs in a worker.
+//!
+//! [`CommandLine`]: crate::command_line::CommandLine
+
+use crate::{
+    events::{AppEvent, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::prelude::*;
+use physics::{BodySet, Integrator, Physics, PhysicsConfig, Scalar, ToF32};
+use std::{fmt, time::Duration};
+
+/// Of the outlines of the bodies of the second simulation
+const TINT: [f32; 4] = [1.0, 0.35, 0.8, 0.8];
+/// Real time spent catching up per frame, so that a slower second simulation falls behind
+/// rather than stalling the window
+#[cfg(not(target_arch = "wasm32"))]
+const STEP_BUDGET: Duration = Duration::from_millis(50);
+/// Ticks simulated between checks of the budget
+#[cfg(not(target_arch = "wasm32"))]
+const STEP_CHUNK: u64 = 16;
+
+/// What the second simulation does differently
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    Integrator(Integrator),
+    DeltaTime(Duration),
+}
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integrator(integrator) => write!(f, "integrator {}", integrator.name()),
+            Self::DeltaTime(delta_time) => {
+                write!(f, "dt {} ms", delta_time.as_secs_f64() * 1e3)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComparisonView {
+    /// The first on the left and the second on the right, seen from the same camera
+    Split,
+    /// The second outlined over the first
+    Overlay,
+}
+
+/// How far apart the bodies of the two simulations are
+#[derive(Clone, Copy, Debug)]
+pub struct Divergence {
+    /// The root mean square of the distances between the same bodies
+    pub rms: Scalar,
+    pub max: Scalar,
+}
+
+struct Run {
+    variant: Variant,
+    physics: Box<Physics>,
+    /// The energy of both when they started from the same bodies
+    energy: Scalar,
+}
+impl Run {
+    fn new(variant: Variant, first: &Physics) -> Self {
+        let mut physics = Box::new(*first);
+        match variant {
+            Variant::Integrator(integrator) => physics.set_config(PhysicsConfig {
+                integrator,
+                ..*physics.config()
+            }),
+            Variant::DeltaTime(delta_time) => physics.set_delta_time(delta_time),
+        }
+        Self {
+            variant,
+            energy: first.conserved().energy(),
+            physics,
+        }
+    }
+}
+
+pub struct Comparison {
+    run: Option<Run>,
+    view: ComparisonView,
+    /// Whether the first was edited since the last update
+    edited: bool,
+}
+impl Comparison {
+    pub fn new() -> Self {
+        Self {
+            run: None,
+            view: ComparisonView::Split,
+            edited: false,
+        }
+    }
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+    pub fn view(&self) -> ComparisonView {
+        self.view
+    }
+    pub fn set_view(&mut self, view: ComparisonView) {
+        self.view = view;
+    }
+    /// Starts the second simulation from the bodies of the first, replacing any running.
+    pub fn start(&mut self, variant: Variant, first: &Physics) -> Result<(), String> {
+        if cfg!(target_arch = "wasm32") {
+            return Err("Simulations cannot be compared on the web".to_owned());
+        }
+        self.run = Some(Run::new(variant, first));
+        Ok(())
+    }
+    pub fn stop(&mut self) {
+        self.run = None;
+    }
+    /// Simulates the second up to the simulated time of the first, or as far as the step
+    /// budget allows, after starting over if the first was edited.
+    pub fn update(&mut self, first: &Physics) {
+        let Some(run) = &mut self.run else {
+            return;
+        };
+        if std::mem::take(&mut self.edited)
+            || run.physics.bodies().len() != first.bodies().len()
+            || run.physics.simulated_time() > first.simulated_time()
+        {
+            *run = Run::new(run.variant, first);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let started = instant::Instant::now();
+            while let Some(behind) = first
+                .simulated_time()
+                .checked_sub(run.physics.simulated_time())
+            {
+                let ticks = behind.as_nanos() / run.physics.delta_time().as_nanos();
+                if ticks == 0 || started.elapsed() > STEP_BUDGET {
+                    break;
+                }
+                run.physics.advance_ticks(STEP_CHUNK.min(ticks as u64));
+            }
+        }
+    }
+    /// The bodies of the second simulation, for the right half of a split screen.
+    pub fn bodies(&self) -> Option<&BodySet> {
+        self.run.as_ref().map(|run| run.physics.bodies())
+    }
+    pub fn divergence(&self, first: &Physics) -> Option<Divergence> {
+        let run = self.run.as_ref()?;
+        let distances = first
+            .bodies()
+            .iter()
+            .zip(run.physics.bodies().iter())
+            .map(|(a, b)| (a.pos - b.pos).magnitude());
+        let (sum2, max) = distances.fold((0.0, 0.0), |(sum2, max): (Scalar, Scalar), d| {
+            (sum2 + d * d, max.max(d))
+        });
+        let count = first.bodies().len().max(1) as Scalar;
+        Some(Divergence {
+            rms: (sum2 / count).sqrt(),
+            max,
+        })
+    }
+}
+impl Subscriber for Comparison {
+    fn notify(&mut self, event: &AppEvent) {
+        // The second starts over from edits, since they cannot be applied to it alike
+        if let AppEvent::CommandExecuted(_) | AppEvent::ScenarioLoaded(_) = event {
+            self.edited = true;
+        }
+    }
+    /// Describes the comparison and its divergence in the overlay, and outlines the bodies of
+    /// the second when overlaid.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (first, world_to_camera) = (frame.physics, frame.world_to_camera());
+        let (Some(run), Some(divergence)) = (&self.run, self.divergence(first)) else {
+            return;
+        };
+        let shown = match self.view {
+            ComparisonView::Split => "on the right",
+            ComparisonView::Overlay => "outlined",
+        };
+        graphics.overlay_line(format_args!(
+            "Comparing with {} {shown}: {:.3e} apart on average, {:.3e} at most",
+            run.variant, divergence.rms, divergence.max
+        ));
+        let drift = |physics: &Physics| {
+            if run.energy == 0.0 {
+                0.0
+            } else {
+                100.0 * (physics.conserved().energy() - run.energy) / run.energy.abs()
+            }
+        };
+        let behind = first
+            .simulated_time()
+            .saturating_sub(run.physics.simulated_time());
+        graphics.overlay_line(format_args!(
+            "energy drift {:+.4}% here, {:+.4}% there{}",
+            drift(first),
+            drift(&run.physics),
+            if behind > run.physics.delta_time() {
+                format!(", {:.2} s behind", behind.as_secs_f32())
+            } else {
+                String::new()
+            }
+        ));
+        if self.view == ComparisonView::Overlay {
+            for body in run.physics.bodies().iter() {
+                let view_center = (world_to_camera * body.pos.to_f32().extend(1.0)).truncate();
+                graphics.overlay_sphere(view_center, body.radius.to_f32(), TINT);
+            }
+        }
+    }
+}
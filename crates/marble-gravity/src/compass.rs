@@ -0,0 +1,62 @@
+//! A gnomon in a corner of the window: the world axes and the direction of the sun as the camera
+//! sees them, for finding the way back to familiar viewpoints. Axes pointing away from the camera
+//! are drawn fainter. Its placement and whether it is shown are in [`OverlayLayout`].
+//!
+//! [`OverlayLayout`]: crate::overlay::OverlayLayout
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+
+/// Length of the axes in pixels
+const RADIUS: f32 = 28.0;
+/// Distance of the axis names beyond the ends of the axes, in pixels
+const NAME_OFFSET: f32 = 10.0;
+/// Half the height of a label in pixels, by which it is lowered to center it on its point
+const NAME_HALF_HEIGHT: f32 = 8.0;
+const RIM_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const SUN_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const SUN_RADIUS: f32 = 4.0;
+/// Opacity of what points away from the camera, relative to what points toward it
+const BEHIND_ALPHA: f32 = 0.4;
+
+/// The gnomon of the world axes and the sun
+pub struct Gnomon;
+impl Subscriber for Gnomon {
+    /// Draws the gnomon for the next frame only, unless it is hidden.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let pose = &frame.pose;
+        let widget = graphics.overlay_layout().compass;
+        if !widget.visible {
+            return;
+        }
+        let (x, y) = widget.top_left((2.0 * RADIUS, 2.0 * RADIUS), graphics.window_size());
+        let center = (x + RADIUS, y + RADIUS);
+        // Where a world direction points on screen, in pixels from the center per unit length, and
+        // the opacity of what points there
+        let on_screen = |direction: Vector3<f32>| {
+            let view = pose.rotation.conjugate().rotate_vector(direction);
+            let alpha = if view.z > 0.0 { BEHIND_ALPHA } else { 1.0 };
+            ((view.x, view.y), alpha)
+        };
+        let at =
+            |(dx, dy): (f32, f32), length: f32| (center.0 + length * dx, center.1 + length * dy);
+        graphics.overlay_circle(center, RADIUS, RIM_COLOR);
+        let axes = [
+            (Vector3::unit_x(), "x", [1.0, 0.35, 0.35]),
+            (Vector3::unit_y(), "y", [0.4, 0.9, 0.4]),
+            (Vector3::unit_z(), "z", [0.45, 0.6, 1.0]),
+        ];
+        for (axis, name, [r, g, b]) in axes {
+            let (direction, alpha) = on_screen(axis);
+            graphics.overlay_segment(center, at(direction, RADIUS), [r, g, b, alpha]);
+            let (x, y) = at(direction, RADIUS + NAME_OFFSET);
+            graphics.label((x, y + NAME_HALF_HEIGHT), alpha, format_args!("{name}"));
+        }
+        let (direction, alpha) = on_screen(graphics.sun().direction());
+        let [r, g, b, a] = SUN_COLOR;
+        graphics.overlay_circle(at(direction, RADIUS), SUN_RADIUS, [r, g, b, a * alpha]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Energy and momentum in the overlay, along with how far they have drifted since the bodies were
+//! last edited, which shows the error of the integrator and the energy lost in collisions.
+
+use crate::{
+    events::{AppEvent, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::prelude::*;
+use physics::{Conserved, Scalar};
+
+pub struct ConservationHud {
+    visible: bool,
+    /// What drift is measured from, and the tick it was taken at
+    baseline: Option<(u64, Conserved)>,
+}
+impl ConservationHud {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            baseline: None,
+        }
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.baseline = None;
+    }
+}
+impl Subscriber for ConservationHud {
+    fn notify(&mut self, event: &AppEvent) {
+        // Edits change what is conserved, so drift is measured from after them
+        if let AppEvent::CommandExecuted(_) | AppEvent::ScenarioLoaded(_) = event {
+            self.baseline = None;
+        }
+    }
+    /// Lists the quantities in the overlay, recomputed every frame while shown.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let physics = frame.physics;
+        if !self.visible {
+            return;
+        }
+        let now = physics.conserved();
+        let &mut (tick, baseline) = self.baseline.get_or_insert((physics.ticks(), now));
+        let drift = |now: Scalar, then: Scalar| {
+            if then == 0.0 {
+                0.0
+            } else {
+                100.0 * (now - then) / then.abs()
+            }
+        };
+        graphics.overlay_line(format_args!(
+            "energy {:.4e} (kinetic {:.4e}, potential {:.4e}), {:+.3}% since tick {tick}",
+            now.energy(),
+            now.kinetic,
+            now.potential,
+            drift(now.energy(), baseline.energy())
+        ));
+        let angular = baseline.angular_momentum.magnitude();
+        graphics.overlay_line(format_args!(
+            "momentum {:.3e}, angular momentum {:.4e}, {:.3}% off",
+            now.momentum.magnitude(),
+            now.angular_momentum.magnitude(),
+            drift(
+                angular + (now.angular_momentum - baseline.angular_momentum).magnitude(),
+                angular
+            )
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+//! Logging grouped by subsystem, and an in-app console showing recent warnings and errors, so
+//! that problems can be seen without a terminal or the developer tools of the browser.
+//!
+//! Natively, `MARBLE_GRAVITY_LOG` sets the level per subsystem on top of `RUST_LOG`, such as
+//! `MARBLE_GRAVITY_LOG=graphics=debug,tree=off`.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use log::{Level, Log, Metadata, Record};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Warnings and errors kept for the console
+const RECENT_CAPACITY: usize = 100;
+/// Entries shown at once, newest last
+const CONSOLE_LINES: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Physics,
+    Graphics,
+    Tree,
+    Input,
+    Worker,
+    /// Everything else
+    App,
+}
+impl Subsystem {
+    const ALL: [Self; 6] = [
+        Self::Physics,
+        Self::Graphics,
+        Self::Tree,
+        Self::Input,
+        Self::Worker,
+        Self::App,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Physics => "physics",
+            Self::Graphics => "graphics",
+            Self::Tree => "tree",
+            Self::Input => "input",
+            Self::Worker => "worker",
+            Self::App => "app",
+        }
+    }
+    /// The log targets of the subsystem, which include their submodules
+    fn modules(self) -> &'static [&'static str] {
+        match self {
+            Self::Physics => &["physics"],
+            Self::Graphics => &[
+                "marble_gravity::contrast",
+                "marble_gravity::diagnose",
+                "marble_gravity::graphics",
+                "marble_gravity::images",
+                "marble_gravity::overlay",
+                "marble_gravity::panels",
+                "marble_gravity::picking",
+                "marble_gravity::rendergraph",
+                "marble_gravity::text",
+                "naga",
+                "wgpu",
+                "wgpu_core",
+                "wgpu_hal",
+            ],
+            Self::Tree => &["spheretree"],
+            Self::Input => &[
+                "marble_gravity::clipboard",
+                "marble_gravity::commands",
+                "marble_gravity::measure",
+                "marble_gravity::run",
+                "marble_gravity::selection",
+            ],
+            Self::Worker => &["worker"],
+            Self::App => &[],
+        }
+    }
+    fn of(target: &str) -> Self {
+        let in_module = |module: &&str| {
+            target
+                .strip_prefix(module)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+        Self::ALL
+            .into_iter()
+            .find(|subsystem| subsystem.modules().iter().any(in_module))
+            .unwrap_or(Self::App)
+    }
+}
+
+struct Entry {
+    level: Level,
+    subsystem: Subsystem,
+    text: String,
+}
+
+static RECENT: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+/// Warnings and errors logged since the console was last shown
+static UNSEEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Forwards to the platform logger, keeping warnings and errors for the console.
+struct ConsoleLogger<L> {
+    inner: L,
+}
+impl<L: Log> Log for ConsoleLogger<L> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+    fn log(&self, record: &Record<'_>) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+        if record.level() > Level::Warn {
+            return;
+        }
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(Entry {
+            level: record.level(),
+            subsystem: Subsystem::of(record.target()),
+            text: record.args().to_string(),
+        });
+        UNSEEN.fetch_add(1, Ordering::Relaxed);
+    }
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the logger, which must happen before anything is logged.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_logging() {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    let mut invalid = None;
+    if let Ok(filters) = std::env::var("MARBLE_GRAVITY_LOG") {
+        for filter in filters.split(',').filter(|filter| !filter.is_empty()) {
+            let parsed = filter.split_once('=').and_then(|(name, level)| {
+                let subsystem = Subsystem::ALL
+                    .into_iter()
+                    .find(|subsystem| subsystem.name() == name)?;
+                Some((subsystem, level.parse().ok()?))
+            });
+            match parsed {
+                Some((subsystem, level)) => {
+                    for module in subsystem.modules() {
+                        builder.filter_module(module, level);
+                    }
+                }
+                None => invalid = Some(filter.to_owned()),
+            }
+        }
+    }
+    let inner = builder.build();
+    log::set_max_level(inner.filter());
+    log::set_boxed_logger(Box::new(ConsoleLogger { inner })).unwrap();
+    if let Some(filter) = invalid {
+        log::warn!("Ignoring the invalid MARBLE_GRAVITY_LOG filter {filter:?}");
+    }
+}
+
+/// Installs the logger, which must happen before anything is logged.
+#[cfg(target_arch = "wasm32")]
+pub fn init_logging() {
+    struct BrowserConsole;
+    impl Log for BrowserConsole {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.level() <= Level::Info
+        }
+        fn log(&self, record: &Record<'_>) {
+            console_log::log(record);
+        }
+        fn flush(&self) {}
+    }
+    log::set_max_level(log::LevelFilter::Info);
+    log::set_boxed_logger(Box::new(ConsoleLogger {
+        inner: BrowserConsole,
+    }))
+    .unwrap();
+}
+
+/// Shows recent warnings and errors in the overlay, from all subsystems or from one.
+pub struct LogConsole {
+    visible: bool,
+    filter: Option<Subsystem>,
+}
+impl LogConsole {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            filter: None,
+        }
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    /// Shows the next subsystem only, or all of them after the last one.
+    pub fn next_filter(&mut self) {
+        self.filter = match self.filter {
+            None => Some(Subsystem::ALL[0]),
+            Some(subsystem) => {
+                let index = Subsystem::ALL.iter().position(|&s| s == subsystem).unwrap();
+                Subsystem::ALL.get(index + 1).copied()
+            }
+        };
+    }
+}
+impl Subscriber for LogConsole {
+    /// Queues the console, or a reminder of unseen entries while hidden.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if !self.visible {
+            let unseen = UNSEEN.load(Ordering::Relaxed);
+            if unseen > 0 {
+                graphics.overlay_line(format_args!(
+                    "{unseen} new warnings or errors, F2 shows the log"
+                ));
+            }
+            return;
+        }
+        UNSEEN.store(0, Ordering::Relaxed);
+        graphics.overlay_line(format_args!(
+            "Log of {}, F3 filters by subsystem",
+            self.filter.map_or("all subsystems", Subsystem::name)
+        ));
+        let recent = RECENT.lock().unwrap();
+        let shown: Vec<&Entry> = recent
+            .iter()
+            .rev()
+            .filter(|entry| self.filter.map_or(true, |filter| entry.subsystem == filter))
+            .take(CONSOLE_LINES)
+            .collect();
+        if shown.is_empty() {
+            graphics.overlay_line(format_args!("No warnings or errors"));
+        }
+        for entry in shown.into_iter().rev() {
+            graphics.overlay_line(format_args!(
+                "[{} {}] {}",
+                entry.level,
+                entry.subsystem.name(),
+                entry.text
+            ));
+        }
+    }
+}
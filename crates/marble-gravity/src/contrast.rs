@@ -0,0 +1,121 @@
+use cgmath::{Matrix4, Vector3, Vector4};
+
+/// Texels along each side of a downsampled skybox face
+const LUMINANCE_RESOLUTION: u32 = 8;
+/// A light theme switches to dark above the upper luminance, and back below the lower one
+const LUMINANCE_HYSTERESIS: (f32, f32) = (0.4, 0.6);
+
+/// A low resolution copy of the skybox brightness, to estimate what is behind the overlay without
+/// reading back the rendered frame. Bodies in front of the skybox are not accounted for.
+pub struct SkyboxLuminance {
+    /// Faces in the order +x, -x, +y, -y, +z, -z, like the skybox cube texture
+    faces: [[f32; (LUMINANCE_RESOLUTION * LUMINANCE_RESOLUTION) as usize]; 6],
+}
+impl SkyboxLuminance {
+    pub fn new(images: &[image::RgbaImage; 6]) -> Self {
+        let faces = images.each_ref().map(|image| {
+            let (width, height) = image.dimensions();
+            let mut face = [0.0; (LUMINANCE_RESOLUTION * LUMINANCE_RESOLUTION) as usize];
+            let mut counts = [0u32; (LUMINANCE_RESOLUTION * LUMINANCE_RESOLUTION) as usize];
+            for (x, y, pixel) in image.enumerate_pixels() {
+                let [r, g, b, _] = pixel.0.map(|c| c as f32 / 255.0);
+                let cell = (y * LUMINANCE_RESOLUTION / height) * LUMINANCE_RESOLUTION
+                    + x * LUMINANCE_RESOLUTION / width;
+                face[cell as usize] += 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                counts[cell as usize] += 1;
+            }
+            for (luminance, count) in face.iter_mut().zip(counts) {
+                *luminance /= count.max(1) as f32;
+            }
+            face
+        });
+        Self { faces }
+    }
+
+    /// The perceived brightness from 0 to 1 of the skybox in a world space direction.
+    pub fn sample(&self, direction: Vector3<f32>) -> f32 {
+        let Vector3 { x, y, z } = direction;
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        // Face selection and orientation as specified for cube textures
+        let (face, s, t, major) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (0, -z, -y, ax)
+            } else {
+                (1, z, -y, ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (2, x, z, ay)
+            } else {
+                (3, x, -z, ay)
+            }
+        } else if z > 0.0 {
+            (4, x, -y, az)
+        } else {
+            (5, -x, -y, az)
+        };
+        let texel = |c: f32| {
+            let c = 0.5 * (c / major + 1.0) * LUMINANCE_RESOLUTION as f32;
+            (c as u32).min(LUMINANCE_RESOLUTION - 1)
+        };
+        self.faces[face][(texel(t) * LUMINANCE_RESOLUTION + texel(s)) as usize]
+    }
+
+    /// The average brightness behind a rectangle of the window, in pixels, using the same
+    /// primary rays as the renderer.
+    pub fn behind(
+        &self,
+        (min, max): ((f32, f32), (f32, f32)),
+        window_size: (u32, u32),
+        view_to_world: Matrix4<f32>,
+    ) -> f32 {
+        let height = window_size.1 as f32;
+        let mid = (0.5 * window_size.0 as f32 / height, 0.5);
+        let mut sum = 0.0;
+        const SAMPLES: usize = 3;
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let fx = (i as f32 + 0.5) / SAMPLES as f32;
+                let fy = (j as f32 + 0.5) / SAMPLES as f32;
+                let px = min.0 + fx * (max.0 - min.0);
+                let py = min.1 + fy * (max.1 - min.1);
+                let ray = Vector4::new(px / height - mid.0, py / height - mid.1, 1.0, 0.0);
+                sum += self.sample((view_to_world * ray).truncate());
+            }
+        }
+        sum / (SAMPLES * SAMPLES) as f32
+    }
+}
+
+/// Overlay colors chosen to contrast with the background
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// Light text on a dark panel, over dark backgrounds
+    Light,
+    /// Dark text on a light panel, over bright backgrounds
+    Dark,
+}
+impl Theme {
+    /// The theme for a background of the given brightness, staying put near the threshold.
+    pub fn adapt(self, luminance: f32) -> Self {
+        let (lower, upper) = LUMINANCE_HYSTERESIS;
+        match self {
+            Self::Light if luminance > upper => Self::Dark,
+            Self::Dark if luminance < lower => Self::Light,
+            theme => theme,
+        }
+    }
+    pub fn text_color(self) -> [f32; 4] {
+        match self {
+            Self::Light => [0.85, 0.85, 0.85, 1.0],
+            Self::Dark => [0.1, 0.1, 0.1, 1.0],
+        }
+    }
+    /// A translucent backing panel, subtle enough to leave the scene visible
+    pub fn panel_color(self) -> [f32; 4] {
+        match self {
+            Self::Light => [0.0, 0.0, 0.0, 0.35],
+            Self::Dark => [1.0, 1.0, 1.0, 0.35],
+        }
+    }
+}
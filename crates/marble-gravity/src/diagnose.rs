@@ -0,0 +1,109 @@
+//! `--diagnose`, which prints a report for bug reports about performance or compatibility
+//! instead of starting the event loop: what the GPU supports, and how fast physics and each
+//! backend and number of ray splits run on it. Not supported on the web.
+
+use crate::graphics::{Backend, Graphics, View, MAX_RAY_SPLITS};
+use camera::Pose;
+use instant::Instant;
+use physics::Physics;
+use spheretree::SphereTreeBuilder;
+use std::fmt::Write;
+
+/// Physics ticks measured, after as many ticks of warm-up
+const PHYSICS_TICKS: u64 = 500;
+/// Frames rendered per configuration, after one frame of warm-up
+const FRAMES: u32 = 10;
+
+/// What the surface supports, which must be queried before it is handed to [`Graphics`]
+pub struct SurfaceSupport {
+    pub formats: Vec<wgpu::TextureFormat>,
+    pub present_modes: Vec<wgpu::PresentMode>,
+}
+
+pub fn is_requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--diagnose")
+}
+
+/// Measures everything and prints the report to stdout.
+pub fn run(adapter: &wgpu::Adapter, surface: &SurfaceSupport, graphics: &mut Graphics) {
+    let mut report = String::new();
+    let out = &mut report;
+    writeln!(out, "Marble Gravity {}", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(
+        out,
+        "Platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+    .unwrap();
+    writeln!(out, "Adapter: {:?}", adapter.get_info()).unwrap();
+    writeln!(out, "Features: {:?}", adapter.features()).unwrap();
+    writeln!(
+        out,
+        "Downlevel flags: {:?}",
+        adapter.get_downlevel_capabilities().flags
+    )
+    .unwrap();
+    let limits = adapter.limits();
+    writeln!(
+        out,
+        "Limits: 2D textures {}px, storage buffers {}B, uniform buffers {}B",
+        limits.max_texture_dimension_2d,
+        limits.max_storage_buffer_binding_size,
+        limits.max_uniform_buffer_binding_size
+    )
+    .unwrap();
+    writeln!(out, "Surface formats: {:?}", surface.formats).unwrap();
+    writeln!(out, "Present modes: {:?}", surface.present_modes).unwrap();
+
+    let mut physics = Physics::initial();
+    physics.advance_ticks(PHYSICS_TICKS);
+    let result = physics.advance_ticks(PHYSICS_TICKS);
+    writeln!(
+        out,
+        "Physics: {:.0} ticks/s with {} bodies, {:.1}x real time",
+        PHYSICS_TICKS as f64 / result.elapsed_real.as_secs_f64(),
+        physics.bodies().len(),
+        (physics.delta_time() * PHYSICS_TICKS as u32).as_secs_f64()
+            / result.elapsed_real.as_secs_f64()
+    )
+    .unwrap();
+
+    let size = graphics.window_size();
+    writeln!(
+        out,
+        "Render times at {}x{}, including readback:",
+        size.0, size.1
+    )
+    .unwrap();
+    let mut sphere_tree = SphereTreeBuilder::new();
+    let pose = Pose::default();
+    let view = View {
+        bodies: sphere_tree.build(physics.bodies(), &physics.config().groups, &pose),
+        pose,
+    };
+    let initial_settings = graphics.settings();
+    for backend in [Backend::Raytraced, Backend::Rasterized, Backend::Hybrid] {
+        graphics.set_backend(backend);
+        for ray_splits in 0..=MAX_RAY_SPLITS {
+            graphics.set_ray_splits(ray_splits);
+            graphics.capture(&view, size);
+            let before = Instant::now();
+            for _ in 0..FRAMES {
+                graphics.capture(&view, size);
+            }
+            let frame = Instant::now().duration_since(before) / FRAMES;
+            writeln!(
+                out,
+                "\t{backend:?} with {ray_splits} ray splits: {:.2}ms",
+                frame.as_secs_f64() * 1000.0
+            )
+            .unwrap();
+            if backend == Backend::Rasterized {
+                break; // Rasterized meshes ignore ray splits
+            }
+        }
+    }
+    graphics.apply_settings(initial_settings);
+    print!("{report}");
+}
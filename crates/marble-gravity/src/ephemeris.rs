@@ -0,0 +1,76 @@
+//! The real Sun and planets, imported from bundled ephemerides and listed in the gallery at each
+//! [`Scale`], with their actual distances and orbital periods under the default gravitational
+//! constant. The ephemerides are JSON, with masses in kilograms, radii in kilometers, positions
+//! in astronomical units and velocities in astronomical units per day:
+//!
+//! ```text
+//! {"bodies": [{"name": "Sun", "mass": 1.98847e30, "radius": 695700, "color": "fdb813",
+//!              "position": [0, 0, 0], "velocity": [0, 0, 0]}, ...]}
+//! ```
+
+use crate::json::{self, Value};
+use physics::{Ephemeris, PhysicsConfig, Scale};
+
+const SOLAR_SYSTEM: &str = include_str!("../assets/ephemerides/solar-system.json");
+
+/// The name and the scenario text of the solar system at each scale.
+pub fn solar_system() -> Vec<(String, String)> {
+    let ephemerides = match parse(SOLAR_SYSTEM) {
+        Ok(ephemerides) => ephemerides,
+        Err(err) => {
+            log::error!("Failed to read the bundled ephemerides: {err}");
+            return Vec::new();
+        }
+    };
+    let gravity = PhysicsConfig::default().gravity;
+    Scale::ALL
+        .into_iter()
+        .map(|scale| {
+            let scenario = scale.scenario(&ephemerides, gravity);
+            let mut text = physics::write_settings(&scenario.settings);
+            text.push_str(&physics::write_scenario(scenario.bodies.iter()));
+            let name = match scale {
+                Scale::Inner => "Inner planets, 10 days a second",
+                Scale::Whole => "Solar system, a year a second",
+            };
+            (name.to_owned(), text)
+        })
+        .collect()
+}
+
+fn parse(text: &str) -> Result<Vec<Ephemeris>, String> {
+    let document = json::parse(text).ok_or("expected JSON")?;
+    let bodies = document
+        .get("bodies")
+        .and_then(Value::as_array)
+        .ok_or("expected an array of bodies")?;
+    bodies
+        .iter()
+        .enumerate()
+        .map(|(index, body)| parse_body(body).ok_or_else(|| format!("body {index} is invalid")))
+        .collect()
+}
+
+fn parse_body(json: &Value) -> Option<Ephemeris> {
+    let vector = |name| -> Option<[f64; 3]> {
+        match json.get(name)?.as_array()? {
+            [x, y, z] => Some([x.as_f64()?, y.as_f64()?, z.as_f64()?]),
+            _ => None,
+        }
+    };
+    let ephemeris = Ephemeris {
+        name: json.get("name")?.as_str()?.to_owned(),
+        mass: json.get("mass")?.as_f64()?,
+        radius: json.get("radius")?.as_f64()?,
+        color: u32::from_str_radix(json.get("color")?.as_str()?, 16)
+            .ok()
+            .filter(|&color| color <= 0xff_ffff)?,
+        position: vector("position")?,
+        velocity: vector("velocity")?,
+    };
+    let valid = ephemeris.mass > 0.0
+        && ephemeris.radius > 0.0
+        && ephemeris.position.iter().all(|x| x.is_finite())
+        && ephemeris.velocity.iter().all(|x| x.is_finite());
+    valid.then_some(ephemeris)
+}
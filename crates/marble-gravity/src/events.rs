@@ -0,0 +1,86 @@
+use crate::{commands::Command, graphics::Graphics};
+use camera::Pose;
+use cgmath::Matrix4;
+use instant::Instant;
+use physics::{Physics, PhysicsResult, ScenarioSettings};
+use std::collections::VecDeque;
+
+/// Something that happened in one subsystem which others may react to.
+#[derive(Clone, Debug)]
+pub enum AppEvent {
+    TicksCompleted(PhysicsResult),
+    /// Two bodies were found touching after not doing so, by their indices with the lower first,
+    /// approaching each other at `speed`. Touches are looked for after each batch of ticks, so
+    /// ones that begin and end within a batch go unnoticed.
+    Collision {
+        bodies: (usize, usize),
+        speed: f32,
+    },
+    /// A body was added, at this index
+    BodyAdded(usize),
+    /// The body at this index was removed, and the last body moved into its place
+    BodyRemoved(usize),
+    /// The bodies were replaced all at once, as by generating a cluster or undoing an edit
+    BodiesReplaced,
+    /// A setting changed, by its name in the console
+    SettingChanged(&'static str),
+    WindowResized((u32, u32)),
+    CameraModeChanged(&'static str),
+    /// A command was executed or redone, but not undone
+    CommandExecuted(Command),
+    /// A scenario replaced the bodies, recommending how to view it
+    ScenarioLoaded(ScenarioSettings),
+}
+
+/// What subscribers draw each frame from, see [`Subscriber::queue`].
+pub struct Frame<'a> {
+    pub now: Instant,
+    pub physics: &'a Physics,
+    pub pose: Pose,
+    /// In physical pixels
+    pub cursor: (f32, f32),
+    /// The body the camera is centered on, if any
+    pub camera_target: Option<usize>,
+}
+impl Frame<'_> {
+    pub fn world_to_camera(&self) -> Matrix4<f32> {
+        self.pose.world_to_camera()
+    }
+}
+
+pub trait Subscriber {
+    fn notify(&mut self, _event: &AppEvent) {}
+    /// Queues whatever the subscriber draws over the next frame.
+    fn queue(&mut self, _graphics: &mut Graphics, _frame: &Frame<'_>) {}
+}
+
+/// Events are queued by producers and delivered to every subscriber once the current winit event
+/// has been handled, so producers need no access to subscribers.
+pub struct EventBus {
+    pending: VecDeque<AppEvent>,
+}
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            pending: VecDeque::new(),
+        }
+    }
+    pub fn publish(&mut self, event: AppEvent) {
+        self.pending.push_back(event);
+    }
+    /// Delivers queued events in order.
+    pub fn dispatch(&mut self, subscribers: &mut [&mut dyn Subscriber]) {
+        while let Some(event) = self.pending.pop_front() {
+            for subscriber in subscribers.iter_mut() {
+                subscriber.notify(&event);
+            }
+        }
+    }
+}
+
+/// Has every subscriber queue what it draws, in order, so that later ones are drawn on top.
+pub fn queue(subscribers: &mut [&mut dyn Subscriber], graphics: &mut Graphics, frame: &Frame<'_>) {
+    for subscriber in subscribers {
+        subscriber.queue(graphics, frame);
+    }
+}
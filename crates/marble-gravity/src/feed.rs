@@ -0,0 +1,235 @@
+//! `--feed -` or `MARBLE_GRAVITY_FEED=-` shows the bodies of another simulation instead of
+//! simulating them, read from standard input as one JSON frame per line, so that the renderer,
+//! the camera, materials and `--record` serve other N-body codes. `--feed 9100` takes frames
+//! from UDP datagrams on that port of localhost instead, or on an address such as `0.0.0.0:9100`
+//! to take them from other machines. A frame lists its bodies, of which only the positions are
+//! required:
+//!
+//! ```text
+//! {"bodies": [{"position": [0, 0.5, 0], "radius": 0.03, "color": 4286595072}]}
+//! ```
+//!
+//! Bodies may also have a `velocity`, which labels and measurements show, a `material` such as
+//! `"glass"`, and `"striped": true`. Colors are `0xrrggbbaa` as numbers, where `aa` is the
+//! transparency. The bodies of `GET /state` from `--remote` are read the same way, and arrays
+//! of bodies on their own are frames too.
+//!
+//! Standard input is taken one frame per rendered frame, so that a writer to the pipe is shown
+//! at the pace of the display and waits while the view is paused, whereas of the datagrams
+//! arriving in a frame only the latest is shown. Each frame is taken as a tick lasting as long
+//! as the rendered frame, see [`Physics::take_tick`], and edits by commands last until the next
+//! one. `WebSocket` connections are not supported, and neither is the web.
+
+use crate::{
+    json::{self, Value},
+    udp::{self, MAX_PACKET},
+};
+use cgmath::{prelude::*, Quaternion, Vector3};
+use instant::Instant;
+use physics::{Body, BodySet, Material, Physics, PhysicsResult, Scalar, BODIES};
+use std::{
+    io::{self, BufRead},
+    net::UdpSocket,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+/// Of bodies without a radius
+const DEFAULT_RADIUS: Scalar = 0.02;
+/// Of bodies without a color, an opaque light gray
+const DEFAULT_COLOR: u32 = 0xc0c0_c000;
+
+enum Source {
+    Off,
+    Stdin(Receiver<BodySet>),
+    Udp {
+        socket: UdpSocket,
+        packet: Vec<u8>,
+    },
+    /// Standard input was closed, leaving the last frame in place
+    Ended,
+}
+
+pub struct Feed {
+    source: Source,
+    /// When the last frame was taken
+    last_frame: Option<Instant>,
+}
+impl Feed {
+    /// Starts reading from the source requested on the command line, if any.
+    pub fn requested() -> Self {
+        let mut feed = Self {
+            source: Source::Off,
+            last_frame: None,
+        };
+        let Some((name, value)) = crate::requested("feed") else {
+            return feed;
+        };
+        let value = value.trim();
+        if value == "-" {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            let spawned = thread::Builder::new()
+                .name("feed".to_owned())
+                .spawn(move || {
+                    for (number, line) in io::stdin().lock().lines().enumerate() {
+                        let line = match line {
+                            Ok(line) if line.trim().is_empty() => continue,
+                            Ok(line) => line,
+                            Err(err) => {
+                                log::warn!("Stopped reading the feed: {err}");
+                                return;
+                            }
+                        };
+                        match parse_frame(&line) {
+                            Ok(bodies) => {
+                                if sender.send(bodies).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(err) => {
+                                log::warn!("Ignoring line {} of the feed: {err}", number + 1);
+                            }
+                        }
+                    }
+                });
+            match spawned {
+                Ok(_) => {
+                    log::info!("Showing bodies from standard input");
+                    feed.source = Source::Stdin(receiver);
+                }
+                Err(err) => log::error!("Failed to start reading the feed: {err}"),
+            }
+            return feed;
+        }
+        if let Some(socket) = udp::listen(&name, value, "the feed") {
+            feed.source = Source::Udp {
+                socket,
+                packet: vec![0; MAX_PACKET],
+            };
+        }
+        feed
+    }
+    /// Whether bodies come from the feed rather than from the simulation.
+    pub fn is_on(&self) -> bool {
+        !matches!(self.source, Source::Off)
+    }
+    /// Takes the next frame as a tick, if one has arrived.
+    pub fn advance(&mut self, now: Instant, physics: &mut Physics) -> PhysicsResult {
+        let Some(bodies) = self.next_frame() else {
+            return PhysicsResult {
+                elapsed_real: Duration::ZERO,
+                elapsed_physics_ticks: 0,
+                checkpoint: None,
+            };
+        };
+        if let Some(last_frame) = self.last_frame {
+            physics.set_delta_time(now - last_frame);
+        }
+        self.last_frame = Some(now);
+        physics.take_tick(bodies)
+    }
+    fn next_frame(&mut self) -> Option<BodySet> {
+        match &mut self.source {
+            Source::Off | Source::Ended => None,
+            Source::Stdin(frames) => match frames.try_recv() {
+                Ok(bodies) => Some(bodies),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => {
+                    log::info!("The feed has ended");
+                    self.source = Source::Ended;
+                    None
+                }
+            },
+            Source::Udp { socket, packet } => {
+                let mut latest = None;
+                loop {
+                    match socket.recv(packet) {
+                        Ok(length) => {
+                            let Ok(text) = std::str::from_utf8(&packet[..length]) else {
+                                log::warn!("Ignoring a feed datagram that is not UTF-8");
+                                continue;
+                            };
+                            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                                match parse_frame(line) {
+                                    Ok(bodies) => latest = Some(bodies),
+                                    Err(err) => log::warn!("Ignoring a frame of the feed: {err}"),
+                                }
+                            }
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            log::warn!("Failed to receive the feed: {err}");
+                            break;
+                        }
+                    }
+                }
+                latest
+            }
+        }
+    }
+}
+
+fn parse_frame(line: &str) -> Result<BodySet, String> {
+    let frame = json::parse(line).ok_or("expected JSON")?;
+    let bodies = frame
+        .get("bodies")
+        .unwrap_or(&frame)
+        .as_array()
+        .ok_or("expected an array of bodies")?;
+    if bodies.len() > BODIES {
+        return Err(format!("more than {BODIES} bodies"));
+    }
+    bodies
+        .iter()
+        .enumerate()
+        .map(|(index, body)| parse_body(body).ok_or_else(|| format!("body {index} is invalid")))
+        .collect()
+}
+
+fn parse_body(json: &Value) -> Option<Body> {
+    let radius = match json.get("radius") {
+        Some(radius) => radius.as_f64()? as Scalar,
+        None => DEFAULT_RADIUS,
+    };
+    let color = match json.get("color") {
+        Some(color) => u32::try_from(color.as_f64()? as i64).ok()?,
+        None => DEFAULT_COLOR,
+    };
+    let mut body = Body {
+        pos: vector(json.get("position")?)?,
+        vel: match json.get("velocity") {
+            Some(velocity) => vector(velocity)?,
+            None => Vector3::zero(),
+        },
+        radius,
+        density: 1.0,
+        color,
+        flags: 0,
+        spin: Vector3::zero(),
+        orientation: Quaternion::one(),
+    };
+    if let Some(material) = json.get("material") {
+        let name = material.as_str()?;
+        let material = Material::ALL.into_iter().find(|m| m.name() == name)?;
+        body.set_material(Some(material));
+    }
+    if let Some(striped) = json.get("striped") {
+        if striped.as_bool()? {
+            body.flags |= Body::STRIPED;
+        }
+    }
+    let valid = body.pos.is_finite() && body.vel.is_finite() && radius > 0.0;
+    valid.then_some(body)
+}
+
+fn vector(value: &Value) -> Option<Vector3<Scalar>> {
+    let [x, y, z] = value.as_array()? else {
+        return None;
+    };
+    Some(Vector3::new(
+        x.as_f64()? as Scalar,
+        y.as_f64()? as Scalar,
+        z.as_f64()? as Scalar,
+    ))
+}
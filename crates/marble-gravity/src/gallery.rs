@@ -0,0 +1,504 @@
+//! A gallery of scenarios to start from, shown on startup: the arrow keys choose, Enter loads
+//! the chosen scenario and Escape keeps the current bodies. Natively, each scenario has a
+//! thumbnail, which is rendered on first use and cached as a PNG file in the temporary
+//! directory. The web shows names only, since captures cannot be read back there. The built-in
+//! scenarios end with the real solar system, see [`crate::ephemeris`].
+//!
+//! Natively, `*.scenario` files in [`USER_SCENARIOS`] are listed after the built-in ones, with
+//! a PNG file of the same name as their thumbnail if there is one. The directory is scanned
+//! again every [`RESCAN_INTERVAL`] while the gallery is open, and scenario files dropped onto
+//! the window are copied into it and loaded. On the web, scenario files dropped onto the canvas
+//! are listed last instead, and loaded.
+
+use crate::{
+    events::{AppEvent, EventBus, Frame, Subscriber},
+    graphics::{Graphics, View},
+    images::{IMAGE_SIZE, IMAGE_SLOTS},
+    toasts::Toasts,
+};
+use camera::Pose;
+use cgmath::{prelude::*, Vector3};
+use instant::Instant;
+use physics::{
+    parse_scenario, Body, Physics, Scenario, ScenarioCamera, ScenarioSettings, ToF32,
+    PHYSICS_DELTA_TIME,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use spheretree::SphereTreeBuilder;
+use std::{borrow::Cow, path::PathBuf, time::Duration};
+use winit::event::VirtualKeyCode;
+
+/// Where user scenarios are listed from and saved to, relative to where the program is run
+pub const USER_SCENARIOS: &str = "scenarios";
+/// How often user scenarios are listed anew while the gallery is open
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+const BUILT_IN: [(&str, &str); 4] = [
+    (
+        "Stable orbit",
+        include_str!("../assets/scenarios/stable-orbit.scenario"),
+    ),
+    (
+        "Binary",
+        include_str!("../assets/scenarios/binary.scenario"),
+    ),
+    (
+        "Dust ring",
+        include_str!("../assets/scenarios/dust-ring.scenario"),
+    ),
+    (
+        "Whirlpool",
+        include_str!("../assets/scenarios/whirlpool.scenario"),
+    ),
+];
+/// Space between thumbnails, and around the grid of them, in pixels
+const GAP: f32 = 40.0;
+const HIGHLIGHT_WIDTH: f32 = 4.0;
+const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.85, 0.4, 1.0];
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const PLACEHOLDER_COLOR: [f32; 4] = [0.2, 0.2, 0.25, 1.0];
+
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    /// The scenario file, or `None` for a random cluster like the one at startup
+    text: Option<Cow<'static, str>>,
+    /// A thumbnail saved along with the scenario
+    thumbnail: Option<PathBuf>,
+}
+
+pub struct Gallery {
+    entries: Vec<Entry>,
+    selected: usize,
+    open: bool,
+    /// Whether the thumbnails of all entries are in their image slots
+    thumbnails_ready: bool,
+    /// An entry to load once physics may be edited
+    chosen: Option<usize>,
+    /// The solar system at each scale, see [`crate::ephemeris`]
+    solar_system: Vec<Entry>,
+    /// Files dropped onto the canvas on the web, which has no directory to copy them into
+    dropped: Vec<Entry>,
+    /// The user scenario directory as of the last scan, to notice files being added
+    #[cfg(not(target_arch = "wasm32"))]
+    listing: Vec<(PathBuf, Option<std::time::SystemTime>)>,
+    last_scan: Instant,
+    /// For rendering thumbnails, apart from the one of the scene
+    #[cfg(not(target_arch = "wasm32"))]
+    sphere_tree: SphereTreeBuilder,
+}
+impl Gallery {
+    pub fn new() -> Self {
+        let mut gallery = Self {
+            entries: Vec::new(),
+            selected: 0,
+            open: false,
+            thumbnails_ready: false,
+            chosen: None,
+            solar_system: crate::ephemeris::solar_system()
+                .into_iter()
+                .map(|(name, text)| Entry {
+                    name,
+                    text: Some(Cow::Owned(text)),
+                    thumbnail: None,
+                })
+                .collect(),
+            dropped: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            listing: Vec::new(),
+            last_scan: Instant::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            sphere_tree: SphereTreeBuilder::new(),
+        };
+        gallery.open();
+        gallery
+    }
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    /// Lists the scenarios anew and shows them.
+    pub fn open(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.listing = user_listing();
+        }
+        self.last_scan = Instant::now();
+        self.entries = std::iter::once(Entry {
+            name: "Random cluster".to_owned(),
+            text: None,
+            thumbnail: None,
+        })
+        .chain(BUILT_IN.iter().map(|&(name, text)| Entry {
+            name: name.to_owned(),
+            text: Some(Cow::Borrowed(text)),
+            thumbnail: None,
+        }))
+        .chain(self.solar_system.iter().cloned())
+        .chain(user_scenarios())
+        .chain(self.dropped.iter().cloned())
+        .take(IMAGE_SLOTS)
+        .collect();
+        self.selected = self.selected.min(self.entries.len() - 1);
+        self.open = true;
+        self.thumbnails_ready = false;
+    }
+    /// Lists the scenarios anew if user scenarios were added or changed since the last scan,
+    /// and chooses the latest scenario file dropped onto the canvas.
+    pub fn poll(&mut self, now: Instant, toasts: &mut Toasts) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let dropped = DROPPED.with(|dropped| std::mem::take(&mut *dropped.borrow_mut()));
+            let mut added = false;
+            for (file_name, text) in dropped {
+                match file_name.strip_suffix(".scenario") {
+                    Some(name) => {
+                        self.dropped.push(Entry {
+                            name: name.to_owned(),
+                            text: Some(Cow::Owned(text)),
+                            thumbnail: None,
+                        });
+                        added = true;
+                    }
+                    None => toasts.error(unsupported(&file_name)),
+                }
+            }
+            if added {
+                self.open();
+                self.choose(self.entries.len() - 1);
+                return;
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = toasts;
+        if !self.open || now.duration_since(self.last_scan) < RESCAN_INTERVAL {
+            return;
+        }
+        self.last_scan = now;
+        #[cfg(not(target_arch = "wasm32"))]
+        if user_listing() != self.listing {
+            log::info!("User scenarios changed, listing them anew");
+            self.open();
+        }
+    }
+    /// Copies a scenario file dropped onto the window into the user scenarios and chooses it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn drop_file(&mut self, path: &std::path::Path, toasts: &mut Toasts) {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.extension().map_or(true, |ext| ext != "scenario") {
+            toasts.error(unsupported(&file_name));
+            return;
+        }
+        let destination = std::path::Path::new(USER_SCENARIOS).join(&*file_name);
+        if let Err(err) =
+            std::fs::create_dir_all(USER_SCENARIOS).and_then(|()| std::fs::copy(path, &destination))
+        {
+            toasts.error(format!("Failed to add {file_name}: {err}"));
+            return;
+        }
+        self.open();
+        let name = file_name.strip_suffix(".scenario").unwrap_or(&file_name);
+        match self.entries.iter().position(|entry| entry.name == name) {
+            Some(index) => self.choose(index),
+            None => toasts.error(format!("Added {file_name}, but the gallery is full")),
+        }
+    }
+    /// Closes the gallery, loading an entry with the next [`Gallery::update`].
+    fn choose(&mut self, index: usize) {
+        self.selected = index;
+        self.chosen = Some(index);
+        self.open = false;
+    }
+    /// Renders or loads all thumbnails again when next shown, after the image slots were emptied.
+    pub fn reload_thumbnails(&mut self) {
+        self.thumbnails_ready = false;
+    }
+    /// Handles a key press while open.
+    pub fn key_input(&mut self, key: VirtualKeyCode, columns: usize) {
+        let last = self.entries.len() - 1;
+        match key {
+            VirtualKeyCode::Left => self.selected = self.selected.saturating_sub(1),
+            VirtualKeyCode::Right => self.selected = (self.selected + 1).min(last),
+            VirtualKeyCode::Up => self.selected = self.selected.saturating_sub(columns),
+            VirtualKeyCode::Down => self.selected = (self.selected + columns).min(last),
+            VirtualKeyCode::Return => self.choose(self.selected),
+            VirtualKeyCode::Escape => self.open = false,
+            _ => {}
+        }
+    }
+    /// Loads the chosen scenario. Must only be called while no worker is advancing `physics`,
+    /// or the loaded bodies would be lost.
+    pub fn update(&mut self, physics: &mut Physics, bus: &mut EventBus, toasts: &mut Toasts) {
+        let Some(index) = self.chosen.take() else {
+            return;
+        };
+        let entry = &self.entries[index];
+        match load(entry) {
+            Ok(scenario) => {
+                toasts.info(format!("Loaded {}", entry.name));
+                bus.publish(AppEvent::ScenarioLoaded(scenario.settings));
+                physics.load_scenario(scenario);
+            }
+            Err(err) => toasts.error(format!("Failed to load {}: {err}", entry.name)),
+        }
+    }
+    /// How many thumbnails fit side by side in the window.
+    pub fn columns(window_size: (u32, u32)) -> usize {
+        let width = window_size.0 as f32 - GAP;
+        ((width / (IMAGE_SIZE.0 as f32 + GAP)) as usize).max(1)
+    }
+}
+impl Subscriber for Gallery {
+    /// Renders missing thumbnails and draws the gallery over the scene.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if !self.open {
+            return;
+        }
+        if !self.thumbnails_ready {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.sphere_tree.set_radius_scale(graphics.radius_scale());
+                for (slot, entry) in self.entries.iter().enumerate() {
+                    match thumbnail(entry, graphics, &mut self.sphere_tree) {
+                        Some(image) => graphics.set_overlay_image(slot, &image),
+                        None => log::warn!("No thumbnail for {}", entry.name),
+                    }
+                }
+            }
+            self.thumbnails_ready = true;
+        }
+        let (width, height) = graphics.window_size();
+        graphics.overlay_rect(((0.0, 0.0), (width as f32, height as f32)), BACKDROP_COLOR);
+        graphics.overlay_line(format_args!(
+            "Choose a scenario with the arrow keys and Enter, or keep the current one with Escape"
+        ));
+        let columns = Self::columns((width, height));
+        let (thumb_width, thumb_height) = (IMAGE_SIZE.0 as f32, IMAGE_SIZE.1 as f32);
+        let grid_width = columns as f32 * (thumb_width + GAP) - GAP;
+        let left = 0.5 * (width as f32 - grid_width).max(0.0);
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (column, row) = (index % columns, index / columns);
+            let min = (
+                left + column as f32 * (thumb_width + GAP),
+                2.0 * GAP + row as f32 * (thumb_height + GAP),
+            );
+            let max = (min.0 + thumb_width, min.1 + thumb_height);
+            if index == self.selected {
+                let w = HIGHLIGHT_WIDTH;
+                graphics.overlay_rect(
+                    ((min.0 - w, min.1 - w), (max.0 + w, max.1 + w)),
+                    HIGHLIGHT_COLOR,
+                );
+            }
+            graphics.overlay_rect((min, max), PLACEHOLDER_COLOR);
+            if cfg!(not(target_arch = "wasm32")) {
+                graphics.overlay_image(index, (min, max));
+            }
+            graphics.label(
+                (0.5 * (min.0 + max.0), max.1 + 0.5 * GAP),
+                1.0,
+                format_args!("{}", entry.name),
+            );
+        }
+    }
+}
+
+fn load(entry: &Entry) -> Result<Scenario, physics::ScenarioError> {
+    match &entry.text {
+        Some(text) => parse_scenario(text),
+        None => Ok(Scenario {
+            bodies: *Physics::initial().bodies(),
+            settings: ScenarioSettings {
+                camera: Some(ScenarioCamera {
+                    position: Pose::default().position,
+                    target: Vector3::zero(),
+                }),
+                delta_time: Some(PHYSICS_DELTA_TIME),
+                ray_splits: None,
+            },
+            groups: Vec::new(),
+            fields: Vec::new(),
+        }),
+    }
+}
+
+fn unsupported(file_name: &str) -> String {
+    format!("Cannot load {file_name}, only .scenario files are supported")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn user_scenarios() -> Vec<Entry> {
+    let Ok(dir) = std::fs::read_dir(USER_SCENARIOS) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<Entry> = dir
+        .filter_map(Result::ok)
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "scenario"))
+        .filter_map(|path| match std::fs::read_to_string(&path) {
+            Ok(text) => Some(Entry {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                text: Some(Cow::Owned(text)),
+                thumbnail: Some(path.with_extension("png")).filter(|png| png.exists()),
+            }),
+            Err(err) => {
+                log::warn!("Failed to read {}: {err}", path.display());
+                None
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+#[cfg(target_arch = "wasm32")]
+fn user_scenarios() -> Vec<Entry> {
+    Vec::new()
+}
+
+/// Every file in the user scenario directory, with when it was last modified.
+#[cfg(not(target_arch = "wasm32"))]
+fn user_listing() -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    let Ok(dir) = std::fs::read_dir(USER_SCENARIOS) else {
+        return Vec::new();
+    };
+    let mut listing: Vec<_> = dir
+        .filter_map(Result::ok)
+        .map(|file| {
+            let modified = file.metadata().and_then(|meta| meta.modified()).ok();
+            (file.path(), modified)
+        })
+        .collect();
+    listing.sort();
+    listing
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// File names and contents of files dropped onto the canvas, not yet in the gallery
+    static DROPPED: std::cell::RefCell<Vec<(String, String)>> = Default::default();
+}
+
+/// Reads scenario files dropped onto the canvas, to be listed by the next [`Gallery::poll`].
+#[cfg(target_arch = "wasm32")]
+pub fn listen_for_drops(canvas: &web_sys::HtmlCanvasElement) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    // Dropping is only allowed where dragging over is cancelled
+    let drag_over = Closure::<dyn FnMut(web_sys::DragEvent)>::new(|event: web_sys::DragEvent| {
+        event.prevent_default();
+    });
+    let drop = Closure::<dyn FnMut(web_sys::DragEvent)>::new(|event: web_sys::DragEvent| {
+        event.prevent_default();
+        let Some(files) = event.data_transfer().and_then(|data| data.files()) else {
+            return;
+        };
+        for file in (0..files.length()).filter_map(|i| files.get(i)) {
+            wasm_bindgen_futures::spawn_local(async move {
+                let name = file.name();
+                match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                    Ok(text) => DROPPED.with(|dropped| {
+                        dropped
+                            .borrow_mut()
+                            .push((name, text.as_string().unwrap_or_default()))
+                    }),
+                    Err(err) => log::error!("Failed to read dropped {name}: {err:?}"),
+                }
+            });
+        }
+    });
+    for (kind, listener) in [("dragover", &drag_over), ("drop", &drop)] {
+        canvas
+            .add_event_listener_with_callback(kind, listener.as_ref().unchecked_ref())
+            .unwrap();
+    }
+    // The listeners are needed for as long as the page is open
+    drag_over.forget();
+    drop.forget();
+}
+
+/// The thumbnail of an entry, saved along with it or from the cache if it is there. Random
+/// clusters differ every time, so they are never cached.
+#[cfg(not(target_arch = "wasm32"))]
+fn thumbnail(
+    entry: &Entry,
+    graphics: &mut Graphics,
+    sphere_tree: &mut SphereTreeBuilder,
+) -> Option<image::RgbaImage> {
+    use std::hash::{Hash, Hasher};
+
+    if let Some(image) = entry
+        .thumbnail
+        .as_ref()
+        .and_then(|path| image::open(path).ok())
+    {
+        return Some(image.to_rgba8());
+    }
+    let path = entry.text.as_ref().map(|text| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (text, IMAGE_SIZE).hash(&mut hasher);
+        std::env::temp_dir()
+            .join("marble-gravity-thumbnails")
+            .join(format!("{:016x}.png", hasher.finish()))
+    });
+    if let Some(image) = path
+        .as_ref()
+        .and_then(|path| image::open(path).ok())
+        .map(|image| image.to_rgba8())
+    {
+        return Some(image);
+    }
+    let image = render_thumbnail(graphics, sphere_tree, &load(entry).ok()?);
+    if let Some(path) = path {
+        save_in_background(image.clone(), path);
+    }
+    Some(image)
+}
+
+/// Renders a scenario as seen from its recommended camera pose, or from somewhat above it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_thumbnail(
+    graphics: &mut Graphics,
+    sphere_tree: &mut SphereTreeBuilder,
+    scenario: &Scenario,
+) -> image::RgbaImage {
+    let pose = match scenario.settings.camera {
+        Some(ScenarioCamera { position, target }) => Pose::looking_at(position, target),
+        None => framing(&scenario.bodies),
+    };
+    let view = View {
+        bodies: sphere_tree.build(&scenario.bodies, &scenario.group_table(), &pose),
+        pose,
+    };
+    graphics.capture(&view, IMAGE_SIZE)
+}
+
+/// Encodes and writes a thumbnail on a thread of its own, creating its directory if needed.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_in_background(image: image::RgbaImage, path: PathBuf) {
+    std::thread::spawn(move || {
+        let saved = std::fs::create_dir_all(path.parent().unwrap())
+            .map_err(image::ImageError::IoError)
+            .and_then(|()| image.save(&path));
+        if let Err(err) = saved {
+            log::warn!("Failed to save a thumbnail to {}: {err}", path.display());
+        }
+    });
+}
+
+/// A pose looking at all of the bodies from somewhat above.
+#[cfg(not(target_arch = "wasm32"))]
+fn framing(bodies: &[Body]) -> Pose {
+    if bodies.is_empty() {
+        return Pose::default();
+    }
+    let center = bodies
+        .iter()
+        .map(|body| body.pos.to_f32())
+        .sum::<Vector3<f32>>()
+        / bodies.len() as f32;
+    let extent = bodies
+        .iter()
+        .map(|body| body.pos.to_f32().distance(center) + body.radius.to_f32())
+        .fold(0.0, f32::max);
+    let direction = Vector3::new(0.0, -0.6, -1.0).normalize();
+    Pose::looking_at(center + (2.5 * extent).max(0.5) * direction, center)
+}
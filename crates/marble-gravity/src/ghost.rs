@@ -0,0 +1,202 @@
+//! `--ghost <file>` or `MARBLE_GRAVITY_GHOST=<file>` shows the bodies of a run recorded with
+//! `--record` as translucent ghosts among the live ones, moving as they did then, for comparing
+//! against a reference such as a run before a change to physics, or a best attempt at a
+//! challenge. The ghost moves in step with the ticks of the live simulation from the start of
+//! both, and loading a scenario, such as by starting a challenge, restarts it from the first
+//! edit in its recording. Ghosts only appear while there is room for them among the bodies that
+//! can be drawn. Not supported on the web.
+
+use crate::{
+    events::{AppEvent, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use instant::Instant;
+use physics::{Body, BodySet, Physics, Scalar};
+
+/// The transparency of ghosts, as in the low byte of [`Body::color`]
+const TRANSPARENCY: u32 = 0xb0;
+
+/// The positions of the bodies at a recorded tick
+struct Sample {
+    tick: u64,
+    /// The index in [`Recording::states`] of the last state recorded before, which the radii and
+    /// colors of the bodies are taken from
+    state: usize,
+    positions: Vec<Vector3<f32>>,
+}
+
+pub struct Ghost {
+    recording: Option<Recording>,
+}
+impl Ghost {
+    /// Loads the recording requested on the command line, if any.
+    pub fn requested() -> Self {
+        let Some((name, path)) = crate::requested("ghost") else {
+            return Self { recording: None };
+        };
+        let recording = match Recording::load(&path) {
+            Ok(recording) => {
+                log::info!("Showing the run recorded in {path} as a ghost");
+                Some(recording)
+            }
+            Err(err) => {
+                log::error!("Failed to load {path} for {name}: {err}");
+                None
+            }
+        };
+        Self { recording }
+    }
+    /// Follows the live simulation, which is at `live_tick`.
+    pub fn update(&mut self, live_tick: u64) {
+        if let Some(recording) = &mut self.recording {
+            recording.update(live_tick);
+        }
+    }
+    /// Adds the ghosts to `bodies` as far as there is room, if there are any.
+    pub fn haunt(&self, bodies: &BodySet) -> Option<BodySet> {
+        self.recording.as_ref()?.haunt(bodies)
+    }
+}
+impl Subscriber for Ghost {
+    fn notify(&mut self, event: &AppEvent) {
+        if let (Some(recording), AppEvent::ScenarioLoaded(_)) = (&mut self.recording, event) {
+            recording.restarting = true;
+        }
+    }
+    /// Describes the ghost in the overlay.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if let Some(recording) = &self.recording {
+            recording.queue(graphics);
+        }
+    }
+}
+
+struct Recording {
+    path: String,
+    /// The bodies of each recorded state, in order
+    states: Vec<BodySet>,
+    /// In order of their ticks
+    samples: Vec<Sample>,
+    /// The recorded tick of the first edit after the initial state
+    first_edit: Option<u64>,
+    /// The recorded tick minus the live tick, once known
+    shift: Option<i128>,
+    /// Whether the ghost restarts from its first edit at the next update
+    restarting: bool,
+    /// The recorded tick shown last
+    tick: u64,
+}
+impl Recording {
+    fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut recording = Self {
+            path: path.to_owned(),
+            states: Vec::new(),
+            samples: Vec::new(),
+            first_edit: None,
+            shift: None,
+            restarting: false,
+            tick: 0,
+        };
+        for (number, line) in text.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let kind = fields.next();
+            if !matches!(kind, Some("state" | "positions")) {
+                continue;
+            }
+            let invalid = || format!("invalid line {}", number + 1);
+            let tick: u64 = fields
+                .next()
+                .and_then(|tick| tick.parse().ok())
+                .ok_or_else(invalid)?;
+            if kind == Some("state") {
+                let words: Vec<u64> = fields
+                    .map(|word| u64::from_str_radix(word, 16).ok())
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                let state = Physics::decode(&words, Instant::now())
+                    .map_err(|err| format!("line {}: {err:?}", number + 1))?;
+                if !recording.states.is_empty() && recording.first_edit.is_none() {
+                    recording.first_edit = Some(tick);
+                }
+                recording.states.push(*state.bodies());
+                continue;
+            }
+            let numbers: Vec<f32> = fields
+                .map(|number| number.parse().ok())
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?;
+            let state = recording.states.len().checked_sub(1).ok_or_else(invalid)?;
+            let positions: Vec<Vector3<f32>> = numbers
+                .chunks_exact(3)
+                .map(|xyz| Vector3::new(xyz[0], xyz[1], xyz[2]))
+                .collect();
+            // Bodies are only added and removed by edits, which record a state first
+            if numbers.len() % 3 != 0 || positions.len() != recording.states[state].len() {
+                return Err(invalid());
+            }
+            recording.samples.push(Sample {
+                tick,
+                state,
+                positions,
+            });
+        }
+        if recording.samples.is_empty() {
+            return Err("no positions recorded, see --record".to_owned());
+        }
+        Ok(recording)
+    }
+    fn update(&mut self, live_tick: u64) {
+        let start = self.samples[0].tick;
+        if std::mem::take(&mut self.restarting) {
+            let restart = self.first_edit.unwrap_or(start);
+            self.shift = Some(i128::from(restart) - i128::from(live_tick));
+        }
+        let shift = *self
+            .shift
+            .get_or_insert(i128::from(start) - i128::from(live_tick));
+        self.tick = u64::try_from(i128::from(live_tick) + shift).unwrap_or(0);
+    }
+    fn haunt(&self, bodies: &BodySet) -> Option<BodySet> {
+        let next = self
+            .samples
+            .partition_point(|sample| sample.tick <= self.tick);
+        let (before, after) = (
+            self.samples.get(next.checked_sub(1)?)?,
+            self.samples.get(next)?,
+        );
+        // Between edits the same bodies are at both samples, and are interpolated
+        let fraction = if before.state == after.state {
+            (self.tick - before.tick) as f32 / (after.tick - before.tick) as f32
+        } else {
+            0.0
+        };
+        let ghosts = self.states[before.state]
+            .iter()
+            .zip(before.positions.iter().zip(&after.positions))
+            .map(|(body, (&from, &to))| Body {
+                pos: from.lerp(to, fraction).map(Scalar::from),
+                color: (body.color & !0xff) | TRANSPARENCY,
+                ..*body
+            });
+        let room = BodySet::capacity() - bodies.len();
+        Some(bodies.iter().copied().chain(ghosts.take(room)).collect())
+    }
+    fn queue(&self, graphics: &mut Graphics) {
+        let (start, end) = (
+            self.samples[0].tick,
+            self.samples[self.samples.len() - 1].tick,
+        );
+        let progress = self.tick.saturating_sub(start) as f32 / (end - start).max(1) as f32;
+        if progress < 1.0 {
+            graphics.overlay_line(format_args!(
+                "Ghost of {}, {:.0}% through",
+                self.path,
+                100.0 * progress
+            ));
+        } else {
+            graphics.overlay_line(format_args!("The ghost of {} has ended", self.path));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Marble golf: launch marbles from the camera to come to rest on a distant green, in as few
+//! launches as possible, while the cluster pulls them off course.
+
+use crate::{
+    commands::{Command, CommandHistory},
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use instant::Instant;
+use physics::{Body, Physics, Scalar, ToF32};
+use std::time::Duration;
+
+/// A frozen body placed away from the cluster, which launched marbles can settle on
+const GREEN_POSITION: Vector3<f32> = Vector3::new(0.0, 0.0, 3.0);
+const GREEN_RADIUS: Scalar = 0.25;
+const GREEN_COLOR: u32 = 0x2a_9d_8f_00;
+/// Marbles with centers within this distance of the green center are in the target zone
+const TARGET_RADIUS: f32 = 0.4;
+const TARGET_COLOR: [f32; 4] = [0.2, 0.9, 0.3, 0.8];
+const BALL_COLOR: u32 = 0xf1_fa_ee_00;
+const BALL_RADIUS: Scalar = 0.03;
+/// Launch speeds at no charge and at full charge
+const LAUNCH_SPEED: (f32, f32) = (0.2, 2.0);
+const FULL_CHARGE: Duration = Duration::from_millis(1500);
+/// A marble slower than this, relative to the green, for long enough is at rest
+const REST_SPEED: Scalar = 0.05;
+const REST_DURATION: Duration = Duration::from_secs(1);
+const CHARGE_BAR_SIZE: (f32, f32) = (200.0, 12.0);
+
+enum State {
+    Off,
+    /// Waiting for physics to be idle, so that the green can be placed
+    Starting,
+    Playing {
+        green: usize,
+        /// Indices of the launched marbles
+        balls: Vec<usize>,
+        /// The index the latest launch will get, once the queued spawn is applied
+        pending_ball: Option<usize>,
+        shots: u32,
+        /// The simulated time from which a marble has been at rest in the target zone
+        resting_since: Option<Duration>,
+    },
+    Holed {
+        shots: u32,
+    },
+}
+
+pub struct Golf {
+    state: State,
+    charging_since: Option<Instant>,
+}
+impl Golf {
+    pub fn new() -> Self {
+        Self {
+            state: State::Off,
+            charging_since: None,
+        }
+    }
+    pub fn toggle(&mut self) {
+        self.state = match self.state {
+            State::Off => State::Starting,
+            _ => State::Off,
+        };
+        self.charging_since = None;
+    }
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, State::Playing { .. })
+    }
+    pub fn begin_charge(&mut self) {
+        if self.is_playing() {
+            self.charging_since = Some(Instant::now());
+        }
+    }
+    /// Spawns a marble in front of the camera, faster the longer the launch was charged.
+    pub fn launch(
+        &mut self,
+        commands: &mut CommandHistory,
+        bodies: &[Body],
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+    ) {
+        let Some(charging_since) = self.charging_since.take() else {
+            return;
+        };
+        let State::Playing {
+            pending_ball,
+            shots,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        let (min, max) = LAUNCH_SPEED;
+        let speed = min + (max - min) * charge(charging_since);
+        commands.push(Command::SpawnBody(Body {
+            radius: BALL_RADIUS,
+            color: BALL_COLOR,
+            flags: 0,
+            ..Body::at(
+                (position + 0.2 * direction).map(Scalar::from),
+                (speed * direction).map(Scalar::from),
+            )
+        }));
+        *pending_ball = Some(bodies.len());
+        *shots += 1;
+        log::info!("Golf shot {shots} at speed {speed:.2}");
+    }
+    /// Must only be called while no worker is advancing `physics`, and after queued commands
+    /// have been applied.
+    pub fn update(&mut self, physics: &mut Physics) {
+        let now = physics.simulated_time();
+        match &mut self.state {
+            State::Off | State::Holed { .. } => {}
+            State::Starting => {
+                let green = physics.bodies().len();
+                let pushed = physics.bodies_mut().push(Body {
+                    radius: GREEN_RADIUS,
+                    color: GREEN_COLOR,
+                    flags: Body::FROZEN,
+                    ..Body::at(GREEN_POSITION.map(Scalar::from), Vector3::zero())
+                });
+                self.state = match pushed {
+                    Ok(()) => State::Playing {
+                        green,
+                        balls: Vec::new(),
+                        pending_ball: None,
+                        shots: 0,
+                        resting_since: None,
+                    },
+                    Err(_) => {
+                        log::warn!("No room for the golf green");
+                        State::Off
+                    }
+                };
+            }
+            State::Playing {
+                green,
+                balls,
+                pending_ball,
+                shots,
+                resting_since,
+            } => {
+                let bodies = physics.bodies();
+                let Some(green) = bodies.get(*green).filter(|body| body.is_frozen()) else {
+                    log::info!("The golf green was removed");
+                    self.state = State::Off;
+                    return;
+                };
+                if let Some(index) = pending_ball.filter(|&index| index < bodies.len()) {
+                    *pending_ball = None;
+                    balls.push(index);
+                }
+                balls.retain(|&index| index < bodies.len());
+                let resting = balls.iter().any(|&index| {
+                    let ball = &bodies[index];
+                    ball.pos.distance(green.pos).to_f32() < TARGET_RADIUS
+                        && (ball.vel - green.vel).magnitude() < REST_SPEED
+                });
+                if !resting {
+                    *resting_since = None;
+                    return;
+                }
+                let since = *resting_since.get_or_insert(now);
+                if now - since >= REST_DURATION {
+                    log::info!("Golf completed in {shots} shots");
+                    self.state = State::Holed { shots: *shots };
+                }
+            }
+        }
+    }
+}
+impl Subscriber for Golf {
+    /// Outlines the target zone, and shows the shot count and launch charge.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let world_to_camera = frame.world_to_camera();
+        match self.state {
+            State::Off | State::Starting => {}
+            State::Playing { shots, .. } => {
+                let view_center = (world_to_camera * GREEN_POSITION.extend(1.0)).truncate();
+                graphics.overlay_sphere(view_center, TARGET_RADIUS, TARGET_COLOR);
+                graphics.overlay_line(format_args!(
+                    "Golf: {shots} shots, hold the right mouse button to launch"
+                ));
+                if let Some(charging_since) = self.charging_since {
+                    let (width, height) = graphics.window_size();
+                    let (bar_width, bar_height) = CHARGE_BAR_SIZE;
+                    let min = (
+                        0.5 * (width as f32 - bar_width),
+                        0.75 * height as f32 - 0.5 * bar_height,
+                    );
+                    graphics.overlay_rect(
+                        (min, (min.0 + bar_width, min.1 + bar_height)),
+                        [0.0, 0.0, 0.0, 0.5],
+                    );
+                    graphics.overlay_rect(
+                        (
+                            min,
+                            (
+                                min.0 + charge(charging_since) * bar_width,
+                                min.1 + bar_height,
+                            ),
+                        ),
+                        TARGET_COLOR,
+                    );
+                }
+            }
+            State::Holed { shots } => {
+                graphics.overlay_line(format_args!("Golf: on the green in {shots} shots"));
+            }
+        }
+    }
+}
+
+/// How charged a launch is, from 0 to 1
+fn charge(since: Instant) -> f32 {
+    (Instant::now().duration_since(since).as_secs_f32() / FULL_CHARGE.as_secs_f32()).min(1.0)
+}
@@ -1,36 +1,106 @@
-use crate::spheretree::Sphere;
-use cgmath::{prelude::*, Matrix3, Matrix4, Quaternion, Vector2, Vector3};
+use crate::{
+    contrast::{SkyboxLuminance, Theme},
+    events::{AppEvent, Subscriber},
+    icosphere::icosphere,
+    images::ImageRenderer,
+    overlay::OverlayLayout,
+    panels::PanelRenderer,
+    picking::{GpuPicker, Picked},
+    rendergraph::{Input, PassDescriptor, RenderGraph, Target, VertexBuffer, Viewport},
+};
+use camera::{Pose, WORLD_UP};
+use cgmath::{prelude::*, Matrix3, Matrix4, Vector2, Vector3};
 use instant::Instant;
-use physics::BODIES;
+use physics::{ScenarioSettings, Tracer, BODIES, MAX_TRACERS};
+use spheretree::{Orientation, Sphere, SphereTree};
 use std::{
     collections::VecDeque,
+    fmt::{self, Write},
     mem,
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    },
+    ops::{Range, RangeInclusive},
+    sync::mpsc,
     time::Duration,
 };
 use wgpu::util::DeviceExt;
 
 const FRAME_TIME_HISTORY_COUNT: usize = 30;
+/// Submitted frames whose completion has not been observed yet. Beyond this, rendering waits for
+/// the GPU (natively; on the web the browser throttles instead).
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+/// The most overlay glyphs expected in a frame. Uploads are staged in chunks fitting this many,
+/// so that the staging belt does not allocate new chunks every frame.
+const OVERLAY_GLYPH_CAPACITY: u64 = 2048;
+/// The size of one glyph instance as uploaded by `wgpu_glyph`
+#[cfg(not(feature = "msdf-text"))]
+const GLYPH_INSTANCE_SIZE: u64 = 52;
+/// Space in pixels between overlay text and the edges of its backing panel
+const PANEL_PADDING: f32 = 4.0;
+const LABEL_SCALE: f32 = 16.0;
+/// The near plane distance of the renderer
+const NEAR: f32 = 0.001;
+const ICOSPHERE_SUBDIVISIONS: u32 = 2;
+/// The deepest ray recursion implemented in the fragment shader
+pub const MAX_RAY_SPLITS: u32 = 4;
+/// The range of [`GraphicsSettings::radius_scale`]
+pub const RADIUS_SCALES: RangeInclusive<f32> = 0.1..=1000.0;
+/// Test particles in the accretion disk, which orbit without affecting anything
+const DISK_PARTICLES: u32 = 8192;
+/// The sphere tree of all bodies, which the shaders bind as one uniform buffer
+const BODY_BUFFER_SIZE: u64 = ((2 * BODIES - 1) * mem::size_of::<Sphere>()) as u64;
+/// The position and velocity of a tracer, as instance data
+const TRACER_ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+    wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct Uniforms {
     sun_direction: Vector3<f32>,
     ray_splits: u32,
-    pub(self) window_size: Vector2<f32>,
-    _padding2: [u32; 2],
+    pub(self) viewport_size: Vector2<f32>,
+    primary_from_gbuffer: u32,
+    medium_extinction: f32,
+    /// Where the top left corner of the viewport is in the target, in pixels
+    viewport_offset: Vector2<f32>,
+    target_size: Vector2<f32>,
+    /// In world space, for the dust noise of the medium
+    camera_position: Vector3<f32>,
+    medium_density: f32,
+    /// The heaviest body in camera space, which bends rays passing it, and its radius, where
+    /// zero turns lensing off
+    lens_position: Vector3<f32>,
+    lens_radius: f32,
+    /// The heaviest body in camera space, which the accretion disk orbits, and its radius,
+    /// where zero hides the disk
+    disk_center: Vector3<f32>,
+    disk_body_radius: f32,
+    /// Simulated seconds, which place the disk particles along their orbits
+    disk_time: f32,
+    /// How many of the tracers in their buffer are in use
+    tracer_count: u32,
+    emission: f32,
+    padding: f32,
     pub(self) view_to_world_space: Matrix4<f32>,
 }
 impl Uniforms {
     pub fn new() -> Self {
         Self {
             sun_direction: Vector3::unit_x(),
-            window_size: Vector2::zero(),
-            ray_splits: 4,
-            _padding2: [0; 2],
+            viewport_size: Vector2::zero(),
+            ray_splits: MAX_RAY_SPLITS,
+            primary_from_gbuffer: 0,
+            medium_extinction: 2.0,
+            viewport_offset: Vector2::zero(),
+            target_size: Vector2::zero(),
+            camera_position: Vector3::zero(),
+            medium_density: 0.0,
+            lens_position: Vector3::zero(),
+            lens_radius: 0.0,
+            disk_center: Vector3::zero(),
+            disk_body_radius: 0.0,
+            disk_time: 0.0,
+            tracer_count: 0,
+            emission: 0.0,
+            padding: 0.0,
             view_to_world_space: Matrix4::one(),
         }
     }
@@ -38,115 +108,542 @@ impl Uniforms {
 unsafe impl bytemuck::Pod for Uniforms {}
 unsafe impl bytemuck::Zeroable for Uniforms {}
 
+/// How bodies are drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Per-pixel traversal of the sphere tree, with reflections, refraction and shadows
+    Raytraced,
+    /// Instanced icosphere meshes with a depth buffer, for weak GPUs and as a cross-check
+    Rasterized,
+    /// Rasterized primary visibility, with only secondary rays traversing the sphere tree
+    Hybrid,
+}
+impl Backend {
+    pub const ALL: [Self; 3] = [Self::Raytraced, Self::Rasterized, Self::Hybrid];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Raytraced => "raytraced",
+            Self::Rasterized => "rasterized",
+            Self::Hybrid => "hybrid",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            Self::Raytraced => Self::Rasterized,
+            Self::Rasterized => Self::Hybrid,
+            Self::Hybrid => Self::Raytraced,
+        }
+    }
+    /// The render graph passes drawing this backend
+    fn passes(self) -> &'static [&'static str] {
+        match self {
+            Self::Raytraced => &["raytrace"],
+            Self::Rasterized => &["skybox", "meshes"],
+            Self::Hybrid => &["gbuffer", "raytrace"],
+        }
+    }
+}
+
+/// What one viewport shows, see [`Graphics::render`]
+pub struct View<'a> {
+    /// The sphere tree in the camera space of this view
+    pub bodies: SphereTree<'a>,
+    pub pose: Pose,
+}
+
+/// A dusty medium filling space, which scatters sunlight into visible shafts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Medium {
+    /// The fraction of space with dust in it, where zero turns the medium off
+    pub density: f32,
+    /// Optical thickness per unit length of the densest dust
+    pub extinction: f32,
+}
+
+/// Where the sun is in the sky, infinitely far away, in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sun {
+    /// Around [`WORLD_UP`], from the x axis toward the z axis
+    pub azimuth: f32,
+    /// Above the plane across [`WORLD_UP`]
+    pub elevation: f32,
+}
+impl Sun {
+    /// The world direction toward the sun.
+    pub fn direction(self) -> Vector3<f32> {
+        let (sin_azimuth, cos_azimuth) = self.azimuth.to_radians().sin_cos();
+        let (sin_elevation, cos_elevation) = self.elevation.to_radians().sin_cos();
+        cos_elevation * Vector3::new(cos_azimuth, 0.0, sin_azimuth) + sin_elevation * WORLD_UP
+    }
+}
+
+/// Everything about how the scene is drawn that can be changed at runtime, see
+/// [`Graphics::apply_settings`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GraphicsSettings {
+    pub backend: Backend,
+    /// How many times rays may reflect or refract, up to [`MAX_RAY_SPLITS`]
+    pub ray_splits: u32,
+    /// Only drawn by the raytraced and hybrid backends
+    pub medium: Medium,
+    /// Whether rays are bent around the heaviest body, as by gravitational lensing. Only drawn
+    /// by the raytraced and hybrid backends, where it costs the hybrid backend its rasterized
+    /// primary visibility.
+    pub lensing: bool,
+    /// Whether a disk of test particles orbits the heaviest body
+    pub accretion_disk: bool,
+    /// Whether the window is split between two side by side viewports
+    pub split_screen: bool,
+    pub sun: Sun,
+    /// Whether walls containing the bodies are outlined, see [`crate::boundary`]
+    pub boundary_outline: bool,
+    /// How many times larger bodies are drawn than they are, so that bodies of realistic
+    /// systems, which are tiny next to the distances between them, can be seen
+    pub radius_scale: f32,
+    /// How brightly bodies glow in their own colors, regardless of the sun, where zero is not at
+    /// all and one is as bright as full sunlight
+    pub emission: f32,
+}
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Raytraced,
+            ray_splits: MAX_RAY_SPLITS,
+            medium: Medium {
+                density: 0.0,
+                extinction: 2.0,
+            },
+            lensing: false,
+            accretion_disk: false,
+            split_screen: false,
+            sun: Sun {
+                azimuth: 0.0,
+                elevation: 0.0,
+            },
+            boundary_outline: true,
+            radius_scale: 1.0,
+            emission: 0.0,
+        }
+    }
+}
+impl GraphicsSettings {
+    /// The nearest valid settings.
+    pub fn validated(self) -> Self {
+        Self {
+            ray_splits: self.ray_splits.min(MAX_RAY_SPLITS),
+            medium: Medium {
+                // Rounded, so that stepping the density down reaches exactly zero, which turns
+                // it off
+                density: (100.0 * self.medium.density.clamp(0.0, 1.0)).round() / 100.0,
+                extinction: self.medium.extinction.max(0.0),
+            },
+            sun: Sun {
+                azimuth: self.sun.azimuth.rem_euclid(360.0),
+                elevation: self.sun.elevation.clamp(-90.0, 90.0),
+            },
+            radius_scale: self
+                .radius_scale
+                .clamp(*RADIUS_SCALES.start(), *RADIUS_SCALES.end()),
+            emission: self.emission.max(0.0),
+            ..self
+        }
+    }
+}
+
+/// Minimum and maximum corners of a rectangle in the window, in pixels
+type ScreenRect = ((f32, f32), (f32, f32));
+
+/// A body label queued for the next frame, see [`Graphics::label`]
+struct QueuedLabel {
+    anchor: (f32, f32),
+    alpha: f32,
+    /// The text within `Graphics::label_text`
+    text: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Parameters {
     pub texture_format: wgpu::TextureFormat,
     pub present_mode: wgpu::PresentMode,
 }
+impl Parameters {
+    /// The first format the surface supports, and a present mode that waits for vertical sync.
+    pub fn preferred(surface: &wgpu::Surface, adapter: &wgpu::Adapter) -> Self {
+        let supported = surface.get_supported_present_modes(adapter);
+        let preferences = [wgpu::PresentMode::FifoRelaxed, wgpu::PresentMode::Fifo];
+        Self {
+            texture_format: *surface.get_supported_formats(adapter).first().unwrap(),
+            present_mode: preferences
+                .into_iter()
+                .find(|mode| supported.contains(mode))
+                .unwrap_or(supported[0]),
+        }
+    }
+}
 
 pub struct Graphics {
     parameters: Parameters,
     queue: wgpu::Queue,
     device: wgpu::Device,
     surface: wgpu::Surface,
-    body_buffer: wgpu::Buffer,
-    uniforms_buffer: wgpu::Buffer,
     uniforms: Uniforms,
     uniforms_are_new: bool,
-    render_tasks: wgpu::RenderBundle,
+    settings: GraphicsSettings,
+    render_graph: RenderGraph,
+    picker: GpuPicker,
+    #[cfg(not(feature = "msdf-text"))]
     staging_belt: wgpu::util::StagingBelt,
+    #[cfg(not(feature = "msdf-text"))]
     glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    #[cfg(feature = "msdf-text")]
+    text: crate::text::TextRenderer,
     window_size: (u32, u32),
-    this_frame_render_time_nanos_or_zero: Arc<AtomicU64>,
+    frames_in_flight: usize,
+    render_time_sender: mpsc::Sender<Duration>,
+    render_time_receiver: mpsc::Receiver<Duration>,
     fps_latest_instant: Instant,
-    fps_recent_frame_and_render_time: VecDeque<[Duration; 2]>,
+    fps_recent_frame_time: VecDeque<Duration>,
+    fps_recent_render_time: VecDeque<Duration>,
     fps_display: f32,
+    fps_text: String,
+    overlay_text: String,
+    overlay_layout: OverlayLayout,
+    /// How much wider the field of view of the first view is than usual, see
+    /// [`Graphics::set_fov_scale`]
+    fov_scale: f32,
+    /// Whether overlays are drawn at all. Overlays are still queued while hidden.
+    overlay_visible: bool,
+    /// The current themes of the FPS and line widgets
+    overlay_themes: [Theme; 2],
+    skybox_luminance: SkyboxLuminance,
+    panels: PanelRenderer,
+    images: ImageRenderer,
+    labels: Vec<QueuedLabel>,
+    label_text: String,
+    /// Screen rectangles of the labels drawn so far this frame
+    label_rects: Vec<ScreenRect>,
+    /// The text of the section being queued, kept to not allocate every frame
+    #[cfg(not(feature = "msdf-text"))]
+    glyph_texts: Vec<wgpu_glyph::Text<'static>>,
+    /// Whether the next frame is captured by an attached frame debugger
+    #[cfg(feature = "renderdoc")]
+    capture_next_frame: bool,
 }
+/// The limits to request of an adapter: the defaults of the platform, raised to fit the body
+/// buffer. Fails with an explanation if the adapter cannot bind all bodies.
+pub fn required_limits(adapter: &wgpu::Adapter) -> Result<wgpu::Limits, String> {
+    let defaults = if cfg!(target_arch = "wasm32") {
+        wgpu::Limits::downlevel_webgl2_defaults()
+    } else {
+        wgpu::Limits::default()
+    };
+    let supported = adapter.limits().max_uniform_buffer_binding_size;
+    if u64::from(supported) < BODY_BUFFER_SIZE {
+        return Err(format!(
+            "{} binds uniform buffers of at most {supported}B, but {BODIES} bodies need \
+             {BODY_BUFFER_SIZE}B",
+            adapter.get_info().name
+        ));
+    }
+    Ok(wgpu::Limits {
+        max_uniform_buffer_binding_size: defaults
+            .max_uniform_buffer_binding_size
+            .max(BODY_BUFFER_SIZE as u32),
+        ..defaults
+    })
+}
+
 impl Graphics {
     pub async fn initialize(
         parameters: Parameters,
         surface: wgpu::Surface,
         device_and_queue: (wgpu::Device, wgpu::Queue),
         size: (u32, u32),
+    ) -> Self {
+        Self::new(parameters, surface, device_and_queue, size)
+    }
+    fn new(
+        parameters: Parameters,
+        surface: wgpu::Surface,
+        device_and_queue: (wgpu::Device, wgpu::Queue),
+        size: (u32, u32),
     ) -> Self {
         let (device, queue) = device_and_queue;
 
-        let mut uniforms = Uniforms::new();
-        uniforms.window_size = Vector2::from(size).cast().unwrap();
+        let uniforms = Uniforms::new();
         configure_surface(&parameters, &device, &surface, size);
 
-        let body_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Body buffer"),
-            size: ((2 * BODIES - 1) * mem::size_of::<Sphere>()) as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        let uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Uniforms buffer"),
-            size: mem::size_of::<Uniforms>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let (skybox_texture_view, skybox_sampler) =
-            make_skybox_texture_view_and_sampler(&device, &queue);
-        let render_tasks = make_render_tasks(
-            &parameters,
-            &device,
-            &body_buffer,
-            &uniforms_buffer,
-            &skybox_texture_view,
-            &skybox_sampler,
-        );
-
-        let font = wgpu_glyph::ab_glyph::FontArc::try_from_slice(include_bytes!(
-            "../assets/Roboto-Regular-Digits.ttf"
-        ))
-        .unwrap();
-        let glyph_brush = wgpu_glyph::GlyphBrushBuilder::using_font(font)
-            .build(&device, parameters.texture_format);
+        let SurfaceRenderers {
+            render_graph,
+            panels,
+            images,
+            #[cfg(not(feature = "msdf-text"))]
+            glyph_brush,
+            #[cfg(feature = "msdf-text")]
+            text,
+            skybox_luminance,
+        } = SurfaceRenderers::new(&parameters, &device, &queue, size);
+        let picker = GpuPicker::new(&device);
+        let (render_time_sender, render_time_receiver) = mpsc::channel();
 
         Self {
             parameters,
             queue,
             device,
             surface,
-            body_buffer,
-            uniforms_buffer,
             uniforms,
             uniforms_are_new: true,
-            render_tasks,
-            staging_belt: wgpu::util::StagingBelt::new(1024),
+            settings: GraphicsSettings::default(),
+            render_graph,
+            picker,
+            #[cfg(not(feature = "msdf-text"))]
+            staging_belt: wgpu::util::StagingBelt::new(
+                OVERLAY_GLYPH_CAPACITY * GLYPH_INSTANCE_SIZE,
+            ),
+            #[cfg(not(feature = "msdf-text"))]
             glyph_brush,
+            #[cfg(feature = "msdf-text")]
+            text,
             window_size: size,
-            this_frame_render_time_nanos_or_zero: Arc::new(AtomicU64::new(10_000_000)),
+            frames_in_flight: 0,
+            render_time_sender,
+            render_time_receiver,
             fps_latest_instant: Instant::now(),
-            fps_recent_frame_and_render_time: std::iter::once([Duration::from_millis(10); 2])
-                .collect(),
+            fps_recent_frame_time: std::iter::once(Duration::from_millis(10)).collect(),
+            fps_recent_render_time: std::iter::once(Duration::from_millis(10)).collect(),
             fps_display: 100.0,
+            fps_text: String::new(),
+            overlay_text: String::new(),
+            overlay_layout: OverlayLayout::default(),
+            fov_scale: 1.0,
+            overlay_visible: true,
+            overlay_themes: [Theme::Light; 2],
+            skybox_luminance,
+            panels,
+            images,
+            labels: Vec::with_capacity(BODIES),
+            label_text: String::new(),
+            label_rects: Vec::with_capacity(BODIES),
+            #[cfg(not(feature = "msdf-text"))]
+            glyph_texts: Vec::with_capacity(1),
+            #[cfg(feature = "renderdoc")]
+            capture_next_frame: false,
         }
     }
-    pub fn change_ray_splits(&mut self, delta: i8) {
-        match delta {
-            1 if self.uniforms.ray_splits < 4 => {
-                self.uniforms.ray_splits += 1;
-                log::info!("Incremented to ray_splits={}", self.uniforms.ray_splits);
+    pub fn settings(&self) -> GraphicsSettings {
+        self.settings
+    }
+    /// Validates and applies new settings, changing only what differs from the current ones.
+    pub fn apply_settings(&mut self, settings: GraphicsSettings) {
+        let settings = settings.validated();
+        let old = std::mem::replace(&mut self.settings, settings);
+        if settings == old {
+            return;
+        }
+        if settings.backend != old.backend {
+            for pass in old.backend.passes() {
+                self.render_graph.set_enabled(pass, false);
             }
-            -1 if self.uniforms.ray_splits > 0 => {
-                self.uniforms.ray_splits -= 1;
-                log::info!("Decremented to ray_splits={}", self.uniforms.ray_splits);
+            for pass in settings.backend.passes() {
+                self.render_graph.set_enabled(pass, true);
             }
-            -1 | 1 => {}
-            other => unreachable!("{}", other),
         }
+        self.render_graph
+            .set_enabled("disk", settings.accretion_disk);
+        self.uniforms.ray_splits = settings.ray_splits;
+        self.uniforms.primary_from_gbuffer = u32::from(settings.backend == Backend::Hybrid);
+        self.uniforms.medium_density = settings.medium.density;
+        self.uniforms.medium_extinction = settings.medium.extinction;
+        self.uniforms.emission = settings.emission;
         self.uniforms_are_new = true;
+        log::info!("Set {settings:?}");
+    }
+    pub fn ray_splits(&self) -> u32 {
+        self.settings.ray_splits
+    }
+    pub fn set_ray_splits(&mut self, ray_splits: u32) {
+        self.apply_settings(GraphicsSettings {
+            ray_splits,
+            ..self.settings
+        });
+    }
+    pub fn backend(&self) -> Backend {
+        self.settings.backend
+    }
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.apply_settings(GraphicsSettings {
+            backend,
+            ..self.settings
+        });
+    }
+    pub fn medium(&self) -> Medium {
+        self.settings.medium
+    }
+    pub fn set_medium(&mut self, medium: Medium) {
+        self.apply_settings(GraphicsSettings {
+            medium,
+            ..self.settings
+        });
+    }
+    pub fn lensing(&self) -> bool {
+        self.settings.lensing
+    }
+    pub fn set_lensing(&mut self, lensing: bool) {
+        self.apply_settings(GraphicsSettings {
+            lensing,
+            ..self.settings
+        });
+    }
+    pub fn sun(&self) -> Sun {
+        self.settings.sun
+    }
+    pub fn set_sun(&mut self, sun: Sun) {
+        self.apply_settings(GraphicsSettings {
+            sun,
+            ..self.settings
+        });
+    }
+    pub fn accretion_disk(&self) -> bool {
+        self.settings.accretion_disk
+    }
+    pub fn set_accretion_disk(&mut self, accretion_disk: bool) {
+        self.apply_settings(GraphicsSettings {
+            accretion_disk,
+            ..self.settings
+        });
+    }
+    pub fn boundary_outline(&self) -> bool {
+        self.settings.boundary_outline
+    }
+    pub fn set_boundary_outline(&mut self, boundary_outline: bool) {
+        self.apply_settings(GraphicsSettings {
+            boundary_outline,
+            ..self.settings
+        });
+    }
+    pub fn radius_scale(&self) -> f32 {
+        self.settings.radius_scale
+    }
+    pub fn set_radius_scale(&mut self, radius_scale: f32) {
+        self.apply_settings(GraphicsSettings {
+            radius_scale,
+            ..self.settings
+        });
+    }
+    pub fn emission(&self) -> f32 {
+        self.settings.emission
+    }
+    pub fn set_emission(&mut self, emission: f32) {
+        self.apply_settings(GraphicsSettings {
+            emission,
+            ..self.settings
+        });
+    }
+    /// Draws massless tracer particles, or none if empty.
+    pub fn set_tracers(&mut self, tracers: &[Tracer]) {
+        let tracers = &tracers[..tracers.len().min(MAX_TRACERS)];
+        if !tracers.is_empty() {
+            self.queue.write_buffer(
+                self.render_graph.buffer("tracers"),
+                0,
+                bytemuck::cast_slice(tracers),
+            );
+        }
+        self.render_graph
+            .set_enabled("tracers", !tracers.is_empty());
+        if self.uniforms.tracer_count != tracers.len() as u32 {
+            self.uniforms.tracer_count = tracers.len() as u32;
+            self.uniforms_are_new = true;
+        }
+    }
+    /// Sets how far the simulation has come, which animates the accretion disk.
+    pub fn set_simulated_time(&mut self, time: Duration) {
+        let seconds = time.as_secs_f32();
+        if seconds != self.uniforms.disk_time {
+            self.uniforms.disk_time = seconds;
+            self.uniforms_are_new = true;
+        }
     }
-    #[cfg(target_arch = "wasm32")]
     pub fn window_size(&self) -> (u32, u32) {
         self.window_size
     }
+    pub fn parameters(&self) -> Parameters {
+        self.parameters
+    }
+    /// Checks what the surface supports anew, which changes when the window is moved to a
+    /// monitor of another GPU. A present mode that is no longer supported only reconfigures the
+    /// surface, but a format that is no longer supported rebuilds everything drawing to it, which
+    /// also empties the image slots.
+    pub fn refresh_parameters(&mut self, adapter: &wgpu::Adapter) {
+        let formats = self.surface.get_supported_formats(adapter);
+        let present_modes = self.surface.get_supported_present_modes(adapter);
+        if formats.is_empty() || present_modes.is_empty() {
+            // Mid-move or minimized, try again once the window settles
+            return;
+        }
+        let current = self.parameters;
+        let preferred = Parameters::preferred(&self.surface, adapter);
+        let parameters = Parameters {
+            texture_format: if formats.contains(&current.texture_format) {
+                current.texture_format
+            } else {
+                preferred.texture_format
+            },
+            present_mode: if present_modes.contains(&current.present_mode) {
+                current.present_mode
+            } else {
+                preferred.present_mode
+            },
+        };
+        if parameters == current {
+            return;
+        }
+        log::info!("Surface changed from {current:?} to {parameters:?}");
+        self.parameters = parameters;
+        configure_surface(
+            &self.parameters,
+            &self.device,
+            &self.surface,
+            self.window_size,
+        );
+        if parameters.texture_format == current.texture_format {
+            return;
+        }
+        // Let the GPU finish with the old pipelines before they are dropped
+        self.device.poll(wgpu::Maintain::Wait);
+        let SurfaceRenderers {
+            render_graph,
+            panels,
+            images,
+            #[cfg(not(feature = "msdf-text"))]
+            glyph_brush,
+            #[cfg(feature = "msdf-text")]
+            text,
+            skybox_luminance: _,
+        } = SurfaceRenderers::new(
+            &self.parameters,
+            &self.device,
+            &self.queue,
+            self.window_size,
+        );
+        self.render_graph = render_graph;
+        self.panels = panels;
+        self.images = images;
+        #[cfg(not(feature = "msdf-text"))]
+        {
+            self.glyph_brush = glyph_brush;
+        }
+        #[cfg(feature = "msdf-text")]
+        {
+            self.text = text;
+        }
+        // The new render graph starts out with the default settings
+        let settings = std::mem::take(&mut self.settings);
+        self.apply_settings(settings);
+        self.uniforms_are_new = true;
+    }
     pub fn resize(&mut self, (w, h): (u32, u32)) {
         self.window_size = (w, h);
-        self.uniforms.window_size = Vector2::from((w as f32, h as f32));
         self.uniforms_are_new = true;
         configure_surface(
             &self.parameters,
@@ -154,56 +651,190 @@ impl Graphics {
             &self.surface,
             self.window_size,
         );
+        self.render_graph.resize(&self.device, self.window_size);
     }
-    pub fn get_recent_avg_frame_and_render_time(&self) -> [Duration; 2] {
-        self.device.poll(wgpu::MaintainBase::Poll);
-        let [f, r] = self
-            .fps_recent_frame_and_render_time
-            .iter()
-            .copied()
-            .reduce(|[f1, r1], [f2, r2]| [f1 + f2, r1 + r2])
-            .unwrap();
-        let n = self.fps_recent_frame_and_render_time.len() as u32;
+    pub fn split_screen(&self) -> bool {
+        self.settings.split_screen
+    }
+    /// With a split screen, [`Graphics::render`] takes two views, shown left and right.
+    pub fn set_split_screen(&mut self, split_screen: bool) {
+        self.apply_settings(GraphicsSettings {
+            split_screen,
+            ..self.settings
+        });
+    }
+    /// The part of the window showing a view. Screen positions given to and returned by the
+    /// overlay methods are within the window, and projections are those of the first view.
+    pub fn viewport(&self, view: usize) -> Viewport {
+        let (width, height) = self.window_size;
+        let count = if self.settings.split_screen { 2 } else { 1 };
+        let x = width / count * view as u32;
+        let end = if view as u32 + 1 == count {
+            width
+        } else {
+            width / count * (view as u32 + 1)
+        };
+        Viewport {
+            offset: (x, 0),
+            size: (end - x, height),
+        }
+    }
+    /// Asks for the body visible at a pixel, to be answered by [`Graphics::poll_pick`] within a
+    /// few frames.
+    pub fn request_pick(&mut self, pixel: (u32, u32)) {
+        self.picker.request(pixel);
+    }
+    pub fn poll_pick(&mut self) -> Option<Picked> {
+        self.picker.poll(&self.device)
+    }
+    /// Adds a line of text below the FPS counter for the next frame only.
+    pub fn overlay_line(&mut self, line: fmt::Arguments<'_>) {
+        writeln!(self.overlay_text, "{line}").unwrap();
+    }
+    /// Widens the field of view of the first view, by a factor of the tangent of its half angle,
+    /// or narrows it with a factor below one. Projections of the overlay follow along.
+    pub fn set_fov_scale(&mut self, scale: f32) {
+        self.fov_scale = scale;
+    }
+    /// The size and offset in pixels of the viewport one unit tall at unit distance, which the
+    /// shaders project a view into, see [`Graphics::capture_viewport`]. It is the part of the
+    /// window showing the view, shrunk about its center to widen the field of view.
+    fn projection_viewport(&self, view: usize) -> (Vector2<f32>, Vector2<f32>) {
+        let Viewport { offset, size } = self.viewport(view);
+        let size = Vector2::new(size.0 as f32, size.1 as f32);
+        let offset = Vector2::new(offset.0 as f32, offset.1 as f32);
+        let scale = if view == 0 { self.fov_scale } else { 1.0 };
+        (size / scale, offset + 0.5 * (size - size / scale))
+    }
+    /// Where a point in camera space appears in the window, in pixels, if in front of the camera.
+    /// This is the projection of the renderer, see `project` in raster.wgsl.
+    pub fn project(&self, view_pos: Vector3<f32>) -> Option<(f32, f32)> {
+        let (size, offset) = self.projection_viewport(0);
+        (view_pos.z > NEAR).then(|| {
+            (
+                offset.x + 0.5 * size.x + view_pos.x / view_pos.z * size.y,
+                offset.y + 0.5 * size.y + view_pos.y / view_pos.z * size.y,
+            )
+        })
+    }
+    /// The point at unit depth in camera space that appears at a pixel, the inverse of
+    /// [`Graphics::project`].
+    pub fn unproject(&self, (px, py): (f32, f32)) -> Vector3<f32> {
+        let (size, offset) = self.projection_viewport(0);
+        Vector3::new(
+            (px - offset.x - 0.5 * size.x) / size.y,
+            (py - offset.y - 0.5 * size.y) / size.y,
+            1.0,
+        )
+    }
+    /// Adds a line between two points in pixels, below the overlay text, for the next frame only.
+    pub fn overlay_segment(&mut self, from: (f32, f32), to: (f32, f32), color: [f32; 4]) {
+        self.panels
+            .queue_line((from, to), 2.0, color, self.window_size);
+    }
+    /// Adds a filled rectangle in pixels, below the overlay text, for the next frame only.
+    pub fn overlay_rect(&mut self, rect: ScreenRect, color: [f32; 4]) {
+        self.panels.queue(rect, color, self.window_size);
+    }
+    /// Replaces an image to draw with [`Graphics::overlay_image`], see [`crate::images`].
+    pub fn set_overlay_image(&mut self, slot: usize, image: &image::RgbaImage) {
+        self.images.upload(&self.queue, slot, image);
+    }
+    /// Draws an image over a rectangle in pixels, above the panels, for the next frame only.
+    pub fn overlay_image(&mut self, slot: usize, rect: ScreenRect) {
+        self.images.queue(slot, rect, self.window_size);
+    }
+    /// Outlines the silhouette of a sphere in camera space for the next frame only, approximated
+    /// as a circle around its projected center.
+    pub fn overlay_sphere(&mut self, view_center: Vector3<f32>, radius: f32, color: [f32; 4]) {
+        let (Some(mid), Some(top)) = (
+            self.project(view_center),
+            self.project(view_center - radius * Vector3::unit_y()),
+        ) else {
+            return;
+        };
+        self.overlay_circle(mid, mid.1 - top.1, color);
+    }
+    /// Outlines a circle in pixels for the next frame only.
+    pub fn overlay_circle(&mut self, center: (f32, f32), radius: f32, color: [f32; 4]) {
+        const SEGMENTS: usize = 48;
+        let point = |i: usize| {
+            let angle = std::f32::consts::TAU * i as f32 / SEGMENTS as f32;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        };
+        for i in 0..SEGMENTS {
+            self.overlay_segment(point(i), point(i + 1), color);
+        }
+    }
+    /// Adds a label centered above `anchor`, in pixels, for the next frame only. It is hidden if
+    /// it would overlap a label added before it.
+    pub fn label(&mut self, anchor: (f32, f32), alpha: f32, text: fmt::Arguments<'_>) {
+        let start = self.label_text.len();
+        self.label_text.write_fmt(text).unwrap();
+        self.labels.push(QueuedLabel {
+            anchor,
+            alpha,
+            text: start..self.label_text.len(),
+        });
+    }
+    /// Captures the next rendered frame in an attached frame debugger such as `RenderDoc`, which
+    /// does nothing unless the program runs under one.
+    #[cfg(feature = "renderdoc")]
+    pub fn capture_next_frame(&mut self) {
+        log::info!("Capturing the next frame with RenderDoc");
+        self.capture_next_frame = true;
+    }
+    pub fn set_overlay_visible(&mut self, visible: bool) {
+        self.overlay_visible = visible;
+    }
+    pub fn overlay_layout(&self) -> &OverlayLayout {
+        &self.overlay_layout
+    }
+    pub fn overlay_layout_mut(&mut self) -> &mut OverlayLayout {
+        &mut self.overlay_layout
+    }
+    pub fn get_recent_avg_frame_and_render_time(&mut self) -> [Duration; 2] {
+        self.collect_render_times();
+        let f: Duration = self.fps_recent_frame_time.iter().sum();
+        let r: Duration = self.fps_recent_render_time.iter().sum();
         [
-            (f + Instant::now().duration_since(self.fps_latest_instant)) / n,
-            r / n,
+            (f + Instant::now().duration_since(self.fps_latest_instant))
+                / self.fps_recent_frame_time.len() as u32,
+            r / self.fps_recent_render_time.len() as u32,
         ]
     }
-    pub fn render(
-        &mut self,
-        bodies: Vec<Sphere>,
-        rotation: Quaternion<f32>,
-        update_fps_display: bool,
-    ) {
-        let now_pre_render = Instant::now();
-        // Copy state to GPU
-        {
-            self.queue
-                .write_buffer(&self.body_buffer, 0, bytemuck::cast_slice(&bodies));
-            let sun_direction = rotation.conjugate().rotate_vector(Vector3::unit_x());
-            let view_to_world_space = Matrix4::from(Matrix3::from_cols(
-                rotation.rotate_vector(Vector3::unit_x()),
-                rotation.rotate_vector(Vector3::unit_y()),
-                rotation.rotate_vector(Vector3::unit_z()),
-            ));
-            if sun_direction != self.uniforms.sun_direction
-                || view_to_world_space != self.uniforms.view_to_world_space
-            {
-                self.uniforms_are_new = true;
-                self.uniforms.sun_direction = sun_direction;
-                self.uniforms.view_to_world_space = view_to_world_space;
-            }
-            if self.uniforms_are_new {
-                self.queue.write_buffer(
-                    &self.uniforms_buffer,
-                    0,
-                    bytemuck::cast_slice(&[self.uniforms]),
-                );
-                self.uniforms_are_new = false;
+    /// Records the render times of frames the GPU has completed, without blocking unless
+    /// [`MAX_FRAMES_IN_FLIGHT`] is reached.
+    fn collect_render_times(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.frames_in_flight >= MAX_FRAMES_IN_FLIGHT {
+            self.device.poll(wgpu::Maintain::Wait);
+        }
+        self.device.poll(wgpu::Maintain::Poll);
+        for render_time in self.render_time_receiver.try_iter() {
+            self.frames_in_flight -= 1;
+            if self.fps_recent_render_time.len() >= FRAME_TIME_HISTORY_COUNT {
+                self.fps_recent_render_time.pop_front();
             }
+            self.fps_recent_render_time.push_back(render_time);
         }
-        // Render
-        let render_time = {
+    }
+    /// Renders one view, or two side by side with a split screen, see
+    /// [`Graphics::set_split_screen`].
+    pub fn render(&mut self, views: &[View<'_>], update_fps_display: bool) {
+        debug_assert_eq!(views.len(), if self.settings.split_screen { 2 } else { 1 });
+        self.collect_render_times();
+        let now_pre_render = Instant::now();
+        #[cfg(feature = "renderdoc")]
+        let capturing = std::mem::take(&mut self.capture_next_frame);
+        #[cfg(feature = "renderdoc")]
+        if capturing {
+            self.device.start_capture();
+        }
+        {
             let surface_texture = self
                 .surface
                 .get_current_texture()
@@ -235,108 +866,567 @@ impl Graphics {
                         array_layer_count: None,
                     });
 
-            let mut encoder = self
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Command encoder"),
-                });
-            encoder
-                .begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("render pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: surface_texture_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 0.0,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                })
-                .execute_bundles(std::iter::once(&self.render_tasks));
-
-            self.glyph_brush.queue(wgpu_glyph::Section {
-                screen_position: (5.0, 5.0),
-                bounds: (self.window_size.0 as f32, self.window_size.1 as f32),
-                text: vec![wgpu_glyph::Text::new({
-                    let fps = self.fps_display;
-                    let precision = (2 - fps.log10().ceil() as isize).max(0) as usize;
-                    &format!("{fps:.precision$}")
-                })
-                .with_color([0.5, 0.5, 0.5, 1.0])
-                .with_scale(32.0)],
-                layout: wgpu_glyph::Layout::default_single_line(),
-            });
-            self.glyph_brush
-                .draw_queued(
-                    &self.device,
-                    &mut self.staging_belt,
-                    &mut encoder,
-                    surface_texture_view,
-                    self.window_size.0,
-                    self.window_size.1,
-                )
-                .unwrap();
-            self.staging_belt.finish();
-
-            let render_time = loop {
-                let render_time = self
-                    .this_frame_render_time_nanos_or_zero
-                    .load(Ordering::SeqCst);
-                if render_time == 0 {
-                    self.device.poll(wgpu::Maintain::Wait);
-                    continue;
+            // Views share the body and uniform buffers, so each is copied to the GPU and submitted
+            // before the next
+            for (index, view) in views.iter().enumerate() {
+                let viewport = self.viewport(index);
+                let (size, offset) = self.projection_viewport(index);
+                self.copy_view_to_gpu(view, size, offset, index == 0);
+                let mut encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Command encoder"),
+                        });
+                self.render_graph
+                    .execute(&mut encoder, surface_texture_view, viewport, index == 0);
+                if index + 1 == views.len() {
+                    self.picker.record(
+                        &mut encoder,
+                        self.render_graph.target_texture("ids"),
+                        self.window_size,
+                    );
+                    if self.overlay_visible {
+                        self.draw_overlay(&mut encoder, surface_texture_view);
+                    } else {
+                        self.panels.clear();
+                        self.images.clear();
+                    }
+                    self.overlay_text.clear();
+                    self.labels.clear();
+                    self.label_text.clear();
                 }
-                self.this_frame_render_time_nanos_or_zero
-                    .compare_exchange(render_time, 0, Ordering::SeqCst, Ordering::SeqCst)
-                    .unwrap();
-                break render_time;
-            };
-            self.queue.submit(std::iter::once(encoder.finish()));
+                let _span = crate::trace::span("gpu submit");
+                self.queue.submit(std::iter::once(encoder.finish()));
+            }
+            self.picker.submitted();
+            self.frames_in_flight += 1;
             self.queue.on_submitted_work_done({
-                let slot = self.this_frame_render_time_nanos_or_zero.clone();
+                let sender = self.render_time_sender.clone();
                 move || {
-                    let render_time: u64 = Instant::now()
-                        .checked_duration_since(now_pre_render)
-                        .unwrap()
-                        .as_nanos()
-                        .try_into()
-                        .unwrap();
-                    slot.compare_exchange(0, render_time, Ordering::SeqCst, Ordering::SeqCst)
-                        .unwrap();
+                    // The receiver only goes away along with `Graphics`
+                    let _ = sender.send(Instant::now().duration_since(now_pre_render));
                 }
             });
             surface_texture.present();
+            #[cfg(not(feature = "msdf-text"))]
             self.staging_belt.recall();
-            render_time
-        };
+        }
+        #[cfg(feature = "renderdoc")]
+        if capturing {
+            self.device.stop_capture();
+        }
         {
             let now = Instant::now();
             let frame_time = now.duration_since(self.fps_latest_instant);
             self.fps_latest_instant = now;
 
-            while self.fps_recent_frame_and_render_time.len() > FRAME_TIME_HISTORY_COUNT {
-                self.fps_recent_frame_and_render_time.pop_front();
+            if self.fps_recent_frame_time.len() >= FRAME_TIME_HISTORY_COUNT {
+                self.fps_recent_frame_time.pop_front();
             }
-            self.fps_recent_frame_and_render_time
-                .push_back([frame_time, Duration::from_nanos(render_time)]);
+            self.fps_recent_frame_time.push_back(frame_time);
 
             if update_fps_display {
-                self.fps_display = (self.fps_recent_frame_and_render_time.len() as f32)
+                self.fps_display = (self.fps_recent_frame_time.len() as f32)
                     / self
-                        .fps_recent_frame_and_render_time
+                        .fps_recent_frame_time
                         .iter()
-                        .map(|[f, _]| f.as_secs_f32())
+                        .map(Duration::as_secs_f32)
                         .sum::<f32>();
             }
         }
     }
 }
+impl Graphics {
+    /// Renders a view into an image of any size, without overlays. The image is rendered in
+    /// window-sized tiles one after another, and this blocks until the GPU is done.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture(&mut self, view: &View<'_>, image_size: (u32, u32)) -> image::RgbaImage {
+        let size = Vector2::new(image_size.0, image_size.1).cast().unwrap();
+        self.capture_viewport(view, image_size, size, Vector2::zero())
+    }
+    /// Like [`Graphics::capture`], but with a viewport of `viewport_size` pixels at
+    /// `viewport_offset` in the image. The viewport is one unit tall at unit distance, so a
+    /// viewport half as tall as a square image centered in it gives a 90 degree field of view.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture_viewport(
+        &mut self,
+        view: &View<'_>,
+        (image_width, image_height): (u32, u32),
+        viewport_size: Vector2<f32>,
+        viewport_offset: Vector2<f32>,
+    ) -> image::RgbaImage {
+        let (width, height) = self.window_size;
+        let tile = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture tile"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.parameters.texture_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let tile_view = tile.create_view(&wgpu::TextureViewDescriptor::default());
+        let bytes_per_row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture buffer"),
+            size: u64::from(bytes_per_row * height),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bgra = matches!(
+            self.parameters.texture_format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut image = image::RgbaImage::new(image_width, image_height);
+        let tiles = (image_width.div_ceil(width), image_height.div_ceil(height));
+        log::info!(
+            "Rendering a {image_width}x{image_height} image in {} tiles",
+            tiles.0 * tiles.1
+        );
+        for (tile_x, tile_y) in (0..tiles.1).flat_map(|y| (0..tiles.0).map(move |x| (x, y))) {
+            self.copy_view_to_gpu(
+                view,
+                viewport_size,
+                viewport_offset
+                    - Vector2::new(tile_x * width, tile_y * height)
+                        .cast()
+                        .unwrap(),
+                false,
+            );
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Capture encoder"),
+                });
+            self.render_graph.execute(
+                &mut encoder,
+                &tile_view,
+                Viewport {
+                    offset: (0, 0),
+                    size: (width, height),
+                },
+                true,
+            );
+            encoder.copy_texture_to_buffer(
+                tile.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+            let slice = buffer.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+            self.device.poll(wgpu::Maintain::Wait);
+            {
+                // Tiles along the right and bottom edges may extend past the image
+                let (origin_x, origin_y) = (tile_x * width, tile_y * height);
+                let visible_width = width.min(image_width - origin_x) as usize;
+                let visible_height = height.min(image_height - origin_y) as usize;
+                let bytes = slice.get_mapped_range();
+                let rows = bytes.chunks(bytes_per_row as usize).take(visible_height);
+                for (y, row) in rows.enumerate() {
+                    for (x, pixel) in row[..4 * visible_width].chunks(4).enumerate() {
+                        let [r, g, b] = if bgra {
+                            [pixel[2], pixel[1], pixel[0]]
+                        } else {
+                            [pixel[0], pixel[1], pixel[2]]
+                        };
+                        image.put_pixel(
+                            origin_x + x as u32,
+                            origin_y + y as u32,
+                            image::Rgba([r, g, b, 255]),
+                        );
+                    }
+                }
+            }
+            buffer.unmap();
+        }
+        image
+    }
+    /// Writes the bodies and uniforms of a view, with a viewport of `size` pixels at `offset` in
+    /// the target. Only the uniforms of the `primary` view, which overlays are drawn for, are
+    /// kept, and skipped when unchanged.
+    fn copy_view_to_gpu(
+        &mut self,
+        view: &View<'_>,
+        size: Vector2<f32>,
+        offset: Vector2<f32>,
+        primary: bool,
+    ) {
+        self.queue.write_buffer(
+            self.render_graph.buffer("bodies"),
+            0,
+            bytemuck::cast_slice(view.bodies.spheres),
+        );
+        self.queue.write_buffer(
+            self.render_graph.buffer("orientations"),
+            0,
+            bytemuck::cast_slice(view.bodies.orientations),
+        );
+        let rotation = view.pose.rotation;
+        // Lensing and the accretion disk are both centered on the heaviest body, and are turned
+        // off by a zero radius
+        let heaviest = Sphere::heaviest(view.bodies.spheres);
+        let heaviest_if = |enabled: bool| {
+            heaviest
+                .filter(|_| enabled)
+                .unwrap_or((Vector3::zero(), 0.0))
+        };
+        let (lens_position, lens_radius) = heaviest_if(self.settings.lensing);
+        let (disk_center, disk_body_radius) = heaviest_if(self.settings.accretion_disk);
+        let uniforms = Uniforms {
+            lens_position,
+            lens_radius,
+            disk_center,
+            disk_body_radius,
+            camera_position: view.pose.position,
+            sun_direction: rotation
+                .conjugate()
+                .rotate_vector(self.settings.sun.direction()),
+            viewport_size: size,
+            viewport_offset: offset,
+            target_size: Vector2::new(self.window_size.0, self.window_size.1)
+                .cast()
+                .unwrap(),
+            view_to_world_space: Matrix4::from(Matrix3::from_cols(
+                rotation.rotate_vector(Vector3::unit_x()),
+                rotation.rotate_vector(Vector3::unit_y()),
+                rotation.rotate_vector(Vector3::unit_z()),
+            )),
+            ..self.uniforms
+        };
+        if !primary || self.uniforms_are_new || uniforms != self.uniforms {
+            self.queue.write_buffer(
+                self.render_graph.buffer("uniforms"),
+                0,
+                bytemuck::cast_slice(&[uniforms]),
+            );
+        }
+        if primary {
+            self.uniforms = uniforms;
+        }
+        // Whatever is drawn next must write its own uniforms after another view
+        self.uniforms_are_new = !primary;
+    }
+    fn update_fps_text(&mut self) {
+        let fps = self.fps_display;
+        let precision = (2 - fps.log10().ceil() as isize).max(0) as usize;
+        self.fps_text.clear();
+        write!(self.fps_text, "{fps:.precision$}").unwrap();
+    }
+    #[cfg(not(feature = "msdf-text"))]
+    fn draw_overlay(&mut self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        use crate::rendergraph::recycle;
+        use wgpu_glyph::{GlyphCruncher, HorizontalAlign, Layout, VerticalAlign};
+        self.update_fps_text();
+        self.label_rects.clear();
+        // Every section is queued by reference and then gives back its text to the next
+        let mut texts = recycle(std::mem::take(&mut self.glyph_texts));
+        for label in &self.labels {
+            let [r, g, b, _] = Theme::Light.text_color();
+            texts.clear();
+            texts.push(
+                wgpu_glyph::Text::new(&self.label_text[label.text.clone()])
+                    .with_scale(LABEL_SCALE)
+                    .with_color([r, g, b, label.alpha]),
+            );
+            let section = wgpu_glyph::Section {
+                screen_position: label.anchor,
+                bounds: (self.window_size.0 as f32, self.window_size.1 as f32),
+                text: texts,
+                layout: Layout::default_single_line()
+                    .h_align(HorizontalAlign::Center)
+                    .v_align(VerticalAlign::Bottom),
+            };
+            if let Some(bounds) = self.glyph_brush.glyph_bounds(&section) {
+                let rect = ((bounds.min.x, bounds.min.y), (bounds.max.x, bounds.max.y));
+                if place_label(&mut self.label_rects, rect) {
+                    self.glyph_brush.queue(&section);
+                }
+            }
+            texts = section.text;
+        }
+        let layout = self.overlay_layout;
+        let widgets = [
+            (&layout.fps, &self.fps_text, 32.0),
+            (&layout.lines, &self.overlay_text, 20.0),
+        ];
+        for (theme, (widget, text, scale)) in self.overlay_themes.iter_mut().zip(widgets) {
+            if !widget.visible {
+                continue;
+            }
+            texts.clear();
+            texts.push(wgpu_glyph::Text::new(text).with_scale(scale));
+            let mut section = glyph_section(widget, self.window_size, texts);
+            if let Some(bounds) = self.glyph_brush.glyph_bounds(&section) {
+                let theme = backdrop(
+                    theme,
+                    &mut self.panels,
+                    &self.skybox_luminance,
+                    ((bounds.min.x, bounds.min.y), (bounds.max.x, bounds.max.y)),
+                    self.window_size,
+                    self.uniforms.view_to_world_space,
+                );
+                section.text[0].extra.color = theme.text_color();
+                self.glyph_brush.queue(&section);
+            }
+            texts = section.text;
+        }
+        self.glyph_texts = recycle(texts);
+        self.panels.draw(&self.device, &self.queue, encoder, target);
+        self.images.draw(&self.queue, encoder, target);
+        // Queued sections are laid out and drawn together
+        self.glyph_brush
+            .draw_queued(
+                &self.device,
+                &mut self.staging_belt,
+                encoder,
+                target,
+                self.window_size.0,
+                self.window_size.1,
+            )
+            .unwrap();
+        self.staging_belt.finish();
+    }
+    #[cfg(feature = "msdf-text")]
+    fn draw_overlay(&mut self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        use crate::text::Text;
+        self.update_fps_text();
+        self.label_rects.clear();
+        for label in &self.labels {
+            let text = &self.label_text[label.text.clone()];
+            let (w, h) = self.text.measure(text, LABEL_SCALE);
+            let position = (label.anchor.0 - 0.5 * w, label.anchor.1 - h);
+            if !place_label(
+                &mut self.label_rects,
+                (position, (position.0 + w, position.1 + h)),
+            ) {
+                continue;
+            }
+            let [r, g, b, _] = Theme::Light.text_color();
+            self.text.queue(
+                &Text {
+                    text,
+                    position,
+                    scale: LABEL_SCALE,
+                    color: [r, g, b, label.alpha],
+                    outline: ([0.0, 0.0, 0.0, 0.8 * label.alpha], 1.5),
+                },
+                self.window_size,
+            );
+        }
+        let layout = self.overlay_layout;
+        let widgets = [
+            (&layout.fps, &self.fps_text, 32.0),
+            (&layout.lines, &self.overlay_text, 20.0),
+        ];
+        for (theme, (widget, text, scale)) in self.overlay_themes.iter_mut().zip(widgets) {
+            if !widget.visible {
+                continue;
+            }
+            let size = self.text.measure(text, scale);
+            if size.0 == 0.0 {
+                continue;
+            }
+            let position = widget.top_left(size, self.window_size);
+            let theme = backdrop(
+                theme,
+                &mut self.panels,
+                &self.skybox_luminance,
+                (position, (position.0 + size.0, position.1 + size.1)),
+                self.window_size,
+                self.uniforms.view_to_world_space,
+            );
+            let [r, g, b, _] = theme.panel_color();
+            self.text.queue(
+                &Text {
+                    text,
+                    position,
+                    scale,
+                    color: theme.text_color(),
+                    outline: ([r, g, b, 0.8], 1.5),
+                },
+                self.window_size,
+            );
+        }
+        self.panels.draw(&self.device, &self.queue, encoder, target);
+        self.images.draw(&self.queue, encoder, target);
+        self.text.draw(&self.device, &self.queue, encoder, target);
+    }
+}
+impl Subscriber for Graphics {
+    fn notify(&mut self, event: &AppEvent) {
+        match *event {
+            AppEvent::WindowResized(size) => self.resize(size),
+            AppEvent::ScenarioLoaded(ScenarioSettings {
+                ray_splits: Some(ray_splits),
+                ..
+            }) => self.set_ray_splits(ray_splits),
+            AppEvent::TicksCompleted(_)
+            | AppEvent::Collision { .. }
+            | AppEvent::BodyAdded(_)
+            | AppEvent::BodyRemoved(_)
+            | AppEvent::BodiesReplaced
+            | AppEvent::SettingChanged(_)
+            | AppEvent::CameraModeChanged(_)
+            | AppEvent::CommandExecuted(_)
+            | AppEvent::ScenarioLoaded(_) => {}
+        }
+    }
+}
+
+/// Records the rectangle of a label unless it overlaps one already placed, returning whether it
+/// was placed.
+fn place_label(placed: &mut Vec<ScreenRect>, rect: ScreenRect) -> bool {
+    let ((x0, y0), (x1, y1)) = rect;
+    let overlaps = placed
+        .iter()
+        .any(|&((a0, b0), (a1, b1))| x0 < a1 && a0 < x1 && y0 < b1 && b0 < y1);
+    if !overlaps {
+        placed.push(rect);
+    }
+    !overlaps
+}
+
+/// Adapts the theme of an overlay widget covering `rect` to the skybox behind it, and queues a
+/// backing panel in that theme.
+fn backdrop(
+    theme: &mut Theme,
+    panels: &mut PanelRenderer,
+    luminance: &SkyboxLuminance,
+    rect: ScreenRect,
+    window_size: (u32, u32),
+    view_to_world: Matrix4<f32>,
+) -> Theme {
+    *theme = theme.adapt(luminance.behind(rect, window_size, view_to_world));
+    let ((x0, y0), (x1, y1)) = rect;
+    panels.queue(
+        (
+            (x0 - PANEL_PADDING, y0 - PANEL_PADDING),
+            (x1 + PANEL_PADDING, y1 + PANEL_PADDING),
+        ),
+        theme.panel_color(),
+        window_size,
+    );
+    *theme
+}
+
+/// A section of text placed by an overlay widget layout
+#[cfg(not(feature = "msdf-text"))]
+fn glyph_section<'a>(
+    layout: &crate::overlay::WidgetLayout,
+    window_size: (u32, u32),
+    text: Vec<wgpu_glyph::Text<'a>>,
+) -> wgpu_glyph::Section<'a> {
+    use wgpu_glyph::{HorizontalAlign, Layout, VerticalAlign};
+    let (fx, fy) = layout.anchor.fraction();
+    let h_align = match fx {
+        x if x < 0.5 => HorizontalAlign::Left,
+        x if x > 0.5 => HorizontalAlign::Right,
+        _ => HorizontalAlign::Center,
+    };
+    let v_align = match fy {
+        y if y < 0.5 => VerticalAlign::Top,
+        y if y > 0.5 => VerticalAlign::Bottom,
+        _ => VerticalAlign::Center,
+    };
+    wgpu_glyph::Section {
+        screen_position: layout.anchor_point(window_size),
+        bounds: (window_size.0 as f32, window_size.1 as f32),
+        text,
+        layout: Layout::default_wrap().h_align(h_align).v_align(v_align),
+    }
+}
+
+/// Everything that draws to the surface, and so depends on its format
+struct SurfaceRenderers {
+    render_graph: RenderGraph,
+    panels: PanelRenderer,
+    images: ImageRenderer,
+    #[cfg(not(feature = "msdf-text"))]
+    glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    #[cfg(feature = "msdf-text")]
+    text: crate::text::TextRenderer,
+    skybox_luminance: SkyboxLuminance,
+}
+impl SurfaceRenderers {
+    fn new(
+        parameters: &Parameters,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+    ) -> Self {
+        let body_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Body buffer"),
+            size: BODY_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Uniforms buffer"),
+            size: mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let skybox_images = load_skybox_images();
+        let skybox_luminance = SkyboxLuminance::new(&skybox_images);
+        let (skybox_texture_view, skybox_sampler) =
+            make_skybox_texture_view_and_sampler(device, queue, skybox_images);
+        let mut render_graph = make_render_graph(
+            parameters,
+            device,
+            body_buffer,
+            uniforms_buffer,
+            skybox_texture_view,
+            skybox_sampler,
+        );
+        render_graph.resize(device, size);
+        let panels = PanelRenderer::new(device, parameters.texture_format, 2);
+        let images = ImageRenderer::new(device, parameters.texture_format);
+        let font = wgpu_glyph::ab_glyph::FontArc::try_from_slice(include_bytes!(
+            "../assets/Roboto-Regular-Digits.ttf"
+        ))
+        .unwrap();
+        #[cfg(not(feature = "msdf-text"))]
+        let glyph_brush = wgpu_glyph::GlyphBrushBuilder::using_font(font)
+            .build(device, parameters.texture_format);
+        #[cfg(feature = "msdf-text")]
+        let text = crate::text::TextRenderer::new(
+            device,
+            queue,
+            parameters.texture_format,
+            font,
+            OVERLAY_GLYPH_CAPACITY as usize,
+        );
+        Self {
+            render_graph,
+            panels,
+            images,
+            #[cfg(not(feature = "msdf-text"))]
+            glyph_brush,
+            #[cfg(feature = "msdf-text")]
+            text,
+            skybox_luminance,
+        }
+    }
+}
 
 fn configure_surface(
     parameters: &Parameters,
@@ -357,20 +1447,25 @@ fn configure_surface(
     );
 }
 
+/// The skybox faces in cube texture order
+fn load_skybox_images() -> [image::RgbaImage; 6] {
+    let png: [&[u8]; 6] = [
+        include_bytes!("../assets/skybox/right.png"),
+        include_bytes!("../assets/skybox/left.png"),
+        include_bytes!("../assets/skybox/top.png"),
+        include_bytes!("../assets/skybox/bottom.png"),
+        include_bytes!("../assets/skybox/front.png"),
+        include_bytes!("../assets/skybox/back.png"),
+    ];
+    png.map(|p| image::load_from_memory(p).unwrap().into_rgba8())
+}
+
 fn make_skybox_texture_view_and_sampler(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+    images: [image::RgbaImage; 6],
 ) -> (wgpu::TextureView, wgpu::Sampler) {
     let texture = {
-        let png: [&[u8]; 6] = [
-            include_bytes!("../assets/skybox/right.png"),
-            include_bytes!("../assets/skybox/left.png"),
-            include_bytes!("../assets/skybox/top.png"),
-            include_bytes!("../assets/skybox/bottom.png"),
-            include_bytes!("../assets/skybox/front.png"),
-            include_bytes!("../assets/skybox/back.png"),
-        ];
-        let images = png.map(|p| image::load_from_memory(p).unwrap().into_rgba8());
         let (width, height) = images[0].dimensions();
         assert_eq!(width, height);
         for im in &images {
@@ -413,161 +1508,205 @@ fn make_skybox_texture_view_and_sampler(
     (texture_view, sampler)
 }
 
-fn make_render_tasks(
+fn make_render_graph(
     parameters: &Parameters,
     device: &wgpu::Device,
-    body_buffer: &wgpu::Buffer,
-    uniforms_buffer: &wgpu::Buffer,
-    skybox_texture_view: &wgpu::TextureView,
-    skybox_sampler: &wgpu::Sampler,
-) -> wgpu::RenderBundle {
-    let mut bundle_encoder =
-        device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
-            label: Some("Render bundle encoder descriptor"),
-            color_formats: &[Some(parameters.texture_format)],
-            depth_stencil: None,
-            sample_count: 1,
-            multiview: None,
-        });
-    let bind_group_layout = make_bind_group_layout(device);
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("Bind group"),
-        layout: &bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer: body_buffer,
-                    offset: 0,
-                    size: None,
-                }),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer: uniforms_buffer,
-                    offset: 0,
-                    size: None,
-                }),
-            },
-            wgpu::BindGroupEntry {
-                binding: 2,
-                resource: wgpu::BindingResource::TextureView(skybox_texture_view),
-            },
-            wgpu::BindGroupEntry {
-                binding: 3,
-                resource: wgpu::BindingResource::Sampler(skybox_sampler),
-            },
-        ],
-    });
-    let pipeline = make_pipeline(parameters, device, &bind_group_layout);
-
-    bundle_encoder.set_pipeline(&pipeline);
-    bundle_encoder.set_bind_group(0, &bind_group, &[]);
-    bundle_encoder.draw(0..4, 0..1);
-    bundle_encoder.finish(&wgpu::RenderBundleDescriptor {
-        label: Some("Render bundle"),
-    })
-}
-
-fn make_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("Body buffer layout"),
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None, // Only applicable to sampled textures
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None, // See above
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::Cube,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                },
-                count: None, // See above
+    body_buffer: wgpu::Buffer,
+    uniforms_buffer: wgpu::Buffer,
+    skybox_texture_view: wgpu::TextureView,
+    skybox_sampler: wgpu::Sampler,
+) -> RenderGraph {
+    let mut graph = RenderGraph::new(parameters.texture_format);
+    graph.add_module(
+        "shader.vert",
+        device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shader.vert.wgsl"
+        ))),
+    );
+    graph.add_module(
+        "shader.frag",
+        device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/shader.frag.wgsl"
+        ))),
+    );
+    graph.add_module(
+        "raster",
+        device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/raster.wgsl"
+        ))),
+    );
+    let mesh = icosphere(ICOSPHERE_SUBDIVISIONS);
+    graph.add_buffer(
+        "icosphere",
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Icosphere buffer"),
+            contents: bytemuck::cast_slice(&mesh),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+    );
+    graph.add_buffer(
+        "tracers",
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tracer buffer"),
+            size: (MAX_TRACERS * mem::size_of::<Tracer>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }),
+    );
+    graph.add_buffer("bodies", body_buffer);
+    graph.add_buffer(
+        "orientations",
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Orientation buffer"),
+            size: (BODIES * mem::size_of::<Orientation>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }),
+    );
+    graph.add_buffer("uniforms", uniforms_buffer);
+    graph.add_texture(
+        "skybox",
+        skybox_texture_view,
+        wgpu::TextureViewDimension::Cube,
+    );
+    graph.add_sampler("skybox", skybox_sampler);
+    graph.add_target("gbuffer", wgpu::TextureFormat::R16Float);
+    graph.add_target("ids", wgpu::TextureFormat::R32Uint);
+    graph.export_target("ids");
+    let mesh_vertex_buffers = || {
+        vec![
+            VertexBuffer {
+                buffer: "icosphere",
+                step_mode: wgpu::VertexStepMode::Vertex,
+                stride: mem::size_of::<[f32; 3]>() as u64,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3],
             },
-            wgpu::BindGroupLayoutEntry {
-                binding: 3,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None, // See above
+            VertexBuffer {
+                buffer: "bodies",
+                step_mode: wgpu::VertexStepMode::Instance,
+                stride: mem::size_of::<Sphere>() as u64,
+                attributes: &Sphere::INSTANCE_ATTRIBUTES,
             },
-        ],
-    })
-}
-
-fn make_pipeline(
-    parameters: &Parameters,
-    device: &wgpu::Device,
-    bind_group_layout: &wgpu::BindGroupLayout,
-) -> wgpu::RenderPipeline {
-    // All uniforms reside in the same bind group (since nothing is ever swapped out).
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("Pipeline layout"),
-        bind_group_layouts: &[bind_group_layout],
-        push_constant_ranges: &[],
-    });
+        ]
+    };
 
-    let vertex_module = device.create_shader_module(wgpu::include_wgsl!(concat!(
-        env!("OUT_DIR"),
-        "/shader.vert.wgsl"
-    )));
-    let fragment_module = device.create_shader_module(wgpu::include_wgsl!(concat!(
-        env!("OUT_DIR"),
-        "/shader.frag.wgsl"
-    )));
+    // Rasterize the index of the body covering each pixel, for the hybrid backend. One icosphere
+    // is drawn per node of the sphere tree, where the shader discards all but the leaves.
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "gbuffer",
+            vertex: ("raster", "vs_mesh"),
+            fragment: ("raster", "fs_gbuffer"),
+            inputs: vec![Input::Uniform("uniforms")],
+            vertex_buffers: mesh_vertex_buffers(),
+            targets: vec![Target::Texture("gbuffer")],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: Some(wgpu::CompareFunction::Less),
+            vertex_count: mesh.len() as u32,
+            instance_count: (2 * BODIES - 1) as u32,
+        },
+    );
 
-    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Render pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &vertex_module,
-            entry_point: "main",
-            buffers: &[],
+    // Cover the viewport with 4 points hardcoded in the vertex shader
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "raytrace",
+            vertex: ("shader.vert", "main"),
+            fragment: ("shader.frag", "main"),
+            inputs: vec![
+                Input::Uniform("bodies"),
+                Input::Uniform("uniforms"),
+                Input::Texture("skybox"),
+                Input::Sampler("skybox"),
+                Input::Target("gbuffer"),
+                Input::Uniform("orientations"),
+            ],
+            vertex_buffers: Vec::new(),
+            targets: vec![Target::Surface, Target::Texture("ids")],
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            depth: Some(wgpu::CompareFunction::Always),
+            vertex_count: 4,
+            instance_count: 1,
         },
-        fragment: Some(wgpu::FragmentState {
-            module: &fragment_module,
-            entry_point: "main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: parameters.texture_format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        // Cover the viewport with 4 points hardcoded in the vertex shader
-        primitive: wgpu::PrimitiveState {
+    );
+
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "skybox",
+            vertex: ("raster", "vs_skybox"),
+            fragment: ("raster", "fs_skybox"),
+            inputs: vec![
+                Input::Uniform("uniforms"),
+                Input::Texture("skybox"),
+                Input::Sampler("skybox"),
+            ],
+            vertex_buffers: Vec::new(),
+            targets: vec![Target::Surface],
             topology: wgpu::PrimitiveTopology::TriangleStrip,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: None,
-            unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
-            conservative: false,
+            depth: None,
+            vertex_count: 4,
+            instance_count: 1,
         },
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
+    );
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "meshes",
+            vertex: ("raster", "vs_mesh"),
+            fragment: ("raster", "fs_mesh"),
+            inputs: vec![Input::Uniform("uniforms")],
+            vertex_buffers: mesh_vertex_buffers(),
+            targets: vec![Target::Surface, Target::Texture("ids")],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: Some(wgpu::CompareFunction::Less),
+            vertex_count: mesh.len() as u32,
+            instance_count: (2 * BODIES - 1) as u32,
         },
-        multiview: None,
-    })
+    );
+    // Billboards of the accretion disk particles, over whichever backend drew the bodies
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "disk",
+            vertex: ("raster", "vs_disk"),
+            fragment: ("raster", "fs_billboard"),
+            inputs: vec![Input::Uniform("uniforms")],
+            vertex_buffers: Vec::new(),
+            targets: vec![Target::Surface],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: Some(wgpu::CompareFunction::Less),
+            vertex_count: 6,
+            instance_count: DISK_PARTICLES,
+        },
+    );
+    graph.add_pass(
+        device,
+        PassDescriptor {
+            name: "tracers",
+            vertex: ("raster", "vs_tracer"),
+            fragment: ("raster", "fs_billboard"),
+            inputs: vec![Input::Uniform("uniforms")],
+            vertex_buffers: vec![VertexBuffer {
+                buffer: "tracers",
+                step_mode: wgpu::VertexStepMode::Instance,
+                stride: mem::size_of::<Tracer>() as u64,
+                attributes: &TRACER_ATTRIBUTES,
+            }],
+            targets: vec![Target::Surface],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: Some(wgpu::CompareFunction::Less),
+            vertex_count: 6,
+            instance_count: MAX_TRACERS as u32,
+        },
+    );
+    for pass in ["gbuffer", "skybox", "meshes", "disk", "tracers"] {
+        graph.set_enabled(pass, false);
+    }
+    graph
 }
@@ -0,0 +1,60 @@
+use cgmath::{prelude::*, Vector3};
+
+/// The unit sphere as a triangle list, made by repeatedly splitting each face of an icosahedron
+/// into four and pushing the new vertices out onto the sphere. Each vertex doubles as its normal.
+pub fn icosphere(subdivisions: u32) -> Vec<[f32; 3]> {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let vertices = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .map(|v| Vector3::from(v).normalize());
+    let faces: [[usize; 3]; 20] = [
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    let mut triangles: Vec<[Vector3<f32>; 3]> = faces
+        .iter()
+        .map(|face| face.map(|index| vertices[index]))
+        .collect();
+    for _ in 0..subdivisions {
+        triangles = triangles
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let ab = (a + b).normalize();
+                let bc = (b + c).normalize();
+                let ca = (c + a).normalize();
+                [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    triangles.into_iter().flatten().map(Into::into).collect()
+}
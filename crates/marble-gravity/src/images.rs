@@ -0,0 +1,244 @@
+use cgmath::Vector2;
+use image::RgbaImage;
+use std::{mem, num::NonZeroU32};
+
+/// The size of each image slot, which larger images are cropped to
+pub const IMAGE_SIZE: (u32, u32) = (192, 108);
+const ATLAS_COLUMNS: u32 = 5;
+const ATLAS_ROWS: u32 = 9;
+pub const IMAGE_SLOTS: usize = (ATLAS_COLUMNS * ATLAS_ROWS) as usize;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ImageInstance {
+    /// Minimum and maximum corners in normalized device coordinates
+    rect: [f32; 4],
+    /// Minimum and maximum texture coordinates in the atlas
+    uv: [f32; 4],
+}
+unsafe impl bytemuck::Pod for ImageInstance {}
+unsafe impl bytemuck::Zeroable for ImageInstance {}
+
+/// Draws images from a fixed number of slots, such as scenario thumbnails, on top of the
+/// overlay panels.
+pub struct ImageRenderer {
+    atlas: wgpu::Texture,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    instances: Vec<ImageInstance>,
+    instance_buffer: wgpu::Buffer,
+}
+impl ImageRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        // Images are captures of the surface, so they are encoded just like it
+        let atlas_format = if format.describe().srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_COLUMNS * IMAGE_SIZE.0,
+                height: ATLAS_ROWS * IMAGE_SIZE.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: atlas_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let atlas_view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("images"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("images"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let module = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/images.wgsl"
+        )));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("images"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("images"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<ImageInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self {
+            atlas,
+            pipeline,
+            bind_group,
+            instances: Vec::with_capacity(IMAGE_SLOTS),
+            instance_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Image instance buffer"),
+                size: (IMAGE_SLOTS * mem::size_of::<ImageInstance>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    /// Replaces the image in a slot, cropped to [`IMAGE_SIZE`].
+    pub fn upload(&self, queue: &wgpu::Queue, slot: usize, image: &RgbaImage) {
+        assert!(slot < IMAGE_SLOTS);
+        let image = image::imageops::crop_imm(image, 0, 0, IMAGE_SIZE.0, IMAGE_SIZE.1).to_image();
+        let (width, height) = image.dimensions();
+        let slot = slot as u32;
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: slot % ATLAS_COLUMNS * IMAGE_SIZE.0,
+                    y: slot / ATLAS_COLUMNS * IMAGE_SIZE.1,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * width),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Adds the image in a slot, stretched over a rectangle with corners in pixels, to be drawn
+    /// by the next [`ImageRenderer::draw`].
+    pub fn queue(
+        &mut self,
+        slot: usize,
+        (min, max): ((f32, f32), (f32, f32)),
+        (width, height): (u32, u32),
+    ) {
+        if slot >= IMAGE_SLOTS || self.instances.len() == IMAGE_SLOTS {
+            return;
+        }
+        let to_ndc = |(x, y): (f32, f32)| {
+            Vector2::new(2.0 * x / width as f32 - 1.0, 1.0 - 2.0 * y / height as f32)
+        };
+        let (min, max) = (to_ndc(min), to_ndc(max));
+        let slot = slot as u32;
+        let uv_min = Vector2::new(
+            (slot % ATLAS_COLUMNS) as f32 / ATLAS_COLUMNS as f32,
+            (slot / ATLAS_COLUMNS) as f32 / ATLAS_ROWS as f32,
+        );
+        let uv_max = uv_min + Vector2::new(1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+        self.instances.push(ImageInstance {
+            rect: [min.x, min.y, max.x, max.y],
+            uv: [uv_min.x, uv_min.y, uv_max.x, uv_max.y],
+        });
+    }
+
+    /// Discards the queued images.
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Draws all queued images on top of `target`.
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        if self.instances.is_empty() {
+            return;
+        }
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("images"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..4, 0..self.instances.len() as u32);
+        drop(pass);
+        self.instances.clear();
+    }
+}
@@ -0,0 +1,30 @@
+// Images from an atlas, such as scenario thumbnails, see images.rs
+
+@group(0) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var atlas_sampler: sampler;
+
+struct ImageOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) uv: vec4<f32>,
+) -> ImageOutput {
+    // A triangle strip covering the image quad
+    let corner = vec2<f32>(f32(index / 2u), f32(index % 2u));
+    return ImageOutput(
+        vec4<f32>(mix(rect.xy, rect.zw, corner), 0.0, 1.0),
+        mix(uv.xy, uv.zw, corner),
+    );
+}
+
+@fragment
+fn fs_main(in: ImageOutput) -> @location(0) vec4<f32> {
+    return textureSample(atlas_texture, atlas_sampler, in.uv);
+}
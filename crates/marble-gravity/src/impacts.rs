@@ -0,0 +1,254 @@
+//! `--impact-sounds on` plays a sound for each collision, through the first audio player of the
+//! platform that runs, see [`PLAYERS`]. `--impact-sounds <file.wav>` writes the sounds to a WAV
+//! file instead, and `--impact-sounds -` to standard output, to be piped to a player of choice.
+//!
+//! The material of each body, its own or otherwise that of its group, selects the sample it
+//! makes, just as it selects how bouncy the collision is. Glass rings, metal clangs, rubber thuds
+//! and plain bodies knock. Faster impacts are louder. Samples are synthesized on start rather
+//! than loaded, so that there are no assets to ship.
+//!
+//! Not supported on the web.
+
+use crate::{
+    events::{AppEvent, Subscriber},
+    tools::{self, Pipe},
+};
+use instant::Instant;
+use physics::{Material, Physics};
+use std::{
+    f32::consts::TAU,
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Players reading a WAV stream from stdin, tried in order
+const PLAYERS: &[(&str, &[&str])] = &[
+    ("aplay", &["-q", "-"]),
+    ("play", &["-q", "-t", "wav", "-"]),
+    ("ffplay", &["-nodisp", "-loglevel", "quiet", "-"]),
+];
+const RATE: u32 = 44100;
+/// How often the stream is written, which bounds the delay before an impact is heard
+const CHUNK: Duration = Duration::from_millis(10);
+/// The approach speed in units per second of an impact at full loudness
+const LOUD_SPEED: f32 = 0.5;
+/// Slower impacts are silent, so that bodies resting on each other do not hum
+const QUIET_SPEED: f32 = 0.01;
+/// The most sounds played at once, beyond which the oldest are cut off
+const MAX_VOICES: usize = 32;
+/// The fade-in in seconds of each sample, so that it starts without a click
+const ATTACK: f32 = 0.002;
+
+/// How an impact on a material sounds: partials by their frequency in hertz and amplitude,
+/// fading out with a time constant in seconds.
+struct Timbre {
+    partials: &'static [(f32, f32)],
+    decay: f32,
+}
+impl Timbre {
+    fn of(material: Material) -> Self {
+        match material {
+            // A short dull knock
+            Material::Solid => Self {
+                partials: &[(180.0, 1.0), (410.0, 0.5), (930.0, 0.2)],
+                decay: 0.04,
+            },
+            // A bright ring, with the overtones of a free bar
+            Material::Glass => Self {
+                partials: &[(1900.0, 1.0), (5240.0, 0.4), (10260.0, 0.15)],
+                decay: 0.35,
+            },
+            // A long inharmonic clang
+            Material::Metal => Self {
+                partials: &[(620.0, 1.0), (1710.0, 0.7), (3350.0, 0.4), (5540.0, 0.2)],
+                decay: 0.9,
+            },
+            // A soft low thud
+            Material::Rubber => Self {
+                partials: &[(90.0, 1.0), (150.0, 0.3)],
+                decay: 0.06,
+            },
+        }
+    }
+    /// The sample at [`RATE`], lasting until it has faded to a thousandth.
+    fn synthesize(&self) -> Vec<f32> {
+        let length = (self.decay * 1000_f32.ln() * RATE as f32) as usize;
+        let total: f32 = self.partials.iter().map(|&(_, amplitude)| amplitude).sum();
+        (0..length)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                let envelope = (t / ATTACK).min(1.0) * (-t / self.decay).exp();
+                let sum: f32 = self
+                    .partials
+                    .iter()
+                    .map(|&(frequency, amplitude)| amplitude * (TAU * frequency * t).sin())
+                    .sum();
+                envelope * sum / total
+            })
+            .collect()
+    }
+}
+
+/// A sample being played
+struct Voice {
+    sample: Arc<[f32]>,
+    position: usize,
+    gain: f32,
+}
+
+pub struct Impacts {
+    /// The sample of each material, indexed like [`Material::ALL`], if sounds are played
+    samples: Option<Vec<Arc<[f32]>>>,
+    /// Shared with the thread writing the stream
+    voices: Arc<Mutex<Vec<Voice>>>,
+    /// Collisions not yet played, by the bodies and their approach speed
+    pending: Vec<((usize, usize), f32)>,
+}
+impl Impacts {
+    /// Starts playing impacts where requested on the command line.
+    pub fn requested() -> Self {
+        let mut impacts = Self {
+            samples: None,
+            voices: Arc::new(Mutex::new(Vec::new())),
+            pending: Vec::new(),
+        };
+        let Some((name, value)) = crate::requested("impact-sounds") else {
+            return impacts;
+        };
+        let output: io::Result<Box<dyn Write + Send>> = match value.trim() {
+            "off" => return impacts,
+            "on" => open_player(),
+            "-" => Ok(Box::new(io::stdout())),
+            path => File::create(path).map(|file| Box::new(BufWriter::new(file)) as _),
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(err) => {
+                log::error!("Failed to play impacts to {name} {value}: {err}");
+                return impacts;
+            }
+        };
+        let voices = Arc::clone(&impacts.voices);
+        let spawned = thread::Builder::new()
+            .name("impacts".to_owned())
+            .spawn(move || {
+                if let Err(err) = stream(output, &voices) {
+                    log::warn!("Stopped playing impacts: {err}");
+                }
+            });
+        if let Err(err) = spawned {
+            log::error!("Failed to play impacts: {err}");
+            return impacts;
+        }
+        impacts.samples = Some(
+            Material::ALL
+                .into_iter()
+                .map(|material| Timbre::of(material).synthesize().into())
+                .collect(),
+        );
+        impacts
+    }
+    /// Plays the collisions since the last call, with the samples of the materials of the bodies.
+    pub fn play(&mut self, physics: &Physics) {
+        let Some(samples) = &self.samples else {
+            return;
+        };
+        if self.pending.is_empty() {
+            return;
+        }
+        let bodies = physics.bodies();
+        let config = physics.config();
+        let mut voices = self.voices.lock().unwrap();
+        for ((a, b), speed) in self.pending.drain(..) {
+            // Bodies may have been removed since
+            let (Some(a), Some(b)) = (bodies.get(a), bodies.get(b)) else {
+                continue;
+            };
+            let (first, second) = (config.material(a), config.material(b));
+            // Each body sounds its half of the impact, and alike ones sound it together
+            let sounds = [Some(first), (second != first).then_some(second)];
+            let gain = (speed / LOUD_SPEED).min(1.0) / sounds.iter().flatten().count() as f32;
+            for material in sounds.into_iter().flatten() {
+                let index = Material::ALL.iter().position(|&m| m == material).unwrap();
+                voices.push(Voice {
+                    sample: Arc::clone(&samples[index]),
+                    position: 0,
+                    gain,
+                });
+            }
+        }
+        let excess = voices.len().saturating_sub(MAX_VOICES);
+        voices.drain(..excess);
+    }
+}
+impl Subscriber for Impacts {
+    fn notify(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Collision { bodies, speed }
+                if self.samples.is_some() && *speed > QUIET_SPEED =>
+            {
+                self.pending.push((*bodies, *speed));
+            }
+            AppEvent::BodiesReplaced => self.pending.clear(),
+            _ => {}
+        }
+    }
+}
+
+/// Pipes into the first player that runs.
+fn open_player() -> io::Result<Box<dyn Write + Send>> {
+    tools::run_first(PLAYERS, Pipe::Stdin, |program, child| {
+        log::info!("Playing impacts with {program}");
+        Ok(Box::new(child.stdin.take().unwrap()) as _)
+    })
+}
+
+/// Writes the voices as mono 16 bit WAV, as fast as they would be heard, until writing fails.
+fn stream(mut output: impl Write, voices: &Mutex<Vec<Voice>>) -> io::Result<()> {
+    // The lengths are unknown up front, as for any stream
+    let mut header = Vec::with_capacity(44);
+    header.extend(b"RIFF");
+    header.extend(u32::MAX.to_le_bytes());
+    header.extend(b"WAVEfmt ");
+    header.extend(16_u32.to_le_bytes());
+    // Integer samples in one channel
+    header.extend(1_u16.to_le_bytes());
+    header.extend(1_u16.to_le_bytes());
+    header.extend(RATE.to_le_bytes());
+    header.extend((2 * RATE).to_le_bytes());
+    header.extend(2_u16.to_le_bytes());
+    header.extend(16_u16.to_le_bytes());
+    header.extend(b"data");
+    header.extend(u32::MAX.to_le_bytes());
+    output.write_all(&header)?;
+    let start = Instant::now();
+    let mut written = 0;
+    let mut mix = Vec::new();
+    let mut bytes = Vec::new();
+    loop {
+        thread::sleep(CHUNK);
+        let due = (start.elapsed().as_secs_f64() * f64::from(RATE)) as usize;
+        mix.clear();
+        mix.resize(due - written, 0.0);
+        written = due;
+        voices.lock().unwrap().retain_mut(|voice| {
+            let rest = &voice.sample[voice.position..];
+            for (out, sample) in mix.iter_mut().zip(rest) {
+                *out += voice.gain * sample;
+            }
+            voice.position += rest.len().min(mix.len());
+            voice.position < voice.sample.len()
+        });
+        bytes.clear();
+        // Many impacts at once are compressed rather than clipped
+        bytes.extend(
+            mix.iter()
+                .flat_map(|x| ((x.tanh() * f32::from(i16::MAX)) as i16).to_le_bytes()),
+        );
+        output.write_all(&bytes)?;
+        output.flush()?;
+    }
+}
@@ -0,0 +1,246 @@
+//! Just enough JSON for the remote control, the data feed and the bundled ephemerides, read into
+//! [`Value`]s and written by hand.
+
+use std::{fmt::Write as _, iter::Peekable, str::Chars};
+
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// The members in order, where later ones win over earlier ones of the same name
+    Object(Vec<(String, Value)>),
+}
+impl Value {
+    /// The member named `name` of an object.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Self::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(x) => Some(*x),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(text) => Some(text),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// How deeply arrays and objects may nest, which bounds the recursion of the parser so that a
+/// document of brackets cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// The value of a whole JSON document, or `None` if it is malformed or nested deeper than
+/// [`MAX_DEPTH`].
+pub fn parse(text: &str) -> Option<Value> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars, 0)?;
+    skip_whitespace(&mut chars);
+    chars.peek().is_none().then_some(value)
+}
+
+fn parse_value(chars: &mut Peekable<Chars<'_>>, depth: usize) -> Option<Value> {
+    skip_whitespace(chars);
+    match *chars.peek()? {
+        '"' => parse_string(chars).map(Value::String),
+        '[' | '{' if depth == MAX_DEPTH => None,
+        '[' => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Some(Value::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => {}
+                    ']' => return Some(Value::Array(values)),
+                    _ => return None,
+                }
+            }
+        }
+        '{' => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Some(Value::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                chars.next_if_eq(&':')?;
+                members.push((key, parse_value(chars, depth + 1)?));
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => {}
+                    '}' => return Some(Value::Object(members)),
+                    _ => return None,
+                }
+            }
+        }
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || "+-.".contains(*c)) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "null" => Some(Value::Null),
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                number => number.parse().ok().map(Value::Number),
+            }
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Option<String> {
+    chars.next_if_eq(&'"')?;
+    let mut string = String::new();
+    loop {
+        let c = match chars.next()? {
+            '"' => return Some(string),
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                // Characters outside the basic multilingual plane, written as surrogate pairs,
+                // are not supported
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    if code.len() != 4 || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return None;
+                    }
+                    char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                escaped => escaped,
+            },
+            c => c,
+        };
+        string.push(c);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub fn string(text: &str) -> String {
+    let mut json = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => write!(json, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// JSON has no infinities or NaN, so those become `null`.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub fn number(x: impl Into<f64>) -> String {
+    let x = x.into();
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_owned()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub fn vector<T: Into<f64>>([x, y, z]: [T; 3]) -> String {
+    format!("[{},{},{}]", number(x), number(y), number(z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() {
+        let value =
+            parse(r#" {"a": [1, -2.5e1, true, null], "b": "x", "a": {"c": false}} "#).unwrap();
+        assert_eq!(value.get("b").and_then(Value::as_str), Some("x"));
+        // Later members win over earlier ones of the same name
+        let a = value.get("a").unwrap();
+        assert_eq!(a.get("c").and_then(Value::as_bool), Some(false));
+        let array = parse("[1, -2.5e1, true, null]").unwrap();
+        let array = array.as_array().unwrap();
+        assert_eq!(array[1].as_f64(), Some(-25.0));
+        assert!(matches!(array[3], Value::Null));
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for text in [
+            "",
+            "[",
+            "[1,",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{a: 1}",
+            "\"open",
+            "nul",
+            "1 2",
+            "[]]",
+        ] {
+            assert!(parse(text).is_none(), "{text}");
+        }
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_none());
+        // Deep enough to overflow the stack without the limit
+        assert!(parse(&"[".repeat(60_000)).is_none());
+        assert!(parse(&"{\"a\":".repeat(60_000)).is_none());
+    }
+
+    #[test]
+    fn unescapes_strings() {
+        let value = parse(r#""a\"\\\n\u00e9\u263A""#).unwrap();
+        assert_eq!(value.as_str(), Some("a\"\\\n\u{e9}\u{263a}"));
+        for text in [r#""\u12""#, r#""\u12g4""#, r#""\u+123""#, r#""\ud800""#] {
+            assert!(parse(text).is_none(), "{text}");
+        }
+        assert_eq!(parse(&string("\"\t\\")).unwrap().as_str(), Some("\"\t\\"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use physics::{Body, ToF32, BODIES};
+
+/// Labels are opaque up to the first distance from the camera, and invisible beyond the second
+const LABEL_FADE: (f32, f32) = (2.0, 8.0);
+
+/// Which bodies get a floating label
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelMode {
+    Off,
+    /// Only the body followed by the camera
+    Selected,
+    All,
+}
+impl LabelMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Selected,
+            Self::Selected => Self::All,
+            Self::All => Self::Off,
+        }
+    }
+}
+
+/// Projects bodies to the screen and queues a label showing index and speed above each, nearest
+/// first so that they win when overlapping labels are hidden.
+pub struct BodyLabels {
+    pub mode: LabelMode,
+    order: Vec<(f32, usize)>,
+}
+impl BodyLabels {
+    pub fn new() -> Self {
+        Self {
+            mode: LabelMode::Off,
+            order: Vec::with_capacity(BODIES),
+        }
+    }
+}
+impl Subscriber for BodyLabels {
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (bodies, selected) = (frame.physics.bodies(), frame.camera_target);
+        let world_to_camera = frame.world_to_camera();
+        self.order.clear();
+        match (self.mode, selected) {
+            (LabelMode::Selected, Some(index)) if index < bodies.len() => {
+                self.order.push((0.0, index));
+            }
+            (LabelMode::All, _) => self.order.extend((0..bodies.len()).map(|i| (0.0, i))),
+            _ => return,
+        }
+        let camera_space =
+            |body: &Body| (world_to_camera * body.pos.to_f32().extend(1.0)).truncate();
+        for (distance, index) in &mut self.order {
+            *distance = camera_space(&bodies[*index]).magnitude();
+        }
+        self.order.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (width, height) = graphics.window_size();
+        let (width, height) = (width as f32, height as f32);
+        let (fade_start, fade_end) = LABEL_FADE;
+        for &(distance, index) in &self.order {
+            let body = &bodies[index];
+            let view_pos = camera_space(body);
+            // Above the top of the body, where y points down
+            let Some((x, y)) =
+                graphics.project(view_pos - body.radius.to_f32() * Vector3::unit_y())
+            else {
+                continue;
+            };
+            if !(0.0..width).contains(&x) || !(0.0..height).contains(&y) {
+                continue;
+            }
+            let alpha = ((fade_end - distance) / (fade_end - fade_start)).clamp(0.0, 1.0);
+            if alpha == 0.0 {
+                continue;
+            }
+            graphics.label(
+                (x, y),
+                alpha,
+                format_args!("{index} {:.2}", body.vel.magnitude()),
+            );
+        }
+    }
+}
@@ -1,14 +1,77 @@
-mod camera;
+mod actions;
+#[cfg(test)]
+mod allocations;
+mod attitude;
+#[cfg(not(target_arch = "wasm32"))]
+mod audio;
+mod boundary;
+mod challenge;
+mod clipboard;
+mod command_line;
+mod commands;
+mod comparison;
+mod compass;
+mod conservation;
+mod console;
+mod contrast;
+#[cfg(not(target_arch = "wasm32"))]
+mod diagnose;
+mod ephemeris;
+mod events;
+#[cfg(not(target_arch = "wasm32"))]
+mod feed;
+mod gallery;
+#[cfg(not(target_arch = "wasm32"))]
+mod ghost;
+mod golf;
 mod graphics;
+mod icosphere;
+mod images;
+#[cfg(not(target_arch = "wasm32"))]
+mod impacts;
+mod json;
+mod labels;
+mod measure;
+mod media;
+mod motion;
+mod mouse;
+#[cfg(not(target_arch = "wasm32"))]
+mod osc;
+mod overlay;
+mod panels;
+#[cfg(not(target_arch = "wasm32"))]
+mod panorama;
+mod photo;
+mod picking;
+#[cfg(not(target_arch = "wasm32"))]
+mod remote;
+mod rendergraph;
+#[cfg(not(target_arch = "wasm32"))]
+mod replay;
 mod run;
-mod spheretree;
+mod selection;
+mod split_screen;
+mod streamlines;
+#[cfg(not(target_arch = "wasm32"))]
+mod sweep;
+#[cfg(feature = "msdf-text")]
+mod text;
+mod timelapse;
+mod toasts;
+#[cfg(not(target_arch = "wasm32"))]
+mod tools;
+mod trace;
+mod tracers;
+mod tutorial;
+#[cfg(not(target_arch = "wasm32"))]
+mod udp;
 
 use crate::{
+    events::{AppEvent, EventBus},
     graphics::{Graphics, Parameters},
-    run::Stats,
 };
 use instant::Instant;
-use physics::{Physics, PhysicsResult};
+use physics::{Physics, PhysicsResult, ToF32};
 use winit::{
     event_loop::{EventLoopBuilder, EventLoopProxy},
     window::WindowBuilder,
@@ -18,13 +81,15 @@ use winit::{
 pub fn start() {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+        console::init_logging();
+        trace::init();
         pollster::block_on(setup_and_run());
     }
     #[cfg(target_arch = "wasm32")]
     {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-        console_log::init_with_level(log::Level::Info).unwrap();
+        console::init_logging();
+        trace::init();
         wasm_bindgen_futures::spawn_local(setup_and_run());
     }
 }
@@ -58,31 +123,47 @@ async fn setup_and_run() {
                 Some(())
             })
             .expect("Couldn't append canvas to document body.");
+        gallery::listen_for_drops(&window.canvas());
+        media::listen_for_actions();
     }
 
     let surface = unsafe { instance.create_surface(&window) };
     let adapter = get_adapter(&instance, &surface).await;
     let size: (u32, u32) = window.inner_size().into();
 
-    let device_and_queue = get_device_and_queue(&adapter).await;
-    let parameters = Parameters {
-        texture_format: *surface.get_supported_formats(&adapter).first().unwrap(),
-        present_mode: (|| {
-            let supported = surface.get_supported_present_modes(&adapter);
-            let preferences = [wgpu::PresentMode::FifoRelaxed, wgpu::PresentMode::Fifo];
-            for p in preferences {
-                if supported.contains(&p) {
-                    return p;
-                }
-            }
-            return *supported.first().unwrap();
-        })(),
+    #[cfg(not(target_arch = "wasm32"))]
+    let surface_support = diagnose::is_requested().then(|| diagnose::SurfaceSupport {
+        formats: surface.get_supported_formats(&adapter),
+        present_modes: surface.get_supported_present_modes(&adapter),
+    });
+    let device_and_queue = match graphics::required_limits(&adapter) {
+        Ok(limits) => get_device_and_queue(&adapter, limits).await,
+        Err(err) => {
+            log::error!("Unsupported GPU: {err}");
+            return;
+        }
     };
+    let parameters = Parameters::preferred(&surface, &adapter);
 
     let graphics = Graphics::initialize(parameters, surface, device_and_queue, size).await;
 
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(surface_support) = surface_support {
+        let mut graphics = graphics;
+        diagnose::run(&adapter, &surface_support, &mut graphics);
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(parameter) = sweep::requested() {
+        let mut graphics = graphics;
+        sweep::run(parameter, &mut graphics);
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    media::listen_for_keys(&event_loop);
     log::info!("Starting event loop");
-    run::run(event_loop, window, graphics);
+    run::run(event_loop, window, graphics, adapter);
 }
 
 async fn get_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface) -> wgpu::Adapter {
@@ -103,17 +184,16 @@ async fn get_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface) -> wgpu
         .expect("Failed to acquire adapter")
 }
 
-async fn get_device_and_queue(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+async fn get_device_and_queue(
+    adapter: &wgpu::Adapter,
+    limits: wgpu::Limits,
+) -> (wgpu::Device, wgpu::Queue) {
     adapter
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: Some("device"),
                 features: wgpu::Features::empty(),
-                limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
+                limits,
             },
             None, // Trace path
         )
@@ -126,31 +206,277 @@ type PhysicsEvent = (Box<Physics>, PhysicsResult);
 #[cfg(not(target_arch = "wasm32"))]
 type PhysicsEvent = ();
 
+/// The fastest the simulation may run relative to real time, beyond which it falls behind
+const MAX_SPEED: f32 = 4.0;
+
 struct PhysicsSystem {
     pub physics: Box<Physics>,
+    /// The master seed of the random streams, see [`physics::stream`]
+    pub seed: u64,
+    /// What drew the initial bodies
+    pub generator: physics::Generator,
+    pending_skip: Option<Instant>,
+    /// How many times as fast as real time the simulation runs, up to [`MAX_SPEED`]
+    pub speed: f32,
+    /// The clock of the simulation when it was last read, see [`PhysicsSystem::clock`], and the
+    /// real instant it was read at
+    clock: Option<(Instant, Instant)>,
+    /// The touching pairs as of the last ticks, in order, to publish only new collisions
+    contacts: Vec<(usize, usize)>,
+    /// The previous `contacts` while they are compared, kept to reuse its allocation
+    previous_contacts: Vec<(usize, usize)>,
     #[cfg(target_arch = "wasm32")]
     currently_running: bool,
 }
+#[cfg(not(target_arch = "wasm32"))]
+fn generator_from_env() -> physics::Generator {
+    let sampling = |name| {
+        let value = std::env::var(name).ok()?;
+        let parsed = physics::Sampling::parse(&value);
+        if parsed.is_none() {
+            log::warn!("Ignoring {name}={value}, expected such as uniform:0.01..0.05");
+        }
+        parsed
+    };
+    let default = physics::Generator::default();
+    physics::Generator {
+        radius: sampling("MARBLE_GRAVITY_RADII").unwrap_or(default.radius),
+        speed: sampling("MARBLE_GRAVITY_SPEEDS"),
+        ..default
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+fn config_from_env() -> physics::PhysicsConfig {
+    let mut config = physics::PhysicsConfig::default();
+    if let Ok(value) = std::env::var("MARBLE_GRAVITY_SOFTENING") {
+        match value.trim().parse::<f32>() {
+            Ok(softening) if softening >= 0.0 => config.softening = softening,
+            _ => log::warn!("Ignoring MARBLE_GRAVITY_SOFTENING={value}, expected a length"),
+        }
+    }
+    if let Ok(value) = std::env::var("MARBLE_GRAVITY_INTEGRATOR") {
+        match physics::Integrator::from_name(value.trim()) {
+            Some(integrator) => config.integrator = integrator,
+            None => log::warn!("Ignoring MARBLE_GRAVITY_INTEGRATOR={value}, expected such as rk4"),
+        }
+    }
+    if let Ok(value) = std::env::var("MARBLE_GRAVITY_BOUNDARY") {
+        match physics::Boundary::from_name(value.trim()) {
+            Some(boundary) => config.boundary = boundary,
+            None => log::warn!("Ignoring MARBLE_GRAVITY_BOUNDARY={value}, expected such as sphere"),
+        }
+    }
+    if let Ok(value) = std::env::var("MARBLE_GRAVITY_BOUNDARY_RADIUS") {
+        match value.trim().parse::<f32>() {
+            Ok(radius) if radius > 0.0 && radius.is_finite() => config.boundary_radius = radius,
+            _ => log::warn!("Ignoring MARBLE_GRAVITY_BOUNDARY_RADIUS={value}, expected a length"),
+        }
+    }
+    config
+}
+
+/// The value of a setting given with `--<name>`, or otherwise in `MARBLE_GRAVITY_<NAME>` with
+/// dashes as underscores, along with where it was given, if anywhere.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn requested(name: &str) -> Option<(String, String)> {
+    let flag = format!("--{name}");
+    let mut args = std::env::args().skip(1);
+    let value = loop {
+        let Some(arg) = args.next() else {
+            break None;
+        };
+        if arg == flag {
+            break args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(&flag)
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            break Some(value.to_owned());
+        }
+    };
+    match value {
+        Some(value) => Some((flag, value)),
+        None => {
+            let var = format!("MARBLE_GRAVITY_{}", name.to_uppercase().replace('-', "_"));
+            let value = std::env::var(&var).ok()?;
+            Some((var, value))
+        }
+    }
+}
+/// The value of a setting given with `?<name>=` in the address of the page, along with its name,
+/// if given.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn requested(name: &str) -> Option<(String, String)> {
+    use js_sys::Reflect;
+
+    let location = Reflect::get(&js_sys::global(), &"location".into()).ok()?;
+    let query = Reflect::get(&location, &"search".into())
+        .ok()?
+        .as_string()?;
+    let value = query.trim_start_matches('?').split('&').find_map(|pair| {
+        pair.strip_prefix(name)
+            .and_then(|pair| pair.strip_prefix('='))
+    })?;
+    Some((name.to_owned(), value.to_owned()))
+}
+fn requested_seed() -> Option<u64> {
+    let (name, value) = requested("seed")?;
+    let parsed = value.trim().parse::<u64>();
+    if parsed.is_err() {
+        log::warn!("Ignoring {name}={value}, expected a number");
+    }
+    parsed.ok()
+}
+fn requested_preset() -> Option<physics::Preset> {
+    let (name, value) = requested("preset")?;
+    let parsed = physics::Preset::from_name(value.trim());
+    if parsed.is_none() {
+        log::warn!("Ignoring {name}={value}, expected such as disc");
+    }
+    parsed
+}
+fn requested_warm_up() -> Option<f32> {
+    let (name, value) = requested("warm-up")?;
+    let parsed = value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|warm_up| *warm_up >= 0.0 && warm_up.is_finite());
+    if parsed.is_none() {
+        log::warn!("Ignoring {name}={value}, expected seconds");
+    }
+    parsed
+}
+
 impl PhysicsSystem {
+    /// Natively, `MARBLE_GRAVITY_THREADS`, such as `4`, sets how many threads physics runs on,
+    /// `MARBLE_GRAVITY_PIN_THREADS=1` pins them to cores other than the first, and
+    /// `MARBLE_GRAVITY_THREAD_PRIORITY=1` raises the priority of them and of the event loop,
+    /// which advances physics and must wake up on time for each frame.
+    /// `--seed 42` or `MARBLE_GRAVITY_SEED=42` reproduces the initial cluster and tracers of a
+    /// run, as does `?seed=42` in the address of the page on the web.
+    /// `--preset disc`, `MARBLE_GRAVITY_PRESET=disc` or `?preset=disc` lays out the initial
+    /// bodies as one of `cluster`, `disc`, `collision`, `lattice`, `shell` and `binary`, see
+    /// [`physics::Preset`].
+    /// `MARBLE_GRAVITY_RADII` and `MARBLE_GRAVITY_SPEEDS`, such as `power-law=2.5:0.005..0.1`,
+    /// set how the radii and speeds of the initial bodies are distributed, see
+    /// [`physics::Sampling`].
+    /// `MARBLE_GRAVITY_SOFTENING`, such as `0.02`, softens gravity between bodies closer than
+    /// about that distance, see [`physics::PhysicsConfig::softening`].
+    /// `--warm-up 2`, `MARBLE_GRAVITY_WARM_UP=2` or `?warm-up=2` fades gravity and collisions
+    /// in over that many seconds, so that the initial cluster settles rather than exploding, see
+    /// [`physics::PhysicsConfig::warm_up`].
+    /// `MARBLE_GRAVITY_INTEGRATOR`, one of `taylor`, `leapfrog`, `rk4` and `yoshida`, chooses
+    /// how bodies are advanced, see [`physics::Integrator`].
+    /// `MARBLE_GRAVITY_BOUNDARY`, one of `damped`, `none`, `sphere` and `periodic`, chooses what
+    /// keeps bodies from leaving, within `MARBLE_GRAVITY_BOUNDARY_RADIUS` of the origin, see
+    /// [`physics::Boundary`].
     pub fn new() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let mut generator = physics::Generator::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut generator = generator_from_env();
+        if let Some(preset) = requested_preset() {
+            generator.preset = preset;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let mut config = physics::PhysicsConfig::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut config = config_from_env();
+        if let Some(warm_up) = requested_warm_up() {
+            config.warm_up = warm_up;
+        }
+        let seed = requested_seed();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let threads =
+                std::env::var("MARBLE_GRAVITY_THREADS")
+                    .ok()
+                    .and_then(|value| match value.trim().parse::<usize>() {
+                        Ok(threads) if threads > 0 => Some(threads),
+                        _ => {
+                            log::warn!("Ignoring MARBLE_GRAVITY_THREADS={value}, expected a count");
+                            None
+                        }
+                    });
+            let pin = std::env::var("MARBLE_GRAVITY_PIN_THREADS").is_ok_and(|v| v == "1");
+            let priority = std::env::var("MARBLE_GRAVITY_THREAD_PRIORITY").is_ok_and(|v| v == "1");
+            if let Err(err) = physics::configure_thread_pool(threads, pin, priority) {
+                log::error!("Failed to create the physics thread pool: {err}");
+            }
+            if priority {
+                physics::raise_thread_priority();
+            }
+        }
+        let seed = seed.unwrap_or_else(physics::random_seed);
+        log::info!("Seed {seed}");
         Self {
-            physics: Physics::initial(),
+            physics: Physics::generated(&generator, config, seed),
+            seed,
+            generator,
+            pending_skip: None,
+            speed: 1.0,
+            clock: None,
+            contacts: Vec::new(),
+            previous_contacts: Vec::new(),
             #[cfg(target_arch = "wasm32")]
             currently_running: false,
         }
     }
-    pub fn start(
+    /// Whether `physics` may be edited, i.e. no worker is currently advancing a copy of it.
+    pub fn is_idle(&self) -> bool {
+        #[cfg(target_arch = "wasm32")]
+        return !self.currently_running;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = self;
+            true
+        }
+    }
+    /// Skip simulating up to `now` (applied once no worker computation is in flight).
+    pub fn skip_to(&mut self, now: Instant) {
+        self.pending_skip = Some(self.clock(now));
+    }
+    /// Simulates up to `now`, or as far as `speed` takes the simulation by then.
+    pub fn start(&mut self, now: Instant, proxy: EventLoopProxy<PhysicsEvent>, bus: &mut EventBus) {
+        if !self.is_idle() {
+            return;
+        }
+        if let Some(skip) = self.pending_skip.take() {
+            self.physics.skip_to(skip);
+        }
+        let target = self.clock(now);
+        self.advance_to(target, proxy, bus);
+    }
+    /// The instant the simulation should have been advanced to at `now`, which runs `speed` times
+    /// as fast as real time.
+    fn clock(&mut self, now: Instant) -> Instant {
+        let (then, clock) = self.clock.unwrap_or((now, now));
+        // Jumps ahead along with the simulation when it is replaced, such as by a loaded scenario
+        let clock = clock.max(self.physics.timestamp())
+            + now.saturating_duration_since(then).mul_f32(self.speed);
+        self.clock = Some((now, clock));
+        clock
+    }
+    /// Advances exactly one tick, for stepping through the paused simulation. Time skipped while
+    /// paused is left pending, so that stepping does not skip it.
+    pub fn step(&mut self, proxy: EventLoopProxy<PhysicsEvent>, bus: &mut EventBus) {
+        if !self.is_idle() {
+            return;
+        }
+        let target = self.physics.timestamp() + self.physics.delta_time();
+        self.advance_to(target, proxy, bus);
+    }
+    fn advance_to(
         &mut self,
         target: Instant,
         proxy: EventLoopProxy<PhysicsEvent>,
-        stats: &mut Stats,
+        bus: &mut EventBus,
     ) {
         #[cfg(target_arch = "wasm32")]
         {
-            let _ = stats;
-            if self.currently_running {
-                return;
-            }
+            let _ = bus;
             match worker::outer::Worker::advance_physics_to(&self.physics, target, proxy) {
                 Ok(()) => self.currently_running = true,
                 Err(()) => {}
@@ -159,29 +485,34 @@ impl PhysicsSystem {
         #[cfg(not(target_arch = "wasm32"))]
         {
             let _ = proxy;
+            let mut span = trace::span("physics");
             let result = self.physics.advance_to(target);
-            Self::report(result, stats);
+            span.arg("ticks", result.elapsed_physics_ticks);
+            drop(span);
+            self.publish_ticks(result, bus);
+        }
+    }
+    /// Publishes the completed ticks, followed by a collision for each pair of bodies that has
+    /// come to touch since the last ticks.
+    pub fn publish_ticks(&mut self, result: PhysicsResult, bus: &mut EventBus) {
+        bus.publish(AppEvent::TicksCompleted(result));
+        std::mem::swap(&mut self.contacts, &mut self.previous_contacts);
+        self.contacts.clear();
+        for (i, j, speed) in self.physics.contacts() {
+            self.contacts.push((i, j));
+            if self.previous_contacts.binary_search(&(i, j)).is_err() {
+                bus.publish(AppEvent::Collision {
+                    bodies: (i, j),
+                    speed: speed.to_f32(),
+                });
+            }
         }
     }
     #[cfg(target_arch = "wasm32")]
-    pub fn handle_event(
-        &mut self,
-        (physics, result): (Box<Physics>, PhysicsResult),
-        stats: &mut Stats,
-    ) {
+    pub fn handle_event(&mut self, (physics, result): PhysicsEvent, bus: &mut EventBus) {
         assert!(self.currently_running);
         self.physics = physics;
         self.currently_running = false;
-        Self::report(result, stats);
-    }
-    fn report(
-        PhysicsResult {
-            elapsed_real,
-            elapsed_physics_ticks,
-        }: PhysicsResult,
-        stats: &mut Stats,
-    ) {
-        stats.time_spent_in_physics += elapsed_real;
-        stats.tick_number += elapsed_physics_ticks;
+        self.publish_ticks(result, bus);
     }
 }
@@ -0,0 +1,68 @@
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::prelude::*;
+use physics::{Body, ToF32};
+
+const MEASURE_LINE_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.8];
+
+/// Distance, relative speed and gravitational force between two picked bodies, shown live.
+pub struct MeasureTool {
+    picked: [Option<usize>; 2],
+}
+impl MeasureTool {
+    pub fn new() -> Self {
+        Self {
+            picked: [None, None],
+        }
+    }
+    /// Picking a third body starts over with it.
+    pub fn pick(&mut self, index: usize) {
+        self.picked = match self.picked {
+            [Some(a), None] if a != index => [Some(a), Some(index)],
+            [Some(a), None] => [Some(a), None],
+            _ => [Some(index), None],
+        };
+        log::info!("Measuring between {:?}", self.picked);
+    }
+    /// Must be called when body indices change, such as after a removal.
+    pub fn clear(&mut self) {
+        self.picked = [None, None];
+    }
+}
+impl Subscriber for MeasureTool {
+    /// Draws a line between the picked bodies and lists the measurements in the overlay.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (physics, world_to_camera) = (frame.physics, frame.world_to_camera());
+        let [Some(a), Some(b)] = self.picked else {
+            return;
+        };
+        let (bodies, config) = (physics.bodies(), physics.config());
+        let (Some(a), Some(b)) = (bodies.get(a), bodies.get(b)) else {
+            return;
+        };
+        let camera_space =
+            |body: &Body| (world_to_camera * body.pos.to_f32().extend(1.0)).truncate();
+        if let (Some(from), Some(to)) = (
+            graphics.project(camera_space(a)),
+            graphics.project(camera_space(b)),
+        ) {
+            graphics.overlay_segment(from, to, MEASURE_LINE_COLOR);
+        }
+        let rel_pos = b.pos - a.pos;
+        let distance = rel_pos.magnitude();
+        let rel_vel = b.vel - a.vel;
+        graphics.overlay_line(format_args!("distance {distance:.4}"));
+        graphics.overlay_line(format_args!(
+            "relative speed {:.4}, approaching at {:.4}",
+            rel_vel.magnitude(),
+            -rel_vel.dot(rel_pos / distance),
+        ));
+        graphics.overlay_line(format_args!(
+            "force {:.4e}",
+            a.gravitational_force(b, config)
+        ));
+        graphics.overlay_line(format_args!("surface gap {:.4}", a.surface_gap(b, config)));
+    }
+}
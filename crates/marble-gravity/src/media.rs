@@ -0,0 +1,134 @@
+//! Play, pause and stop from the media controls of the system, for controlling time-lapse
+//! recording and camera path playback without reaching for the window. Natively, these are the
+//! media keys, which are also heard while another window is focused, see [`listen_for_keys`]. On
+//! the web, they are the action handlers of the Media Session API, which browsers also show in
+//! their media overlays. Both are queued along with the pause key and taken by [`take_action`].
+
+use std::{cell::RefCell, collections::VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode},
+    event_loop::{DeviceEventFilter, EventLoopWindowTarget},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaAction {
+    /// Only sent by the web, as keyboards have a single key for both
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    Play,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    Pause,
+    PlayPause,
+    /// Ends a time-lapse or camera path playback
+    Stop,
+}
+
+/// Whether recording or playback is going, as shown by the media controls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaState {
+    Idle,
+    Playing,
+    Paused,
+}
+
+thread_local! {
+    /// Actions not yet taken, in the order they arrived
+    static ACTIONS: RefCell<VecDeque<MediaAction>> = const { RefCell::new(VecDeque::new()) };
+    /// The state last shown, to only update the media controls on changes
+    #[cfg(target_arch = "wasm32")]
+    static SHOWN: std::cell::Cell<MediaState> = const { std::cell::Cell::new(MediaState::Idle) };
+}
+
+/// Queues an action, to be taken by [`take_action`].
+pub fn push(action: MediaAction) {
+    ACTIONS.with(|actions| actions.borrow_mut().push_back(action));
+}
+
+/// Takes the oldest action not yet taken.
+pub fn take_action() -> Option<MediaAction> {
+    ACTIONS.with(|actions| actions.borrow_mut().pop_front())
+}
+
+/// Has keys reported as device events even while another window is focused, to be passed to
+/// [`key`]. This works on X11 and Windows, but not on Wayland or macOS, where media keys are only
+/// heard while the window is focused.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn listen_for_keys<T>(event_loop: &EventLoopWindowTarget<T>) {
+    event_loop.set_device_event_filter(DeviceEventFilter::Never);
+}
+
+/// Queues the action of a media key pressed while another window is focused, as reported by
+/// device events. Keys pressed while the window is focused go through the input map instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn key(input: &KeyboardInput) {
+    if input.state != ElementState::Pressed {
+        return;
+    }
+    match input.virtual_keycode {
+        Some(VirtualKeyCode::PlayPause) => push(MediaAction::PlayPause),
+        Some(VirtualKeyCode::MediaStop) => push(MediaAction::Stop),
+        _ => {}
+    }
+}
+
+/// Queues the actions of the Media Session API, where the browser supports it.
+#[cfg(target_arch = "wasm32")]
+pub fn listen_for_actions() {
+    use js_sys::Reflect;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+    let Some(session) = media_session() else {
+        log::warn!("No media session, media controls are unavailable");
+        return;
+    };
+    let Ok(set_action_handler) = Reflect::get(&session, &"setActionHandler".into())
+        .and_then(|method| method.dyn_into::<js_sys::Function>())
+    else {
+        return;
+    };
+    for (name, action) in [
+        ("play", MediaAction::Play),
+        ("pause", MediaAction::Pause),
+        ("stop", MediaAction::Stop),
+    ] {
+        let handler = Closure::<dyn FnMut()>::new(move || push(action));
+        // Browsers throw on actions they do not support, which are then left out
+        if let Err(err) =
+            set_action_handler.call2(&session, &JsValue::from_str(name), handler.as_ref())
+        {
+            log::warn!("No {name} media action: {err:?}");
+        }
+        // The handlers are needed for as long as the page is open
+        handler.forget();
+    }
+}
+
+/// Shows the state in the media controls, where the browser supports it.
+#[cfg(target_arch = "wasm32")]
+pub fn show_state(state: MediaState) {
+    if SHOWN.with(|shown| shown.replace(state)) == state {
+        return;
+    }
+    let Some(session) = media_session() else {
+        return;
+    };
+    let state = match state {
+        MediaState::Idle => "none",
+        MediaState::Playing => "playing",
+        MediaState::Paused => "paused",
+    };
+    let _ = js_sys::Reflect::set(&session, &"playbackState".into(), &state.into());
+}
+/// Shows the state in the media controls, which natively are not told about it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn show_state(_state: MediaState) {}
+
+/// `navigator.mediaSession`, which is missing in older browsers.
+#[cfg(target_arch = "wasm32")]
+fn media_session() -> Option<wasm_bindgen::JsValue> {
+    use js_sys::Reflect;
+
+    let navigator = Reflect::get(&js_sys::global(), &"navigator".into()).ok()?;
+    let session = Reflect::get(&navigator, &"mediaSession".into()).ok()?;
+    (!session.is_undefined()).then_some(session)
+}
@@ -0,0 +1,100 @@
+//! Effects making flight feel fast: the field of view widens a little with the speed of the
+//! camera, and a faint vignette darkens the edges of the view. Both follow a smoothed speed, so
+//! that they ease in and out rather than jumping along with every key press.
+//!
+//! They are on by default. `--motion-effects off` or `MARBLE_GRAVITY_MOTION_EFFECTS=off`, or
+//! `?motion-effects=off` in the address of the page on the web, turns them off for those prone
+//! to motion sickness.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use std::time::Duration;
+
+/// How much the tangent of the half angle of the field of view grows at most
+const MAX_WIDENING: f32 = 0.15;
+/// The speed at which the effects reach `1 - 1/e` of their most, that of flying freely
+const FULL_SPEED: f32 = 2.0;
+/// Time constant in seconds of the smoothing of the speed
+const SPEED_SMOOTHING: f32 = 0.25;
+/// Faster movements are taken to be jumps, such as to a pasted pose, and ignored
+const JUMP_SPEED: f32 = 50.0;
+/// The opacity of the vignette at the edges of the view, at most
+const MAX_VIGNETTE: f32 = 0.3;
+/// How far the vignette reaches into the view, as a fraction of its smaller side
+const VIGNETTE_WIDTH: f32 = 0.15;
+/// The vignette is drawn as nested frames, which darken toward the edges
+const VIGNETTE_BANDS: usize = 8;
+
+pub struct MotionEffects {
+    enabled: bool,
+    /// Smoothed, in units per second
+    speed: f32,
+    last_position: Option<Vector3<f32>>,
+}
+impl MotionEffects {
+    pub fn new() -> Self {
+        let enabled = match crate::requested("motion-effects") {
+            None => true,
+            Some((_, value)) if value.trim() == "on" => true,
+            Some((_, value)) if value.trim() == "off" => false,
+            Some((name, value)) => {
+                log::warn!("Ignoring {name}={value}, expected on or off");
+                true
+            }
+        };
+        Self {
+            enabled,
+            speed: 0.0,
+            last_position: None,
+        }
+    }
+    /// Follows the camera to where it is after a frame lasting `frame`.
+    pub fn update(&mut self, position: Vector3<f32>, frame: Duration) {
+        let dt = frame.as_secs_f32();
+        if !self.enabled || dt <= 0.0 {
+            return;
+        }
+        let speed = self
+            .last_position
+            .replace(position)
+            .map_or(0.0, |last| (position - last).magnitude() / dt);
+        if speed < JUMP_SPEED {
+            self.speed += (speed - self.speed) * (1.0 - (-dt / SPEED_SMOOTHING).exp());
+        }
+    }
+    /// Whether the effects have settled, such that frames need not be rendered for them.
+    pub fn is_still(&self) -> bool {
+        !self.enabled || self.speed < 1e-3
+    }
+}
+impl Subscriber for MotionEffects {
+    /// Sets the field of view and draws the vignette for the next frame.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if !self.enabled {
+            return;
+        }
+        let intensity = 1.0 - (-self.speed / FULL_SPEED).exp();
+        graphics.set_fov_scale(1.0 + MAX_WIDENING * intensity);
+        let opacity = MAX_VIGNETTE * intensity;
+        if opacity < 0.01 {
+            return;
+        }
+        // Each band covers those inside it, so the edges are covered by every band
+        let alpha = 1.0 - (1.0 - opacity).powf(1.0 / VIGNETTE_BANDS as f32);
+        let viewport = graphics.viewport(0);
+        let (x0, y0) = (viewport.offset.0 as f32, viewport.offset.1 as f32);
+        let (x1, y1) = (x0 + viewport.size.0 as f32, y0 + viewport.size.1 as f32);
+        let band = VIGNETTE_WIDTH * (x1 - x0).min(y1 - y0) / VIGNETTE_BANDS as f32;
+        for i in 1..=VIGNETTE_BANDS {
+            let t = band * i as f32;
+            let color = [0.0, 0.0, 0.0, alpha];
+            graphics.overlay_rect(((x0, y0), (x1, y0 + t)), color);
+            graphics.overlay_rect(((x0, y1 - t), (x1, y1)), color);
+            graphics.overlay_rect(((x0, y0 + t), (x0 + t, y1 - t)), color);
+            graphics.overlay_rect(((x1 - t, y0 + t), (x1, y1 - t)), color);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! How movement of the captured mouse looks around. Raw motion is gathered over a frame and
+//! scaled once per frame, so that the acceleration curve sees the speed of the mouse rather
+//! than however the platform happens to split motion into events.
+//!
+//! Natively, these are set by environment variables:
+//! - `MARBLE_GRAVITY_MOUSE_SENSITIVITY`, such as `1.5`, scales all movement.
+//! - `MARBLE_GRAVITY_MOUSE_ACCELERATION`, such as `0.5`, turns faster movements further, by
+//!   this much more per [`ACCELERATION_SPEED`], up to [`MAX_GAIN`] times as far.
+//! - `MARBLE_GRAVITY_INVERT_MOUSE`, `x`, `y` or `xy`, inverts those axes.
+
+use std::time::Duration;
+
+/// The speed in counts per second at which acceleration adds its full amount to the gain
+const ACCELERATION_SPEED: f64 = 1000.0;
+/// The most acceleration multiplies movement by
+const MAX_GAIN: f64 = 4.0;
+
+pub struct MouseLook {
+    sensitivity: f64,
+    acceleration: f64,
+    invert_x: bool,
+    invert_y: bool,
+    /// Raw motion since the last [`MouseLook::take`]
+    pending: (f64, f64),
+}
+impl MouseLook {
+    pub fn new() -> Self {
+        let mut look = Self {
+            sensitivity: 1.0,
+            acceleration: 0.0,
+            invert_x: false,
+            invert_y: false,
+            pending: (0.0, 0.0),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let number = |name: &str| {
+                let value = std::env::var(name).ok()?;
+                let parsed = value.trim().parse::<f64>().ok().filter(|n| *n >= 0.0);
+                if parsed.is_none() {
+                    log::warn!("Ignoring {name}={value}, expected a non-negative number");
+                }
+                parsed
+            };
+            if let Some(sensitivity) = number("MARBLE_GRAVITY_MOUSE_SENSITIVITY") {
+                look.sensitivity = sensitivity;
+            }
+            if let Some(acceleration) = number("MARBLE_GRAVITY_MOUSE_ACCELERATION") {
+                look.acceleration = acceleration;
+            }
+            if let Ok(value) = std::env::var("MARBLE_GRAVITY_INVERT_MOUSE") {
+                match value.trim() {
+                    "x" => look.invert_x = true,
+                    "y" => look.invert_y = true,
+                    "xy" | "yx" => (look.invert_x, look.invert_y) = (true, true),
+                    _ => log::warn!(
+                        "Ignoring MARBLE_GRAVITY_INVERT_MOUSE={value}, expected x, y or xy"
+                    ),
+                }
+            }
+        }
+        look
+    }
+    /// Gathers raw motion, from [`DeviceEvent::MouseMotion`](winit::event::DeviceEvent).
+    pub fn motion(&mut self, dx: f64, dy: f64) {
+        self.pending.0 += dx;
+        self.pending.1 += dy;
+    }
+    /// The movement to look by since the last call, `frame` ago, if the mouse moved.
+    pub fn take(&mut self, frame: Duration) -> Option<(f64, f64)> {
+        let (dx, dy) = std::mem::take(&mut self.pending);
+        if dx == 0.0 && dy == 0.0 {
+            return None;
+        }
+        let speed = dx.hypot(dy) / frame.as_secs_f64().max(1e-3);
+        let gain = (1.0 + self.acceleration * speed / ACCELERATION_SPEED).min(MAX_GAIN);
+        let sign = |inverted| if inverted { -1.0 } else { 1.0 };
+        let scale = self.sensitivity * gain;
+        Some((
+            sign(self.invert_x) * scale * dx,
+            sign(self.invert_y) * scale * dy,
+        ))
+    }
+}
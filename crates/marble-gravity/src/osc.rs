@@ -0,0 +1,472 @@
+//! `--osc 9000` or `MARBLE_GRAVITY_OSC=9000` listens for Open Sound Control messages on that UDP
+//! port of localhost, or on an address such as `0.0.0.0:9000` to take them from other machines,
+//! so that controllers and VJ software can play the simulation as a visual instrument in time
+//! with music. Messages become lines of the developer console, see [`CommandLine`]:
+//!
+//! - `/marble/set/gravity 20` changes a setting, such as `gravity`, `speed` or `sun-azimuth`.
+//! - `/marble/pause`, `/marble/path` and other commands run when sent without arguments or with
+//!   a nonzero one, so that buttons run them when pressed but not when released.
+//! - `/marble/command "spawn 10"` runs any line.
+//!
+//! `--osc-map <file>` maps other addresses, one per line. `/1/fader1 gravity 0 40` turns a fader
+//! from 0 to 1 into a setting from 0 to 40, and `/1/push1 path` runs a command like the buttons
+//! above. Lines starting with `#` are comments.
+//!
+//! `--midi /dev/snd/midiC1D0` or `MARBLE_GRAVITY_MIDI` reads MIDI from a raw device, such as
+//! those of ALSA or `/dev/midi1` of OSS, and turns it into OSC messages for the map. Control
+//! change 7 arrives as `/midi/cc/7` with a value from 0 to 1, and note 36 as `/midi/note/36`
+//! with its velocity from 0 to 1, or 0 when released, on any channel. So `/midi/cc/7 gravity 0 40`
+//! turns a knob into gravity and `/midi/note/36 path` a pad into a command. Where there are no
+//! such devices, bridges from MIDI to OSC work instead. Not supported on the web.
+//!
+//! [`CommandLine`]: crate::command_line::CommandLine
+
+use crate::udp::{self, MAX_PACKET};
+use std::{
+    fs::File,
+    io::{self, Read},
+    net::UdpSocket,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+#[derive(Debug, PartialEq)]
+enum Argument {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    /// A setting, which a value from 0 to 1 sets from the first to the second number
+    Setting(String, (f32, f32)),
+    Command(String),
+}
+
+pub struct Osc {
+    socket: Option<UdpSocket>,
+    /// The messages of the MIDI device, read by another thread
+    midi: Option<Receiver<(String, Vec<Argument>)>>,
+    map: Vec<(String, Target)>,
+    packet: Vec<u8>,
+}
+impl Osc {
+    /// Listens on the port or address and to the MIDI device requested on the command line, if
+    /// any.
+    pub fn requested() -> Self {
+        let mut osc = Self {
+            socket: None,
+            midi: None,
+            map: Vec::new(),
+            packet: vec![0; MAX_PACKET],
+        };
+        if let Some((name, value)) = crate::requested("osc") {
+            osc.socket = udp::listen(&name, &value, "OSC");
+        }
+        if let Some((name, path)) = crate::requested("midi") {
+            match open_midi(&path) {
+                Ok(midi) => {
+                    log::info!("Reading MIDI from {path}");
+                    osc.midi = Some(midi);
+                }
+                Err(err) => log::error!("Failed to read MIDI from {name} {path}: {err}"),
+            }
+        }
+        if osc.socket.is_none() && osc.midi.is_none() {
+            return osc;
+        }
+        if let Some((name, path)) = crate::requested("osc-map") {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => osc.map = parse_map(&text),
+                Err(err) => log::error!("Failed to read {name} {path}: {err}"),
+            }
+        }
+        osc
+    }
+    /// The console lines of the messages received since the last call, in order, and whether
+    /// each sets a setting from a value, as faders do continuously, rather than running a
+    /// command.
+    pub fn poll(&mut self) -> Vec<(String, bool)> {
+        let mut lines: Vec<(String, bool)> = Vec::new();
+        let mut messages = Vec::new();
+        while let Some(socket) = &self.socket {
+            match socket.recv(&mut self.packet) {
+                Ok(length) => {
+                    if parse_packet(&self.packet[..length], &mut messages).is_none() {
+                        log::warn!("Ignoring a malformed OSC packet");
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    log::warn!("Failed to receive OSC: {err}");
+                    break;
+                }
+            }
+        }
+        if let Some(midi) = &self.midi {
+            messages.extend(midi.try_iter());
+        }
+        for (address, arguments) in messages {
+            let Some((line, continuous)) = self.line(&address, &arguments) else {
+                log::debug!("Ignoring OSC message to {address}");
+                continue;
+            };
+            // Faders send many values a frame, of which only the last matters
+            let setting = setting_of(&line);
+            match lines
+                .iter_mut()
+                .find(|(earlier, _)| setting.is_some() && setting_of(earlier) == setting)
+            {
+                Some(earlier) => *earlier = (line, continuous),
+                None => lines.push((line, continuous)),
+            }
+        }
+        lines
+    }
+    fn line(&self, address: &str, arguments: &[Argument]) -> Option<(String, bool)> {
+        let pressed = match arguments.first() {
+            Some(Argument::Number(x)) => *x != 0.0,
+            Some(Argument::Text(_)) | None => true,
+        };
+        if let Some((_, target)) = self.map.iter().find(|(mapped, _)| mapped == address) {
+            return match target {
+                Target::Setting(name, (min, max)) => match arguments.first()? {
+                    Argument::Number(x) => {
+                        let x = (*x as f32).clamp(0.0, 1.0);
+                        Some((format!("set {name} {}", min + (max - min) * x), true))
+                    }
+                    Argument::Text(_) => None,
+                },
+                Target::Command(line) => pressed.then(|| (line.clone(), false)),
+            };
+        }
+        let path = address.strip_prefix("/marble/")?;
+        if let Some(name) = path.strip_prefix("set/") {
+            return Some(match arguments.first()? {
+                Argument::Number(x) => (format!("set {name} {x}"), true),
+                Argument::Text(value) => (format!("set {name} {value}"), false),
+            });
+        }
+        if path == "command" {
+            return match arguments.first()? {
+                Argument::Text(line) => Some((line.clone(), false)),
+                Argument::Number(_) => None,
+            };
+        }
+        pressed.then(|| (path.to_owned(), false))
+    }
+}
+
+/// Reads the raw MIDI device at `path` on another thread, sending its messages as OSC ones.
+fn open_midi(path: &str) -> io::Result<Receiver<(String, Vec<Argument>)>> {
+    let mut device = File::open(path)?;
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("midi".to_owned())
+        .spawn(move || {
+            let mut parser = MidiParser::default();
+            let mut bytes = [0; 256];
+            loop {
+                let length = match device.read(&mut bytes) {
+                    Ok(0) => {
+                        log::warn!("Stopped reading MIDI, the device was closed");
+                        return;
+                    }
+                    Ok(length) => length,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        log::warn!("Stopped reading MIDI: {err}");
+                        return;
+                    }
+                };
+                for &byte in &bytes[..length] {
+                    let Some(message) = parser.byte(byte) else {
+                        continue;
+                    };
+                    // The receiver is gone once the event loop has exited
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                }
+            }
+        })?;
+    Ok(receiver)
+}
+
+/// Splits a raw MIDI stream into messages, of which notes and control changes are kept.
+#[derive(Default)]
+struct MidiParser {
+    /// The last channel message status, which later messages may leave out, or 0 after others
+    status: u8,
+    data: Vec<u8>,
+}
+impl MidiParser {
+    fn byte(&mut self, byte: u8) -> Option<(String, Vec<Argument>)> {
+        match byte {
+            // Real-time messages may come between the bytes of others, and are skipped
+            0xF8..=0xFF => return None,
+            0x80..=0xEF => {
+                self.status = byte;
+                self.data.clear();
+                return None;
+            }
+            // System exclusive and common messages, skipped along with their data
+            0xF0..=0xF7 => {
+                self.status = 0;
+                return None;
+            }
+            _ if self.status == 0 => return None,
+            data => self.data.push(data),
+        }
+        let kind = self.status >> 4;
+        // Program changes and channel pressure have a single data byte
+        if self.data.len() < if kind == 0xC || kind == 0xD { 1 } else { 2 } {
+            return None;
+        }
+        let (number, value) = match self.data[..] {
+            [number, value] => (number, f64::from(value) / 127.0),
+            _ => (self.data[0], 0.0),
+        };
+        self.data.clear();
+        let (address, value) = match kind {
+            // Releases, as are presses without velocity
+            0x8 => (format!("/midi/note/{number}"), 0.0),
+            0x9 => (format!("/midi/note/{number}"), value),
+            0xB => (format!("/midi/cc/{number}"), value),
+            _ => return None,
+        };
+        Some((address, vec![Argument::Number(value)]))
+    }
+}
+
+/// The setting a line sets, if it sets one.
+fn setting_of(line: &str) -> Option<&str> {
+    line.strip_prefix("set ")?.split_whitespace().next()
+}
+
+fn parse_map(text: &str) -> Vec<(String, Target)> {
+    let mut map = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let target = match words[..] {
+            [address, ..] if !address.starts_with('/') => {
+                log::warn!("Ignoring OSC mapping {line}, expected an address starting with /");
+                continue;
+            }
+            [_, name, min, max] => match (min.parse(), max.parse()) {
+                (Ok(min), Ok(max)) => Target::Setting(name.to_owned(), (min, max)),
+                _ => Target::Command(words[1..].join(" ")),
+            },
+            [_, ..] if words.len() > 1 => Target::Command(words[1..].join(" ")),
+            _ => {
+                log::warn!("Ignoring OSC mapping {line}, expected a command after the address");
+                continue;
+            }
+        };
+        map.push((words[0].to_owned(), target));
+    }
+    log::info!("Mapped {} OSC addresses", map.len());
+    map
+}
+
+/// Appends the messages of a packet, which may be a bundle of further packets. Bundles are run
+/// as soon as they arrive, regardless of their time tags.
+fn parse_packet(packet: &[u8], messages: &mut Vec<(String, Vec<Argument>)>) -> Option<()> {
+    if let Some(mut elements) = packet.strip_prefix(b"#bundle\0") {
+        // Skips the time tag
+        elements = elements.get(8..)?;
+        while !elements.is_empty() {
+            let size = i32::from_be_bytes(elements.get(..4)?.try_into().unwrap());
+            let end = 4 + usize::try_from(size).ok()?;
+            parse_packet(elements.get(4..end)?, messages)?;
+            elements = &elements[end..];
+        }
+        return Some(());
+    }
+    let mut rest = packet;
+    let address = read_string(&mut rest)?;
+    // Old senders may leave out the type tags, and with them the arguments
+    let tags = if rest.is_empty() {
+        String::new()
+    } else {
+        read_string(&mut rest)?
+    };
+    let mut arguments = Vec::new();
+    for tag in tags.strip_prefix(',').unwrap_or_default().chars() {
+        let argument = match tag {
+            'i' => Argument::Number(f64::from(i32::from_be_bytes(read(&mut rest)?))),
+            'f' => Argument::Number(f64::from(f32::from_be_bytes(read(&mut rest)?))),
+            'h' => Argument::Number(i64::from_be_bytes(read(&mut rest)?) as f64),
+            'd' => Argument::Number(f64::from_be_bytes(read(&mut rest)?)),
+            'T' => Argument::Number(1.0),
+            'F' | 'N' => Argument::Number(0.0),
+            's' | 'S' => Argument::Text(read_string(&mut rest)?),
+            'b' => {
+                let size = usize::try_from(i32::from_be_bytes(read(&mut rest)?)).ok()?;
+                rest = rest.get(size.next_multiple_of(4)..)?;
+                continue;
+            }
+            // The sizes of other types are unknown, so the arguments after them are lost
+            _ => break,
+        };
+        arguments.push(argument);
+    }
+    messages.push((address, arguments));
+    Some(())
+}
+
+fn read<const N: usize>(rest: &mut &[u8]) -> Option<[u8; N]> {
+    let bytes = rest.get(..N)?.try_into().unwrap();
+    *rest = &rest[N..];
+    Some(bytes)
+}
+
+/// Reads a string terminated by a zero byte and padded to a multiple of four bytes.
+fn read_string(rest: &mut &[u8]) -> Option<String> {
+    let length = rest.iter().position(|&byte| byte == 0)?;
+    let string = String::from_utf8(rest[..length].to_vec()).ok()?;
+    *rest = rest.get((length + 1).next_multiple_of(4)..)?;
+    Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn midi(bytes: &[u8]) -> Vec<(String, Vec<Argument>)> {
+        let mut parser = MidiParser::default();
+        bytes.iter().filter_map(|&byte| parser.byte(byte)).collect()
+    }
+
+    fn message(address: &str, value: f64) -> (String, Vec<Argument>) {
+        (address.to_owned(), vec![Argument::Number(value)])
+    }
+
+    /// The bytes of a string padded with zeros to a multiple of four
+    fn padded(string: &str) -> Vec<u8> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize((string.len() + 1).next_multiple_of(4), 0);
+        bytes
+    }
+
+    fn float_packet(address: &str, value: f32) -> Vec<u8> {
+        [padded(address), padded(",f"), value.to_be_bytes().to_vec()].concat()
+    }
+
+    fn bundle(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = padded("#bundle");
+        bytes.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for packet in packets {
+            bytes.extend((packet.len() as i32).to_be_bytes());
+            bytes.extend(packet);
+        }
+        bytes
+    }
+
+    fn parsed(packet: &[u8]) -> Option<Vec<(String, Vec<Argument>)>> {
+        let mut messages = Vec::new();
+        parse_packet(packet, &mut messages).map(|()| messages)
+    }
+
+    #[test]
+    fn midi_keeps_notes_and_control_changes() {
+        assert_eq!(
+            midi(&[0xB3, 7, 127, 0x90, 36, 0, 0x80, 36, 64]),
+            [
+                message("/midi/cc/7", 1.0),
+                message("/midi/note/36", 0.0),
+                message("/midi/note/36", 0.0),
+            ]
+        );
+        // Program changes take a single data byte, after which the next message starts
+        assert_eq!(midi(&[0xC0, 5, 0xB0, 1, 0]), [message("/midi/cc/1", 0.0)]);
+    }
+
+    #[test]
+    fn midi_running_status_repeats_the_last_status() {
+        assert_eq!(
+            midi(&[0xB0, 7, 64, 8, 127]),
+            [
+                message("/midi/cc/7", 64.0 / 127.0),
+                message("/midi/cc/8", 1.0)
+            ]
+        );
+        // System messages end the running status, so the data after them is skipped
+        assert_eq!(midi(&[0xB0, 7, 64, 0xF0, 1, 2, 0xF7, 8, 64]).len(), 1);
+    }
+
+    #[test]
+    fn midi_skips_real_time_bytes() {
+        assert_eq!(
+            midi(&[0xF8, 0xB0, 0xF8, 7, 0xFE, 127, 0xFF]),
+            [message("/midi/cc/7", 1.0)]
+        );
+    }
+
+    #[test]
+    fn parses_messages() {
+        assert_eq!(
+            parsed(&float_packet("/marble/set/gravity", 20.0)),
+            Some(vec![message("/marble/set/gravity", 20.0)])
+        );
+        let packet = [padded("/marble/command"), padded(",sT"), padded("spawn 10")].concat();
+        assert_eq!(
+            parsed(&packet),
+            Some(vec![(
+                "/marble/command".to_owned(),
+                vec![Argument::Text("spawn 10".to_owned()), Argument::Number(1.0)]
+            )])
+        );
+        // Without type tags
+        assert_eq!(
+            parsed(&padded("/marble/pause")),
+            Some(vec![("/marble/pause".to_owned(), Vec::new())])
+        );
+    }
+
+    #[test]
+    fn parses_nested_bundles() {
+        let inner = bundle(&[float_packet("/b", 2.0)]);
+        let packet = bundle(&[float_packet("/a", 1.0), inner]);
+        assert_eq!(
+            parsed(&packet),
+            Some(vec![message("/a", 1.0), message("/b", 2.0)])
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_packets() {
+        let packet = float_packet("/marble/set/gravity", 20.0);
+        for length in [0, 3, packet.len() - 1] {
+            assert_eq!(parsed(&packet[..length]), None, "{length}");
+        }
+        let packet = bundle(&[float_packet("/a", 1.0)]);
+        assert_eq!(parsed(&packet[..packet.len() - 1]), None);
+        assert_eq!(parsed(&packet[..12]), None);
+    }
+
+    #[test]
+    fn parses_maps() {
+        let map = parse_map(
+            "# A comment\n\n/1/fader1 gravity 0 40\n  /1/push1 path  \n/1/push2 spawn 1 x\n\
+             fader gravity 0 1\n/1/push3\n",
+        );
+        assert_eq!(
+            map,
+            [
+                (
+                    "/1/fader1".to_owned(),
+                    Target::Setting("gravity".to_owned(), (0.0, 40.0))
+                ),
+                ("/1/push1".to_owned(), Target::Command("path".to_owned())),
+                (
+                    "/1/push2".to_owned(),
+                    Target::Command("spawn 1 x".to_owned())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+/// A point of the window that an overlay widget is attached to, along with the same point of the
+/// widget itself.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+impl Anchor {
+    /// Where along each axis the anchor is, from 0 (left/top) to 1 (right/bottom)
+    pub fn fraction(self) -> (f32, f32) {
+        let x = match self {
+            Self::TopLeft | Self::Left | Self::BottomLeft => 0.0,
+            Self::Top | Self::Center | Self::Bottom => 0.5,
+            Self::TopRight | Self::Right | Self::BottomRight => 1.0,
+        };
+        let y = match self {
+            Self::TopLeft | Self::Top | Self::TopRight => 0.0,
+            Self::Left | Self::Center | Self::Right => 0.5,
+            Self::BottomLeft | Self::Bottom | Self::BottomRight => 1.0,
+        };
+        (x, y)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WidgetLayout {
+    pub anchor: Anchor,
+    /// Distance in pixels from the window edges the anchor is on, and ignored along centered axes
+    pub margin: (f32, f32),
+    pub visible: bool,
+}
+impl WidgetLayout {
+    /// The anchor point in the window, in pixels.
+    pub fn anchor_point(&self, (width, height): (u32, u32)) -> (f32, f32) {
+        let (fx, fy) = self.anchor.fraction();
+        let (mx, my) = self.margin;
+        (
+            fx * width as f32 + (1.0 - 2.0 * fx) * mx,
+            fy * height as f32 + (1.0 - 2.0 * fy) * my,
+        )
+    }
+    /// The top left corner in the window of a widget of the given size, in pixels.
+    pub fn top_left(&self, (w, h): (f32, f32), window_size: (u32, u32)) -> (f32, f32) {
+        let (fx, fy) = self.anchor.fraction();
+        let (x, y) = self.anchor_point(window_size);
+        (x - fx * w, y - fy * h)
+    }
+}
+
+/// Placement of every overlay widget
+#[derive(Clone, Copy, Debug)]
+pub struct OverlayLayout {
+    pub fps: WidgetLayout,
+    /// Lines added through `Graphics::overlay_line`
+    pub lines: WidgetLayout,
+    /// The attitude indicator, see [`crate::attitude`]
+    pub attitude: WidgetLayout,
+    /// The axis gnomon, see [`crate::compass`]
+    pub compass: WidgetLayout,
+}
+impl Default for OverlayLayout {
+    fn default() -> Self {
+        Self {
+            fps: WidgetLayout {
+                anchor: Anchor::TopLeft,
+                margin: (5.0, 5.0),
+                visible: true,
+            },
+            lines: WidgetLayout {
+                anchor: Anchor::TopLeft,
+                margin: (5.0, 37.0),
+                visible: true,
+            },
+            attitude: WidgetLayout {
+                anchor: Anchor::Bottom,
+                margin: (0.0, 20.0),
+                visible: false,
+            },
+            compass: WidgetLayout {
+                anchor: Anchor::BottomRight,
+                margin: (20.0, 20.0),
+                visible: false,
+            },
+        }
+    }
+}
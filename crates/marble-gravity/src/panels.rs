@@ -0,0 +1,185 @@
+use cgmath::{prelude::*, Vector2};
+use std::mem;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PanelInstance {
+    /// A corner, followed by the edges from it to the two adjacent corners, in normalized device
+    /// coordinates
+    origin: [f32; 2],
+    edges: [f32; 4],
+    color: [f32; 4],
+}
+unsafe impl bytemuck::Pod for PanelInstance {}
+unsafe impl bytemuck::Zeroable for PanelInstance {}
+
+/// Draws solid, alpha blended rectangles, as backgrounds for overlay widgets and as lines.
+pub struct PanelRenderer {
+    pipeline: wgpu::RenderPipeline,
+    instances: Vec<PanelInstance>,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+}
+impl PanelRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        instance_capacity: usize,
+    ) -> Self {
+        let module = device.create_shader_module(wgpu::include_wgsl!(concat!(
+            env!("OUT_DIR"),
+            "/panels.wgsl"
+        )));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("panels"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("panels"),
+                    bind_group_layouts: &[],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<PanelInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x4,
+                        2 => Float32x4,
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self {
+            pipeline,
+            instances: Vec::with_capacity(instance_capacity),
+            instance_buffer: make_instance_buffer(device, instance_capacity),
+            instance_capacity,
+        }
+    }
+
+    /// Adds a rectangle with corners in pixels, to be drawn by the next [`PanelRenderer::draw`].
+    pub fn queue(
+        &mut self,
+        (min, max): ((f32, f32), (f32, f32)),
+        color: [f32; 4],
+        window_size: (u32, u32),
+    ) {
+        self.queue_quad(
+            Vector2::from(min),
+            [
+                Vector2::new(max.0 - min.0, 0.0),
+                Vector2::new(0.0, max.1 - min.1),
+            ],
+            color,
+            window_size,
+        );
+    }
+
+    /// Adds a line between two points in pixels, to be drawn by the next
+    /// [`PanelRenderer::draw`].
+    pub fn queue_line(
+        &mut self,
+        (from, to): ((f32, f32), (f32, f32)),
+        width: f32,
+        color: [f32; 4],
+        window_size: (u32, u32),
+    ) {
+        let (from, to) = (Vector2::from(from), Vector2::from(to));
+        let along = to - from;
+        if along.is_zero() {
+            return;
+        }
+        let across = width * Vector2::new(-along.y, along.x).normalize();
+        self.queue_quad(from - 0.5 * across, [along, across], color, window_size);
+    }
+
+    fn queue_quad(
+        &mut self,
+        origin: Vector2<f32>,
+        [a, b]: [Vector2<f32>; 2],
+        color: [f32; 4],
+        (width, height): (u32, u32),
+    ) {
+        let scale = Vector2::new(2.0 / width as f32, -2.0 / height as f32);
+        let origin = origin.mul_element_wise(scale) + Vector2::new(-1.0, 1.0);
+        let [a, b] = [a, b].map(|edge| edge.mul_element_wise(scale));
+        self.instances.push(PanelInstance {
+            origin: origin.into(),
+            edges: [a.x, a.y, b.x, b.y],
+            color,
+        });
+    }
+
+    /// Discards the queued rectangles and lines.
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Draws all queued rectangles and lines on top of `target`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        if self.instances.is_empty() {
+            return;
+        }
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = make_instance_buffer(device, self.instance_capacity);
+        }
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("panels"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..4, 0..self.instances.len() as u32);
+        drop(pass);
+        self.instances.clear();
+    }
+}
+
+fn make_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Panel instance buffer"),
+        size: (capacity * mem::size_of::<PanelInstance>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
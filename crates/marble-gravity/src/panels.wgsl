@@ -0,0 +1,24 @@
+// Translucent rectangles behind overlay text, and lines, see panels.rs
+
+struct PanelOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @location(0) origin: vec2<f32>,
+    @location(1) edges: vec4<f32>,
+    @location(2) color: vec4<f32>,
+) -> PanelOutput {
+    // A triangle strip covering the parallelogram spanned by the edges
+    let corner = vec2<f32>(f32(index / 2u), f32(index % 2u));
+    let position = origin + corner.x * edges.xy + corner.y * edges.zw;
+    return PanelOutput(vec4<f32>(position, 0.0, 1.0), color);
+}
+
+@fragment
+fn fs_main(in: PanelOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
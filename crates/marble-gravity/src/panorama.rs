@@ -0,0 +1,153 @@
+//! 360 degree panoramas. The scene is rendered into the six faces of a cube around the camera,
+//! which are resampled into an equirectangular image, as read by VR photo viewers and usable as
+//! a skybox.
+
+use crate::graphics::{Graphics, View};
+use camera::Pose;
+use cgmath::{prelude::*, Matrix3, Quaternion, Vector2, Vector3};
+use image::{GenericImage, Rgba, RgbaImage};
+use physics::Physics;
+use spheretree::SphereTreeBuilder;
+use std::f32::consts::{PI, TAU};
+
+/// Distance between the eyes of stereo panoramas, in world units
+const EYE_SEPARATION: f32 = 0.02;
+
+/// A cube face, as the directions of its right, down and forward axes in camera space
+struct Face {
+    right: Vector3<f32>,
+    down: Vector3<f32>,
+    forward: Vector3<f32>,
+}
+const FACES: [Face; 6] = [
+    face([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    face([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+    face([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+    face([0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]),
+    face([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+    face([1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+];
+const fn face(right: [f32; 3], down: [f32; 3], forward: [f32; 3]) -> Face {
+    Face {
+        right: Vector3::new(right[0], right[1], right[2]),
+        down: Vector3::new(down[0], down[1], down[2]),
+        forward: Vector3::new(forward[0], forward[1], forward[2]),
+    }
+}
+
+/// Renders a panorama around `pose`, `width` pixels wide and half as tall. Stereo panoramas are
+/// top-bottom, with the left eye above the right eye.
+pub fn capture_panorama(
+    graphics: &mut Graphics,
+    sphere_tree: &mut SphereTreeBuilder,
+    physics: &Physics,
+    pose: Pose,
+    width: u32,
+    stereo: bool,
+) -> RgbaImage {
+    let height = width / 2;
+    let eyes: &[f32] = if stereo { &[-0.5, 0.5] } else { &[0.0] };
+    let mut image = RgbaImage::new(width, height * eyes.len() as u32);
+    for (index, eye) in eyes.iter().enumerate() {
+        let faces = render_cube(
+            graphics,
+            sphere_tree,
+            physics,
+            pose,
+            (width / 4).max(1),
+            eye * EYE_SEPARATION,
+        );
+        image
+            .copy_from(
+                &equirectangular(&faces, (width, height)),
+                0,
+                index as u32 * height,
+            )
+            .unwrap();
+    }
+    image
+}
+
+/// Renders the faces of a cube around `pose`, in the order of [`FACES`]. Side faces are seen from
+/// `eye_offset` along their right axis, which approximates stereo in every horizontal direction.
+fn render_cube(
+    graphics: &mut Graphics,
+    sphere_tree: &mut SphereTreeBuilder,
+    physics: &Physics,
+    pose: Pose,
+    size: u32,
+    eye_offset: f32,
+) -> Vec<RgbaImage> {
+    FACES
+        .iter()
+        .map(|face| {
+            let offset = if face.forward.y == 0.0 {
+                eye_offset * face.right
+            } else {
+                Vector3::zero()
+            };
+            let face_pose = Pose {
+                position: pose.position + pose.rotation.rotate_vector(offset),
+                rotation: pose.rotation
+                    * Quaternion::from(Matrix3::from_cols(face.right, face.down, face.forward)),
+            };
+            let view = View {
+                bodies: sphere_tree.build(physics.bodies(), &physics.config().groups, &face_pose),
+                pose: face_pose,
+            };
+            // A 90 degree field of view
+            let half = 0.5 * size as f32;
+            graphics.capture_viewport(
+                &view,
+                (size, size),
+                Vector2::new(half, half),
+                Vector2::new(0.5 * half, 0.5 * half),
+            )
+        })
+        .collect()
+}
+
+/// Resamples cube faces into an image with longitude along x and latitude along y, centered on
+/// the forward direction.
+fn equirectangular(faces: &[RgbaImage], (width, height): (u32, u32)) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let longitude = ((x as f32 + 0.5) / width as f32 - 0.5) * TAU;
+        let latitude = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+        let direction = Vector3::new(
+            latitude.cos() * longitude.sin(),
+            -latitude.sin(),
+            latitude.cos() * longitude.cos(),
+        );
+        let (face, image) = FACES
+            .iter()
+            .zip(faces)
+            .max_by(|(a, _), (b, _)| {
+                a.forward
+                    .dot(direction)
+                    .total_cmp(&b.forward.dot(direction))
+            })
+            .unwrap();
+        let along = face.forward.dot(direction);
+        sample(
+            image,
+            0.5 + 0.5 * face.right.dot(direction) / along,
+            0.5 + 0.5 * face.down.dot(direction) / along,
+        )
+    })
+}
+
+/// Bilinearly samples an image at coordinates from 0 to 1
+fn sample(image: &RgbaImage, u: f32, v: f32) -> Rgba<u8> {
+    let (width, height) = image.dimensions();
+    let x = (u * width as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x as u32, y as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+    Rgba(std::array::from_fn(|channel| {
+        let texel = |x, y| f32::from(image.get_pixel(x, y)[channel]);
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x1, y0) * fx;
+        let bottom = texel(x0, y1) * (1.0 - fx) + texel(x1, y1) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }))
+}
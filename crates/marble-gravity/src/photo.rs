@@ -0,0 +1,155 @@
+//! Photo mode: time stands still, the camera moves slowly for precise framing, bodies are drawn
+//! at the highest quality and overlays are hidden, so that large screenshots can be captured.
+//!
+//! Natively, `MARBLE_GRAVITY_PHOTO_SIZE`, such as `15360x8640`, adds a capture size of its own.
+
+use crate::graphics::{Graphics, View, MAX_RAY_SPLITS};
+use camera::Pose;
+use physics::Physics;
+use spheretree::SphereTreeBuilder;
+
+/// Mouse look is scaled by this while in photo mode
+pub const PHOTO_LOOK_SCALE: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug)]
+enum CaptureSize {
+    /// A multiple of the window resolution along each axis
+    WindowMultiple(u32),
+    Pixels(u32, u32),
+}
+
+/// The width of panoramas, which are half as tall per eye
+const PANORAMA_WIDTH: u32 = 4096;
+/// Capture sizes to choose between, for posters and wallpapers
+const CAPTURE_SIZES: [CaptureSize; 3] = [
+    CaptureSize::WindowMultiple(4),
+    CaptureSize::Pixels(7680, 4320),
+    CaptureSize::Pixels(15360, 8640),
+];
+
+pub struct PhotoMode {
+    /// The ray splits to return to, while in photo mode
+    previous_ray_splits: Option<u32>,
+    capture_sizes: Vec<CaptureSize>,
+    capture_size: usize,
+}
+impl PhotoMode {
+    pub fn new() -> Self {
+        let mut capture_sizes = CAPTURE_SIZES.to_vec();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(size) = std::env::var("MARBLE_GRAVITY_PHOTO_SIZE") {
+            match parse_size(&size) {
+                Some((width, height)) => {
+                    capture_sizes.insert(0, CaptureSize::Pixels(width, height));
+                }
+                None => log::warn!("Ignoring MARBLE_GRAVITY_PHOTO_SIZE={size}, expected WxH"),
+            }
+        }
+        Self {
+            previous_ray_splits: None,
+            capture_sizes,
+            capture_size: 0,
+        }
+    }
+    pub fn is_active(&self) -> bool {
+        self.previous_ray_splits.is_some()
+    }
+    pub fn toggle(&mut self, graphics: &mut Graphics) {
+        match self.previous_ray_splits.take() {
+            Some(ray_splits) => {
+                graphics.set_ray_splits(ray_splits);
+                graphics.set_overlay_visible(true);
+                log::info!("Left photo mode");
+            }
+            None => {
+                self.previous_ray_splits = Some(graphics.ray_splits());
+                graphics.set_ray_splits(MAX_RAY_SPLITS);
+                graphics.set_overlay_visible(false);
+                log::info!("Entered photo mode");
+            }
+        }
+    }
+    pub fn next_capture_size(&mut self) {
+        self.capture_size = (self.capture_size + 1) % self.capture_sizes.len();
+        log::info!("Capture size: {:?}", self.capture_sizes[self.capture_size]);
+    }
+    /// Saves a supersampled screenshot as a PNG file while in photo mode. This is not supported
+    /// on the web.
+    pub fn capture(&self, graphics: &mut Graphics, view: &View<'_>) {
+        if !self.is_active() {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let size = match self.capture_sizes[self.capture_size] {
+                CaptureSize::WindowMultiple(scale) => {
+                    let (width, height) = graphics.window_size();
+                    (scale * width, scale * height)
+                }
+                CaptureSize::Pixels(width, height) => (width, height),
+            };
+            save(&graphics.capture(view, size), "photo");
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (graphics, view);
+            log::warn!("Photos cannot be saved on the web");
+        }
+    }
+    /// Saves an equirectangular 360 degree panorama around the camera as a PNG file while in
+    /// photo mode, optionally in top-bottom stereo. This is not supported on the web.
+    pub fn capture_panorama(
+        &self,
+        graphics: &mut Graphics,
+        sphere_tree: &mut SphereTreeBuilder,
+        physics: &Physics,
+        pose: Pose,
+        stereo: bool,
+    ) {
+        if !self.is_active() {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let image = crate::panorama::capture_panorama(
+                graphics,
+                sphere_tree,
+                physics,
+                pose,
+                PANORAMA_WIDTH,
+                stereo,
+            );
+            save(&image, "panorama");
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (graphics, sphere_tree, physics, pose, stereo);
+            log::warn!("Panoramas cannot be saved on the web");
+        }
+    }
+}
+
+/// Saves an image in the working directory, named by kind and time.
+#[cfg(not(target_arch = "wasm32"))]
+fn save(image: &image::RgbaImage, kind: &str) {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = format!("{kind}-{seconds}.png");
+    match image.save(&path) {
+        Ok(()) => log::info!(
+            "Saved a {}x{} {kind} to {path}",
+            image.width(),
+            image.height()
+        ),
+        Err(err) => log::error!("Failed to save {kind} to {path}: {err}"),
+    }
+}
+
+/// Parses sizes such as `1920x1080`.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    let (width, height) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
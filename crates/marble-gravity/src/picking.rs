@@ -0,0 +1,118 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// What was visible at a picked pixel
+#[derive(Clone, Copy, Debug)]
+pub enum Picked {
+    /// A body index, referring to the bodies as they were in the frame the pick was rendered in
+    Body(usize),
+    Background,
+}
+
+enum State {
+    Idle,
+    Requested((u32, u32)),
+    Copied,
+    Mapping(Arc<AtomicBool>),
+}
+
+/// Reads back the object-ID target under a single pixel. The answer arrives a few frames after
+/// the request, once the GPU has caught up, and only one pick is in flight at a time.
+pub struct GpuPicker {
+    buffer: wgpu::Buffer,
+    state: State,
+}
+impl GpuPicker {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Picking buffer"),
+                size: std::mem::size_of::<u32>() as u64,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            state: State::Idle,
+        }
+    }
+    /// Ignored while another pick is in flight.
+    pub fn request(&mut self, pixel: (u32, u32)) {
+        if let State::Idle = self.state {
+            self.state = State::Requested(pixel);
+        }
+    }
+    /// Copies the requested pixel out of `ids`, after the passes writing it have been recorded.
+    pub fn record(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        ids: &wgpu::Texture,
+        (width, height): (u32, u32),
+    ) {
+        let State::Requested((x, y)) = self.state else {
+            return;
+        };
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: ids,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    // The window may have shrunk since the request
+                    x: x.min(width.saturating_sub(1)),
+                    y: y.min(height.saturating_sub(1)),
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.state = State::Copied;
+    }
+    /// Must be called after the encoder given to [`GpuPicker::record`] has been submitted.
+    pub fn submitted(&mut self) {
+        if let State::Copied = self.state {
+            let mapped = Arc::new(AtomicBool::new(false));
+            self.buffer.slice(..).map_async(wgpu::MapMode::Read, {
+                let mapped = mapped.clone();
+                move |result| {
+                    result.unwrap();
+                    mapped.store(true, Ordering::SeqCst);
+                }
+            });
+            self.state = State::Mapping(mapped);
+        }
+    }
+    /// What was under the picked pixel, once available.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Picked> {
+        let State::Mapping(mapped) = &self.state else {
+            return None;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        if !mapped.load(Ordering::SeqCst) {
+            return None;
+        }
+        let id = {
+            let bytes = self.buffer.slice(..).get_mapped_range();
+            u32::from_le_bytes(bytes[..4].try_into().unwrap())
+        };
+        self.buffer.unmap();
+        self.state = State::Idle;
+        // Zero is the background, see `f_body` in shader.frag
+        Some(match id.checked_sub(1) {
+            Some(index) => Picked::Body(index as usize),
+            None => Picked::Background,
+        })
+    }
+}
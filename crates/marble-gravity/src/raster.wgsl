@@ -0,0 +1,295 @@
+// Rasterized alternative to the raytracer in shader.frag. Bodies are drawn as instanced
+// icospheres with plain sun lighting and no reflections or shadows, on top of the skybox. The
+// same meshes also fill the G-buffer of the hybrid backend.
+
+// Must match `Uniforms` in graphics.rs
+struct Uniforms {
+    sun_direction: vec3<f32>,
+    ray_splits: u32,
+    viewport_size: vec2<f32>,
+    primary_from_gbuffer: u32,
+    medium_extinction: f32,
+    viewport_offset: vec2<f32>,
+    target_size: vec2<f32>,
+    camera_position: vec3<f32>,
+    medium_density: f32,
+    lens_position: vec3<f32>,
+    lens_radius: f32,
+    disk_center: vec3<f32>,
+    disk_body_radius: f32,
+    disk_time: f32,
+    tracer_count: u32,
+    emission: f32,
+    padding: f32,
+    view_to_world_space: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+@group(0) @binding(1)
+var skybox_texture: texture_cube<f32>;
+@group(0) @binding(2)
+var skybox_sampler: sampler;
+
+// Same as in shader.frag
+fn color_w(color: u32) -> f32 {
+    return f32(color & 0xFFu) / 255.0;
+}
+fn color_xyz(color: u32) -> vec3<f32> {
+    let r = (color >> 24u) & 0xFFu;
+    let g = (color >> 16u) & 0xFFu;
+    let b = (color >> 8u) & 0xFFu;
+    return vec3<f32>(f32(r), f32(g), f32(b)) / 255.0;
+}
+
+// Same as in shader.frag
+let NEAR: f32 = 0.001;
+let FAR: f32 = 1e7;
+
+// The camera looks along +z with +y down, and the viewport is one unit tall at unit distance,
+// exactly like the primary rays of the raytracer. The viewport may extend beyond the target, as
+// when rendering tiles. The depth of clip space only clips at the near plane, as fragments write
+// their own with `log_depth`.
+fn project(view_pos: vec3<f32>) -> vec4<f32> {
+    // The target pixel, multiplied by depth to stay linear
+    let pixel = (uniforms.viewport_offset + 0.5 * uniforms.viewport_size) * view_pos.z
+        + uniforms.viewport_size.y * view_pos.xy;
+    return vec4<f32>(
+        2.0 * pixel.x / uniforms.target_size.x - view_pos.z,
+        view_pos.z - 2.0 * pixel.y / uniforms.target_size.y,
+        view_pos.z - NEAR,
+        view_pos.z
+    );
+}
+
+// Logarithmic in the distance along the view, so that the precision of the depth buffer is the
+// same fraction of the distance from marbles up close to orbits far away, rather than almost all
+// spent near the camera. Matches `depth` in shader.frag.
+fn log_depth(z: f32) -> f32 {
+    return clamp(log2(max(z, NEAR) / NEAR) / log2(FAR / NEAR), 0.0, 1.0);
+}
+
+struct SkyboxOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+@vertex
+fn vs_skybox(@builtin(vertex_index) index: u32) -> SkyboxOutput {
+    // A triangle strip covering the target
+    let x = f32(index / 2u) * 2.0 - 1.0;
+    let y = f32(index % 2u) * 2.0 - 1.0;
+    return SkyboxOutput(vec4<f32>(x, y, 0.0, 1.0));
+}
+
+@fragment
+fn fs_skybox(in: SkyboxOutput) -> @location(0) vec4<f32> {
+    let frag_pos = (in.position.xy - uniforms.viewport_offset) / uniforms.viewport_size.y;
+    let mid_frag_pos = vec2<f32>(0.5 * uniforms.viewport_size.x / uniforms.viewport_size.y, 0.5);
+    let ray = normalize(vec3<f32>(frag_pos - mid_frag_pos, 1.0));
+    let world_ray = uniforms.view_to_world_space * vec4<f32>(ray, 1.0);
+    return vec4<f32>(textureSample(skybox_texture, skybox_sampler, world_ray.xyz).xyz, 1.0);
+}
+
+struct MeshOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) view_pos: vec3<f32>,
+    @location(2) @interpolate(flat) color: u32,
+    @location(3) @interpolate(flat) node: u32,
+    @location(4) @interpolate(flat) body: u32,
+}
+
+// Same outputs as shader.frag
+struct MeshFragment {
+    @location(0) color: vec4<f32>,
+    @location(1) body: u32,
+    // Per fragment, since a triangle of an icosphere spans a range of depths
+    @builtin(frag_depth) depth: f32,
+}
+
+// Instances are the nodes of the sphere tree, of which only the leaves are bodies.
+@vertex
+fn vs_mesh(
+    @location(0) vertex: vec3<f32>,
+    @location(1) center: vec3<f32>,
+    @location(2) radius: f32,
+    @location(3) left: i32,
+    @location(4) color: u32,
+    @location(5) body: u32,
+    @builtin(instance_index) node: u32,
+) -> MeshOutput {
+    let view_pos = center + radius * vertex;
+    var out: MeshOutput;
+    out.position = project(view_pos);
+    out.normal = vertex;
+    out.view_pos = view_pos;
+    out.color = color;
+    out.node = node;
+    out.body = body;
+    if (left != -1 || radius <= 0.0) {
+        // Collapse branches and unused nodes to a point behind the near plane
+        out.position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+    }
+    return out;
+}
+
+@fragment
+fn fs_mesh(in: MeshOutput) -> MeshFragment {
+    let ambient = vec3<f32>(0.08);
+    let sun_color = vec3<f32>(1.0);
+    let sun_corona = 1e-3;
+
+    let normal = normalize(in.normal);
+    let ray = normalize(in.view_pos);
+    let color = color_xyz(in.color);
+    let opacity_factor = color_w(in.color);
+    let opacity = 1.0 - opacity_factor * opacity_factor;
+
+    let alignment = max(0.0, dot(normal, normalize(uniforms.sun_direction - ray)));
+    var light = (ambient + uniforms.emission) * opacity * color;
+    light += color * sun_color * opacity * alignment;
+    light += sun_color * (1.0 - opacity) * pow(alignment, inverseSqrt(sun_corona));
+    return MeshFragment(vec4<f32>(light, 1.0), in.body + 1u, log_depth(in.view_pos.z));
+}
+
+struct GbufferFragment {
+    @location(0) node: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+// Primary visibility for the hybrid backend, read back by `primary_hit` in shader.frag
+@fragment
+fn fs_gbuffer(in: MeshOutput) -> GbufferFragment {
+    return GbufferFragment(vec4<f32>(f32(in.node + 1u), 0.0, 0.0, 0.0), log_depth(in.view_pos.z));
+}
+
+// The default gravity of `PhysicsConfig` in the physics crate, where mass is radius cubed
+let GRAVITY_CONSTANT: f32 = 40.0;
+// The disk spans these multiples of the radius of the body it orbits
+let DISK_INNER: f32 = 2.0;
+let DISK_OUTER: f32 = 7.0;
+let DISK_THICKNESS: f32 = 0.15;
+let DISK_PARTICLE_SIZE: f32 = 0.02;
+// A notional speed of light, low enough that the orbital speed visibly tints particles
+let DISK_LIGHT_SPEED: f32 = 2.0;
+let TRACER_SIZE: f32 = 0.004;
+// Tracers at least this fast are drawn in the brightest color
+let TRACER_FAST_SPEED: f32 = 1.0;
+
+// A glowing dot facing the camera, drawn as two triangles
+struct BillboardOutput {
+    @builtin(position) position: vec4<f32>,
+    // Within the billboard, from -1 to 1
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    // Billboards face the camera, so this is the same at every corner
+    @location(2) view_z: f32,
+}
+
+struct BillboardFragment {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+fn billboard_corner(vertex: u32) -> vec2<f32> {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    return corners[vertex];
+}
+
+fn world_to_view() -> mat3x3<f32> {
+    return transpose(mat3x3<f32>(
+        uniforms.view_to_world_space[0].xyz,
+        uniforms.view_to_world_space[1].xyz,
+        uniforms.view_to_world_space[2].xyz,
+    ));
+}
+
+// A pseudorandom number in [0, 1] for each integer
+fn hash(x: u32) -> f32 {
+    var h = x * 747796405u + 2891336453u;
+    h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+    h = (h >> 22u) ^ h;
+    return f32(h) / 4294967295.0;
+}
+
+// Each instance is a test particle on a circular orbit in the world xz plane around the heaviest
+// body. Orbits are solved in closed form from the simulated time, so the particles cost no
+// physics at all.
+@vertex
+fn vs_disk(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) particle: u32,
+) -> BillboardOutput {
+    let body_radius = uniforms.disk_body_radius;
+    // Denser toward the inner edge
+    let along = hash(3u * particle);
+    let radius = body_radius * mix(DISK_INNER, DISK_OUTER, along * along);
+    let angular_speed = sqrt(GRAVITY_CONSTANT * body_radius * body_radius * body_radius
+        / (radius * radius * radius));
+    let angle = 6.2831853 * hash(3u * particle + 1u) + angular_speed * uniforms.disk_time;
+    let height = body_radius * DISK_THICKNESS * (2.0 * hash(3u * particle + 2u) - 1.0);
+    let world_to_view = world_to_view();
+    let offset = world_to_view * vec3<f32>(radius * cos(angle), height, radius * sin(angle));
+    let velocity = world_to_view
+        * (angular_speed * radius * vec3<f32>(-sin(angle), 0.0, cos(angle)));
+    let center = uniforms.disk_center + offset;
+
+    // Hotter, whiter particles toward the inner edge, shifted toward blue and brightened when
+    // approaching the camera, and the opposite when receding
+    let hot = vec3<f32>(1.6, 1.4, 1.1);
+    let cool = vec3<f32>(0.9, 0.3, 0.05);
+    let approach = -dot(velocity, normalize(center)) / DISK_LIGHT_SPEED;
+    let doppler = sqrt((1.0 + approach) / (1.0 - approach));
+    let tint = vec3<f32>(1.0 / doppler, 1.0, doppler);
+
+    let corner = billboard_corner(vertex);
+    var out: BillboardOutput;
+    out.position = project(center + body_radius * DISK_PARTICLE_SIZE * vec3<f32>(corner, 0.0));
+    out.corner = corner;
+    out.view_z = center.z;
+    out.color = mix(hot, cool, along) * tint * doppler * doppler * doppler;
+    if (body_radius <= 0.0) {
+        out.position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+    }
+    return out;
+}
+
+// Tracers are instances with their world space position and velocity, and only the first
+// `tracer_count` are in use.
+@vertex
+fn vs_tracer(
+    @builtin(vertex_index) vertex: u32,
+    @builtin(instance_index) tracer: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) velocity: vec3<f32>,
+) -> BillboardOutput {
+    let center = world_to_view() * (position - uniforms.camera_position);
+    let corner = billboard_corner(vertex);
+    // Faster tracers are brighter and whiter
+    let speed = clamp(length(velocity) / TRACER_FAST_SPEED, 0.0, 1.0);
+    var out: BillboardOutput;
+    out.position = project(center + TRACER_SIZE * vec3<f32>(corner, 0.0));
+    out.corner = corner;
+    out.view_z = center.z;
+    out.color = mix(vec3<f32>(0.1, 0.2, 0.5), vec3<f32>(0.9, 0.95, 1.0), speed);
+    if (tracer >= uniforms.tracer_count) {
+        out.position = vec4<f32>(0.0, 0.0, -1.0, 1.0);
+    }
+    return out;
+}
+
+@fragment
+fn fs_billboard(in: BillboardOutput) -> BillboardFragment {
+    let falloff = 1.0 - dot(in.corner, in.corner);
+    if (falloff <= 0.0) {
+        discard;
+    }
+    return BillboardFragment(vec4<f32>(in.color * falloff, 1.0), log_depth(in.view_z));
+}
@@ -0,0 +1,251 @@
+//! `--remote 7878` or `MARBLE_GRAVITY_REMOTE=7878` serves a small HTTP interface on that port of
+//! localhost, so that scripts, stream decks and other tools can drive the running simulation
+//! without the window having focus:
+//!
+//! - `POST /command` with `{"command": "spawn 10"}` runs a command of the developer console,
+//!   see [`CommandLine`], and answers `{"output": "Spawned 10 bodies"}`, or `{"error": ...}`.
+//! - `GET /state` answers the tick, the simulated time, the camera and the bodies.
+//! - `GET /stats` answers how many frames and ticks have passed and where the time went.
+//!
+//! Only connections from this machine are accepted, and commands must be sent as
+//! `application/json`, which web pages cannot do to other origins without permission that is
+//! never given. Not supported on the web.
+//!
+//! [`CommandLine`]: crate::command_line::CommandLine
+
+use crate::json::{self, number, string, vector};
+use camera::Camera;
+use physics::Physics;
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Read, Write as _},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+/// Larger requests are refused, headers included
+const MAX_REQUEST: u64 = 64 * 1024;
+/// How long a client may take to send its request, and the simulation to answer it
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum Request {
+    /// A line for the developer console
+    Command(String),
+    State,
+    Stats,
+}
+/// What a command printed or the JSON of the state or stats, or what went wrong
+pub type Reply = Result<String, String>;
+
+pub struct Remote {
+    requests: Option<Receiver<(Request, Sender<Reply>)>>,
+}
+impl Remote {
+    /// Starts serving on the port requested on the command line, if any.
+    pub fn requested() -> Self {
+        let off = Self { requests: None };
+        let Some((name, value)) = crate::requested("remote") else {
+            return off;
+        };
+        let Ok(port) = value.trim().parse::<u16>() else {
+            log::warn!("Ignoring {name}={value}, expected a port such as 7878");
+            return off;
+        };
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            Ok(listener) => listener,
+            Err(err) => {
+                log::error!("Failed to serve remote control on port {port}: {err}");
+                return off;
+            }
+        };
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("remote control".to_owned())
+            .spawn(move || listen(&listener, &sender));
+        if let Err(err) = spawned {
+            log::error!("Failed to start remote control: {err}");
+            return off;
+        }
+        log::info!("Serving remote control on http://localhost:{port}");
+        Self {
+            requests: Some(receiver),
+        }
+    }
+    /// Answers the requests received since the last call.
+    pub fn serve(&self, mut answer: impl FnMut(Request) -> Reply) {
+        let Some(requests) = &self.requests else {
+            return;
+        };
+        for (request, reply) in requests.try_iter() {
+            // The client may have given up waiting
+            let _ = reply.send(answer(request));
+        }
+    }
+}
+
+/// The state of the simulation as JSON, for `GET /state`.
+pub fn state_json(physics: &Physics, camera: &Camera, paused: bool) -> String {
+    let mut json = String::new();
+    write!(
+        json,
+        "{{\"tick\":{},\"time\":{},\"delta_time\":{},\"paused\":{paused}",
+        physics.ticks(),
+        number(physics.simulated_time().as_secs_f64()),
+        number(physics.delta_time().as_secs_f64()),
+    )
+    .unwrap();
+    write!(
+        json,
+        ",\"camera\":{{\"mode\":{},\"position\":{},\"forward\":{}}}",
+        string(camera.controller_name()),
+        vector(camera.position().into()),
+        vector(camera.forward().into()),
+    )
+    .unwrap();
+    json.push_str(",\"bodies\":[");
+    for (i, body) in physics.bodies().iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(
+            json,
+            "{{\"position\":{},\"velocity\":{},\"radius\":{},\"mass\":{},\"color\":{}}}",
+            vector(body.pos.into()),
+            vector(body.vel.into()),
+            number(body.radius),
+            number(body.mass()),
+            body.color,
+        )
+        .unwrap();
+    }
+    json.push_str("]}");
+    json
+}
+
+/// Named numbers as a JSON object, for `GET /stats`.
+pub fn stats_json(fields: &[(&str, f64)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|&(name, value)| format!("{}:{}", string(name), number(value)))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn listen(listener: &TcpListener, requests: &Sender<(Request, Sender<Reply>)>) {
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("Failed to accept a remote control connection: {err}");
+                continue;
+            }
+        };
+        let (status, body) = respond(&stream, requests);
+        let written = write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len(),
+        );
+        if let Err(err) = written {
+            log::warn!("Failed to answer a remote control request: {err}");
+        }
+    }
+}
+
+/// The status and JSON body answering a request.
+fn respond(
+    stream: &TcpStream,
+    requests: &Sender<(Request, Sender<Reply>)>,
+) -> (&'static str, String) {
+    let error = |status, message: &str| (status, format!("{{\"error\":{}}}", string(message)));
+    let (method, path, is_json, body) = match read_request(stream) {
+        Ok(request) => request,
+        Err(err) => return error("400 Bad Request", &err.to_string()),
+    };
+    let request = match (method.as_str(), path.as_str()) {
+        ("POST", "/command") if !is_json => {
+            return error(
+                "415 Unsupported Media Type",
+                "Expected Content-Type: application/json",
+            );
+        }
+        ("POST", "/command") => match json::parse(&body)
+            .as_ref()
+            .and_then(|json| json.get("command")?.as_str())
+        {
+            Some(line) => Request::Command(line.to_owned()),
+            None => {
+                return error(
+                    "400 Bad Request",
+                    "Expected a body such as {\"command\": \"spawn 10\"}",
+                );
+            }
+        },
+        ("GET", "/state") => Request::State,
+        ("GET", "/stats") => Request::Stats,
+        (_, "/command" | "/state" | "/stats") => {
+            return error(
+                "405 Method Not Allowed",
+                "Expected POST /command, GET /state or GET /stats",
+            );
+        }
+        _ => return error("404 Not Found", "Expected /command, /state or /stats"),
+    };
+    let is_command = matches!(request, Request::Command(_));
+    let (sender, receiver) = mpsc::channel();
+    if requests.send((request, sender)).is_err() {
+        return error("503 Service Unavailable", "The simulation has stopped");
+    }
+    match receiver.recv_timeout(TIMEOUT) {
+        Ok(Ok(output)) if is_command => ("200 OK", format!("{{\"output\":{}}}", string(&output))),
+        Ok(Ok(json)) => ("200 OK", json),
+        Ok(Err(err)) => error("400 Bad Request", &err),
+        Err(_) => error(
+            "503 Service Unavailable",
+            "The simulation did not answer in time",
+        ),
+    }
+}
+
+/// The method, the path without any query, whether the body is JSON, and the body.
+fn read_request(stream: &TcpStream) -> io::Result<(String, String, bool, String)> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return Err(invalid("Expected a request line"));
+    };
+    let (method, path) = (
+        method.to_owned(),
+        target.split('?').next().unwrap().to_owned(),
+    );
+    let mut length = 0;
+    let mut is_json = false;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("Expected the headers to end"));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            break;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value
+                .parse()
+                .map_err(|_| invalid("Expected a number for Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("content-type") {
+            is_json = value.split(';').next().unwrap().trim() == "application/json";
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("Expected the body in UTF-8"))?;
+    Ok((method, path, is_json, body))
+}
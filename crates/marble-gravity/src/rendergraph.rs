@@ -0,0 +1,671 @@
+use std::collections::HashMap;
+
+/// The format of the depth buffer shared by all passes with a `depth` test
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// A resource read by a pass. Bindings are numbered in declaration order within bind group 0.
+#[derive(Clone, Copy, Debug)]
+pub enum Input {
+    Uniform(&'static str),
+    Texture(&'static str),
+    Sampler(&'static str),
+    /// A transient target written by an earlier pass.
+    Target(&'static str),
+}
+
+/// Where a pass writes one of its color outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Surface,
+    /// A transient window-sized texture, see [`RenderGraph::add_target`].
+    Texture(&'static str),
+}
+
+/// The part of the targets an execution of the graph draws to, in pixels. Passes still see the
+/// whole target as their viewport, so shaders place the view within it themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub offset: (u32, u32),
+    pub size: (u32, u32),
+}
+
+/// A named buffer bound as a vertex buffer, with slots numbered in declaration order.
+pub struct VertexBuffer {
+    pub buffer: &'static str,
+    pub step_mode: wgpu::VertexStepMode,
+    pub stride: u64,
+    pub attributes: &'static [wgpu::VertexAttribute],
+}
+
+/// A single draw call. Shader stages are given as `(module name, entry point)` so one module may
+/// serve several passes or stages. Full-screen passes have no vertex buffers and generate their
+/// vertices from the vertex index.
+pub struct PassDescriptor {
+    pub name: &'static str,
+    pub vertex: (&'static str, &'static str),
+    pub fragment: (&'static str, &'static str),
+    pub inputs: Vec<Input>,
+    pub vertex_buffers: Vec<VertexBuffer>,
+    /// Color outputs in shader location order
+    pub targets: Vec<Target>,
+    pub topology: wgpu::PrimitiveTopology,
+    /// How to test against the shared depth buffer, which is written by every pass using it.
+    /// Passes computing exact depth themselves may use `Always`.
+    pub depth: Option<wgpu::CompareFunction>,
+    pub vertex_count: u32,
+    pub instance_count: u32,
+}
+
+struct Pass {
+    descriptor: PassDescriptor,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    enabled: bool,
+    /// Built by [`RenderGraph::resize`], since it captures transient texture views.
+    bundle: Option<wgpu::RenderBundle>,
+}
+
+/// Transient targets share a physical texture whenever their formats match and the passes using
+/// them do not overlap.
+struct Transient {
+    format: wgpu::TextureFormat,
+    /// Exported targets are never aliased, see [`RenderGraph::export_target`].
+    exported: bool,
+    physical: Option<usize>,
+}
+
+/// Owns the named GPU resources shared between passes, and executes passes in the order they
+/// were added.
+pub struct RenderGraph {
+    surface_format: wgpu::TextureFormat,
+    modules: HashMap<&'static str, wgpu::ShaderModule>,
+    buffers: HashMap<&'static str, wgpu::Buffer>,
+    textures: HashMap<&'static str, (wgpu::TextureView, wgpu::TextureViewDimension)>,
+    samplers: HashMap<&'static str, wgpu::Sampler>,
+    transients: HashMap<&'static str, Transient>,
+    physical_textures: Vec<(wgpu::Texture, wgpu::TextureView)>,
+    depth_texture: Option<wgpu::TextureView>,
+    passes: Vec<Pass>,
+    /// Scratch space of [`RenderGraph::execute`], kept to not allocate every frame
+    cleared: Vec<Target>,
+    color_attachments: Vec<Option<wgpu::RenderPassColorAttachment<'static>>>,
+}
+impl RenderGraph {
+    pub fn new(surface_format: wgpu::TextureFormat) -> Self {
+        Self {
+            surface_format,
+            modules: HashMap::new(),
+            buffers: HashMap::new(),
+            textures: HashMap::new(),
+            samplers: HashMap::new(),
+            transients: HashMap::new(),
+            physical_textures: Vec::new(),
+            depth_texture: None,
+            passes: Vec::new(),
+            cleared: Vec::new(),
+            color_attachments: Vec::new(),
+        }
+    }
+    pub fn add_module(&mut self, name: &'static str, module: wgpu::ShaderModule) {
+        assert!(self.modules.insert(name, module).is_none(), "{name}");
+    }
+    pub fn add_buffer(&mut self, name: &'static str, buffer: wgpu::Buffer) {
+        assert!(self.buffers.insert(name, buffer).is_none(), "{name}");
+    }
+    pub fn add_texture(
+        &mut self,
+        name: &'static str,
+        view: wgpu::TextureView,
+        dimension: wgpu::TextureViewDimension,
+    ) {
+        assert!(
+            self.textures.insert(name, (view, dimension)).is_none(),
+            "{name}"
+        );
+    }
+    pub fn add_sampler(&mut self, name: &'static str, sampler: wgpu::Sampler) {
+        assert!(self.samplers.insert(name, sampler).is_none(), "{name}");
+    }
+    /// Declares a window-sized texture that passes may render to and later passes may read. It is
+    /// allocated (and reallocated) by [`RenderGraph::resize`].
+    pub fn add_target(&mut self, name: &'static str, format: wgpu::TextureFormat) {
+        let transient = Transient {
+            format,
+            exported: false,
+            physical: None,
+        };
+        assert!(self.transients.insert(name, transient).is_none(), "{name}");
+    }
+    /// Keeps a target intact after [`RenderGraph::execute`] by never aliasing it, so that its
+    /// contents can be copied out of [`RenderGraph::target_texture`].
+    pub fn export_target(&mut self, name: &'static str) {
+        self.transients.get_mut(name).unwrap().exported = true;
+    }
+    pub fn target_texture(&self, name: &str) -> &wgpu::Texture {
+        &self.physical_textures[self.physical_index(name)].0
+    }
+    pub fn buffer(&self, name: &str) -> &wgpu::Buffer {
+        &self.buffers[name]
+    }
+    /// Disabled passes are skipped by [`RenderGraph::execute`], which lets one graph hold
+    /// alternative ways of drawing the same frame.
+    pub fn set_enabled(&mut self, pass_name: &str, enabled: bool) {
+        let pass = self
+            .passes
+            .iter_mut()
+            .find(|pass| pass.descriptor.name == pass_name)
+            .unwrap_or_else(|| panic!("no pass named {pass_name}"));
+        pass.enabled = enabled;
+    }
+
+    pub fn add_pass(&mut self, device: &wgpu::Device, descriptor: PassDescriptor) {
+        for target in &descriptor.targets {
+            if let Target::Texture(name) = target {
+                assert!(self.transients.contains_key(name), "{name}");
+            }
+        }
+        for vertex_buffer in &descriptor.vertex_buffers {
+            assert!(
+                self.buffers.contains_key(vertex_buffer.buffer),
+                "{}",
+                vertex_buffer.buffer
+            );
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(descriptor.name),
+            entries: &descriptor
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(binding, input)| wgpu::BindGroupLayoutEntry {
+                    binding: binding as u32,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: self.binding_type(input),
+                    count: None, // Only applicable to sampled textures
+                })
+                .collect::<Vec<_>>(),
+        });
+        let pipeline = self.make_pipeline(device, &descriptor, &bind_group_layout);
+        self.passes.push(Pass {
+            descriptor,
+            bind_group_layout,
+            pipeline,
+            enabled: true,
+            bundle: None,
+        });
+    }
+
+    /// (Re)allocates all transient targets at the given size and rebuilds the passes' bind
+    /// groups. Must be called after the last pass is added, and on every window resize.
+    pub fn resize(&mut self, device: &wgpu::Device, (width, height): (u32, u32)) {
+        self.alias_transients();
+        self.physical_textures = (0..self.physical_count())
+            .map(|physical| {
+                let format = self
+                    .transients
+                    .values()
+                    .find(|t| t.physical == Some(physical))
+                    .unwrap()
+                    .format;
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("transient target"),
+                    size: wgpu::Extent3d {
+                        width: width.max(1),
+                        height: height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC,
+                });
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                (texture, view)
+            })
+            .collect();
+        self.depth_texture = self
+            .passes
+            .iter()
+            .any(|pass| pass.descriptor.depth.is_some())
+            .then(|| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("depth buffer"),
+                        size: wgpu::Extent3d {
+                            width: width.max(1),
+                            height: height.max(1),
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: DEPTH_FORMAT,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            });
+
+        let bundles: Vec<wgpu::RenderBundle> = self
+            .passes
+            .iter()
+            .map(|pass| self.make_bundle(device, pass))
+            .collect();
+        for (pass, bundle) in self.passes.iter_mut().zip(bundles) {
+            pass.bundle = Some(bundle);
+        }
+    }
+
+    /// Records all enabled passes, drawing only within `viewport`. With `clear`, the first pass
+    /// writing to a target (or to the depth buffer) clears all of it, so later executions for
+    /// other viewports must not clear.
+    pub fn execute(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+        viewport: Viewport,
+        clear: bool,
+    ) {
+        let mut cleared = std::mem::take(&mut self.cleared);
+        cleared.clear();
+        let mut color_attachments = recycle(std::mem::take(&mut self.color_attachments));
+        let mut depth_cleared = !clear;
+        for pass in self.passes.iter().filter(|pass| pass.enabled) {
+            color_attachments.clear();
+            color_attachments.extend(pass.descriptor.targets.iter().map(|&target| {
+                let load = if !clear || cleared.contains(&target) {
+                    wgpu::LoadOp::Load
+                } else {
+                    cleared.push(target);
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                };
+                let view = match target {
+                    Target::Surface => surface_view,
+                    Target::Texture(name) => self.transient_view(name),
+                };
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load, store: true },
+                })
+            }));
+            let depth_stencil_attachment = pass.descriptor.depth.map(|_| {
+                let load = if depth_cleared {
+                    wgpu::LoadOp::Load
+                } else {
+                    depth_cleared = true;
+                    wgpu::LoadOp::Clear(1.0)
+                };
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: self.depth_texture.as_ref().unwrap(),
+                    depth_ops: Some(wgpu::Operations { load, store: true }),
+                    stencil_ops: None,
+                }
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pass.descriptor.name),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment,
+            });
+            let Viewport {
+                offset: (x, y),
+                size: (width, height),
+            } = viewport;
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.execute_bundles(pass.bundle.iter());
+        }
+        self.color_attachments = recycle(color_attachments);
+        self.cleared = cleared;
+    }
+
+    /// Greedily assigns each transient the first physical texture of the same format that is
+    /// free during the span of passes using it.
+    fn alias_transients(&mut self) {
+        let lifetimes = lifetimes(self.passes.iter().map(|pass| &pass.descriptor));
+        alias(&mut self.transients, lifetimes);
+    }
+    fn physical_count(&self) -> usize {
+        self.transients
+            .values()
+            .filter_map(|t| t.physical)
+            .max()
+            .map_or(0, |max| max + 1)
+    }
+    fn physical_index(&self, name: &str) -> usize {
+        self.transients[name]
+            .physical
+            .unwrap_or_else(|| panic!("transient target {name} is never used"))
+    }
+    fn transient_view(&self, name: &str) -> &wgpu::TextureView {
+        &self.physical_textures[self.physical_index(name)].1
+    }
+    fn make_bundle(&self, device: &wgpu::Device, pass: &Pass) -> wgpu::RenderBundle {
+        let descriptor = &pass.descriptor;
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(descriptor.name),
+            layout: &pass.bind_group_layout,
+            entries: &descriptor
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(binding, input)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: self.binding_resource(input),
+                })
+                .collect::<Vec<_>>(),
+        });
+        let mut bundle_encoder =
+            device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some(descriptor.name),
+                color_formats: &descriptor
+                    .targets
+                    .iter()
+                    .map(|&target| Some(self.target_format(target)))
+                    .collect::<Vec<_>>(),
+                depth_stencil: descriptor.depth.map(|_| wgpu::RenderBundleDepthStencil {
+                    format: DEPTH_FORMAT,
+                    depth_read_only: false,
+                    stencil_read_only: true,
+                }),
+                sample_count: 1,
+                multiview: None,
+            });
+        bundle_encoder.set_pipeline(&pass.pipeline);
+        bundle_encoder.set_bind_group(0, &bind_group, &[]);
+        for (slot, vertex_buffer) in descriptor.vertex_buffers.iter().enumerate() {
+            bundle_encoder
+                .set_vertex_buffer(slot as u32, self.buffers[vertex_buffer.buffer].slice(..));
+        }
+        bundle_encoder.draw(0..descriptor.vertex_count, 0..descriptor.instance_count);
+        bundle_encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some(descriptor.name),
+        })
+    }
+
+    fn target_format(&self, target: Target) -> wgpu::TextureFormat {
+        match target {
+            Target::Surface => self.surface_format,
+            Target::Texture(name) => self.transients[name].format,
+        }
+    }
+    fn binding_type(&self, input: &Input) -> wgpu::BindingType {
+        match *input {
+            Input::Uniform(_) => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            Input::Texture(name) => wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: self.textures[name].1,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            Input::Sampler(_) => wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            Input::Target(_) => wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+        }
+    }
+    fn binding_resource(&self, input: &Input) -> wgpu::BindingResource<'_> {
+        match *input {
+            Input::Uniform(name) => self.buffers[name].as_entire_binding(),
+            Input::Texture(name) => wgpu::BindingResource::TextureView(&self.textures[name].0),
+            Input::Sampler(name) => wgpu::BindingResource::Sampler(&self.samplers[name]),
+            Input::Target(name) => wgpu::BindingResource::TextureView(self.transient_view(name)),
+        }
+    }
+    fn make_pipeline(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &PassDescriptor,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        // All inputs of a pass reside in the same bind group (since nothing is ever swapped out).
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(descriptor.name),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let (vertex_module, vertex_entry_point) = descriptor.vertex;
+        let (fragment_module, fragment_entry_point) = descriptor.fragment;
+        let vertex_buffers: Vec<wgpu::VertexBufferLayout<'_>> = descriptor
+            .vertex_buffers
+            .iter()
+            .map(|vertex_buffer| wgpu::VertexBufferLayout {
+                array_stride: vertex_buffer.stride,
+                step_mode: vertex_buffer.step_mode,
+                attributes: vertex_buffer.attributes,
+            })
+            .collect();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(descriptor.name),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.modules[vertex_module],
+                entry_point: vertex_entry_point,
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.modules[fragment_module],
+                entry_point: fragment_entry_point,
+                targets: &descriptor
+                    .targets
+                    .iter()
+                    .map(|&target| {
+                        Some(wgpu::ColorTargetState {
+                            format: self.target_format(target),
+                            // Replace without blending, which integer targets do not support
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: descriptor.topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: descriptor
+                .depth
+                .map(|depth_compare| wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+}
+
+/// The first and last index of the passes using each transient target, in order of first use.
+fn lifetimes<'a>(
+    descriptors: impl Iterator<Item = &'a PassDescriptor>,
+) -> Vec<(&'static str, usize, usize)> {
+    let mut lifetimes: Vec<(&'static str, usize, usize)> = Vec::new();
+    for (index, descriptor) in descriptors.enumerate() {
+        let outputs = descriptor.targets.iter().filter_map(|target| match target {
+            Target::Texture(name) => Some(*name),
+            Target::Surface => None,
+        });
+        let inputs = descriptor.inputs.iter().filter_map(|input| match input {
+            Input::Target(name) => Some(*name),
+            _ => None,
+        });
+        for name in outputs.chain(inputs) {
+            match lifetimes.iter_mut().find(|(n, _, _)| *n == name) {
+                Some((_, _, last)) => *last = index,
+                None => lifetimes.push((name, index, index)),
+            }
+        }
+    }
+    lifetimes
+}
+
+/// Assigns physical textures to transients by their lifetimes, see
+/// [`RenderGraph::alias_transients`]. Transients never used get none.
+fn alias(
+    transients: &mut HashMap<&'static str, Transient>,
+    lifetimes: Vec<(&'static str, usize, usize)>,
+) {
+    // (format, last use) per physical texture
+    let mut physical: Vec<(wgpu::TextureFormat, usize)> = Vec::new();
+    for transient in transients.values_mut() {
+        transient.physical = None;
+    }
+    for (name, first, last) in lifetimes {
+        let transient = transients.get_mut(name).unwrap();
+        let last = if transient.exported { usize::MAX } else { last };
+        let slot = physical
+            .iter()
+            .position(|&(format, busy_until)| format == transient.format && busy_until < first);
+        transient.physical = Some(match slot {
+            Some(slot) => {
+                physical[slot].1 = last;
+                slot
+            }
+            None => {
+                physical.push((transient.format, last));
+                physical.len() - 1
+            }
+        });
+    }
+}
+
+/// Empties a vector to reuse its allocation for another element type of the same size and
+/// alignment, such as the same type borrowing for another lifetime. Collecting from a vector's
+/// own iterator reuses its buffer in place when the layouts match.
+pub fn recycle<T, U>(mut vec: Vec<T>) -> Vec<U> {
+    vec.clear();
+    vec.into_iter().map(|_| unreachable!()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocations;
+    use wgpu::TextureFormat::{R32Uint, Rgba16Float};
+
+    /// A full-screen pass reading and writing the given transient targets
+    fn pass(inputs: &[&'static str], targets: &[&'static str]) -> PassDescriptor {
+        PassDescriptor {
+            name: "pass",
+            vertex: ("module", "vs_main"),
+            fragment: ("module", "fs_main"),
+            inputs: inputs.iter().map(|&name| Input::Target(name)).collect(),
+            vertex_buffers: Vec::new(),
+            targets: targets.iter().map(|&name| Target::Texture(name)).collect(),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: None,
+            vertex_count: 3,
+            instance_count: 1,
+        }
+    }
+
+    fn transients(
+        formats: &[(&'static str, wgpu::TextureFormat, bool)],
+    ) -> HashMap<&'static str, Transient> {
+        formats
+            .iter()
+            .map(|&(name, format, exported)| {
+                let transient = Transient {
+                    format,
+                    exported,
+                    physical: None,
+                };
+                (name, transient)
+            })
+            .collect()
+    }
+
+    fn physical(transients: &HashMap<&'static str, Transient>, name: &str) -> Option<usize> {
+        transients[name].physical
+    }
+
+    #[test]
+    fn lifetimes_span_from_first_to_last_use() {
+        let mut last = pass(&["color", "ids"], &[]);
+        last.inputs.push(Input::Uniform("uniforms"));
+        last.targets.push(Target::Surface);
+        let passes = [
+            pass(&[], &["color", "ids"]),
+            pass(&["color"], &["bloom"]),
+            pass(&[], &[]),
+            last,
+        ];
+        assert_eq!(
+            lifetimes(passes.iter()),
+            [("color", 0, 3), ("ids", 0, 3), ("bloom", 1, 1)]
+        );
+    }
+
+    #[test]
+    fn targets_alias_when_formats_match_and_uses_do_not_overlap() {
+        let passes = [
+            pass(&[], &["a"]),
+            pass(&["a"], &["b"]),
+            pass(&["b"], &["c", "ids"]),
+            pass(&["c", "ids"], &[]),
+        ];
+        let mut transients = transients(&[
+            ("a", Rgba16Float, false),
+            ("b", Rgba16Float, false),
+            ("c", Rgba16Float, false),
+            ("ids", R32Uint, false),
+            ("unused", Rgba16Float, false),
+        ]);
+        alias(&mut transients, lifetimes(passes.iter()));
+        // `b` is written while `a` is read, but `a` is free again by the time `c` is written
+        assert_eq!(physical(&transients, "a"), Some(0));
+        assert_eq!(physical(&transients, "b"), Some(1));
+        assert_eq!(physical(&transients, "c"), Some(0));
+        // Free as the texture of `b` is, its format differs
+        assert_eq!(physical(&transients, "ids"), Some(2));
+        assert_eq!(physical(&transients, "unused"), None);
+    }
+
+    #[test]
+    fn exported_targets_are_never_aliased() {
+        let passes = [
+            pass(&[], &["ids"]),
+            pass(&["ids"], &[]),
+            pass(&[], &["later"]),
+        ];
+        let mut transients =
+            transients(&[("ids", Rgba16Float, true), ("later", Rgba16Float, false)]);
+        alias(&mut transients, lifetimes(passes.iter()));
+        assert_eq!(physical(&transients, "ids"), Some(0));
+        assert_eq!(physical(&transients, "later"), Some(1));
+    }
+
+    #[test]
+    fn recycling_keeps_the_allocation() {
+        let text = String::from("label");
+        let mut scratch: Vec<&'static str> = Vec::with_capacity(4);
+        let allocations = allocations::count(|| {
+            for _ in 0..3 {
+                let mut borrowed: Vec<&str> = recycle(std::mem::take(&mut scratch));
+                borrowed.extend([text.as_str(); 4]);
+                scratch = recycle(borrowed);
+            }
+        });
+        assert_eq!(allocations, 0);
+        assert_eq!(scratch.capacity(), 4);
+    }
+}
@@ -0,0 +1,378 @@
+//! `--record <file>` saves a run such that `--replay <file>` reproduces it exactly, for debugging
+//! and for capturing videos. Everything is recorded by the physics tick it happened at: the seed,
+//! the state of the simulation after each edit, the pose of the camera each frame it moved, the
+//! state hashes of checkpoints and the positions of the bodies every [`POSITIONS_INTERVAL`] of
+//! simulated time, which `--ghost <file>` shows, see [`crate::ghost`]. Edits are recorded as the
+//! states they lead to rather than as the inputs leading there, so that loaded scenarios, games and
+//! the tutorial replay just like commands do, and the commands behind them are kept as comments.
+//!
+//! Physics is deterministic between edits, so playback steps it through the same ticks in real
+//! time, swaps in each edited state at the tick it was made at, and reports the first checkpoint
+//! whose state hash differs from the recording. Not supported on the web.
+
+use crate::events::{AppEvent, Subscriber};
+use camera::{Camera, Pose};
+use cgmath::{Quaternion, Vector3};
+use instant::Instant;
+use physics::{Checkpoint, Physics, PhysicsResult, ToF32, PHYSICS_MAX_BEHIND_TIME};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write as _},
+    fs::File,
+    io::{BufWriter, Write as _},
+    time::Duration,
+};
+
+/// Simulated time between recorded positions of the bodies
+pub const POSITIONS_INTERVAL: Duration = Duration::from_millis(50);
+
+pub enum Replay {
+    Off,
+    Recording(Recorder),
+    Playing(Playback),
+}
+impl Replay {
+    /// Records the seed and initial state, or takes the recorded seed.
+    pub fn start(&mut self, seed: &mut u64, physics: &Physics) {
+        match self {
+            Self::Off => {}
+            Self::Recording(recorder) => recorder.start(*seed, physics),
+            Self::Playing(playback) => *seed = playback.seed,
+        }
+    }
+    /// Must be called before anything edits the simulation in a frame.
+    pub fn begin_edits(&mut self, physics: &Physics) {
+        if let Self::Recording(recorder) = self {
+            recorder.unedited = physics.encode();
+        }
+    }
+    /// Records the state if it was edited since [`Replay::begin_edits`].
+    pub fn end_edits(&mut self, physics: &Physics) {
+        if let Self::Recording(recorder) = self {
+            if physics.encode() != recorder.unedited {
+                recorder.state(physics);
+            }
+        }
+    }
+    /// Steps the simulation while playing back, see [`Playback::advance`], and otherwise leaves
+    /// it to run in real time.
+    pub fn advance(
+        &mut self,
+        physics: &mut Box<Physics>,
+        elapsed: Duration,
+    ) -> Option<PhysicsResult> {
+        match self {
+            Self::Playing(playback) if !playback.finished => {
+                Some(playback.advance(physics, elapsed))
+            }
+            _ => None,
+        }
+    }
+    /// Records the pose of the camera, or moves it to the recorded pose.
+    pub fn camera(&mut self, tick: u64, camera: &mut Camera) {
+        match self {
+            Self::Off => {}
+            Self::Recording(recorder) => recorder.camera(tick, camera.pose()),
+            Self::Playing(playback) => {
+                if let Some(pose) = playback.pose(tick) {
+                    camera.set_pose(pose);
+                }
+            }
+        }
+    }
+    /// Records the positions of the bodies, if [`POSITIONS_INTERVAL`] has passed since they
+    /// were last recorded.
+    pub fn positions(&mut self, physics: &Physics) {
+        if let Self::Recording(recorder) = self {
+            recorder.positions(physics);
+        }
+    }
+    pub fn finish(&mut self) {
+        if let Self::Recording(recorder) = self {
+            recorder.finish();
+        }
+    }
+}
+impl Subscriber for Replay {
+    fn notify(&mut self, event: &AppEvent) {
+        let Self::Recording(recorder) = self else {
+            return;
+        };
+        match event {
+            AppEvent::TicksCompleted(PhysicsResult {
+                checkpoint: Some(Checkpoint { tick, hash }),
+                ..
+            }) => recorder.line(format_args!("checkpoint {tick} {hash:x}")),
+            AppEvent::CommandExecuted(command) => recorder.line(format_args!("# {command:?}")),
+            _ => {}
+        }
+    }
+}
+
+/// The recording or playback requested on the command line, if any.
+pub fn requested() -> Replay {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (flag, path) = match arg.split_once('=') {
+            Some((flag, path)) => (flag.to_owned(), Some(path.to_owned())),
+            None => (arg, None),
+        };
+        let replay = match flag.as_str() {
+            "--record" => Recorder::create,
+            "--replay" => Playback::load,
+            _ => continue,
+        };
+        let Some(path) = path.or_else(|| args.next()) else {
+            log::error!("Expected a file after {flag}");
+            return Replay::Off;
+        };
+        return match replay(&path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                log::error!("Failed to open {path} for {flag}: {err}");
+                Replay::Off
+            }
+        };
+    }
+    Replay::Off
+}
+
+pub struct Recorder {
+    path: String,
+    out: Option<BufWriter<File>>,
+    /// The encoded state before the edits of the current frame, see [`Replay::begin_edits`]
+    unedited: Vec<u64>,
+    last_pose: Option<Pose>,
+    /// The simulated time the positions of the bodies were last recorded at
+    last_positions: Option<Duration>,
+}
+impl Recorder {
+    fn create(path: &str) -> Result<Replay, String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        log::info!("Recording the run to {path}");
+        Ok(Replay::Recording(Self {
+            path: path.to_owned(),
+            out: Some(BufWriter::new(file)),
+            unedited: Vec::new(),
+            last_pose: None,
+            last_positions: None,
+        }))
+    }
+    fn start(&mut self, seed: u64, physics: &Physics) {
+        self.line(format_args!("seed {seed}"));
+        self.state(physics);
+    }
+    /// Writes a line, or stops recording once writing fails.
+    fn line(&mut self, line: fmt::Arguments<'_>) {
+        let Some(out) = &mut self.out else {
+            return;
+        };
+        if let Err(err) = writeln!(out, "{line}") {
+            log::error!("Stopped recording to {}: {err}", self.path);
+            self.out = None;
+        }
+    }
+    fn state(&mut self, physics: &Physics) {
+        let mut words = String::new();
+        for word in physics.encode() {
+            write!(words, " {word:x}").unwrap();
+        }
+        self.line(format_args!("state {}{words}", physics.ticks()));
+    }
+    fn camera(&mut self, tick: u64, pose: Pose) {
+        if self.last_pose.replace(pose) == Some(pose) {
+            return;
+        }
+        let Pose {
+            position: p,
+            rotation: r,
+        } = pose;
+        self.line(format_args!(
+            "camera {tick} {} {} {} {} {} {} {}",
+            p.x, p.y, p.z, r.s, r.v.x, r.v.y, r.v.z
+        ));
+    }
+    fn positions(&mut self, physics: &Physics) {
+        let now = physics.simulated_time();
+        let due = self
+            .last_positions
+            .map_or(true, |last| now < last || now - last >= POSITIONS_INTERVAL);
+        if !due {
+            return;
+        }
+        self.last_positions = Some(now);
+        let mut numbers = String::new();
+        for body in physics.bodies().iter() {
+            let pos = body.pos.to_f32();
+            write!(numbers, " {} {} {}", pos.x, pos.y, pos.z).unwrap();
+        }
+        self.line(format_args!("positions {}{numbers}", physics.ticks()));
+    }
+    fn finish(&mut self) {
+        let Some(mut out) = self.out.take() else {
+            return;
+        };
+        match out.flush() {
+            Ok(()) => log::info!("Recorded the run to {}", self.path),
+            Err(err) => log::error!("Failed to finish recording to {}: {err}", self.path),
+        }
+    }
+}
+
+pub struct Playback {
+    seed: u64,
+    /// Encoded states by the tick they are swapped in at
+    states: VecDeque<(u64, Vec<u64>)>,
+    poses: VecDeque<(u64, Pose)>,
+    checkpoints: HashMap<u64, u64>,
+    /// The simulated time owed to real time
+    due: Duration,
+    /// The first checkpoint differing from the recording, once reached
+    diverged: Option<u64>,
+    /// The last tick anything was recorded at
+    end: u64,
+    finished: bool,
+}
+impl Playback {
+    fn load(path: &str) -> Result<Replay, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut playback = Self {
+            seed: 0,
+            states: VecDeque::new(),
+            poses: VecDeque::new(),
+            checkpoints: HashMap::new(),
+            due: Duration::ZERO,
+            diverged: None,
+            end: 0,
+            finished: false,
+        };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            playback
+                .parse_line(line)
+                .ok_or_else(|| format!("invalid line {}", number + 1))?;
+        }
+        if playback.states.is_empty() {
+            return Err("no states recorded".to_owned());
+        }
+        log::info!("Replaying {path}");
+        Ok(Replay::Playing(playback))
+    }
+    fn parse_line(&mut self, line: &str) -> Option<()> {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next()?;
+        if kind == "seed" {
+            self.seed = fields.next()?.parse().ok()?;
+            return Some(());
+        }
+        let tick: u64 = fields.next()?.parse().ok()?;
+        self.end = self.end.max(tick);
+        match kind {
+            "state" => {
+                let words = fields
+                    .map(|word| u64::from_str_radix(word, 16).ok())
+                    .collect::<Option<_>>()?;
+                self.states.push_back((tick, words));
+            }
+            "camera" => {
+                let numbers: Vec<f32> = fields.map(|n| n.parse().ok()).collect::<Option<_>>()?;
+                let &[x, y, z, s, i, j, k] = numbers.as_slice() else {
+                    return None;
+                };
+                let pose = Pose {
+                    position: Vector3::new(x, y, z),
+                    rotation: Quaternion::new(s, i, j, k),
+                };
+                self.poses.push_back((tick, pose));
+            }
+            "checkpoint" => {
+                let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+                self.checkpoints.insert(tick, hash);
+            }
+            // Only for ghosts, since playback simulates the bodies again
+            "positions" => {}
+            _ => return None,
+        }
+        Some(())
+    }
+    /// Steps the simulation by `elapsed` real time, stopping at the ticks of recorded states to
+    /// swap them in. Playback slows down rather than skipping ticks when it falls behind.
+    fn advance(&mut self, physics: &mut Box<Physics>, elapsed: Duration) -> PhysicsResult {
+        let before = Instant::now();
+        let mut result = PhysicsResult {
+            elapsed_real: Duration::ZERO,
+            elapsed_physics_ticks: 0,
+            checkpoint: None,
+        };
+        self.due = (self.due + elapsed).min(PHYSICS_MAX_BEHIND_TIME);
+        loop {
+            while let Some((tick, words)) = self.states.front() {
+                if *tick > physics.ticks() {
+                    break;
+                }
+                if *tick < physics.ticks() {
+                    log::warn!("Skipping the state recorded at tick {tick}, which has passed");
+                } else {
+                    match Physics::decode(words, physics.timestamp()) {
+                        Ok(state) => *physics = state,
+                        Err(err) => {
+                            log::error!("Failed to decode the state at tick {tick}: {err:?}");
+                        }
+                    }
+                }
+                self.states.pop_front();
+            }
+            let delta_time = physics.delta_time();
+            let mut ticks = (self.due.as_nanos() / delta_time.as_nanos()) as u64;
+            if let Some(&(tick, _)) = self.states.front() {
+                ticks = ticks.min(tick - physics.ticks());
+            }
+            if ticks == 0 {
+                break;
+            }
+            let stepped = physics.advance_ticks(ticks);
+            self.due = self.due.saturating_sub(delta_time * ticks as u32);
+            result.elapsed_physics_ticks += ticks;
+            result.checkpoint = stepped.checkpoint.or(result.checkpoint);
+            if let Some(checkpoint) = stepped.checkpoint {
+                self.check(checkpoint);
+            }
+        }
+        self.finish_if_done(physics.ticks());
+        result.elapsed_real = Instant::now() - before;
+        result
+    }
+    fn check(&mut self, Checkpoint { tick, hash }: Checkpoint) {
+        let Some(&recorded) = self.checkpoints.get(&tick) else {
+            return;
+        };
+        if recorded != hash && self.diverged.is_none() {
+            log::error!(
+                "Replay diverged at tick {tick}: state hash {hash:016x}, recorded {recorded:016x}"
+            );
+            self.diverged = Some(tick);
+        }
+    }
+    fn finish_if_done(&mut self, tick: u64) {
+        if self.finished || tick < self.end {
+            return;
+        }
+        self.finished = true;
+        match self.diverged {
+            Some(tick) => log::warn!("Replay finished, having diverged at tick {tick}"),
+            None => log::info!("Replay finished, matching every recorded checkpoint"),
+        }
+    }
+    /// The latest camera pose recorded at or before `tick`, if a new one was reached.
+    fn pose(&mut self, tick: u64) -> Option<Pose> {
+        let mut pose = None;
+        while let Some(&(_, next)) = self.poses.front().filter(|&&(at, _)| at <= tick) {
+            pose = Some(next);
+            self.poses.pop_front();
+        }
+        pose
+    }
+}
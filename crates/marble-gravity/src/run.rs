@@ -1,30 +1,249 @@
-use crate::{camera::Camera, graphics::Graphics, spheretree, PhysicsEvent, PhysicsSystem};
+use crate::{
+    actions::{Action, Gamepad, InputEvent, InputMap, Touches},
+    attitude, boundary,
+    challenge::ChallengeMode,
+    clipboard,
+    command_line::{self, CommandLine},
+    commands::{Command, CommandHistory},
+    comparison::Comparison,
+    compass,
+    conservation::ConservationHud,
+    console::LogConsole,
+    events::{self, AppEvent, EventBus, Frame, Subscriber},
+    gallery::{Gallery, RESCAN_INTERVAL, USER_SCENARIOS},
+    golf::Golf,
+    graphics::{Graphics, View, MAX_RAY_SPLITS},
+    labels::BodyLabels,
+    measure::MeasureTool,
+    media::{self, MediaAction, MediaState},
+    motion::MotionEffects,
+    mouse::MouseLook,
+    photo::{PhotoMode, PHOTO_LOOK_SCALE},
+    picking::Picked,
+    selection::{Selection, SelectionTool},
+    split_screen::SecondPlayer,
+    streamlines::Streamlines,
+    timelapse::TimeLapse,
+    toasts::Toasts,
+    trace,
+    tracers::TracerCloud,
+    tutorial::{Tutorial, STABLE_ORBIT},
+    PhysicsEvent, PhysicsSystem,
+};
+use camera::{Camera, FlightKey, Follow, FreeFly, Orbit, PathPlayback, Pose, WORLD_UP};
+use cgmath::{prelude::*, Vector3};
 use instant::Instant;
+use physics::{
+    Body, Checkpoint, Generator, PhysicsResult, Preset, Scalar, ScenarioCamera, ScenarioSettings,
+    ToF32, CHECKPOINT_INTERVAL, MAX_DELTA_TIME, MIN_DELTA_TIME, PHYSICS_MAX_BEHIND_TIME,
+};
+use spheretree::SphereTreeBuilder;
 use std::{collections::VecDeque, time::Duration};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{
-        DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent,
+        DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, WindowEvent,
     },
     event_loop::{ControlFlow, EventLoop},
     window::{CursorGrabMode, Window},
 };
 
-pub struct Stats {
+struct Stats {
     frame_number: u64,
-    pub tick_number: u64,
+    tick_number: u64,
     instant_start: Instant,
-    pub time_spent_in_physics: Duration,
+    time_spent_in_physics: Duration,
     time_spent_in_graphics: Duration,
     event_loop_times: VecDeque<Duration>,
+    /// How many times bodies have come to touch
+    collisions: u64,
+    /// The latest state hash, to compare runs for determinism
+    checkpoint: Option<Checkpoint>,
+    /// Whether state hashes are logged and shown in the overlay
+    show_checkpoints: bool,
+}
+impl Subscriber for Stats {
+    fn notify(&mut self, event: &AppEvent) {
+        match *event {
+            AppEvent::TicksCompleted(PhysicsResult {
+                elapsed_real,
+                elapsed_physics_ticks,
+                checkpoint,
+            }) => {
+                self.time_spent_in_physics += elapsed_real;
+                self.tick_number += elapsed_physics_ticks;
+                if let Some(Checkpoint { tick, hash }) = checkpoint {
+                    self.checkpoint = checkpoint;
+                    if self.show_checkpoints {
+                        log::info!("Tick {tick}: state hash {hash:016x}");
+                    }
+                }
+            }
+            AppEvent::Collision {
+                bodies: (a, b),
+                speed,
+            } => {
+                self.collisions += 1;
+                log::trace!("Bodies {a} and {b} touched at {speed}");
+            }
+            AppEvent::BodyAdded(index) => log::debug!("Added body {index}"),
+            AppEvent::BodyRemoved(index) => log::debug!("Removed body {index}"),
+            AppEvent::BodiesReplaced => log::debug!("Replaced the bodies"),
+            AppEvent::SettingChanged(name) => log::debug!("Changed {name}"),
+            AppEvent::WindowResized(_)
+            | AppEvent::CommandExecuted(_)
+            | AppEvent::ScenarioLoaded(_) => {}
+            AppEvent::CameraModeChanged(mode) => log::info!("Camera mode: {mode}"),
+        }
+    }
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if self.show_checkpoints {
+            match self.checkpoint {
+                Some(Checkpoint { tick, hash }) => {
+                    graphics.overlay_line(format_args!("Tick {tick}: state hash {hash:016x}"));
+                }
+                None => graphics
+                    .overlay_line(format_args!("State hash every {CHECKPOINT_INTERVAL} ticks")),
+            }
+        }
+    }
+}
+
+/// The camera pose recommended by the latest loaded scenario, until it is applied
+struct ScenarioPose(Option<Pose>);
+impl Subscriber for ScenarioPose {
+    fn notify(&mut self, event: &AppEvent) {
+        if let AppEvent::ScenarioLoaded(ScenarioSettings {
+            camera: Some(ScenarioCamera { position, target }),
+            ..
+        }) = *event
+        {
+            self.0 = Some(Pose::looking_at(position, target));
+        }
+    }
+}
+
+/// What the camera controller is centered on
+#[derive(Clone, Copy)]
+enum CameraTarget {
+    None,
+    CenterOfMass,
+    Body(usize),
 }
 
-pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Graphics) {
+/// What to do with the next body picked on the GPU
+#[derive(Clone, Copy)]
+enum PickPurpose {
+    Remove,
+    Measure,
+}
+
+/// Colors cycled through by recoloring a selection
+const RECOLOR_PALETTE: [u32; 6] = [
+    0xe6_39_46_00,
+    0xf4_a2_61_00,
+    0xe9_c4_6a_00,
+    0x2a_9d_8f_00,
+    0x45_7b_9d_00,
+    0xf1_fa_ee_00,
+];
+/// Velocity added to a selection along the view direction
+const SELECTION_KICK: f32 = 0.3;
+/// The sphere selecting bodies in front of the camera
+const SELECTION_SPHERE_DISTANCE: f32 = 1.0;
+const SELECTION_SPHERE_RADIUS: f32 = 0.5;
+
+/// Steps of the medium density, and factors of its extinction, per key press
+const MEDIUM_DENSITY_STEP: f32 = 0.1;
+const MEDIUM_EXTINCTION_FACTOR: f32 = 1.5;
+/// Factor of the physics tick duration per key press
+const DELTA_TIME_FACTOR: u32 = 2;
+/// Factor of the strength of gravity per key press
+const GRAVITY_FACTOR: f32 = 1.25;
+
+/// How long to keep rendering after the last window event, before a paused and still scene is
+/// left idle, which gives pending picks and the like time to complete
+const IDLE_DELAY: Duration = Duration::from_secs(1);
+
+/// Seconds between recorded camera path keyframes
+const KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+/// How long the camera takes to fly to a pose framing bodies
+const FRAMING_TIME: Duration = Duration::from_millis(800);
+/// The radius of framed bodies is enlarged by this factor, to leave a margin around them
+const FRAMING_MARGIN: f32 = 1.1;
+
+pub fn run(
+    event_loop: EventLoop<PhysicsEvent>,
+    window: Window,
+    mut graphics: Graphics,
+    adapter: wgpu::Adapter,
+) {
     let mut camera = Camera::new();
+    let mut camera_target = CameraTarget::None;
+    let mut camera_keyframes: Vec<(Duration, Pose)> = Vec::new();
+    let mut scenario_pose = ScenarioPose(None);
+    let mut second_player = SecondPlayer::new();
+
+    let mut labels = BodyLabels::new();
+    let mut measure = MeasureTool::new();
+    let mut pick_purpose = PickPurpose::Remove;
+    let mut selection = SelectionTool::new();
+    let mut recolor_count = 0;
+    let mut tutorial = Tutorial::new();
+    let mut challenge = ChallengeMode::new();
+    let mut golf = Golf::new();
+    let mut photo = PhotoMode::new();
+    let mut timelapse = TimeLapse::new();
+    let mut tracers = TracerCloud::new();
+    let mut streamlines = Streamlines::new();
+    let mut gallery = Gallery::new();
+    let mut toasts = Toasts::new();
+    let mut log_console = LogConsole::new();
+    let mut command_line = CommandLine::new();
+    let mut conservation = ConservationHud::new();
+    let mut comparison = Comparison::new();
 
     let mut physics = PhysicsSystem::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut replay = crate::replay::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    replay.start(&mut physics.seed, &physics.physics);
+    #[cfg(not(target_arch = "wasm32"))]
+    let remote = crate::remote::Remote::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut osc = crate::osc::Osc::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut audio = crate::audio::Audio::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut feed = crate::feed::Feed::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut ghost = crate::ghost::Ghost::requested();
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut impacts = crate::impacts::Impacts::requested();
+    // Shown throughout the run, so that clusters worth sharing can be recreated
+    window.set_title(&title(&physics));
+    let mut sphere_tree = SphereTreeBuilder::new();
+    let mut second_sphere_tree = SphereTreeBuilder::new();
+    let mut commands = CommandHistory::new();
     let mut capture_mouse = false;
+    // Windows are created focused, and media keys are heard through device events when not
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut focused = true;
+    let mut mouse_look = MouseLook::new();
+    let mut motion = MotionEffects::new();
+    let input_map = InputMap::new();
+    // Actions not yet carried out, and whether their input was pressed or released
+    let mut actions = VecDeque::new();
+    let mut touches = Touches::new();
+    let mut gamepad = Gamepad::new();
+    let mut cursor = PhysicalPosition::new(0.0, 0.0);
+    let mut modifiers = ModifiersState::empty();
     let mut slow_mode = false;
+    let mut show_help = false;
+    let mut paused = false;
+    let mut step_requested = false;
+    let mut last_window_event = Instant::now();
+    let mut last_poses = None;
 
     const DESIRED_FRAME_MULTIPLE: u32 = if cfg!(target_arch = "wasm32") { 2 } else { 1 };
     let desired_frame_time = match window
@@ -46,22 +265,50 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
         time_spent_in_physics: Duration::ZERO,
         time_spent_in_graphics: Duration::ZERO,
         event_loop_times: VecDeque::new(),
+        collisions: 0,
+        checkpoint: None,
+        show_checkpoints: false,
     };
 
+    let mut bus = EventBus::new();
     let proxy = event_loop.create_proxy();
+    // What the console acts on, borrowed anew wherever lines are run
+    macro_rules! console_context {
+        () => {
+            command_line::Context {
+                commands: &mut commands,
+                graphics: &mut graphics,
+                physics: &physics.physics,
+                speed: &mut physics.speed,
+                camera: &camera,
+                actions: &mut actions,
+                comparison: &mut comparison,
+                bus: &mut bus,
+            }
+        };
+    }
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
+        if let Event::WindowEvent { .. } = event {
+            last_window_event = Instant::now();
+        }
         match event {
             Event::WindowEvent {
                 window_id: _id,
                 event: w_event,
             } => match w_event {
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::DroppedFile(path) => gallery.drop_file(&path, &mut toasts),
                 WindowEvent::Resized(PhysicalSize { width, height })
                 | WindowEvent::ScaleFactorChanged {
                     scale_factor: _,
                     new_inner_size: &mut PhysicalSize { width, height },
-                } => graphics.resize((width, height)),
+                } => {
+                    bus.publish(AppEvent::WindowResized((width, height)));
+                    refresh_surface(&mut graphics, &adapter, &mut gallery);
+                }
+                WindowEvent::Moved(_) => refresh_surface(&mut graphics, &adapter, &mut gallery),
                 WindowEvent::ModifiersChanged(mods) => {
                     if mods.alt() || mods.logo() {
                         stop_capture_mouse(&window);
@@ -70,68 +317,610 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
                         capture_mouse = begin_capture_mouse(&window).is_ok();
                     }
                     slow_mode = mods.ctrl();
+                    modifiers = mods;
                 }
                 WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            state,
+                            virtual_keycode: Some(vk),
+                            state: ElementState::Pressed,
                             ..
                         },
                     ..
-                } => {
-                    if state == ElementState::Pressed {
-                        stop_capture_mouse(&window);
-                        capture_mouse = false;
-                    }
+                } if gallery.is_open() => {
+                    gallery.key_input(vk, Gallery::columns(graphics.window_size()));
                 }
                 WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
-                            virtual_keycode: Some(vk @ (VirtualKeyCode::Up | VirtualKeyCode::Down)),
+                            virtual_keycode: Some(vk),
                             state: ElementState::Pressed,
                             ..
                         },
                     ..
-                } => graphics.change_ray_splits(match vk {
-                    VirtualKeyCode::Up => 1,
-                    VirtualKeyCode::Down => -1,
-                    _ => unreachable!(),
-                }),
+                } if command_line.is_open() => {
+                    if let Some(line) = command_line.key_input(vk) {
+                        command_line.run(&line, &mut console_context!());
+                    }
+                }
+                WindowEvent::ReceivedCharacter(c) => command_line.character(c),
+                WindowEvent::KeyboardInput { input: key, .. } => {
+                    match input_map.key(&key, modifiers) {
+                        Some(action) => {
+                            actions.push_back((action, key.state == ElementState::Pressed));
+                        }
+                        None => {
+                            if !(graphics.split_screen() && second_player.key_input(key, slow_mode))
+                            {
+                                capture_mouse = begin_capture_mouse(&window).is_ok();
+                            }
+                        }
+                    }
+                }
+                WindowEvent::Touch(touch) => {
+                    for event in touches.touch(&touch) {
+                        queue_input(&input_map, event, &mut actions, &mut mouse_look);
+                    }
+                }
+                WindowEvent::CursorMoved { position, .. } => cursor = position,
+                WindowEvent::MouseInput {
+                    button: MouseButton::Right,
+                    state,
+                    ..
+                } => match state {
+                    ElementState::Pressed => golf.begin_charge(),
+                    ElementState::Released => golf.launch(
+                        &mut commands,
+                        physics.physics.bodies(),
+                        camera.position(),
+                        camera.forward(),
+                    ),
+                },
                 WindowEvent::MouseInput {
                     button: MouseButton::Left,
                     state,
                     ..
-                } => {
-                    if state == ElementState::Pressed {
+                } => match state {
+                    // Alt releases the mouse, so that a selection box can be dragged
+                    ElementState::Pressed if modifiers.alt() => {
+                        selection.begin_drag((cursor.x as f32, cursor.y as f32));
+                    }
+                    ElementState::Pressed => {
                         capture_mouse = begin_capture_mouse(&window).is_ok();
                     }
-                }
-                WindowEvent::KeyboardInput { input: key, .. } => {
-                    capture_mouse = begin_capture_mouse(&window).is_ok();
-                    camera.key_input(key, slow_mode);
-                }
+                    ElementState::Released => selection.end_drag(
+                        (cursor.x as f32, cursor.y as f32),
+                        &graphics,
+                        physics.physics.bodies(),
+                        camera.world_to_camera(),
+                    ),
+                },
                 WindowEvent::Focused(false) => {
                     stop_capture_mouse(&window);
                     capture_mouse = false;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        focused = false;
+                    }
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::Focused(true) => focused = true,
                 _ => {}
             },
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::DeviceEvent {
+                device_id: _,
+                event: DeviceEvent::Key(key),
+            } if !focused => media::key(&key),
             Event::DeviceEvent {
                 device_id: _,
                 event: DeviceEvent::MouseMotion { delta: (dx, dy) },
-            } => {
-                if capture_mouse && continue_capture_mouse(&window) {
-                    camera.mouse_input(dx, dy);
-                }
-            }
+            } if capture_mouse && continue_capture_mouse(&window) => mouse_look.motion(dx, dy),
             Event::MainEventsCleared => {
+                let _span = trace::span("event loop");
                 let now = Instant::now();
+                gallery.poll(now, &mut toasts);
+                if let Some(text) = clipboard::take_pasted() {
+                    paste(&text, &mut scenario_pose, &mut commands, &mut toasts);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                let room = physics::BODIES - physics.physics.bodies().len();
+                #[cfg(not(target_arch = "wasm32"))]
+                let lines = osc
+                    .poll()
+                    .into_iter()
+                    .map(|(line, continuous)| ("OSC", line, continuous))
+                    .chain(
+                        audio
+                            .update(now, room)
+                            .into_iter()
+                            .map(|line| ("Audio", line, true)),
+                    );
+                #[cfg(not(target_arch = "wasm32"))]
+                for (source, line, continuous) in lines {
+                    let context = &mut console_context!();
+                    // Settings driven every frame stay out of the undo history
+                    let result = if continuous {
+                        command_line.modulate(&line, context)
+                    } else {
+                        command_line.execute(&line, context)
+                    };
+                    match result {
+                        Ok(output) => log::debug!("{source} {line}: {output}"),
+                        Err(err) => log::warn!("{source} {line}: {err}"),
+                    }
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                remote.serve(|request| match request {
+                    crate::remote::Request::Command(line) => {
+                        log::info!("Remote command: {line}");
+                        command_line.execute(&line, &mut console_context!())
+                    }
+                    crate::remote::Request::State => {
+                        Ok(crate::remote::state_json(&physics.physics, &camera, paused))
+                    }
+                    crate::remote::Request::Stats => {
+                        let event_loop_time = stats.event_loop_times.iter().sum::<Duration>()
+                            / stats.event_loop_times.len().max(1) as u32;
+                        let mut fields = vec![
+                            ("frames", stats.frame_number as f64),
+                            ("ticks", stats.tick_number as f64),
+                            ("collisions", stats.collisions as f64),
+                            ("uptime", (now - stats.instant_start).as_secs_f64()),
+                            ("physics_time", stats.time_spent_in_physics.as_secs_f64()),
+                            ("graphics_time", stats.time_spent_in_graphics.as_secs_f64()),
+                            ("event_loop_time", event_loop_time.as_secs_f64()),
+                        ];
+                        if let Some(divergence) = comparison.divergence(&physics.physics) {
+                            fields.push(("divergence_rms", divergence.rms.to_f32().into()));
+                            fields.push(("divergence_max", divergence.max.to_f32().into()));
+                        }
+                        Ok(crate::remote::stats_json(&fields))
+                    }
+                });
+                for event in gamepad.poll(now) {
+                    queue_input(&input_map, event, &mut actions, &mut mouse_look);
+                }
+                while let Some((action, pressed)) = actions.pop_front() {
+                    match action {
+                        Action::Fly(key) => {
+                            capture_mouse = begin_capture_mouse(&window).is_ok();
+                            camera.flight_input(key, pressed, slow_mode || photo.is_active());
+                        }
+                        _ if !pressed => {}
+                        Action::OpenGallery => gallery.open(),
+                        Action::ToggleLog => log_console.toggle(),
+                        Action::ToggleConsole => command_line.toggle(),
+                        Action::NextLogFilter => log_console.next_filter(),
+                        Action::ToggleCheckpoints => {
+                            stats.show_checkpoints = !stats.show_checkpoints;
+                        }
+                        Action::ToggleHelp => show_help = !show_help,
+                        Action::CaptureFrame => {
+                            #[cfg(feature = "renderdoc")]
+                            {
+                                graphics.capture_next_frame();
+                                window.request_redraw();
+                            }
+                        }
+                        Action::ReleaseMouse => {
+                            stop_capture_mouse(&window);
+                            capture_mouse = false;
+                            selection.clear();
+                        }
+                        Action::MoreRaySplits | Action::FewerRaySplits => {
+                            let ray_splits = match action {
+                                Action::MoreRaySplits => graphics.ray_splits() + 1,
+                                _ => graphics.ray_splits().saturating_sub(1),
+                            };
+                            if ray_splits != graphics.ray_splits() && ray_splits <= MAX_RAY_SPLITS {
+                                commands.push(Command::SetRaySplits(ray_splits));
+                            }
+                        }
+                        Action::ShorterTicks | Action::LongerTicks => {
+                            let delta_time = physics.physics.delta_time();
+                            let delta_time = match action {
+                                Action::ShorterTicks => delta_time / DELTA_TIME_FACTOR,
+                                _ => delta_time * DELTA_TIME_FACTOR,
+                            }
+                            .clamp(MIN_DELTA_TIME, MAX_DELTA_TIME);
+                            if delta_time != physics.physics.delta_time() {
+                                commands.push(Command::SetDeltaTime(delta_time));
+                            }
+                        }
+                        Action::WeakerGravity | Action::StrongerGravity => {
+                            let mut config = *physics.physics.config();
+                            config.gravity *= match action {
+                                Action::WeakerGravity => GRAVITY_FACTOR.recip(),
+                                _ => GRAVITY_FACTOR,
+                            };
+                            commands.push(Command::SetPhysicsConfig(Box::new(config)));
+                        }
+                        Action::NextBackend => graphics.set_backend(graphics.backend().next()),
+                        Action::ToggleLensing => graphics.set_lensing(!graphics.lensing()),
+                        Action::ToggleAccretionDisk => {
+                            graphics.set_accretion_disk(!graphics.accretion_disk());
+                        }
+                        Action::ToggleTracers => {
+                            tracers.toggle(&mut graphics, &physics.physics, physics.seed);
+                        }
+                        Action::PlayPause => media::push(MediaAction::PlayPause),
+                        Action::Step => {
+                            paused = true;
+                            step_requested = true;
+                        }
+                        Action::Stop => media::push(MediaAction::Stop),
+                        Action::NextStreamlines => {
+                            streamlines.seeding = streamlines.seeding.next();
+                            log::info!("Streamlines: {:?}", streamlines.seeding);
+                        }
+                        Action::DenserMedium
+                        | Action::ThinnerMedium
+                        | Action::MoreExtinction
+                        | Action::LessExtinction => {
+                            let mut medium = graphics.medium();
+                            match action {
+                                Action::DenserMedium => medium.density += MEDIUM_DENSITY_STEP,
+                                Action::ThinnerMedium => medium.density -= MEDIUM_DENSITY_STEP,
+                                Action::MoreExtinction => {
+                                    medium.extinction *= MEDIUM_EXTINCTION_FACTOR;
+                                }
+                                _ => medium.extinction /= MEDIUM_EXTINCTION_FACTOR,
+                            }
+                            graphics.set_medium(medium);
+                        }
+                        Action::NextLabels => {
+                            labels.mode = labels.mode.next();
+                            log::info!("Body labels: {:?}", labels.mode);
+                        }
+                        Action::ToggleConservation => conservation.toggle(),
+                        Action::ToggleFps => {
+                            let fps = &mut graphics.overlay_layout_mut().fps;
+                            fps.visible = !fps.visible;
+                        }
+                        Action::Undo => commands.undo(),
+                        Action::Redo => commands.redo(),
+                        // The selected bodies, or else the camera pose, in the scenario format
+                        Action::Copy if selection.selection.is_empty() => {
+                            let position = camera.position();
+                            clipboard::copy(physics::write_settings(&ScenarioSettings {
+                                camera: Some(ScenarioCamera {
+                                    position,
+                                    target: position + camera.forward(),
+                                }),
+                                ..ScenarioSettings::default()
+                            }));
+                            toasts.info("Copied the camera pose".to_owned());
+                        }
+                        Action::Copy => {
+                            let bodies = physics.physics.bodies();
+                            clipboard::copy(physics::write_scenario(
+                                selection.selection.iter().filter_map(|i| bodies.get(i)),
+                            ));
+                            toasts.info(format!("Copied {} bodies", selection.selection.len()));
+                        }
+                        Action::Paste => clipboard::request_paste(),
+                        Action::SpawnMarble => commands.push(Command::SpawnBody(Body::at(
+                            (camera.position() + 0.5 * camera.forward()).map(Scalar::from),
+                            Vector3::zero(),
+                        ))),
+                        Action::Remove if !selection.selection.is_empty() => {
+                            commands.push(Command::RemoveBodies(selection.selection));
+                            selection.clear();
+                            measure.clear();
+                        }
+                        Action::Remove | Action::Measure => {
+                            pick_purpose = match action {
+                                Action::Remove => PickPurpose::Remove,
+                                _ => PickPurpose::Measure,
+                            };
+                            // The crosshair is at the center of the first view while the mouse
+                            // is captured
+                            let viewport = graphics.viewport(0);
+                            let pixel = if capture_mouse {
+                                (
+                                    viewport.offset.0 + viewport.size.0 / 2,
+                                    viewport.offset.1 + viewport.size.1 / 2,
+                                )
+                            } else {
+                                (cursor.x as u32, cursor.y as u32)
+                            };
+                            graphics.request_pick(pixel);
+                        }
+                        Action::NextCameraTarget => {
+                            let bodies = physics.physics.bodies();
+                            let under_crosshair =
+                                body_under_crosshair(bodies, camera.position(), camera.forward());
+                            camera_target = match (camera_target, under_crosshair) {
+                                (CameraTarget::None, _) => {
+                                    camera.set_controller(Box::new(Orbit::new(
+                                        physics.physics.center_of_mass().to_f32(),
+                                    )));
+                                    CameraTarget::CenterOfMass
+                                }
+                                (CameraTarget::CenterOfMass, Some(index)) => {
+                                    camera.set_controller(Box::new(Follow::new(
+                                        bodies[index].pos.to_f32(),
+                                    )));
+                                    CameraTarget::Body(index)
+                                }
+                                (CameraTarget::CenterOfMass | CameraTarget::Body(_), _) => {
+                                    camera.set_controller(Box::new(FreeFly::new()));
+                                    CameraTarget::None
+                                }
+                            };
+                            bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                        }
+                        Action::FrameAll | Action::FrameSelection => {
+                            let bounds = match action {
+                                Action::FrameAll => {
+                                    let pose = camera.pose();
+                                    sphere_tree
+                                        .build(
+                                            physics.physics.bodies(),
+                                            &physics.physics.config().groups,
+                                            &pose,
+                                        )
+                                        .bounds()
+                                        .map(|(center, radius)| {
+                                            (
+                                                pose.position + pose.rotation.rotate_vector(center),
+                                                radius,
+                                            )
+                                        })
+                                }
+                                _ => {
+                                    selection_bounds(physics.physics.bodies(), selection.selection)
+                                }
+                            };
+                            match bounds {
+                                Some((center, radius)) => {
+                                    let viewport = graphics.viewport(0);
+                                    let aspect = viewport.size.0 as f32 / viewport.size.1 as f32;
+                                    let pose = camera.pose().framing(
+                                        center,
+                                        FRAMING_MARGIN * radius,
+                                        0.5 * aspect.min(1.0),
+                                    );
+                                    camera.set_controller(Box::new(PathPlayback::transition(
+                                        camera.pose(),
+                                        pose,
+                                        FRAMING_TIME,
+                                    )));
+                                    camera_target = CameraTarget::None;
+                                    bus.publish(AppEvent::CameraModeChanged(
+                                        camera.controller_name(),
+                                    ));
+                                }
+                                None if action == Action::FrameSelection => {
+                                    toasts.info("Select bodies to frame them".to_owned());
+                                }
+                                None => {}
+                            }
+                        }
+                        Action::Level => camera.level(WORLD_UP),
+                        Action::ToggleAttitude => {
+                            let attitude = &mut graphics.overlay_layout_mut().attitude;
+                            attitude.visible = !attitude.visible;
+                        }
+                        Action::ToggleCompass => {
+                            let compass = &mut graphics.overlay_layout_mut().compass;
+                            compass.visible = !compass.visible;
+                        }
+                        Action::RecordKeyframe => {
+                            let time = KEYFRAME_INTERVAL * camera_keyframes.len() as u32;
+                            camera_keyframes.push((time, camera.pose()));
+                            log::info!("Recorded camera keyframe {}", camera_keyframes.len());
+                        }
+                        Action::PlayPath => {
+                            if !camera_keyframes.is_empty() {
+                                camera.set_controller(Box::new(PathPlayback::new(
+                                    camera_keyframes.clone(),
+                                    true,
+                                )));
+                                camera_target = CameraTarget::None;
+                                bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                            }
+                        }
+                        Action::NextPreset => {
+                            let generator = Generator {
+                                preset: physics.generator.preset.next(),
+                                ..physics.generator
+                            };
+                            commands.push(Command::Generate(generator, physics.seed));
+                            toasts.info(format!("Preset: {}", generator.preset.name()));
+                            selection.clear();
+                            measure.clear();
+                        }
+                        Action::StartTutorial => {
+                            tutorial.start(STABLE_ORBIT);
+                            selection.clear();
+                            measure.clear();
+                        }
+                        Action::StartChallenge => {
+                            challenge.start_next();
+                            selection.clear();
+                            measure.clear();
+                        }
+                        Action::ToggleGolf => golf.toggle(),
+                        Action::ToggleSplitScreen => {
+                            let split_screen = !graphics.split_screen();
+                            if split_screen {
+                                second_player.camera.set_pose(camera.pose());
+                            }
+                            graphics.set_split_screen(split_screen);
+                        }
+                        Action::ToggleTimeLapse => timelapse.toggle(&physics.physics),
+                        Action::TogglePhotoMode => {
+                            photo.toggle(&mut graphics);
+                            if photo.is_active() {
+                                camera.set_controller(Box::new(FreeFly::new()));
+                                camera_target = CameraTarget::None;
+                                bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                            }
+                        }
+                        Action::NextCaptureSize => {
+                            if photo.is_active() {
+                                photo.next_capture_size();
+                            }
+                        }
+                        Action::CapturePanorama | Action::CaptureStereoPanorama => {
+                            photo.capture_panorama(
+                                &mut graphics,
+                                &mut sphere_tree,
+                                &physics.physics,
+                                camera.pose(),
+                                action == Action::CaptureStereoPanorama,
+                            );
+                        }
+                        Action::CapturePhoto => photo.capture(
+                            &mut graphics,
+                            &View {
+                                bodies: sphere_tree.build(
+                                    physics.physics.bodies(),
+                                    &physics.physics.config().groups,
+                                    &camera.pose(),
+                                ),
+                                pose: camera.pose(),
+                            },
+                        ),
+                        Action::SelectSphere => selection.select_sphere(
+                            physics.physics.bodies(),
+                            camera.position() + SELECTION_SPHERE_DISTANCE * camera.forward(),
+                            SELECTION_SPHERE_RADIUS,
+                        ),
+                        // Edits of the selection, which do nothing without one
+                        _ if selection.selection.is_empty() => {}
+                        Action::FreezeSelection => {
+                            commands.push(Command::ToggleFrozen(selection.selection));
+                        }
+                        Action::RecolorSelection => {
+                            let color = RECOLOR_PALETTE[recolor_count % RECOLOR_PALETTE.len()];
+                            recolor_count += 1;
+                            commands.push(Command::Recolor(selection.selection, color));
+                        }
+                        Action::KickSelection => commands.push(Command::AddVelocity(
+                            selection.selection,
+                            SELECTION_KICK * camera.forward(),
+                        )),
+                        Action::ExportSelection => export_selection(
+                            &mut graphics,
+                            &mut sphere_tree,
+                            physics.physics.bodies(),
+                            selection.selection,
+                        ),
+                    }
+                }
+                while let Some(action) = media::take_action() {
+                    match action {
+                        MediaAction::Play => paused = false,
+                        MediaAction::Pause => paused = true,
+                        MediaAction::PlayPause => paused = !paused,
+                        MediaAction::Stop => {
+                            if timelapse.is_running() {
+                                timelapse.toggle(&physics.physics);
+                            }
+                            if camera.is_playing_path() {
+                                camera.set_controller(Box::new(FreeFly::new()));
+                                bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                            }
+                            continue;
+                        }
+                    }
+                    log::info!("{}", if paused { "Paused" } else { "Resumed" });
+                }
+                media::show_state(
+                    match (timelapse.is_running() || camera.is_playing_path(), paused) {
+                        (false, _) => MediaState::Idle,
+                        (true, false) => MediaState::Playing,
+                        (true, true) => MediaState::Paused,
+                    },
+                );
                 if !initialized {
                     camera_timestamp = now;
                     initialized = true;
                 }
-                camera_timestamp += camera.update_return_stepped(now - camera_timestamp);
+                let suspended = now.duration_since(last_begun_main_events_cleared);
+                if suspended > PHYSICS_MAX_BEHIND_TIME {
+                    // The event loop was not running, most likely because the tab was hidden.
+                    // Resume from now instead of trying to catch up.
+                    log::info!("Skipping {}ms after suspension", suspended.as_millis());
+                    camera_timestamp = now;
+                    physics.skip_to(now);
+                }
+                if let Some(pose) = scenario_pose.0.take() {
+                    camera.set_pose(pose);
+                    camera.set_controller(Box::new(FreeFly::new()));
+                    camera_target = CameraTarget::None;
+                    bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                }
+                match camera_target {
+                    CameraTarget::None => {}
+                    CameraTarget::CenterOfMass => {
+                        camera.track(physics.physics.center_of_mass().to_f32());
+                    }
+                    CameraTarget::Body(index) => match physics.physics.bodies().get(index) {
+                        Some(body) => camera.track(body.pos.to_f32()),
+                        None => {
+                            camera.set_controller(Box::new(FreeFly::new()));
+                            camera_target = CameraTarget::None;
+                            bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                        }
+                    },
+                }
+                if let Some((dx, dy)) =
+                    mouse_look.take(now.duration_since(last_begun_main_events_cleared))
+                {
+                    let scale = if photo.is_active() {
+                        PHOTO_LOOK_SCALE
+                    } else {
+                        1.0
+                    };
+                    camera.mouse_input(scale * dx, scale * dy);
+                }
+                second_player.update(now);
+                second_player
+                    .camera
+                    .update_return_stepped(now - camera_timestamp);
+                if paused && camera.is_playing_path() {
+                    // Camera paths pause along with the simulation they are filming
+                    camera_timestamp = now;
+                } else {
+                    camera_timestamp += camera.update_return_stepped(now - camera_timestamp);
+                }
+                if camera.controller_finished() {
+                    camera.set_controller(Box::new(FreeFly::new()));
+                    bus.publish(AppEvent::CameraModeChanged(camera.controller_name()));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    replay.camera(physics.physics.ticks(), &mut camera);
+                    replay.positions(&physics.physics);
+                    ghost.update(physics.physics.ticks());
+                }
+                {
+                    // Nothing moves, so wait for events instead of rendering the same frame
+                    let poses = (camera.pose(), second_player.camera.pose());
+                    let still = last_poses.replace(poses) == Some(poses);
+                    if still
+                        && (paused || photo.is_active() || gallery.is_open())
+                        && !timelapse.is_running()
+                        && toasts.is_empty()
+                        && motion.is_still()
+                        // Gamepads are polled rather than sending events
+                        && !gamepad.is_connected()
+                        && now.duration_since(last_window_event) > IDLE_DELAY
+                    {
+                        physics.skip_to(now);
+                        if gallery.is_open() {
+                            // Still notice scenarios being added
+                            control_flow.set_wait_until(now + RESCAN_INTERVAL);
+                        } else {
+                            control_flow.set_wait();
+                        }
+                        return;
+                    }
+                }
                 if now < last_begun_main_events_cleared + desired_event_loop_period {
                     control_flow
                         .set_wait_until(last_begun_main_events_cleared + desired_event_loop_period);
@@ -145,9 +934,82 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
                         stats.event_loop_times.pop_front();
                     }
                 }
+                motion.update(
+                    camera.position(),
+                    now.duration_since(last_begun_main_events_cleared),
+                );
                 last_begun_main_events_cleared = now;
 
-                physics.start(now, proxy.clone(), &mut stats);
+                match (graphics.poll_pick(), pick_purpose) {
+                    (Some(Picked::Body(index)), PickPurpose::Remove) => {
+                        commands.push(Command::RemoveBody(index));
+                        measure.clear();
+                        selection.clear();
+                    }
+                    (Some(Picked::Body(index)), PickPurpose::Measure) => measure.pick(index),
+                    (Some(Picked::Background), PickPurpose::Measure) => measure.clear(),
+                    (Some(Picked::Background), PickPurpose::Remove) | (None, _) => {}
+                }
+                if physics.is_idle() {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    replay.begin_edits(&physics.physics);
+                    let generated = (physics.generator, physics.seed);
+                    commands.apply_queued(&mut graphics, &mut physics, &mut bus);
+                    if (physics.generator, physics.seed) != generated {
+                        window.set_title(&title(&physics));
+                    }
+                    gallery.update(&mut physics.physics, &mut bus, &mut toasts);
+                    tutorial.update(&mut physics.physics, &mut bus);
+                    challenge.update(&mut physics.physics, &mut bus);
+                    golf.update(&mut physics.physics);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    replay.end_edits(&physics.physics);
+                    if timelapse.advance(&mut physics.physics) {
+                        timelapse.capture(
+                            &mut graphics,
+                            &View {
+                                bodies: sphere_tree.build(
+                                    physics.physics.bodies(),
+                                    &physics.physics.config().groups,
+                                    &camera.pose(),
+                                ),
+                                pose: camera.pose(),
+                            },
+                        );
+                    }
+                }
+                if paused || photo.is_active() || gallery.is_open() || timelapse.is_running() {
+                    // Paused or advanced by the time-lapse, and resuming from whenever that ends
+                    physics.skip_to(now);
+                    if step_requested && physics.is_idle() && !timelapse.is_running() {
+                        physics.step(proxy.clone(), &mut bus);
+                        step_requested = false;
+                    }
+                } else {
+                    // Bodies from the feed are recorded as edits, since they are not simulated
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let replayed = if feed.is_on() {
+                        replay.begin_edits(&physics.physics);
+                        let fed = feed.advance(now, &mut physics.physics);
+                        replay.end_edits(&physics.physics);
+                        Some(fed)
+                    } else {
+                        replay.advance(
+                            &mut physics.physics,
+                            now.duration_since(last_begun_main_events_cleared),
+                        )
+                    };
+                    #[cfg(target_arch = "wasm32")]
+                    let replayed = None;
+                    match replayed {
+                        Some(result) => {
+                            physics.publish_ticks(result, &mut bus);
+                            physics.skip_to(now);
+                        }
+                        None => physics.start(now, proxy.clone(), &mut bus),
+                    }
+                }
+                comparison.update(&physics.physics);
                 {
                     let [frame, render] = graphics.get_recent_avg_frame_and_render_time();
                     let sufficient_non_render_time =
@@ -172,14 +1034,86 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
                     }
                 }
                 let instant_pre_graphics = Instant::now();
-                graphics.render(
-                    spheretree::make_sphere_tree(
-                        physics.physics.bodies(),
-                        camera.world_to_camera(),
-                    ),
-                    camera.rotation(),
-                    stats.frame_number % 30 == 0,
+                graphics.set_simulated_time(physics.physics.simulated_time());
+                let frame = Frame {
+                    now: instant_pre_graphics,
+                    physics: &physics.physics,
+                    pose: camera.pose(),
+                    cursor: (cursor.x as f32, cursor.y as f32),
+                    camera_target: match camera_target {
+                        CameraTarget::Body(index) => Some(index),
+                        CameraTarget::None | CameraTarget::CenterOfMass => None,
+                    },
+                };
+                events::queue(
+                    &mut [
+                        &mut toasts,
+                        &mut gallery,
+                        &mut labels,
+                        &mut measure,
+                        &mut boundary::Outline,
+                        &mut attitude::Indicator,
+                        &mut compass::Gnomon,
+                        &mut motion,
+                        &mut tutorial,
+                        &mut log_console,
+                        &mut command_line,
+                        &mut conservation,
+                        &mut comparison,
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &mut ghost,
+                        &mut Help {
+                            shown: show_help,
+                            input_map: &input_map,
+                        },
+                        &mut stats,
+                        &mut timelapse,
+                        &mut challenge,
+                        &mut golf,
+                        &mut selection,
+                        &mut tracers,
+                        &mut streamlines,
+                    ],
+                    &mut graphics,
+                    &frame,
                 );
+                let bodies = physics.physics.bodies();
+                #[cfg(not(target_arch = "wasm32"))]
+                let haunted = ghost.haunt(bodies);
+                #[cfg(not(target_arch = "wasm32"))]
+                let bodies = haunted.as_ref().unwrap_or(bodies);
+                let groups = &physics.physics.config().groups;
+                #[cfg(not(target_arch = "wasm32"))]
+                let first_pose = audio.shake(camera.pose());
+                #[cfg(target_arch = "wasm32")]
+                let first_pose = camera.pose();
+                sphere_tree.set_radius_scale(graphics.radius_scale());
+                second_sphere_tree.set_radius_scale(graphics.radius_scale());
+                let mut span = trace::span("sphere tree");
+                span.arg("bodies", bodies.len() as u64);
+                let first_view = View {
+                    bodies: sphere_tree.build(bodies, groups, &first_pose),
+                    pose: first_pose,
+                };
+                drop(span);
+                let update_fps_display = stats.frame_number % 30 == 0;
+                if graphics.split_screen() {
+                    // A comparison is seen from the same camera, and a second player from theirs
+                    let (second_bodies, second_pose) = match comparison.bodies() {
+                        Some(compared) => (compared, first_pose),
+                        None => (bodies, second_player.camera.pose()),
+                    };
+                    let mut span = trace::span("sphere tree");
+                    span.arg("bodies", second_bodies.len() as u64);
+                    let second_view = View {
+                        bodies: second_sphere_tree.build(second_bodies, groups, &second_pose),
+                        pose: second_pose,
+                    };
+                    drop(span);
+                    graphics.render(&[first_view, second_view], update_fps_display);
+                } else {
+                    graphics.render(&[first_view], update_fps_display);
+                }
                 stats.time_spent_in_graphics += Instant::now().duration_since(instant_pre_graphics);
                 stats.frame_number += 1;
                 if stats.frame_number.is_power_of_two() || stats.frame_number % 1024 == 0 {
@@ -195,9 +1129,14 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
                 control_flow
                     .set_wait_until(last_begun_main_events_cleared + desired_event_loop_period);
             }
+            Event::LoopDestroyed => {
+                trace::finish();
+                #[cfg(not(target_arch = "wasm32"))]
+                replay.finish();
+            }
             Event::UserEvent(event) => {
                 #[cfg(target_arch = "wasm32")]
-                physics.handle_event(event, &mut stats);
+                physics.handle_event(event, &mut bus);
                 #[cfg(not(target_arch = "wasm32"))]
                 {
                     let () = event;
@@ -206,13 +1145,231 @@ pub fn run(event_loop: EventLoop<PhysicsEvent>, window: Window, mut graphics: Gr
             }
             _ => {}
         }
+        bus.dispatch(&mut [
+            &mut stats,
+            &mut graphics,
+            &mut tutorial,
+            &mut challenge,
+            &mut scenario_pose,
+            &mut conservation,
+            &mut comparison,
+            &mut selection,
+            #[cfg(not(target_arch = "wasm32"))]
+            &mut replay,
+            #[cfg(not(target_arch = "wasm32"))]
+            &mut ghost,
+            #[cfg(not(target_arch = "wasm32"))]
+            &mut impacts,
+        ]);
+        #[cfg(not(target_arch = "wasm32"))]
+        impacts.play(&physics.physics);
     });
 }
 
+/// Adapts graphics to what the surface supports now, which may change with the monitor.
+fn refresh_surface(graphics: &mut Graphics, adapter: &wgpu::Adapter, gallery: &mut Gallery) {
+    let format = graphics.parameters().texture_format;
+    graphics.refresh_parameters(adapter);
+    if graphics.parameters().texture_format != format {
+        gallery.reload_thumbnails();
+    }
+}
+
+/// The key bindings, while toggled on
+struct Help<'a> {
+    shown: bool,
+    input_map: &'a InputMap,
+}
+impl Subscriber for Help<'_> {
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if self.shown {
+            queue_help(graphics, self.input_map);
+        }
+    }
+}
+
+/// The controls, named by the keys they are bound to as they type on the layout in use.
+fn queue_help(graphics: &mut Graphics, input_map: &InputMap) {
+    let key = |key| input_map.key_label(Action::Fly(key));
+    graphics.overlay_line(format_args!(
+        "Fly with {} {} {} {}, rise with {} and sink with {}",
+        key(FlightKey::Forwards),
+        key(FlightKey::Left),
+        key(FlightKey::Backwards),
+        key(FlightKey::Right),
+        key(FlightKey::Up),
+        key(FlightKey::Down)
+    ));
+    graphics.overlay_line(format_args!(
+        "Roll with {} and {}, look around with the mouse and hold Ctrl to fly slowly",
+        key(FlightKey::RollLeft),
+        key(FlightKey::RollRight)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} levels the horizon, {} shows the attitude and {} the world axes and sun",
+        input_map.key_label(Action::Level),
+        input_map.key_label(Action::ToggleAttitude),
+        input_map.key_label(Action::ToggleCompass)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} frames every body and {} the selection",
+        input_map.key_label(Action::FrameAll),
+        input_map.key_label(Action::FrameSelection)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} starts over from the next preset of initial conditions",
+        input_map.key_label(Action::NextPreset)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} pauses and resumes, and {} steps a single tick",
+        input_map.key_label(Action::PlayPause),
+        input_map.key_label(Action::Step)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} opens the gallery, {} the log, {} the console and {} this help",
+        input_map.key_label(Action::OpenGallery),
+        input_map.key_label(Action::ToggleLog),
+        input_map.key_label(Action::ToggleConsole),
+        input_map.key_label(Action::ToggleHelp)
+    ));
+    graphics.overlay_line(format_args!(
+        "{} shows energy and momentum, and how far they drift",
+        input_map.key_label(Action::ToggleConservation)
+    ));
+}
+
+/// Queues the action bound to a gamepad or touch input, or looks around.
+fn queue_input(
+    input_map: &InputMap,
+    event: InputEvent,
+    actions: &mut VecDeque<(Action, bool)>,
+    mouse_look: &mut MouseLook,
+) {
+    match event {
+        InputEvent::Input(input, pressed) => {
+            if let Some(action) = input_map.action(input) {
+                actions.push_back((action, pressed));
+            }
+        }
+        InputEvent::Look(dx, dy) => mouse_look.motion(dx, dy),
+    }
+}
+
+/// The title of the window, naming what to pass to recreate the initial bodies.
+fn title(physics: &PhysicsSystem) -> String {
+    match physics.generator.preset {
+        Preset::Cluster => format!("Marble Gravity, seed {}", physics.seed),
+        preset => format!("Marble Gravity, {}, seed {}", preset.name(), physics.seed),
+    }
+}
+
+/// Applies a camera pose and adds bodies, copied in the scenario format.
+fn paste(
+    text: &str,
+    scenario_pose: &mut ScenarioPose,
+    commands: &mut CommandHistory,
+    toasts: &mut Toasts,
+) {
+    let scenario = match physics::parse_scenario(text) {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            toasts.error(format!("Cannot paste, {err}"));
+            return;
+        }
+    };
+    if let Some(ScenarioCamera { position, target }) = scenario.settings.camera {
+        scenario_pose.0 = Some(Pose::looking_at(position, target));
+        toasts.info("Pasted a camera pose".to_owned());
+    }
+    if !scenario.bodies.is_empty() {
+        toasts.info(format!("Pasted {} bodies", scenario.bodies.len()));
+    }
+    for &body in scenario.bodies.iter() {
+        commands.push(Command::SpawnBody(body));
+    }
+}
+
+/// Saves the selected bodies as a scenario file in the gallery, along with a thumbnail, or logs
+/// it on the web.
+fn export_selection(
+    graphics: &mut Graphics,
+    sphere_tree: &mut SphereTreeBuilder,
+    bodies: &[Body],
+    selection: Selection,
+) {
+    let selected = || selection.iter().filter_map(|i| bodies.get(i));
+    let text = physics::write_scenario(selected());
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path =
+            std::path::Path::new(USER_SCENARIOS).join(format!("selection-{seconds}.scenario"));
+        match std::fs::create_dir_all(USER_SCENARIOS).and_then(|()| std::fs::write(&path, text)) {
+            Ok(()) => log::info!("Exported {} bodies to {}", selection.len(), path.display()),
+            Err(err) => {
+                log::error!("Failed to export selection to {}: {err}", path.display());
+                return;
+            }
+        }
+        let scenario = physics::Scenario {
+            bodies: selected().copied().collect(),
+            settings: physics::ScenarioSettings::default(),
+            groups: Vec::new(),
+            fields: Vec::new(),
+        };
+        crate::gallery::save_in_background(
+            crate::gallery::render_thumbnail(graphics, sphere_tree, &scenario),
+            path.with_extension("png"),
+        );
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = (graphics, sphere_tree);
+        log::info!("Exported {} bodies:\n{text}", selection.len());
+    }
+}
+
+/// The center and radius of a sphere bounding the selected bodies, if any.
+fn selection_bounds(bodies: &[Body], selection: Selection) -> Option<(Vector3<f32>, f32)> {
+    let selected = || (0..bodies.len()).filter(move |&i| selection.contains(i));
+    let count = selected().count();
+    if count == 0 {
+        return None;
+    }
+    let center = selected().map(|i| bodies[i].pos).sum::<Vector3<Scalar>>() / count as Scalar;
+    let radius = selected()
+        .map(|i| (bodies[i].pos - center).magnitude() + bodies[i].radius)
+        .fold(0.0, Scalar::max);
+    Some((center.to_f32(), radius.to_f32()))
+}
+
+/// The index of the nearest body hit by a ray
+fn body_under_crosshair(
+    bodies: &[Body],
+    origin: Vector3<f32>,
+    direction: Vector3<f32>,
+) -> Option<usize> {
+    bodies
+        .iter()
+        .enumerate()
+        .filter_map(|(i, body)| {
+            let rel_pos = body.pos.to_f32() - origin;
+            let along = rel_pos.dot(direction);
+            let across2 = rel_pos.magnitude2() - along * along;
+            (along > 0.0 && across2 < body.radius.to_f32().powi(2)).then_some((along, i))
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+        .map(|(_, i)| i)
+}
+
 fn begin_capture_mouse(window: &Window) -> Result<(), ()> {
+    // Looking around only uses raw motion, so locking is preferred where supported, and
+    // elsewhere the cursor is confined and kept centered by `continue_capture_mouse`
     window
-        .set_cursor_grab(CursorGrabMode::Confined)
-        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+        .set_cursor_grab(CursorGrabMode::Locked)
+        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
         .map_err(|_| ())?;
     window.set_cursor_visible(false);
     Ok(())
@@ -0,0 +1,143 @@
+use crate::{
+    events::{AppEvent, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Matrix4, Vector3};
+use physics::{Body, ToF32, BODIES};
+use std::fmt;
+
+const SELECTION_COLOR: [f32; 4] = [0.3, 0.8, 1.0, 0.8];
+
+/// A set of body indices, small enough to copy into commands
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Selection {
+    words: [u64; BODIES.div_ceil(64)],
+}
+impl Selection {
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    /// Indices in increasing order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        (0..BODIES).filter(|&index| self.contains(index))
+    }
+}
+impl fmt::Debug for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Selects bodies by dragging a box on the screen or by a sphere in the world, and marks the
+/// selected bodies on screen.
+pub struct SelectionTool {
+    pub selection: Selection,
+    drag_start: Option<(f32, f32)>,
+}
+impl SelectionTool {
+    pub fn new() -> Self {
+        Self {
+            selection: Selection::default(),
+            drag_start: None,
+        }
+    }
+    pub fn clear(&mut self) {
+        self.selection = Selection::default();
+    }
+    pub fn begin_drag(&mut self, cursor: (f32, f32)) {
+        self.drag_start = Some(cursor);
+    }
+    /// Selects the bodies with centers inside the dragged box, if a drag was in progress.
+    pub fn end_drag(
+        &mut self,
+        cursor: (f32, f32),
+        graphics: &Graphics,
+        bodies: &[Body],
+        world_to_camera: Matrix4<f32>,
+    ) {
+        let Some(start) = self.drag_start.take() else {
+            return;
+        };
+        let ((x0, y0), (x1, y1)) = sorted_corners(start, cursor);
+        self.select_where(bodies, |body| {
+            graphics
+                .project((world_to_camera * body.pos.to_f32().extend(1.0)).truncate())
+                .is_some_and(|(x, y)| (x0..=x1).contains(&x) && (y0..=y1).contains(&y))
+        });
+    }
+    /// Selects the bodies with centers inside a sphere.
+    pub fn select_sphere(&mut self, bodies: &[Body], center: Vector3<f32>, radius: f32) {
+        self.select_where(bodies, |body| {
+            body.pos.to_f32().distance2(center) <= radius * radius
+        });
+    }
+    fn select_where(&mut self, bodies: &[Body], mut predicate: impl FnMut(&Body) -> bool) {
+        self.clear();
+        for (index, body) in bodies.iter().enumerate() {
+            if predicate(body) {
+                self.selection.insert(index);
+            }
+        }
+        log::info!("Selected {} bodies", self.selection.len());
+    }
+}
+impl Subscriber for SelectionTool {
+    /// Forgets the selection once indices no longer refer to the same bodies.
+    fn notify(&mut self, event: &AppEvent) {
+        if let AppEvent::BodyRemoved(_) | AppEvent::BodiesReplaced = event {
+            self.clear();
+        }
+    }
+    /// Outlines the dragged box and the selected bodies.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (cursor, bodies) = (frame.cursor, frame.physics.bodies());
+        let world_to_camera = frame.world_to_camera();
+        if let Some(start) = self.drag_start {
+            outline(graphics, sorted_corners(start, cursor));
+        }
+        for index in self.selection.iter() {
+            let Some(body) = bodies.get(index) else {
+                break;
+            };
+            let view_pos = (world_to_camera * body.pos.to_f32().extend(1.0)).truncate();
+            let (Some(center), Some(top)) = (
+                graphics.project(view_pos),
+                graphics.project(view_pos - body.radius.to_f32() * Vector3::unit_y()),
+            ) else {
+                continue;
+            };
+            // Slightly larger than the body, and visible even when it is tiny
+            let half = (1.2 * (center.1 - top.1)).max(4.0);
+            outline(
+                graphics,
+                (
+                    (center.0 - half, center.1 - half),
+                    (center.0 + half, center.1 + half),
+                ),
+            );
+        }
+    }
+}
+
+fn sorted_corners((ax, ay): (f32, f32), (bx, by): (f32, f32)) -> ((f32, f32), (f32, f32)) {
+    ((ax.min(bx), ay.min(by)), (ax.max(bx), ay.max(by)))
+}
+
+fn outline(graphics: &mut Graphics, ((x0, y0), (x1, y1)): ((f32, f32), (f32, f32))) {
+    let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+    for i in 0..4 {
+        graphics.overlay_segment(corners[i], corners[(i + 1) % 4], SELECTION_COLOR);
+    }
+}
@@ -1,6 +1,5 @@
 #version 450
 
-// Note that the rust side includes 8 bytes padding at the end which is implicit here
 // Buffer items need their size to be a multiple of 16 bytes. This struct is 32 bytes.
 struct Body {
     vec3 pos;
@@ -8,7 +7,7 @@ struct Body {
     int left;
     int right;
     uint color;
-    uint padding;
+    uint body;
 };
 // Internal structs
 struct HitReport {
@@ -35,12 +34,31 @@ const vec3 SUN_COLOR = vec3(1);
 const float SUN_SIZE = 1e-2;
 const float SUN_CORONA = 1e-3;
 const float REFRACTIVE_INDEX = 1.1;
+// Must match `project` and `log_depth` in raster.wgsl
+const float NEAR = 0.001;
+const float FAR = 1e7;
+// The medium is sampled at this many points along camera rays, up to a distance
+const int MEDIUM_STEPS = 24;
+const float MEDIUM_MAX_DISTANCE = 4.0;
+// Dust clouds are about this many per world unit across
+const float MEDIUM_NOISE_SCALE = 3.0;
+// Henyey-Greenstein asymmetry, where positive values scatter forwards, toward the sun
+const float MEDIUM_ANISOTROPY = 0.5;
+// Deflection of a ray passing the lens, per cubed lens radius over the closest distance. Far
+// stronger than real gravity, so that the heaviest marbles visibly bend light.
+const float LENS_STRENGTH = 12.0;
+const float LENS_MAX_DEFLECTION = 1.2;
+// Bands of latitude on striped bodies, and how dark every other band is
+const float STRIPES = 5.0;
+const float STRIPE_SHADE = 0.45;
 
 // Global variables ===
 bool stack_overflow = false;
 
 // IO ===
 layout(location=0) out vec4 f_color;
+// One plus the index of the body seen at this pixel, or zero for the background
+layout(location=1) out uint f_body;
 
 // Buffers & Uniforms ===
 layout(set=0, binding=0) uniform Bodies {
@@ -50,12 +68,31 @@ layout(set=0, binding=0) uniform Bodies {
 layout(set=0, binding=1) uniform Uniforms {
     vec3 sun_direction;
     uint ray_splits;
-    vec2 window_size;
-    vec2 padding2;
+    vec2 viewport_size;
+    uint primary_from_gbuffer;
+    float medium_extinction;
+    vec2 viewport_offset;
+    vec2 target_size;
+    vec3 camera_position;
+    float medium_density;
+    vec3 lens_position;
+    float lens_radius;
+    vec3 disk_center;
+    float disk_body_radius;
+    float disk_time;
+    uint tracer_count;
+    float emission;
+    float padding;
     mat4 view_to_world_space;
 };
 layout(set=0, binding=2) uniform textureCube skybox_texture;
 layout(set=0, binding=3) uniform sampler skybox_sampler;
+// One plus the index of the body rasterized at each pixel, or zero for the background
+layout(set=0, binding=4) uniform texture2D gbuffer_texture;
+// The rotation from camera space to the frame of each body as a quaternion, zero unless striped
+layout(set=0, binding=5) uniform Orientations {
+    vec4 orientations[BODIES];
+};
 
 // Forward function declarations ===
 float softmax(float a, float b, float c);
@@ -63,31 +100,56 @@ float rings(float x);
 vec3 background_light(const vec3 ray);
 float hit_time(const vec3 from, const vec3 ray, const uint body);
 HitReport cast_ray(const vec3 from, const vec3 ray);
+HitReport primary_hit(const vec3 ray);
 vec3 refract3(vec3 incident, vec3 normal, float eta);
 Rays ray_tracing_data(const vec3 normal, const vec3 ray, const uint hit_id);
+float depth(const HitReport hit);
 float color_w(const uint color);
 vec3 color_xyz(const uint color);
+vec3 surface_color(const HitReport hit);
+vec3 lensed(const vec3 from, const vec3 ray);
+vec3 split0_hit(const HitReport hit, const vec3 ray);
 vec3 split0_ray(const vec3 from, const vec3 ray);
+vec3 split1_hit(const HitReport hit, const vec3 ray);
 vec3 split1_ray(const vec3 from, const vec3 ray);
+vec3 split2_hit(const HitReport hit, const vec3 ray);
 vec3 split2_ray(const vec3 from, const vec3 ray);
+vec3 split3_hit(const HitReport hit, const vec3 ray);
 vec3 split3_ray(const vec3 from, const vec3 ray);
+vec3 split4_hit(const HitReport hit, const vec3 ray);
 vec3 split4_ray(const vec3 from, const vec3 ray);
+float hash(const vec3 p);
+float value_noise(const vec3 p);
+float dust_density(const vec3 world_pos);
+float hit_distance(const HitReport hit);
+vec3 through_medium(const vec3 ray, const float distance, const vec3 light);
 
 void fs_main() {
-    const vec2 frag_pos = gl_FragCoord.xy / window_size.y;
-    const vec2 mid_frag_pos = vec2(0.5 * window_size.x / window_size.y, 0.5);
-    const vec3 camera_ray = normalize(vec3(frag_pos - mid_frag_pos, 1));
+    const vec2 frag_pos = (gl_FragCoord.xy - viewport_offset) / viewport_size.y;
+    const vec2 mid_frag_pos = vec2(0.5 * viewport_size.x / viewport_size.y, 0.5);
+    const vec3 camera_ray = lensed(vec3(0), normalize(vec3(frag_pos - mid_frag_pos, 1)));
+    HitReport hit;
+    // The G-buffer holds unbent primary visibility
+    if (primary_from_gbuffer != 0 && lens_radius == 0) {
+        hit = primary_hit(camera_ray);
+    } else {
+        hit = cast_ray(vec3(0), camera_ray);
+    }
+    vec3 light;
     if (ray_splits == 0) {
-        f_color = vec4(split0_ray(vec3(0), camera_ray), 1);
+        light = split0_hit(hit, camera_ray);
     } else if (ray_splits == 1) {
-        f_color = vec4(split1_ray(vec3(0), camera_ray), 1);
+        light = split1_hit(hit, camera_ray);
     } else if (ray_splits == 2) {
-        f_color = vec4(split2_ray(vec3(0), camera_ray), 1);
+        light = split2_hit(hit, camera_ray);
     } else if (ray_splits == 3) {
-        f_color = vec4(split3_ray(vec3(0), camera_ray), 1);
+        light = split3_hit(hit, camera_ray);
     } else {
-        f_color = vec4(split4_ray(vec3(0), camera_ray), 1);
+        light = split4_hit(hit, camera_ray);
     }
+    f_color = vec4(through_medium(camera_ray, hit_distance(hit), light), 1);
+    gl_FragDepth = depth(hit);
+    f_body = hit.id == NO_HIT ? 0 : bodies[hit.id].body + 1;
     if (stack_overflow) {
         f_color = RED;
     }
@@ -95,6 +157,81 @@ void fs_main() {
 void main() {
     fs_main();
 }
+// The depth of a primary hit, logarithmic in the distance along the view like that of rasterized
+// geometry so that the two occlude each other correctly
+float depth(const HitReport hit) {
+    if (hit.id == NO_HIT) {
+        return 1.0;
+    }
+    const float z = bodies[hit.id].pos.z + bodies[hit.id].radius * hit.normal.z;
+    return clamp(log2(max(z, NEAR) / NEAR) / log2(FAR / NEAR), 0.0, 1.0);
+}
+// The distance from the camera to a primary hit
+float hit_distance(const HitReport hit) {
+    if (hit.id == NO_HIT) {
+        return MEDIUM_MAX_DISTANCE;
+    }
+    return length(bodies[hit.id].pos + bodies[hit.id].radius * hit.normal);
+}
+
+// Medium ===
+// A pseudorandom number in [0, 1) for each point
+float hash(const vec3 p) {
+    const vec3 q = 17.0 * fract(p * 0.3183099 + 0.1);
+    return fract(q.x * q.y * q.z * (q.x + q.y + q.z));
+}
+// Smoothly interpolated hashes of the integer lattice, in [0, 1]
+float value_noise(const vec3 p) {
+    const vec3 i = floor(p);
+    const vec3 f = fract(p);
+    const vec3 t = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(
+            mix(hash(i), hash(i + vec3(1, 0, 0)), t.x),
+            mix(hash(i + vec3(0, 1, 0)), hash(i + vec3(1, 1, 0)), t.x),
+            t.y),
+        mix(
+            mix(hash(i + vec3(0, 0, 1)), hash(i + vec3(1, 0, 1)), t.x),
+            mix(hash(i + vec3(0, 1, 1)), hash(i + vec3(1, 1, 1)), t.x),
+            t.y),
+        t.z);
+}
+// The dust density at a point, from zero to one, covering a `medium_density` fraction of space
+float dust_density(const vec3 world_pos) {
+    const vec3 p = MEDIUM_NOISE_SCALE * world_pos;
+    const float noise = 0.5 * value_noise(p) + 0.3 * value_noise(2.03 * p) + 0.2 * value_noise(4.01 * p);
+    return smoothstep(1.0 - medium_density, 1.0, noise);
+}
+// Ray-marches the medium between the camera and `distance` along `ray`. Light from behind is
+// attenuated, and sunlight reaching each sample without hitting a body is scattered toward the
+// camera, so bodies cast shafts of shadow.
+vec3 through_medium(const vec3 ray, const float distance, const vec3 light) {
+    if (medium_density <= 0) {
+        return light;
+    }
+    const float step_length = min(distance, MEDIUM_MAX_DISTANCE) / MEDIUM_STEPS;
+    // Jittered per pixel, trading banding for noise
+    const float jitter = hash(vec3(gl_FragCoord.xy, 0));
+    const float g = MEDIUM_ANISOTROPY;
+    const float phase = (1 - g * g) / pow(1 + g * g - 2 * g * dot(ray, sun_direction), 1.5);
+    float transmittance = 1;
+    vec3 scattered = vec3(0);
+    for (int i = 0; i < MEDIUM_STEPS; i++) {
+        const vec3 pos = (float(i) + jitter) * step_length * ray;
+        const vec3 world_pos = (view_to_world_space * vec4(pos, 0)).xyz + camera_position;
+        const float extinction = medium_extinction * dust_density(world_pos);
+        if (extinction <= 0) {
+            continue;
+        }
+        const float step_transmittance = exp(-extinction * step_length);
+        if (cast_ray(pos, sun_direction).id == NO_HIT) {
+            scattered += transmittance * (1 - step_transmittance) * phase * SUN_COLOR;
+        }
+        transmittance *= step_transmittance;
+    }
+    return transmittance * light + scattered;
+}
+
 float color_w(const uint color) {
     return float(color & 0xFF) / 0xFF;
 }
@@ -104,55 +241,105 @@ vec3 color_xyz(const uint color) {
     uint b = (color >> 8) & 0xFF;
     return vec3(float(r) / 0xFF, float(g) / 0xFF, float(b) / 0xFF);
 }
+// The color of a body where it was hit, with stripes that turn with it if it has any
+vec3 surface_color(const HitReport hit) {
+    const vec3 color = color_xyz(bodies[hit.id].color);
+    const vec4 q = orientations[bodies[hit.id].body];
+    if (dot(q, q) == 0) {
+        return color;
+    }
+    const vec3 n = hit.normal;
+    const vec3 local = n + 2 * cross(q.xyz, cross(q.xyz, n) + q.w * n);
+    const float latitude = asin(clamp(local.y, -1.0, 1.0));
+    return sin(STRIPES * latitude) > 0 ? color : STRIPE_SHADE * color;
+}
+
+// Lensing ===
+// Bends a ray passing the lens toward it, by an angle inversely proportional to how closely it
+// passes. This is a thin-lens approximation: all deflection happens at the start of the ray
+// rather than at its closest approach, which is exact for the background but only approximate
+// for bodies behind the lens.
+vec3 lensed(const vec3 from, const vec3 ray) {
+    if (lens_radius == 0) {
+        return ray;
+    }
+    const vec3 to_lens = lens_position - from;
+    const float along = dot(to_lens, ray);
+    if (along <= 0) {
+        return ray;
+    }
+    const vec3 offset = to_lens - along * ray;
+    const float closest = length(offset);
+    if (closest <= lens_radius) {
+        return ray;
+    }
+    const float deflection = min(
+        LENS_STRENGTH * lens_radius * lens_radius * lens_radius / closest,
+        LENS_MAX_DEFLECTION
+    );
+    return normalize(ray + tan(deflection) * offset / closest);
+}
 
 vec3 split4_ray(const vec3 from, const vec3 ray) {
-    const HitReport hit = cast_ray(from, ray);
+    const vec3 bent = lensed(from, ray);
+    return split4_hit(cast_ray(from, bent), bent);
+}
+vec3 split4_hit(const HitReport hit, const vec3 ray) {
     if (hit.id == NO_HIT) {
         return background_light(ray);
     }
     const Rays next = ray_tracing_data(hit.normal, ray, hit.id);
     const float opacity = color_w(bodies[hit.id].color);
 
-    vec3 light = AMBIENT * opacity * color_xyz(bodies[hit.id].color); // Ambient
+    vec3 light = (AMBIENT + emission) * opacity * surface_color(hit); // Ambient and emitted
     light += opacity * split3_ray(next.reflected_pos, next.reflected_ray); // Reflected
     light += (1 - opacity) * split3_ray(next.refracted_pos, next.refracted_ray); // Refracted
     return light;
 }
 vec3 split3_ray(const vec3 from, const vec3 ray) {
-    const HitReport hit = cast_ray(from, ray);
+    const vec3 bent = lensed(from, ray);
+    return split3_hit(cast_ray(from, bent), bent);
+}
+vec3 split3_hit(const HitReport hit, const vec3 ray) {
     if (hit.id == NO_HIT) {
         return background_light(ray);
     }
     const Rays next = ray_tracing_data(hit.normal, ray, hit.id);
     const float opacity = color_w(bodies[hit.id].color);
 
-    vec3 light = AMBIENT * opacity * color_xyz(bodies[hit.id].color); // Ambient
+    vec3 light = (AMBIENT + emission) * opacity * surface_color(hit); // Ambient and emitted
     light += opacity * split2_ray(next.reflected_pos, next.reflected_ray); // Reflected
     light += (1 - opacity) * split2_ray(next.refracted_pos, next.refracted_ray); // Refracted
     return light;
 }
 vec3 split2_ray(const vec3 from, const vec3 ray) {
-    const HitReport hit = cast_ray(from, ray);
+    const vec3 bent = lensed(from, ray);
+    return split2_hit(cast_ray(from, bent), bent);
+}
+vec3 split2_hit(const HitReport hit, const vec3 ray) {
     if (hit.id == NO_HIT) {
         return background_light(ray);
     }
     const Rays next = ray_tracing_data(hit.normal, ray, hit.id);
     const float opacity = color_w(bodies[hit.id].color);
 
-    vec3 light = AMBIENT * opacity * color_xyz(bodies[hit.id].color); // Ambient
+    vec3 light = (AMBIENT + emission) * opacity * surface_color(hit); // Ambient and emitted
     light += opacity * split1_ray(next.reflected_pos, next.reflected_ray); // Reflected
     light += (1 - opacity) * split1_ray(next.refracted_pos, next.refracted_ray); // Refracted
     return light;
 }
 vec3 split1_ray(const vec3 from, const vec3 ray) {
-    const HitReport hit = cast_ray(from, ray);
+    const vec3 bent = lensed(from, ray);
+    return split1_hit(cast_ray(from, bent), bent);
+}
+vec3 split1_hit(const HitReport hit, const vec3 ray) {
     if (hit.id == NO_HIT) {
         return background_light(ray);
     }
     const Rays next = ray_tracing_data(hit.normal, ray, hit.id);
     const float opacity = color_w(bodies[hit.id].color);
 
-    vec3 light = AMBIENT * opacity * color_xyz(bodies[hit.id].color); // Ambient
+    vec3 light = (AMBIENT + emission) * opacity * surface_color(hit); // Ambient and emitted
     light += opacity * split0_ray(next.reflected_pos, next.reflected_ray); // Reflected
     light += (1 - opacity) * split0_ray(next.refracted_pos, next.refracted_ray); // Refracted
     return light;
@@ -183,18 +370,21 @@ Rays ray_tracing_data(const vec3 normal, const vec3 ray, const uint hit_id) {
 
 // Casts a ray using Blinn-Phong illumination
 vec3 split0_ray(const vec3 from, const vec3 ray) {
-    HitReport hit = cast_ray(from, ray);
+    const vec3 bent = lensed(from, ray);
+    return split0_hit(cast_ray(from, bent), bent);
+}
+vec3 split0_hit(const HitReport hit, const vec3 ray) {
     if (hit.id == NO_HIT) {
         return background_light(ray);
     }
     const vec3 normal = hit.normal;
     const vec3 hit_point = bodies[hit.id].pos + (1 + EPSILON) * bodies[hit.id].radius * normal;
-    const vec3 color = color_xyz(bodies[hit.id].color);
+    const vec3 color = surface_color(hit);
     const float opacity_factor = color_w(bodies[hit.id].color);
     const float opacity = 1.0 - opacity_factor * opacity_factor;
 
-    // Ambient
-    vec3 light = AMBIENT * opacity * color;
+    // Ambient and emitted
+    vec3 light = (AMBIENT + emission) * opacity * color;
     if (cast_ray(hit_point, sun_direction).id == NO_HIT) {
         const float alignment = dot(normal, normalize(sun_direction - ray));
         // Diffuse
@@ -280,6 +470,22 @@ HitReport cast_ray(const vec3 from, const vec3 ray) {
     return HitReport(normalize(hit_pos - bodies[first_hit_target].pos), first_hit_target);
 }
 
+// Looks up the body hit by a camera ray in the rasterized G-buffer instead of traversing the
+// tree. The hit is then refined against the exact sphere, falling back to a full traversal near
+// silhouettes where the mesh and the sphere disagree.
+HitReport primary_hit(const vec3 ray) {
+    const float stored = texelFetch(sampler2D(gbuffer_texture, skybox_sampler), ivec2(gl_FragCoord.xy), 0).x;
+    const int id = int(stored + 0.5) - 1;
+    if (id == NO_HIT) {
+        return HitReport(vec3(0), NO_HIT);
+    }
+    const float time = hit_time(vec3(0), ray, uint(id));
+    if (time <= 0) {
+        return cast_ray(vec3(0), ray);
+    }
+    return HitReport(normalize(ray * time - bodies[id].pos), id);
+}
+
 // When will the ray from [from] along [ray] intersect body [body]?
 float hit_time(const vec3 from, const vec3 ray, const uint body) {
     /* Solve system for t:
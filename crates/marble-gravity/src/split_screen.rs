@@ -0,0 +1,78 @@
+//! Input for the second player of a split screen. Winit has no gamepad support, so the second
+//! camera flies with the numeric keypad instead: 8, 4, 5 and 6 move, 0 and the decimal key rise
+//! and sink, 7 and 9 roll, and 1, 3, minus and plus look around.
+
+use camera::{Camera, FlightKey};
+use instant::Instant;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+
+/// Look rate of held keys, in mouse movement per second
+const LOOK_RATE: f64 = 800.0;
+
+pub struct SecondPlayer {
+    pub camera: Camera,
+    /// Held look keys, as -1, 0 or 1 per axis
+    yaw: f64,
+    pitch: f64,
+    latest_update: Instant,
+}
+impl SecondPlayer {
+    pub fn new() -> Self {
+        Self {
+            camera: Camera::new(),
+            yaw: 0.0,
+            pitch: 0.0,
+            latest_update: Instant::now(),
+        }
+    }
+    /// Routes a key to the second camera, returning whether it was one of its keys.
+    pub fn key_input(&mut self, key: KeyboardInput, slow_mode: bool) -> bool {
+        use VirtualKeyCode::{
+            Numpad0, Numpad1, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+            NumpadAdd, NumpadDecimal, NumpadSubtract,
+        };
+        let Some(keycode) = key.virtual_keycode else {
+            return false;
+        };
+        let held = if key.state == ElementState::Pressed {
+            1.0
+        } else {
+            0.0
+        };
+        let flight_key = match keycode {
+            Numpad8 => FlightKey::Forwards,
+            Numpad5 => FlightKey::Backwards,
+            Numpad4 => FlightKey::Left,
+            Numpad6 => FlightKey::Right,
+            Numpad7 => FlightKey::RollLeft,
+            Numpad9 => FlightKey::RollRight,
+            Numpad0 => FlightKey::Up,
+            NumpadDecimal => FlightKey::Down,
+            Numpad1 | Numpad3 => {
+                self.yaw = if keycode == Numpad1 { -held } else { held };
+                return true;
+            }
+            NumpadSubtract | NumpadAdd => {
+                self.pitch = if keycode == NumpadSubtract {
+                    -held
+                } else {
+                    held
+                };
+                return true;
+            }
+            _ => return false,
+        };
+        self.camera
+            .flight_input(flight_key, key.state == ElementState::Pressed, slow_mode);
+        true
+    }
+    /// Turns the camera by the held look keys.
+    pub fn update(&mut self, now: Instant) {
+        let dt = now.duration_since(self.latest_update).as_secs_f64();
+        self.latest_update = now;
+        if self.yaw != 0.0 || self.pitch != 0.0 {
+            self.camera
+                .mouse_input(LOOK_RATE * dt * self.yaw, LOOK_RATE * dt * self.pitch);
+        }
+    }
+}
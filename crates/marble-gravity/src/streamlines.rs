@@ -0,0 +1,115 @@
+//! Streamlines of the gravitational field, showing where test masses released at rest would
+//! start to flow. They are traced anew every frame from seeds on a grid across the view, or
+//! around the cursor, and fade along their length.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::{prelude::*, Vector3};
+use physics::{gravitational_field, Physics};
+
+/// Seeds lie this far in front of the camera
+const SEED_DISTANCE: f32 = 2.0;
+const GRID_SIZE: (u32, u32) = (10, 6);
+/// Seeds around the cursor lie on a ring of this radius, in world units
+const CURSOR_RING_RADIUS: f32 = 0.15;
+const CURSOR_SEEDS: usize = 12;
+/// Arc length per integration step, and steps per streamline
+const STEP_LENGTH: f32 = 0.04;
+const STEPS: usize = 40;
+const STREAMLINE_COLOR: [f32; 3] = [0.4, 0.9, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seeding {
+    Off,
+    Grid,
+    Cursor,
+}
+impl Seeding {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Grid,
+            Self::Grid => Self::Cursor,
+            Self::Cursor => Self::Off,
+        }
+    }
+}
+
+pub struct Streamlines {
+    pub seeding: Seeding,
+    seeds: Vec<Vector3<f32>>,
+}
+impl Streamlines {
+    pub fn new() -> Self {
+        Self {
+            seeding: Seeding::Off,
+            seeds: Vec::new(),
+        }
+    }
+}
+impl Subscriber for Streamlines {
+    /// Traces streamlines through the current bodies and draws them as overlay lines.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let (physics, pose, cursor) = (frame.physics, frame.pose, frame.cursor);
+        let to_world =
+            |view_pos: Vector3<f32>| pose.position + pose.rotation.rotate_vector(view_pos);
+        self.seeds.clear();
+        match self.seeding {
+            Seeding::Off => return,
+            Seeding::Grid => {
+                let (width, height) = graphics.window_size();
+                let (columns, rows) = GRID_SIZE;
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let pixel = (
+                            (column as f32 + 0.5) / columns as f32 * width as f32,
+                            (row as f32 + 0.5) / rows as f32 * height as f32,
+                        );
+                        self.seeds
+                            .push(to_world(SEED_DISTANCE * graphics.unproject(pixel)));
+                    }
+                }
+            }
+            Seeding::Cursor => {
+                let center = SEED_DISTANCE * graphics.unproject(cursor);
+                for i in 0..CURSOR_SEEDS {
+                    let angle = std::f32::consts::TAU * i as f32 / CURSOR_SEEDS as f32;
+                    let offset = Vector3::new(angle.cos(), angle.sin(), 0.0);
+                    self.seeds
+                        .push(to_world(center + CURSOR_RING_RADIUS * offset));
+                }
+            }
+        }
+        let world_to_camera = pose.world_to_camera();
+        let project = |graphics: &Graphics, pos: Vector3<f32>| {
+            graphics.project((world_to_camera * pos.extend(1.0)).truncate())
+        };
+        for &seed in &self.seeds {
+            let mut pos = seed;
+            for step in 0..STEPS {
+                let Some(next) = trace_step(physics, pos) else {
+                    break;
+                };
+                if let (Some(from), Some(to)) = (project(graphics, pos), project(graphics, next)) {
+                    let [r, g, b] = STREAMLINE_COLOR;
+                    let alpha = 1.0 - step as f32 / STEPS as f32;
+                    graphics.overlay_segment(from, to, [r, g, b, alpha]);
+                }
+                pos = next;
+            }
+        }
+    }
+}
+
+/// One midpoint step along the field direction, or `None` inside a body or where the field
+/// vanishes.
+fn trace_step(physics: &Physics, pos: Vector3<f32>) -> Option<Vector3<f32>> {
+    let direction = |pos| {
+        gravitational_field(physics.bodies(), pos, physics.config())
+            .filter(|field| field.magnitude2() > 0.0)
+            .map(InnerSpace::normalize)
+    };
+    let midpoint = pos + 0.5 * STEP_LENGTH * direction(pos)?;
+    Some(pos + STEP_LENGTH * direction(midpoint)?)
+}
@@ -0,0 +1,183 @@
+//! `--sweep <parameter>`, which steps one of `bodies`, `ray-splits` or `render-scale` across its
+//! range instead of starting the event loop, measuring physics and rendering at each value, and
+//! prints a table of which values this machine sustains at 60 frames per second in real time.
+//! Render times include reading the image back, so they overestimate those of the event loop.
+//! Not supported on the web.
+
+use crate::graphics::{Graphics, View, MAX_RAY_SPLITS};
+use camera::Pose;
+use instant::Instant;
+use physics::{Physics, BODIES};
+use spheretree::SphereTreeBuilder;
+use std::{fmt::Write, time::Duration};
+
+/// Physics ticks measured per value, after as many ticks of warm-up
+const PHYSICS_TICKS: u64 = 200;
+/// Frames rendered per value, after one frame of warm-up
+const FRAMES: u32 = 10;
+/// The longest frame sustaining 60 frames per second
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parameter {
+    Bodies,
+    RaySplits,
+    RenderScale,
+}
+impl Parameter {
+    const ALL: [Self; 3] = [Self::Bodies, Self::RaySplits, Self::RenderScale];
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bodies => "bodies",
+            Self::RaySplits => "ray-splits",
+            Self::RenderScale => "render-scale",
+        }
+    }
+    /// The values swept, in increasing cost
+    fn values(self) -> Vec<f32> {
+        match self {
+            Self::Bodies => [16, 32, 64, 128, BODIES]
+                .into_iter()
+                .map(|bodies| bodies as f32)
+                .collect(),
+            Self::RaySplits => (0..=MAX_RAY_SPLITS).map(|splits| splits as f32).collect(),
+            Self::RenderScale => vec![0.25, 0.5, 0.75, 1.0, 1.5, 2.0],
+        }
+    }
+}
+
+/// The parameter requested on the command line, if any.
+pub fn requested() -> Option<Parameter> {
+    let mut args = std::env::args().skip(1);
+    let name = loop {
+        let arg = args.next()?;
+        if arg == "--sweep" {
+            break args.next().unwrap_or_default();
+        }
+        if let Some(name) = arg.strip_prefix("--sweep=") {
+            break name.to_owned();
+        }
+    };
+    let parameter = Parameter::ALL
+        .into_iter()
+        .find(|parameter| parameter.name() == name);
+    if parameter.is_none() {
+        log::error!("Cannot sweep {name:?}, expected bodies, ray-splits or render-scale");
+    }
+    parameter
+}
+
+struct Measurement {
+    value: f32,
+    /// The real time of a tick, which must be below its simulated time to keep up
+    tick: Duration,
+    delta_time: Duration,
+    frame: Duration,
+    worst_frame: Duration,
+}
+impl Measurement {
+    fn sustainable(&self) -> bool {
+        self.tick <= self.delta_time && self.frame <= FRAME_BUDGET
+    }
+}
+
+/// Measures every value of `parameter` and prints the table to stdout.
+pub fn run(parameter: Parameter, graphics: &mut Graphics) {
+    let initial_settings = graphics.settings();
+    let window_size = graphics.window_size();
+    let mut sphere_tree = SphereTreeBuilder::new();
+    let pose = Pose::default();
+    let mut measurements = Vec::new();
+    for value in parameter.values() {
+        let mut physics = Physics::initial();
+        let mut size = window_size;
+        match parameter {
+            Parameter::Bodies => physics.bodies_mut().truncate(value as usize),
+            Parameter::RaySplits => graphics.set_ray_splits(value as u32),
+            Parameter::RenderScale => {
+                size = (
+                    (window_size.0 as f32 * value).round().max(1.0) as u32,
+                    (window_size.1 as f32 * value).round().max(1.0) as u32,
+                );
+            }
+        }
+        physics.advance_ticks(PHYSICS_TICKS);
+        let ticks = physics.advance_ticks(PHYSICS_TICKS);
+        let view = View {
+            bodies: sphere_tree.build(physics.bodies(), &physics.config().groups, &pose),
+            pose,
+        };
+        graphics.capture(&view, size);
+        let mut frames = Vec::new();
+        for _ in 0..FRAMES {
+            let before = Instant::now();
+            graphics.capture(&view, size);
+            frames.push(Instant::now().duration_since(before));
+        }
+        let measurement = Measurement {
+            value,
+            tick: ticks.elapsed_real / PHYSICS_TICKS as u32,
+            delta_time: physics.delta_time(),
+            frame: frames.iter().sum::<Duration>() / FRAMES,
+            worst_frame: frames.into_iter().max().unwrap_or_default(),
+        };
+        log::info!(
+            "Swept {} {value}: {:.2}ms per frame",
+            parameter.name(),
+            measurement.frame.as_secs_f64() * 1000.0
+        );
+        measurements.push(measurement);
+    }
+    graphics.apply_settings(initial_settings);
+
+    let mut report = String::new();
+    let out = &mut report;
+    writeln!(
+        out,
+        "Sweep of {} at {}x{}, including readback:",
+        parameter.name(),
+        window_size.0,
+        window_size.1
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "{:>12}  {:>9}  {:>9}  {:>11}  {:>11}  sustainable",
+        parameter.name(),
+        "frame",
+        "worst",
+        "ticks/s",
+        "real time"
+    )
+    .unwrap();
+    let ms = |duration: Duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0);
+    for measurement in &measurements {
+        writeln!(
+            out,
+            "{:>12}  {:>9}  {:>9}  {:>11.0}  {:>10.1}x  {}",
+            measurement.value,
+            ms(measurement.frame),
+            ms(measurement.worst_frame),
+            1.0 / measurement.tick.as_secs_f64(),
+            measurement.delta_time.as_secs_f64() / measurement.tick.as_secs_f64(),
+            if measurement.sustainable() {
+                "yes"
+            } else {
+                "no"
+            }
+        )
+        .unwrap();
+    }
+    // Values are in increasing cost, so the last sustainable one is the best this machine runs
+    match measurements.iter().rev().find(|m| m.sustainable()) {
+        Some(best) => writeln!(
+            out,
+            "Highest sustainable {}: {}",
+            parameter.name(),
+            best.value
+        ),
+        None => writeln!(out, "No sustainable {}", parameter.name()),
+    }
+    .unwrap();
+    print!("{report}");
+}
@@ -0,0 +1,645 @@
+//! Multi-channel signed distance field (MSDF) text, replacing `wgpu_glyph` when the `msdf-text`
+//! feature is enabled. Every glyph of a font is turned into a distance field once, from which text
+//! is drawn at any scale without re-rasterization, in any color and with an outline.
+
+use cgmath::{prelude::*, Vector2};
+use std::mem;
+use wgpu::util::DeviceExt;
+use wgpu_glyph::ab_glyph::{Font, FontArc, GlyphId, OutlineCurve};
+
+/// Atlas texels per unit of text scale, see [`Text::scale`]
+const TEXELS_PER_SCALE: f32 = 32.0;
+/// The distance in atlas texels covered by the encoded distances, half of it on either side of
+/// an edge
+const DISTANCE_RANGE: f32 = 4.0;
+const ATLAS_WIDTH: u32 = 512;
+/// Segments each curve is flattened into
+const CURVE_SEGMENTS: usize = 8;
+/// The sine of the smallest change of direction between edges that counts as a corner
+const CORNER_SINE: f32 = 0.14;
+
+const RED: u8 = 0b001;
+const GREEN: u8 = 0b010;
+const BLUE: u8 = 0b100;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// A run of text, laid out from its top left corner.
+pub struct Text<'a> {
+    pub text: &'a str,
+    pub position: (f32, f32),
+    /// The height in pixels from the highest ascender to the lowest descender, like
+    /// `wgpu_glyph::Text::scale`
+    pub scale: f32,
+    pub color: [f32; 4],
+    /// Color and width in pixels of an outline, for readability over bright backgrounds
+    pub outline: ([f32; 4], f32),
+}
+
+/// Where a glyph is in the atlas, with lengths in units of text scale relative to the pen
+/// position on the baseline, y pointing down.
+#[derive(Clone, Copy)]
+struct GlyphInfo {
+    atlas_min: Vector2<f32>,
+    atlas_max: Vector2<f32>,
+    offset: Vector2<f32>,
+    size: Vector2<f32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GlyphInstance {
+    /// Corners in normalized device coordinates
+    rect: [f32; 4],
+    /// Corners in texture coordinates
+    uv: [f32; 4],
+    color: [f32; 4],
+    outline_color: [f32; 4],
+    /// The encoded distance range in pixels at this size, and the outline width in pixels
+    range_and_outline: [f32; 2],
+}
+unsafe impl bytemuck::Pod for GlyphInstance {}
+unsafe impl bytemuck::Zeroable for GlyphInstance {}
+
+pub struct TextRenderer {
+    font: FontArc,
+    /// Font units per unit of text scale
+    units_per_scale: f32,
+    glyphs: Vec<(GlyphId, Option<GlyphInfo>)>,
+    atlas_size: Vector2<f32>,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    instances: Vec<GlyphInstance>,
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+}
+impl TextRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        font: FontArc,
+        instance_capacity: usize,
+    ) -> Self {
+        let units_per_scale = font.height_unscaled();
+        let (glyphs, atlas_size, atlas) = build_atlas(&font, units_per_scale);
+        let atlas_view = device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("MSDF atlas"),
+                    size: wgpu::Extent3d {
+                        width: atlas_size.x,
+                        height: atlas_size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                },
+                bytemuck::cast_slice(&atlas),
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let module = device
+            .create_shader_module(wgpu::include_wgsl!(concat!(env!("OUT_DIR"), "/text.wgsl")));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("text"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("text"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<GlyphInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x4,
+                        1 => Float32x4,
+                        2 => Float32x4,
+                        3 => Float32x4,
+                        4 => Float32x2,
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            font,
+            units_per_scale,
+            glyphs,
+            atlas_size: atlas_size.cast().unwrap(),
+            pipeline,
+            bind_group,
+            instances: Vec::with_capacity(instance_capacity),
+            instance_buffer: make_instance_buffer(device, instance_capacity),
+            instance_capacity,
+        }
+    }
+
+    /// Lays out text to be drawn by the next [`TextRenderer::draw`]. Newlines start a new line.
+    pub fn queue(&mut self, text: &Text<'_>, (width, height): (u32, u32)) {
+        let to_ndc = |p: Vector2<f32>| {
+            [
+                2.0 * p.x / width as f32 - 1.0,
+                1.0 - 2.0 * p.y / height as f32,
+            ]
+        };
+        let font = &self.font;
+        let px_per_unit = text.scale / self.units_per_scale;
+        let line_height = (font.height_unscaled() + font.line_gap_unscaled()) * px_per_unit;
+        let range = DISTANCE_RANGE * text.scale / TEXELS_PER_SCALE;
+
+        let origin = Vector2::from(text.position);
+        let mut pen = origin + Vector2::unit_y() * font.ascent_unscaled() * px_per_unit;
+        let mut previous: Option<GlyphId> = None;
+        for c in text.text.chars() {
+            if c == '\n' {
+                pen = Vector2::new(origin.x, pen.y + line_height);
+                previous = None;
+                continue;
+            }
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                pen.x += font.kern_unscaled(previous, id) * px_per_unit;
+            }
+            previous = Some(id);
+            if let Some(info) = self.info(id) {
+                let min = pen + info.offset * text.scale;
+                let max = min + info.size * text.scale;
+                let [left, top] = to_ndc(min);
+                let [right, bottom] = to_ndc(max);
+                let uv_min = info.atlas_min.div_element_wise(self.atlas_size);
+                let uv_max = info.atlas_max.div_element_wise(self.atlas_size);
+                self.instances.push(GlyphInstance {
+                    rect: [left, top, right, bottom],
+                    uv: [uv_min.x, uv_min.y, uv_max.x, uv_max.y],
+                    color: text.color,
+                    outline_color: text.outline.0,
+                    range_and_outline: [range, text.outline.1],
+                });
+            }
+            pen.x += font.h_advance_unscaled(id) * px_per_unit;
+        }
+    }
+
+    /// The width and height in pixels that [`TextRenderer::queue`] would lay `text` out in.
+    pub fn measure(&self, text: &str, scale: f32) -> (f32, f32) {
+        let font = &self.font;
+        let px_per_unit = scale / self.units_per_scale;
+        let line_height = (font.height_unscaled() + font.line_gap_unscaled()) * px_per_unit;
+        let mut lines = 0;
+        let mut width: f32 = 0.0;
+        for line in text.lines() {
+            let mut advance = 0.0;
+            let mut previous: Option<GlyphId> = None;
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                if let Some(previous) = previous {
+                    advance += font.kern_unscaled(previous, id);
+                }
+                previous = Some(id);
+                advance += font.h_advance_unscaled(id);
+            }
+            width = width.max(advance * px_per_unit);
+            lines += 1;
+        }
+        if lines == 0 {
+            return (0.0, 0.0);
+        }
+        let height = (lines - 1) as f32 * line_height + font.height_unscaled() * px_per_unit;
+        (width, height)
+    }
+
+    /// Draws all queued text on top of `target`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = make_instance_buffer(device, self.instance_capacity);
+        }
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("text"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..4, 0..self.instances.len() as u32);
+        drop(pass);
+        self.instances.clear();
+    }
+
+    fn info(&self, id: GlyphId) -> Option<GlyphInfo> {
+        let index = self.glyphs.binary_search_by_key(&id, |&(id, _)| id).ok()?;
+        self.glyphs[index].1
+    }
+}
+
+fn make_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Glyph instance buffer"),
+        contents: &vec![0; capacity.max(1) * mem::size_of::<GlyphInstance>()],
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// Generates distance fields for every glyph in the font, packed into rows of an RGBA atlas.
+/// Glyphs are sorted by id, and those without an outline (like spaces) have no info.
+#[allow(clippy::type_complexity)]
+fn build_atlas(
+    font: &FontArc,
+    units_per_scale: f32,
+) -> (
+    Vec<(GlyphId, Option<GlyphInfo>)>,
+    Vector2<u32>,
+    Vec<[u8; 4]>,
+) {
+    let texels_per_unit = TEXELS_PER_SCALE / units_per_scale;
+    let padding = DISTANCE_RANGE / 2.0 + 1.0;
+
+    let mut ids: Vec<GlyphId> = font.codepoint_ids().map(|(id, _)| id).collect();
+    ids.sort_by_key(|id| id.0);
+    ids.dedup();
+
+    let mut fields: Vec<(GlyphId, Option<(GlyphInfo, Vector2<u32>, Vec<[u8; 4]>)>)> = Vec::new();
+    for id in ids {
+        let Some(outline) = font.outline(id) else {
+            fields.push((id, None));
+            continue;
+        };
+        // Glyph space has y down and its origin at the top left texel
+        let min = Vector2::new(outline.bounds.min.x, -outline.bounds.min.y) * texels_per_unit
+            - Vector2::new(padding, padding);
+        let max = Vector2::new(outline.bounds.max.x, -outline.bounds.max.y) * texels_per_unit
+            + Vector2::new(padding, padding);
+        let size = Vector2::new((max.x - min.x).ceil() as u32, (max.y - min.y).ceil() as u32);
+        let to_glyph_space = |x: f32, y: f32| Vector2::new(x, -y) * texels_per_unit - min;
+        let shape = Shape::new(&outline.curves, to_glyph_space);
+        let info = GlyphInfo {
+            atlas_min: Vector2::zero(),
+            atlas_max: size.cast().unwrap(),
+            offset: min / TEXELS_PER_SCALE,
+            size: size.cast().unwrap() / TEXELS_PER_SCALE,
+        };
+        fields.push((id, Some((info, size, shape.distance_field(size)))));
+    }
+
+    // Shelf packing, in order of id
+    let mut cursor = Vector2::new(0, 0);
+    let mut row_height = 0;
+    let mut placed = Vec::with_capacity(fields.len());
+    for (_, field) in &fields {
+        placed.push(field.as_ref().map(|(_, size, _)| {
+            if cursor.x + size.x > ATLAS_WIDTH {
+                cursor = Vector2::new(0, cursor.y + row_height);
+                row_height = 0;
+            }
+            let at = cursor;
+            cursor.x += size.x;
+            row_height = row_height.max(size.y);
+            at
+        }));
+    }
+    let atlas_size = Vector2::new(ATLAS_WIDTH, (cursor.y + row_height).max(1));
+    let mut atlas = vec![[0; 4]; (atlas_size.x * atlas_size.y) as usize];
+    let mut glyphs = Vec::with_capacity(fields.len());
+    for ((id, field), at) in fields.into_iter().zip(placed) {
+        let info = field.zip(at).map(|((mut info, size, texels), at)| {
+            for y in 0..size.y {
+                let row = ((at.y + y) * atlas_size.x + at.x) as usize;
+                atlas[row..row + size.x as usize]
+                    .copy_from_slice(&texels[(y * size.x) as usize..((y + 1) * size.x) as usize]);
+            }
+            info.atlas_min = at.cast().unwrap();
+            info.atlas_max += info.atlas_min;
+            info
+        });
+        glyphs.push((id, info));
+    }
+    (glyphs, atlas_size, atlas)
+}
+
+/// A curve of the outline, flattened into a polyline
+struct Edge {
+    points: Vec<Vector2<f32>>,
+    color: u8,
+}
+
+/// Where a point is relative to an edge
+struct EdgeDistance {
+    /// Distance to the closest point on the edge
+    distance: f32,
+    /// How far the point is from the closest point along the edge direction, which breaks ties
+    /// at shared corners
+    orthogonality: f32,
+    /// Distance to the edge extended along its end tangents, positive to the left of the edge
+    pseudo: f32,
+}
+
+struct Shape {
+    contours: Vec<Vec<Edge>>,
+    /// Flips distances so that they are positive inside, whichever way the contours wind
+    orientation: f32,
+}
+impl Shape {
+    fn new(curves: &[OutlineCurve], to_glyph_space: impl Fn(f32, f32) -> Vector2<f32>) -> Self {
+        let mut contours: Vec<Vec<Edge>> = Vec::new();
+        let mut last_end: Option<Vector2<f32>> = None;
+        for curve in curves {
+            let p = |point: &wgpu_glyph::ab_glyph::Point| to_glyph_space(point.x, point.y);
+            let points: Vec<Vector2<f32>> = match curve {
+                OutlineCurve::Line(a, b) => vec![p(a), p(b)],
+                OutlineCurve::Quad(a, b, c) => {
+                    let (a, b, c) = (p(a), p(b), p(c));
+                    (0..=CURVE_SEGMENTS)
+                        .map(|i| {
+                            let t = i as f32 / CURVE_SEGMENTS as f32;
+                            a * (1.0 - t).powi(2) + b * 2.0 * t * (1.0 - t) + c * t.powi(2)
+                        })
+                        .collect()
+                }
+                OutlineCurve::Cubic(a, b, c, d) => {
+                    let (a, b, c, d) = (p(a), p(b), p(c), p(d));
+                    (0..=CURVE_SEGMENTS)
+                        .map(|i| {
+                            let t = i as f32 / CURVE_SEGMENTS as f32;
+                            a * (1.0 - t).powi(3)
+                                + b * 3.0 * t * (1.0 - t).powi(2)
+                                + c * 3.0 * t.powi(2) * (1.0 - t)
+                                + d * t.powi(3)
+                        })
+                        .collect()
+                }
+            };
+            let start = points[0];
+            let end = points[points.len() - 1];
+            if (start - end).magnitude2() < 1e-12 {
+                continue;
+            }
+            if last_end.map_or(true, |last| (last - start).magnitude2() > 1e-6) {
+                contours.push(Vec::new());
+            }
+            last_end = Some(end);
+            contours.last_mut().unwrap().push(Edge {
+                points,
+                color: WHITE,
+            });
+        }
+        for contour in &mut contours {
+            color_edges(contour);
+        }
+        // Shoelace formula, where a positive area means that the inside is to the left of edges
+        let area: f32 = contours
+            .iter()
+            .flatten()
+            .flat_map(|edge| edge.points.windows(2))
+            .map(|pair| pair[0].perp_dot(pair[1]))
+            .sum();
+        Self {
+            contours,
+            orientation: if area > 0.0 { 1.0 } else { -1.0 },
+        }
+    }
+
+    fn distance_field(&self, size: Vector2<u32>) -> Vec<[u8; 4]> {
+        let encode = |distance: f32| {
+            ((0.5 + distance / DISTANCE_RANGE).clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        let mut texels = Vec::with_capacity((size.x * size.y) as usize);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let point = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let mut channels = [0.0; 3];
+                for (channel, distance) in channels.iter_mut().enumerate() {
+                    *distance = self.channel_distance(point, 1 << channel);
+                }
+                // Where the channels disagree with the actual inside, which happens where
+                // differently colored edges meet, fall back to a plain distance field
+                let inside = self.winding(point) != 0;
+                if (median(channels) > 0.0) != inside {
+                    let distance = self.channel_distance(point, WHITE).abs();
+                    channels = [if inside { distance } else { -distance }; 3];
+                }
+                texels.push([
+                    encode(channels[0]),
+                    encode(channels[1]),
+                    encode(channels[2]),
+                    255,
+                ]);
+            }
+        }
+        texels
+    }
+
+    /// The signed pseudo-distance to the closest edge with any of the given color bits
+    fn channel_distance(&self, point: Vector2<f32>, color: u8) -> f32 {
+        let closest = self
+            .contours
+            .iter()
+            .flatten()
+            .filter(|edge| edge.color & color != 0)
+            .map(|edge| edge_distance(&edge.points, point))
+            .min_by(|a, b| {
+                (a.distance, a.orthogonality)
+                    .partial_cmp(&(b.distance, b.orthogonality))
+                    .unwrap()
+            });
+        closest.map_or(-DISTANCE_RANGE, |closest| self.orientation * closest.pseudo)
+    }
+
+    /// Nonzero winding number of the outline around a point
+    fn winding(&self, point: Vector2<f32>) -> i32 {
+        let mut winding = 0;
+        for pair in self
+            .contours
+            .iter()
+            .flatten()
+            .flat_map(|edge| edge.points.windows(2))
+        {
+            let (a, b) = (pair[0], pair[1]);
+            let side = (b - a).perp_dot(point - a);
+            if a.y <= point.y && b.y > point.y && side > 0.0 {
+                winding += 1;
+            } else if a.y > point.y && b.y <= point.y && side < 0.0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+}
+
+fn edge_distance(points: &[Vector2<f32>], point: Vector2<f32>) -> EdgeDistance {
+    let segments = points.len() - 1;
+    let mut best = (f32::INFINITY, 0, 0.0);
+    for (index, pair) in points.windows(2).enumerate() {
+        let (a, b) = (pair[0], pair[1]);
+        let t = ((point - a).dot(b - a) / (b - a).magnitude2()).clamp(0.0, 1.0);
+        let distance = (a + (b - a) * t - point).magnitude();
+        if distance < best.0 {
+            best = (distance, index, t);
+        }
+    }
+    let (distance, index, t) = best;
+    let (a, b) = (points[index], points[index + 1]);
+    let direction = (b - a).normalize();
+    let to_point = point - a;
+    // Past either end of the edge, use the distance to its tangent line instead
+    let unclamped = to_point.dot(b - a) / (b - a).magnitude2();
+    let beyond_start = index == 0 && unclamped < 0.0;
+    let beyond_end = index == segments - 1 && unclamped > 1.0;
+    let signed = direction.perp_dot(to_point);
+    let pseudo = if beyond_start || beyond_end {
+        signed
+    } else {
+        signed.signum() * distance
+    };
+    let closest = a + (b - a) * t;
+    let orthogonality = if distance > 0.0 {
+        1.0 - direction.perp_dot((point - closest) / distance).abs()
+    } else {
+        0.0
+    };
+    EdgeDistance {
+        distance,
+        orthogonality,
+        pseudo,
+    }
+}
+
+/// Assigns channels to the edges of a contour so that edges meeting at a corner never share all
+/// channels, which is what keeps corners sharp. Smooth contours use all channels.
+fn color_edges(contour: &mut [Edge]) {
+    let direction_at = |points: &[Vector2<f32>], end: bool| {
+        let n = points.len();
+        let v = if end {
+            points[n - 1] - points[n - 2]
+        } else {
+            points[1] - points[0]
+        };
+        v.normalize()
+    };
+    let corners: Vec<usize> = (0..contour.len())
+        .filter(|&i| {
+            let previous = &contour[(i + contour.len() - 1) % contour.len()];
+            let a = direction_at(&previous.points, true);
+            let b = direction_at(&contour[i].points, false);
+            a.dot(b) <= 0.0 || a.perp_dot(b).abs() > CORNER_SINE
+        })
+        .collect();
+    match corners.len() {
+        0 => {}
+        1 => {
+            // A teardrop, whose single corner is split by giving its edges three colors
+            let colors = [RED | GREEN, WHITE, GREEN | BLUE];
+            let start = corners[0];
+            let n = contour.len();
+            for k in 0..n {
+                contour[(start + k) % n].color = colors[(3 * k / n).min(2)];
+            }
+        }
+        _ => {
+            let mut color = RED | GREEN;
+            let start = corners[0];
+            let n = contour.len();
+            for k in 0..n {
+                let index = (start + k) % n;
+                if k > 0 && corners.contains(&index) {
+                    color = match color {
+                        c if c == RED | GREEN => GREEN | BLUE,
+                        _ => RED | GREEN,
+                    };
+                    // Give the last spline a third color if it would meet the first one
+                    if corners.last() == Some(&index) && color == RED | GREEN {
+                        color = RED | BLUE;
+                    }
+                }
+                contour[index].color = color;
+            }
+        }
+    }
+}
+
+fn median([a, b, c]: [f32; 3]) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
@@ -0,0 +1,50 @@
+// MSDF text, see text.rs
+
+@group(0) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var atlas_sampler: sampler;
+
+struct GlyphOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) outline_color: vec4<f32>,
+    @location(3) range_and_outline: vec2<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) uv: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) outline_color: vec4<f32>,
+    @location(4) range_and_outline: vec2<f32>,
+) -> GlyphOutput {
+    // A triangle strip covering the glyph quad
+    let corner = vec2<f32>(f32(index / 2u), f32(index % 2u));
+    var out: GlyphOutput;
+    out.position = vec4<f32>(mix(rect.xy, rect.zw, corner), 0.0, 1.0);
+    out.uv = mix(uv.xy, uv.zw, corner);
+    out.color = color;
+    out.outline_color = outline_color;
+    out.range_and_outline = range_and_outline;
+    return out;
+}
+
+fn median(v: vec3<f32>) -> f32 {
+    return max(min(v.r, v.g), min(max(v.r, v.g), v.b));
+}
+
+@fragment
+fn fs_main(in: GlyphOutput) -> @location(0) vec4<f32> {
+    let field = textureSample(atlas_texture, atlas_sampler, in.uv).rgb;
+    // Signed distance to the glyph edge in pixels, positive inside
+    let distance = (median(field) - 0.5) * in.range_and_outline.x;
+    let fill = clamp(distance + 0.5, 0.0, 1.0);
+    let outline = clamp(distance + in.range_and_outline.y + 0.5, 0.0, 1.0);
+    let color = mix(in.outline_color.rgb, in.color.rgb, fill);
+    let alpha = mix(in.outline_color.a * outline, in.color.a, fill);
+    return vec4<f32>(color, alpha);
+}
@@ -0,0 +1,150 @@
+//! Time-lapses of slow structural evolution: physics runs as fast as it can instead of in real
+//! time, and a frame is saved every [`FRAME_INTERVAL`] of simulated time. Frames are saved as
+//! numbered PNG files, which is not supported on the web.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::{Graphics, View},
+};
+use instant::Instant;
+use physics::Physics;
+use std::time::Duration;
+
+/// Simulated time between saved frames
+const FRAME_INTERVAL: Duration = Duration::from_secs(10);
+const FRAME_COUNT: u32 = 300;
+/// Real time spent simulating per event loop iteration, so that the window stays responsive
+#[cfg(not(target_arch = "wasm32"))]
+const STEP_BUDGET: Duration = Duration::from_millis(50);
+/// Ticks simulated between checks of the budget
+#[cfg(not(target_arch = "wasm32"))]
+const STEP_CHUNK: u64 = 16;
+
+struct Run {
+    directory: String,
+    frames_saved: u32,
+    next_frame_time: Duration,
+    started: Instant,
+}
+
+pub struct TimeLapse {
+    run: Option<Run>,
+}
+impl TimeLapse {
+    pub fn new() -> Self {
+        Self { run: None }
+    }
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+    pub fn toggle(&mut self, physics: &Physics) {
+        if let Some(run) = self.run.take() {
+            log::info!(
+                "Stopped time-lapse after {} frames in {}",
+                run.frames_saved,
+                run.directory
+            );
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            let directory = format!("timelapse-{seconds}");
+            if let Err(err) = std::fs::create_dir_all(&directory) {
+                log::error!("Failed to create {directory}: {err}");
+                return;
+            }
+            log::info!("Started a time-lapse of {FRAME_COUNT} frames in {directory}");
+            self.run = Some(Run {
+                directory,
+                frames_saved: 0,
+                next_frame_time: physics.simulated_time(),
+                started: Instant::now(),
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = physics;
+            log::warn!("Time-lapses cannot be saved on the web");
+        }
+    }
+    /// Simulates until the next frame is due or the step budget is spent, and returns whether
+    /// a frame is due. Must only be called while no worker is advancing `physics`.
+    pub fn advance(&mut self, physics: &mut Physics) -> bool {
+        let Some(run) = &self.run else {
+            return false;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let started = Instant::now();
+            while let Some(remaining) = run
+                .next_frame_time
+                .checked_sub(physics.simulated_time())
+                .filter(|remaining| !remaining.is_zero())
+            {
+                if Instant::now().duration_since(started) > STEP_BUDGET {
+                    return false;
+                }
+                let remaining_ticks = remaining
+                    .as_nanos()
+                    .div_ceil(physics.delta_time().as_nanos());
+                physics.advance_ticks(STEP_CHUNK.min(remaining_ticks as u64));
+            }
+            true
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (run, physics);
+            false
+        }
+    }
+    /// Saves the view as the next frame, ending the time-lapse after the last one.
+    pub fn capture(&mut self, graphics: &mut Graphics, view: &View<'_>) {
+        let Some(run) = &mut self.run else {
+            return;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = format!("{}/frame-{:05}.png", run.directory, run.frames_saved);
+            if let Err(err) = graphics.capture(view, graphics.window_size()).save(&path) {
+                log::error!("Failed to save time-lapse frame to {path}: {err}");
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (graphics, view);
+        run.frames_saved += 1;
+        run.next_frame_time += FRAME_INTERVAL;
+        if run.frames_saved % 10 == 0 || run.frames_saved == FRAME_COUNT {
+            log::info!(
+                "Time-lapse frame {} of {FRAME_COUNT}, after {}s",
+                run.frames_saved,
+                Instant::now().duration_since(run.started).as_secs()
+            );
+        }
+        if run.frames_saved == FRAME_COUNT {
+            log::info!("Time-lapse completed in {}", run.directory);
+            self.run = None;
+        }
+    }
+}
+impl Subscriber for TimeLapse {
+    /// Shows the progress in the overlay.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if let Some(run) = &self.run {
+            let elapsed = Instant::now().duration_since(run.started).as_secs_f32();
+            let remaining = match run.frames_saved {
+                0 => String::new(),
+                saved => format!(
+                    ", about {:.0}s left",
+                    elapsed / saved as f32 * (FRAME_COUNT - saved) as f32
+                ),
+            };
+            graphics.overlay_line(format_args!(
+                "Time-lapse: frame {} of {FRAME_COUNT}{remaining}",
+                run.frames_saved
+            ));
+        }
+    }
+}
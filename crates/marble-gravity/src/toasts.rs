@@ -0,0 +1,75 @@
+//! Short messages confirming what just happened, shown at the bottom of the window for a few
+//! seconds and then faded out.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use instant::Instant;
+use std::{collections::VecDeque, time::Duration};
+
+const SHOWN_FOR: Duration = Duration::from_secs(4);
+const FADE_OUT: Duration = Duration::from_secs(1);
+/// More toasts than this push out the oldest ones
+const MAX_TOASTS: usize = 4;
+/// Distance between the bottom of the window and the newest toast, and between toasts, in pixels
+const SPACING: f32 = 30.0;
+
+struct Toast {
+    text: String,
+    shown: Instant,
+}
+
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+}
+impl Toasts {
+    pub fn new() -> Self {
+        Self {
+            toasts: VecDeque::with_capacity(MAX_TOASTS),
+        }
+    }
+    /// Shows and logs a confirmation.
+    pub fn info(&mut self, text: String) {
+        log::info!("{text}");
+        self.push(text);
+    }
+    /// Shows and logs an error.
+    pub fn error(&mut self, text: String) {
+        log::error!("{text}");
+        self.push(format!("Error: {text}"));
+    }
+    fn push(&mut self, text: String) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            text,
+            shown: Instant::now(),
+        });
+    }
+    /// Whether no toast is visible, so that frames need not keep being rendered.
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+}
+impl Subscriber for Toasts {
+    /// Drops expired toasts and queues the rest as labels, newest at the bottom.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let now = frame.now;
+        self.toasts
+            .retain(|toast| now.duration_since(toast.shown) < SHOWN_FOR + FADE_OUT);
+        let (width, height) = graphics.window_size();
+        for (row, toast) in self.toasts.iter().rev().enumerate() {
+            let fading = now.duration_since(toast.shown).saturating_sub(SHOWN_FOR);
+            graphics.label(
+                (
+                    0.5 * width as f32,
+                    height as f32 - SPACING * (row + 1) as f32,
+                ),
+                1.0 - fading.as_secs_f32() / FADE_OUT.as_secs_f32(),
+                format_args!("{}", toast.text),
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Running the command line tools of the platform, such as clipboard tools, audio players and
+//! recorders, rather than linking to the libraries behind them. Each use lists the tools that
+//! would do, in order of preference, of which the first that works is used.
+
+use std::{
+    io,
+    process::{Child, Command, Stdio},
+};
+
+/// Programs and their arguments, tried in order
+pub type Tools = [(&'static str, &'static [&'static str])];
+
+/// The standard stream of a tool that is piped, the others being null.
+#[derive(Clone, Copy)]
+pub enum Pipe {
+    Stdin,
+    Stdout,
+}
+
+/// Spawns each tool in turn, until `attempt` succeeds with one, returning what it returned.
+/// Tools that fail to spawn are skipped, and those for which `attempt` fails are killed. Fails
+/// with the error of the last attempt, if any tool spawned.
+pub fn run_first<T>(
+    tools: &Tools,
+    pipe: Pipe,
+    mut attempt: impl FnMut(&str, &mut Child) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut error = None;
+    for &(program, args) in tools {
+        let (stdin, stdout) = match pipe {
+            Pipe::Stdin => (Stdio::piped(), Stdio::null()),
+            Pipe::Stdout => (Stdio::null(), Stdio::piped()),
+        };
+        let Ok(mut child) = Command::new(program)
+            .args(args)
+            .stdin(stdin)
+            .stdout(stdout)
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        match attempt(program, &mut child) {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                // It may have exited already
+                let _ = child.kill();
+                let _ = child.wait();
+                error = Some(io::Error::new(err.kind(), format!("{program}: {err}")));
+            }
+        }
+    }
+    Err(error.unwrap_or_else(|| {
+        let programs: Vec<&str> = tools.iter().map(|&(program, _)| program).collect();
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("none ran, expected one of {}", programs.join(", ")),
+        )
+    }))
+}
+
+/// Waits for a tool to exit, failing unless it succeeded.
+pub fn wait_success(child: &mut Child) -> io::Result<()> {
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with {status}")));
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! `--trace-out <file>`, which records how long event loop iterations, physics, sphere tree
+//! builds and GPU submissions take over a run, and writes them on exit as JSON in the trace
+//! event format of `chrome://tracing`, which Perfetto and speedscope also open. Meant to be
+//! attached to reports of stutter. Not supported on the web.
+
+use instant::Instant;
+use std::{
+    cell::Cell,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+/// Spans kept at most, about an hour of a run at 60 frames per second, after which recording
+/// stops rather than growing without bound
+const MAX_SPANS: usize = 1 << 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+static NEXT_THREAD: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    /// The id of the thread in the trace, assigned when it first records a span
+    static THREAD: Cell<u32> = const { Cell::new(0) };
+}
+
+struct Recording {
+    path: String,
+    start: Instant,
+    spans: Vec<Finished>,
+}
+
+struct Finished {
+    name: &'static str,
+    thread: u32,
+    start: Instant,
+    end: Instant,
+    args: Vec<(&'static str, u64)>,
+}
+
+/// The trace file requested on the command line, if any.
+#[cfg(not(target_arch = "wasm32"))]
+fn requested_path() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--trace-out" {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix("--trace-out=") {
+            return Some(path.to_owned());
+        }
+    }
+    None
+}
+#[cfg(target_arch = "wasm32")]
+fn requested_path() -> Option<String> {
+    None
+}
+
+/// Starts recording if requested on the command line.
+pub fn init() {
+    let Some(path) = requested_path() else {
+        return;
+    };
+    log::info!("Recording a trace to {path}");
+    *RECORDING.lock().unwrap() = Some(Recording {
+        path,
+        start: Instant::now(),
+        spans: Vec::new(),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stops recording and writes the trace, if one was recorded.
+pub fn finish() {
+    ENABLED.store(false, Ordering::Relaxed);
+    let Some(recording) = RECORDING.lock().unwrap().take() else {
+        return;
+    };
+    match std::fs::write(&recording.path, recording.to_json()) {
+        Ok(()) => log::info!(
+            "Wrote {} spans to {}",
+            recording.spans.len(),
+            recording.path
+        ),
+        Err(err) => log::error!("Failed to write the trace to {}: {err}", recording.path),
+    }
+}
+
+/// Measures from now until dropped, if recording.
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: ENABLED.load(Ordering::Relaxed).then(Instant::now),
+        args: Vec::new(),
+    }
+}
+
+pub struct Span {
+    name: &'static str,
+    /// `None` when not recording
+    start: Option<Instant>,
+    args: Vec<(&'static str, u64)>,
+}
+impl Span {
+    /// Attaches a number to the span, shown when it is selected.
+    pub fn arg(&mut self, name: &'static str, value: u64) {
+        if self.start.is_some() {
+            self.args.push((name, value));
+        }
+    }
+}
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let end = Instant::now();
+        let thread = THREAD.with(|thread| {
+            if thread.get() == 0 {
+                thread.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+            }
+            thread.get()
+        });
+        let mut recording = RECORDING.lock().unwrap();
+        let Some(recording) = recording.as_mut() else {
+            return;
+        };
+        if recording.spans.len() == MAX_SPANS {
+            ENABLED.store(false, Ordering::Relaxed);
+            log::warn!("Stopped recording the trace after {MAX_SPANS} spans");
+            return;
+        }
+        recording.spans.push(Finished {
+            name: self.name,
+            thread,
+            start,
+            end,
+            args: std::mem::take(&mut self.args),
+        });
+    }
+}
+
+impl Recording {
+    fn to_json(&self) -> String {
+        let micros = |instant: Instant| instant.duration_since(self.start).as_secs_f64() * 1e6;
+        let mut json = String::from("{\"displayTimeUnit\":\"ms\",\"traceEvents\":[");
+        for (i, span) in self.spans.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            // Names and argument names are literals without characters needing escapes
+            write!(
+                json,
+                "\n{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3},\"args\":{{",
+                span.name,
+                span.thread,
+                micros(span.start),
+                micros(span.end) - micros(span.start),
+            )
+            .unwrap();
+            for (j, (name, value)) in span.args.iter().enumerate() {
+                let separator = if j > 0 { "," } else { "" };
+                write!(json, "{separator}\"{name}\":{value}").unwrap();
+            }
+            json.push_str("}}");
+        }
+        json.push_str("\n]}\n");
+        json
+    }
+}
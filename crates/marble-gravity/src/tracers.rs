@@ -0,0 +1,54 @@
+//! A cloud of massless tracers showing the flow of space around the bodies, stepped on the main
+//! thread alongside rendering.
+//!
+//! Natively, `MARBLE_GRAVITY_TRACERS`, such as `16384`, sets how many tracers there are.
+
+use crate::{
+    events::{Frame, Subscriber},
+    graphics::Graphics,
+};
+use physics::{Physics, Tracers, MAX_TRACERS};
+
+const DEFAULT_TRACER_COUNT: usize = 4096;
+
+pub struct TracerCloud {
+    count: usize,
+    tracers: Option<Tracers>,
+}
+impl TracerCloud {
+    pub fn new() -> Self {
+        let mut count = DEFAULT_TRACER_COUNT;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(value) = std::env::var("MARBLE_GRAVITY_TRACERS") {
+            match value.trim().parse::<usize>() {
+                Ok(parsed) if parsed <= MAX_TRACERS => count = parsed,
+                _ => log::warn!(
+                    "Ignoring MARBLE_GRAVITY_TRACERS={value}, expected at most {MAX_TRACERS}"
+                ),
+            }
+        }
+        Self {
+            count,
+            tracers: None,
+        }
+    }
+    pub fn toggle(&mut self, graphics: &mut Graphics, physics: &Physics, seed: u64) {
+        if self.tracers.take().is_some() {
+            graphics.set_tracers(&[]);
+            log::info!("Removed tracers");
+        } else {
+            self.tracers = Some(Tracers::new(self.count, physics.simulated_time(), seed));
+            log::info!("Added {} tracers", self.count);
+        }
+    }
+}
+impl Subscriber for TracerCloud {
+    /// Catches the tracers up with `physics` and uploads them.
+    fn queue(&mut self, graphics: &mut Graphics, frame: &Frame<'_>) {
+        let physics = frame.physics;
+        if let Some(tracers) = &mut self.tracers {
+            tracers.advance_to(physics);
+            graphics.set_tracers(tracers.as_slice());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Guided lessons, made of scripted steps that set up bodies, prompt the user and wait for them
+//! or the simulation to do something.
+
+use crate::{
+    commands::Command,
+    events::{AppEvent, EventBus, Frame, Subscriber},
+    graphics::Graphics,
+};
+use cgmath::prelude::*;
+use physics::{parse_scenario, Physics};
+use std::time::Duration;
+
+pub enum Step {
+    /// Replaces all bodies with those of a scenario file
+    Load(&'static str),
+    /// Shown in the overlay until the next prompt
+    Prompt(&'static str),
+    Wait(Condition),
+}
+
+pub enum Condition {
+    /// An event is published, such as a command being executed
+    Event(fn(&AppEvent) -> bool),
+    /// A predicate holds continuously for a duration of simulated time
+    Holds(fn(&Physics) -> bool, Duration),
+}
+
+/// Put the small marble of the scenario in orbit around the frozen one
+pub const STABLE_ORBIT: &[Step] = &[
+    Step::Load(include_str!("../assets/scenarios/stable-orbit.scenario")),
+    Step::Prompt("Alt+drag around the small marble to select it, then press 3 to push it"),
+    Step::Wait(Condition::Event(|event| {
+        matches!(event, AppEvent::CommandExecuted(Command::AddVelocity(..)))
+    })),
+    Step::Prompt("Keep it in orbit for 20 seconds, pushing it again if needed"),
+    Step::Wait(Condition::Holds(
+        |physics| match &physics.bodies()[..] {
+            [center, orbiting, ..] => (0.4..2.0).contains(&center.pos.distance(orbiting.pos)),
+            _ => false,
+        },
+        Duration::from_secs(20),
+    )),
+    Step::Prompt("Stable orbit achieved"),
+    Step::Wait(Condition::Holds(|_| true, Duration::from_secs(5))),
+];
+
+/// Runs one lesson at a time.
+pub struct Tutorial {
+    steps: &'static [Step],
+    current: usize,
+    prompt: Option<&'static str>,
+    /// Whether the awaited event has been published
+    event_seen: bool,
+    /// The tick from which the awaited predicate has held
+    holding_since: Option<Duration>,
+}
+impl Tutorial {
+    pub fn new() -> Self {
+        Self {
+            steps: &[],
+            current: 0,
+            prompt: None,
+            event_seen: false,
+            holding_since: None,
+        }
+    }
+    pub fn start(&mut self, steps: &'static [Step]) {
+        *self = Self {
+            steps,
+            ..Self::new()
+        };
+        log::info!("Starting tutorial");
+    }
+    pub fn is_running(&self) -> bool {
+        self.current < self.steps.len()
+    }
+    /// Advances through the steps that are done. Must only be called while no worker is
+    /// advancing `physics`, or loaded bodies would be lost.
+    pub fn update(&mut self, physics: &mut Physics, bus: &mut EventBus) {
+        while let Some(step) = self.steps.get(self.current) {
+            match step {
+                Step::Load(scenario) => {
+                    let scenario = parse_scenario(scenario).expect("built-in scenarios are valid");
+                    bus.publish(AppEvent::ScenarioLoaded(scenario.settings));
+                    physics.load_scenario(scenario);
+                }
+                Step::Prompt(text) => self.prompt = Some(text),
+                Step::Wait(Condition::Event(_)) => {
+                    if !self.event_seen {
+                        return;
+                    }
+                }
+                Step::Wait(Condition::Holds(predicate, duration)) => {
+                    if !predicate(physics) {
+                        self.holding_since = None;
+                        return;
+                    }
+                    let now = physics.simulated_time();
+                    let held = now - *self.holding_since.get_or_insert(now);
+                    if held < *duration {
+                        return;
+                    }
+                }
+            }
+            self.current += 1;
+            self.event_seen = false;
+            self.holding_since = None;
+            if !self.is_running() {
+                self.prompt = None;
+                log::info!("Tutorial completed");
+            }
+        }
+    }
+}
+impl Subscriber for Tutorial {
+    fn notify(&mut self, event: &AppEvent) {
+        if let Some(Step::Wait(Condition::Event(matches))) = self.steps.get(self.current) {
+            self.event_seen |= matches(event);
+        }
+    }
+    /// Shows the current prompt in the overlay.
+    fn queue(&mut self, graphics: &mut Graphics, _frame: &Frame<'_>) {
+        if let Some(prompt) = self.prompt {
+            graphics.overlay_line(format_args!("{prompt}"));
+        }
+    }
+}
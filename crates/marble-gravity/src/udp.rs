@@ -0,0 +1,34 @@
+//! Listening for UDP datagrams from other programs, such as OSC messages and feed frames.
+
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+/// The largest packet UDP can carry
+pub const MAX_PACKET: usize = 65536;
+
+/// Binds a nonblocking socket to the port or address given as `value` by the setting `name`,
+/// such as `9000` for localhost or `0.0.0.0:9000` for other machines too. Logs what it listens
+/// for, or why it cannot.
+pub fn listen(name: &str, value: &str, purpose: &str) -> Option<UdpSocket> {
+    let address = match (value.trim().parse(), value.trim().parse()) {
+        (Ok(address), _) => address,
+        (_, Ok(port)) => SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        _ => {
+            log::warn!("Ignoring {name}={value}, expected a port such as 9000 or an address");
+            return None;
+        }
+    };
+    let socket = UdpSocket::bind(address).and_then(|socket| {
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    });
+    match socket {
+        Ok(socket) => {
+            log::info!("Listening for {purpose} on {address}");
+            Some(socket)
+        }
+        Err(err) => {
+            log::error!("Failed to listen for {purpose} on {address}: {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Benchmarks of the simulation at several numbers of bodies, of the acceleration of a single
+//! body, of a step of the integrator given the accelerations, and of whole ticks.
+//!
+//! Run with `cargo bench -p physics`, and compare against a baseline with
+//! `cargo bench -p physics -- --save-baseline before` and then `--baseline before`.
+
+use cgmath::Vector3;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use physics::{Body, Physics, Scalar, BODIES};
+
+const BODY_COUNTS: [usize; 4] = [16, 64, 128, BODIES];
+/// Ticks per iteration of `advance_to`
+const TICKS: u32 = 10;
+
+/// The first `count` bodies of the default cluster.
+fn physics(count: usize) -> Box<Physics> {
+    let mut physics = Physics::seeded(0);
+    physics.bodies_mut().truncate(count);
+    physics
+}
+
+fn accel_from(c: &mut Criterion) {
+    let mut group = c.benchmark_group("accel_from");
+    for count in BODY_COUNTS {
+        let physics = physics(count);
+        let (bodies, config) = (physics.bodies(), physics.config());
+        let dt = physics.delta_time().as_secs_f64() as Scalar;
+        group.bench_with_input(BenchmarkId::from_parameter(count), bodies, |b, bodies| {
+            b.iter(|| black_box(&bodies[0]).accel_from(black_box(bodies), dt, config));
+        });
+    }
+    group.finish();
+}
+
+fn perform_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perform_step");
+    for count in BODY_COUNTS {
+        let physics = physics(count);
+        let config = physics.config();
+        let dt = physics.delta_time().as_secs_f64() as Scalar;
+        let accels: Vec<Vector3<Scalar>> = physics
+            .bodies()
+            .iter()
+            .map(|body| body.accel_from(physics.bodies(), dt, config))
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(count), &accels, |b, accels| {
+            b.iter_batched_ref(
+                || *physics.bodies(),
+                |bodies| Body::perform_step(bodies, accels, dt, config),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn advance_to(c: &mut Criterion) {
+    let mut group = c.benchmark_group("advance_to");
+    for count in BODY_COUNTS {
+        let physics = physics(count);
+        group.bench_function(BenchmarkId::from_parameter(count), |b| {
+            b.iter_batched_ref(
+                || physics.clone(),
+                |physics| {
+                    // Exactly `TICKS` behind, which is well within the time that may be caught
+                    // up on
+                    let target = physics.timestamp() + physics.delta_time() * TICKS;
+                    physics.advance_to(target)
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, accel_from, perform_step, advance_to);
+criterion_main!(benches);
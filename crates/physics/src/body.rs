@@ -1,57 +1,428 @@
-use crate::PHYSICS_DELTA_TIME;
-use cgmath::{prelude::*, Vector3};
-use rand_distr::Distribution;
+use crate::{Boundary, ForceField, Integrator, Scalar, ToF32, MAX_FIELDS};
+use cgmath::{prelude::*, Quaternion, Vector3};
+use std::time::Duration;
 
-const SYSTEM_RADIUS: f32 = 5.0;
-const GRAVITY_CONSTANT: f32 = 40.0;
-const GAP: f32 = 0.001;
-const STIFFNESS: f32 = 1.0;
-const DAMPING: f32 = 0.2; // In (0,1); less than 0.05 is wonky
+/// The default of [`PhysicsConfig::boundary_radius`]
+pub(crate) const SYSTEM_RADIUS: Scalar = 5.0;
+/// Bodies move at most this fraction of the smallest radius per step, so that fast bodies cannot
+/// pass through others between two steps
+const MAX_STEP_DISPLACEMENT: Scalar = 0.25;
+const MAX_SUBSTEPS: u32 = 64;
+/// The coefficient of friction between touching bodies, which spins them up as they rub
+const FRICTION: Scalar = 0.3;
+/// Group ids are stored in the flags of bodies, in the bits from [`Body::GROUP_SHIFT`], where 0
+/// is the group of bodies not in any
+pub const MAX_GROUPS: usize = 8;
+
+/// The constants of the interactions between bodies, which may be changed while the simulation
+/// runs, see [`Physics::set_config`](crate::Physics::set_config).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PhysicsConfig {
+    /// The gravitational constant, with mass as in [`Body::mass`]
+    pub gravity: f32,
+    /// The spring constant of collisions
+    pub stiffness: f32,
+    /// In (0,1); less than 0.05 is wonky
+    pub damping: f32,
+    /// The distance kept between the surfaces of touching bodies
+    pub gap: f32,
+    /// The softening length of gravity, which attracts as if bodies were this much further
+    /// apart when close, and so keeps close approaches between small bodies from causing huge
+    /// accelerations
+    pub softening: f32,
+    pub integrator: Integrator,
+    /// Overrides of the bodies of each group, see [`Body::group`]
+    pub groups: [Group; MAX_GROUPS],
+    /// Forces from outside the system acting on every body, as set by scenarios, see
+    /// [`ForceField`]
+    pub fields: [Option<ForceField>; MAX_FIELDS],
+    pub boundary: Boundary,
+    /// The size of the boundary, see [`Boundary`]
+    pub boundary_radius: f32,
+    /// The simulated seconds over which gravity and the stiffness of collisions fade in from
+    /// zero at the start, so that a random initial cluster settles into orbits rather than
+    /// exploding on contact. Zero starts at full strength.
+    pub warm_up: f32,
+}
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 40.0,
+            stiffness: 1.0,
+            damping: 0.2,
+            gap: 0.001,
+            softening: 0.0,
+            integrator: Integrator::default(),
+            groups: [Group::default(); MAX_GROUPS],
+            fields: [None; MAX_FIELDS],
+            boundary: Boundary::default(),
+            boundary_radius: SYSTEM_RADIUS.to_f32(),
+            warm_up: 0.0,
+        }
+    }
+}
+impl PhysicsConfig {
+    pub fn group(&self, body: &Body) -> &Group {
+        &self.groups[body.group()]
+    }
+    /// The constants in effect once `elapsed` has been simulated, with gravity and stiffness
+    /// faded in linearly over [`PhysicsConfig::warm_up`].
+    pub fn warmed_up(&self, elapsed: Duration) -> Self {
+        if elapsed.as_secs_f32() >= self.warm_up {
+            return *self;
+        }
+        let fade = elapsed.as_secs_f32() / self.warm_up;
+        Self {
+            gravity: fade * self.gravity,
+            stiffness: fade * self.stiffness,
+            ..*self
+        }
+    }
+    /// The acceleration of a body by all of [`PhysicsConfig::fields`] together.
+    pub fn field_accel(&self, body: &Body) -> Vector3<Scalar> {
+        let accel = |field: &ForceField| field.accel(body.pos, body.vel);
+        self.fields.iter().flatten().map(accel).sum()
+    }
+    /// How strongly two bodies attract each other, over the product of their masses
+    fn gravity_between(&self, a: &Body, b: &Body) -> Scalar {
+        Scalar::from(self.gravity * self.group(a).coupling * self.group(b).coupling)
+    }
+    /// The squared softening length of a body, see [`PhysicsConfig::softening`]
+    pub(crate) fn softening_squared(&self, body: &Body) -> f32 {
+        self.group(body).softening.unwrap_or(self.softening).powi(2)
+    }
+    /// The squared distance between two bodies, softened by the mean of their squared softening
+    /// lengths
+    fn softened_distance2(&self, a: &Body, b: &Body) -> Scalar {
+        a.pos.distance2(b.pos)
+            + Scalar::from(0.5 * (self.softening_squared(a) + self.softening_squared(b)))
+    }
+    /// The material of a body, its own if it has one and otherwise that of its group
+    pub fn material(&self, body: &Body) -> Material {
+        body.material().unwrap_or(self.group(body).material)
+    }
+    /// The spring constant of a collision between two bodies
+    fn stiffness_between(&self, a: &Body, b: &Body) -> Scalar {
+        let stiffness = |body| self.group(body).stiffness * self.material(body).stiffness();
+        Scalar::from(self.stiffness * 0.5 * (stiffness(a) + stiffness(b)))
+    }
+    /// The damping of a collision between two bodies, averaged between their materials
+    fn damping_between(&self, a: &Body, b: &Body) -> Scalar {
+        let damping = |body| self.material(body).damping().unwrap_or(self.damping);
+        Scalar::from(0.5 * (damping(a) + damping(b)))
+    }
+}
+
+/// How the bodies of a group differ from others, as set by scenarios.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Group {
+    /// Scales the gravitational mass, so the attraction between two bodies scales with the
+    /// product of the couplings of their groups
+    pub coupling: f32,
+    /// Scales the spring constant of collisions, averaged between the two groups colliding
+    pub stiffness: f32,
+    /// Replaces [`PhysicsConfig::softening`], with the squares averaged between two groups
+    pub softening: Option<f32>,
+    pub material: Material,
+}
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            coupling: 1.0,
+            stiffness: 1.0,
+            softening: None,
+            material: Material::Solid,
+        }
+    }
+}
+
+/// How bodies are drawn and how they collide, set for a whole group or for single bodies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Material {
+    /// Drawn with the transparency of their colors, and colliding as set by [`PhysicsConfig`]
+    Solid,
+    /// Drawn at least partly transparent, hard and fairly bouncy
+    Glass,
+    /// Hardest of all, and losing more energy in collisions than the default
+    Metal,
+    /// Soft and the most bouncy
+    Rubber,
+}
+impl Material {
+    pub const ALL: [Self; 4] = [Self::Solid, Self::Glass, Self::Metal, Self::Rubber];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Solid => "solid",
+            Self::Glass => "glass",
+            Self::Metal => "metal",
+            Self::Rubber => "rubber",
+        }
+    }
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|material| material.name() == name)
+    }
+    /// Scales the spring constant of collisions, on top of [`Group::stiffness`]
+    pub fn stiffness(self) -> f32 {
+        match self {
+            Self::Solid => 1.0,
+            Self::Glass => 2.0,
+            Self::Metal => 4.0,
+            Self::Rubber => 0.25,
+        }
+    }
+    /// Replaces [`PhysicsConfig::damping`], where less damping means more bouncy collisions
+    pub fn damping(self) -> Option<f32> {
+        match self {
+            Self::Solid => None,
+            Self::Glass => Some(0.12),
+            Self::Metal => Some(0.35),
+            Self::Rubber => Some(0.06),
+        }
+    }
+}
+
+/// The quantities that the simulation conserves, but for collision losses and integration error.
+/// Frozen bodies are held in place and count as external, so
+/// they add to the potential energy but not to the kinetic energy or the momenta.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Conserved {
+    /// Of both motion and spin
+    pub kinetic: Scalar,
+    pub potential: Scalar,
+    pub momentum: Vector3<Scalar>,
+    /// About the center of mass, of both orbits and spin
+    pub angular_momentum: Vector3<Scalar>,
+}
+impl Conserved {
+    pub fn energy(&self) -> Scalar {
+        self.kinetic + self.potential
+    }
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct Body {
-    pub pos: Vector3<f32>,
-    pub vel: Vector3<f32>,
-    pub radius: f32,
+    pub pos: Vector3<Scalar>,
+    pub vel: Vector3<Scalar>,
+    pub radius: Scalar,
+    /// The mass per volume, where 1 is the density of bodies that are not given one
+    pub density: Scalar,
     pub color: u32,
+    /// A combination of [`Body::FROZEN`], [`Body::STRIPED`], the material from
+    /// [`Body::MATERIAL_SHIFT`] and the group id from [`Body::GROUP_SHIFT`]
+    pub flags: u32,
+    /// The angular velocity, in radians per second about its axis
+    pub spin: Vector3<Scalar>,
+    /// The rotation from the frame of the body to world space
+    pub orientation: Quaternion<f32>,
 }
 unsafe impl bytemuck::Zeroable for Body {}
 unsafe impl bytemuck::Pod for Body {}
 impl Body {
+    /// Held in place, while still attracting and colliding with other bodies
+    pub const FROZEN: u32 = 1;
+    /// Drawn with bands of latitude by the raytracer, which show how it spins
+    pub const STRIPED: u32 = 2;
+    /// The lowest bit of the material in the flags, which is 0 for the material of the group and
+    /// otherwise one more than the index of the material in [`Material::ALL`]
+    pub const MATERIAL_SHIFT: u32 = 4;
+    const MATERIAL_MASK: u32 = 0b111 << Self::MATERIAL_SHIFT;
+    /// The lowest bit of the group id in the flags
+    pub const GROUP_SHIFT: u32 = 8;
+
+    /// A body drawn by the default [`Generator`](crate::Generator).
     pub fn initial() -> Body {
-        let mut normal = rand_distr::Normal::new(0.0f32, 1.0)
-            .unwrap()
-            .sample_iter(rand::thread_rng());
-        let mut r = move || normal.next().unwrap();
-        let pos = [r(), r(), r()].into();
-        let rand = [r(), r(), r()].into();
+        crate::Generator::default().body(&mut rand::thread_rng())
+    }
+    /// A body of random size and color, like [`Body::initial`], at a given position.
+    pub fn at(pos: Vector3<Scalar>, vel: Vector3<Scalar>) -> Body {
         Body {
             pos,
-            vel: 0.1 * pos.cross(rand),
-            radius: 0.03 * (0.8 * r().abs() + 0.2),
-            color: rand::random(),
+            vel,
+            ..Body::initial()
         }
     }
-    pub fn perform_step(bodies: &mut [Body], accels: Vec<Vector3<f32>>) {
-        let mut vels: Vec<_> = bodies.iter().map(Body::new_vel).collect();
-        let total_mass: f32 = bodies.iter().map(|b| b.radius.powi(3)).sum();
-        let total_momentum: Vector3<f32> = bodies
+
+    /// The density times the radius cubed, which leaves out the factor of 4/3 pi that every mass
+    /// would share.
+    pub fn mass(&self) -> Scalar {
+        self.density * self.radius.powi(3)
+    }
+
+    /// Rescales velocities relative to the center of mass so that the kinetic energy is half the
+    /// magnitude of the gravitational potential energy, as the virial theorem holds for a cluster
+    /// in equilibrium. Clusters that start out this way stay bound for long, rather than promptly
+    /// collapsing or evaporating. Does nothing to bodies that are all at rest.
+    pub fn virialize(bodies: &mut [Body], config: &PhysicsConfig) {
+        let (kinetic, potential) = Body::energies(bodies, config);
+        if kinetic <= 0.0 {
+            return;
+        }
+        let scale = (-potential / (2.0 * kinetic)).sqrt();
+        let drift = Body::mean_velocity(bodies);
+        for body in bodies.iter_mut().filter(|b| !b.is_frozen()) {
+            body.vel = drift + scale * (body.vel - drift);
+        }
+    }
+    /// The kinetic energy relative to the center of mass and the gravitational potential energy.
+    pub(crate) fn energies(bodies: &[Body], config: &PhysicsConfig) -> (Scalar, Scalar) {
+        let drift = Body::mean_velocity(bodies);
+        let kinetic = bodies
             .iter()
-            .zip(&vels)
-            .map(|(b, v)| b.radius.powi(3) * v)
+            .filter(|b| !b.is_frozen())
+            .map(|b| 0.5 * b.mass() * (b.vel - drift).magnitude2())
             .sum();
-        vels.iter_mut()
-            .for_each(|v| *v -= total_momentum / total_mass);
-        bodies
-            .iter_mut()
-            .zip(vels)
-            .zip(accels)
-            .for_each(|((b, v), a)| b.step_using_vel_accel([v, a]));
+        let potential = bodies
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| bodies[i + 1..].iter().map(move |b| (a, b)))
+            .map(|(a, b)| {
+                -config.gravity_between(a, b) * a.mass() * b.mass()
+                    / config.softened_distance2(a, b).sqrt()
+            })
+            .sum();
+        (kinetic, potential)
+    }
+    /// The energies and momenta of the bodies, with angular momentum about `center`.
+    pub(crate) fn conserved(
+        bodies: &[Body],
+        config: &PhysicsConfig,
+        center: Vector3<Scalar>,
+    ) -> Conserved {
+        let (_, potential) = Body::energies(bodies, config);
+        let mut conserved = Conserved {
+            kinetic: 0.0,
+            potential,
+            momentum: Vector3::zero(),
+            angular_momentum: Vector3::zero(),
+        };
+        for body in bodies.iter().filter(|b| !b.is_frozen()) {
+            let mass = body.mass();
+            let inertia = body.moment_of_inertia();
+            conserved.kinetic +=
+                0.5 * (mass * body.vel.magnitude2() + inertia * body.spin.magnitude2());
+            conserved.momentum += mass * body.vel;
+            conserved.angular_momentum +=
+                mass * (body.pos - center).cross(body.vel) + inertia * body.spin;
+        }
+        conserved
+    }
+    /// The mass-weighted mean velocity of the bodies that are not frozen.
+    fn mean_velocity(bodies: &[Body]) -> Vector3<Scalar> {
+        let moving = || bodies.iter().filter(|b| !b.is_frozen());
+        let total_mass: Scalar = moving().map(Body::mass).sum();
+        if total_mass == 0.0 {
+            return Vector3::zero();
+        }
+        moving().map(|b| b.mass() * b.vel).sum::<Vector3<Scalar>>() / total_mass
+    }
+    pub fn is_frozen(&self) -> bool {
+        self.flags & Self::FROZEN != 0
+    }
+    /// The material of the body itself, which overrides that of its group, see
+    /// [`PhysicsConfig::material`].
+    pub fn material(&self) -> Option<Material> {
+        let index = (self.flags & Self::MATERIAL_MASK) >> Self::MATERIAL_SHIFT;
+        Material::ALL.get((index as usize).checked_sub(1)?).copied()
+    }
+    pub fn set_material(&mut self, material: Option<Material>) {
+        let index = material.map_or(0, |material| material as u32 + 1);
+        self.flags = (self.flags & !Self::MATERIAL_MASK) | (index << Self::MATERIAL_SHIFT);
     }
-    pub fn accel_from(&self, bodies: &[Body]) -> Vector3<f32> {
-        let dt = PHYSICS_DELTA_TIME.as_secs_f32();
+    /// The index of the group of the body in [`PhysicsConfig::groups`], 0 if in none.
+    pub fn group(&self) -> usize {
+        (self.flags >> Self::GROUP_SHIFT) as usize % MAX_GROUPS
+    }
+    /// A step of [`Integrator::Taylor`](crate::Integrator::Taylor).
+    pub fn perform_step(
+        bodies: &mut [Body],
+        accels: &[Vector3<Scalar>],
+        dt: Scalar,
+        config: &PhysicsConfig,
+    ) {
+        Body::settle_velocities(bodies, config);
+        bodies.iter_mut().zip(accels).for_each(|(b, &a)| {
+            if !b.is_frozen() {
+                b.step_using_vel_accel([b.vel, a], dt);
+            }
+        });
+    }
+    /// Applies what changes velocities besides forces, before each step: bodies leaving the
+    /// system are slowed by [`Boundary::Damped`], the drift of the total momentum is removed and
+    /// frozen bodies stopped.
+    pub(crate) fn settle_velocities(bodies: &mut [Body], config: &PhysicsConfig) {
+        // `new_vel` is cheap, so it is evaluated twice rather than stored
+        let total_mass: Scalar = bodies.iter().map(Body::mass).sum();
+        let total_momentum: Vector3<Scalar> =
+            bodies.iter().map(|b| b.mass() * b.new_vel(config)).sum();
+        // Frozen bodies and the walls of a sphere take up momentum, and fields add to it, so it is
+        // only conserved without any of them
+        let conserved = !bodies.iter().any(Body::is_frozen)
+            && config.fields.iter().all(Option::is_none)
+            && config.boundary != Boundary::Sphere;
+        let correction = if conserved {
+            total_momentum / total_mass
+        } else {
+            Vector3::zero()
+        };
+        for b in bodies.iter_mut() {
+            b.vel = if b.is_frozen() {
+                Vector3::zero()
+            } else {
+                b.new_vel(config) - correction
+            };
+        }
+    }
+    pub fn accel_from(
+        &self,
+        bodies: &[Body],
+        dt: Scalar,
+        config: &PhysicsConfig,
+    ) -> Vector3<Scalar> {
+        self.accels_from(bodies, dt, config).0
+    }
+    /// The linear and angular accelerations of the body, from gravity, from collisions with any
+    /// of the other bodies and from [`PhysicsConfig::fields`]. The simulation itself only looks
+    /// for collisions among bodies found nearby by its broad phase.
+    pub fn accels_from(
+        &self,
+        bodies: &[Body],
+        dt: Scalar,
+        config: &PhysicsConfig,
+    ) -> (Vector3<Scalar>, Vector3<Scalar>) {
+        let (accel, angular_accel) = self.contact_accels(bodies, dt, config);
+        let accel = accel + self.gravity_accel(bodies, config) + config.field_accel(self);
+        (accel, angular_accel)
+    }
+    /// The acceleration of the body by the gravity of the others.
+    pub(crate) fn gravity_accel(&self, bodies: &[Body], config: &PhysicsConfig) -> Vector3<Scalar> {
         let mut accel = Vector3::zero();
         for other in bodies {
+            if other.pos == self.pos {
+                continue; // Same body
+            }
+            let rel_pos_norm = (other.pos - self.pos).normalize();
+            accel += config.gravity_between(self, other) * other.mass()
+                / config.softened_distance2(self, other)
+                * rel_pos_norm;
+        }
+        accel
+    }
+    /// The linear and angular accelerations of the body from collisions with any of `others`.
+    /// Touching bodies push each other apart, and rub against each other with friction, which
+    /// spins them.
+    pub(crate) fn contact_accels<'a>(
+        &self,
+        others: impl IntoIterator<Item = &'a Body>,
+        dt: Scalar,
+        config: &PhysicsConfig,
+    ) -> (Vector3<Scalar>, Vector3<Scalar>) {
+        let gap = Scalar::from(config.gap);
+        let mass = self.mass();
+        let mut accel = Vector3::zero();
+        let mut angular_accel = Vector3::zero();
+        for other in others {
             if other.pos == self.pos {
                 continue; // Same body
             }
@@ -60,27 +431,125 @@ impl Body {
             let rel_pos_norm = rel_pos / distance;
             let rel_vel = (other.vel - self.vel).dot(rel_pos_norm);
 
+            let damping = config.damping_between(self, other);
             let overlap =
-                self.radius + GAP + other.radius - distance - rel_vel * dt * (1.0 + DAMPING) / 2.0;
-            if overlap > 0.0 {
-                // Spring-based collision
-                let force_towards_other = -STIFFNESS * overlap;
-                accel += force_towards_other / self.radius.powi(3) * rel_pos_norm;
+                self.radius + gap + other.radius - distance - rel_vel * dt * (1.0 + damping) / 2.0;
+            if overlap <= 0.0 {
+                continue;
+            }
+            // Spring-based collision
+            let normal_force = config.stiffness_between(self, other) * overlap;
+            accel -= normal_force / mass * rel_pos_norm;
+            // Friction against the slip of the two surfaces where they touch, stopping it within
+            // the step at most
+            let surface_vel = |body: &Body, arm: Vector3<Scalar>| body.vel + body.spin.cross(arm);
+            let slip = surface_vel(other, -other.radius * rel_pos_norm)
+                - surface_vel(self, self.radius * rel_pos_norm);
+            let slip = slip - slip.dot(rel_pos_norm) * rel_pos_norm;
+            let slip_speed = slip.magnitude();
+            if slip_speed > 0.0 {
+                // A solid sphere resists sliding at its surface with 2/7 of its mass
+                let effective_mass = 1.0 / (3.5 / mass + 3.5 / other.mass());
+                let friction = (FRICTION * normal_force).min(effective_mass * slip_speed / dt);
+                let force = friction / slip_speed * slip;
+                accel += force / mass;
+                angular_accel +=
+                    (self.radius * rel_pos_norm).cross(force) / self.moment_of_inertia();
             }
-            // Gravitational interaction
-            accel += GRAVITY_CONSTANT * other.radius.powi(3) / distance.powi(2) * rel_pos_norm;
         }
-        accel
+        (accel, angular_accel)
+    }
+    /// The furthest apart two of the bodies can be and still collide in a step of `dt` seconds,
+    /// as [`Body::contact_accels`] sees it: by their radii and the gap, and further by how fast
+    /// they approach each other, which is at most twice the speed of the fastest.
+    #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+    pub(crate) fn contact_reach(bodies: &[Body], dt: Scalar, config: &PhysicsConfig) -> Scalar {
+        let most = |of: &dyn Fn(&Body) -> Scalar| bodies.iter().map(of).fold(0.0, Scalar::max);
+        let radius = most(&|body| body.radius);
+        let speed = most(&|body| body.vel.magnitude());
+        let damping = most(&|body| config.damping_between(body, body));
+        2.0 * radius + Scalar::from(config.gap) + speed * dt * (1.0 + damping)
+    }
+    /// Of a solid sphere of even density
+    fn moment_of_inertia(&self) -> Scalar {
+        0.4 * self.mass() * self.radius.powi(2)
+    }
+    /// Spins the bodies up by their angular accelerations, and turns them by their spin.
+    pub(crate) fn rotate(bodies: &mut [Body], angular_accels: &[Vector3<Scalar>], dt: Scalar) {
+        for (body, &angular_accel) in bodies.iter_mut().zip(angular_accels) {
+            if body.is_frozen() {
+                body.spin = Vector3::zero();
+                continue;
+            }
+            body.spin += angular_accel * dt;
+            // The orientation only shows the spin, so it stays in single precision
+            let turn = Quaternion::from_sv(0.0, (0.5 * dt * body.spin).to_f32()) * body.orientation;
+            body.orientation = (body.orientation + turn).normalize();
+        }
+    }
+    /// The magnitude of the gravitational force between two bodies, as applied by
+    /// [`Body::accel_from`].
+    pub fn gravitational_force(&self, other: &Body, config: &PhysicsConfig) -> Scalar {
+        config.gravity_between(self, other) * self.mass() * other.mass()
+            / config.softened_distance2(self, other)
+    }
+    /// The distance between the surfaces of two bodies, beyond the gap kept between touching
+    /// bodies. Collision forces apply when this is negative, ignoring damping.
+    pub fn surface_gap(&self, other: &Body, config: &PhysicsConfig) -> Scalar {
+        (other.pos - self.pos).magnitude() - self.radius - Scalar::from(config.gap) - other.radius
+    }
+    /// An estimate of the longest stable tick, in seconds. Two touching bodies of the lightest
+    /// mass for their stiffness form a spring oscillating at `sqrt(2 * stiffness / mass)`, which
+    /// explicit integration only keeps bounded for ticks shorter than two over that frequency.
+    pub(crate) fn stable_delta_time(bodies: &[Body], config: &PhysicsConfig) -> Option<Scalar> {
+        let mass_per_stiffness = bodies
+            .iter()
+            .filter(|body| !body.is_frozen())
+            .map(|body| body.mass() / config.stiffness_between(body, body))
+            .min_by(Scalar::total_cmp)?;
+        Some(2.0 / (2.0 / mass_per_stiffness).sqrt())
+    }
+    /// How many steps a tick of `dt` seconds is split into, so that no body moves further than
+    /// [`MAX_STEP_DISPLACEMENT`] of the smallest radius per step, neither by its velocity nor by
+    /// its acceleration at the start of the tick. Calm systems take one step, while violent
+    /// collisions, whose spring forces are large, are resolved in many.
+    pub(crate) fn substeps(bodies: &[Body], accels: &[Vector3<Scalar>], dt: Scalar) -> u32 {
+        let Some(radius) = bodies
+            .iter()
+            .map(|body| body.radius)
+            .min_by(Scalar::total_cmp)
+        else {
+            return 1;
+        };
+        if radius <= 0.0 {
+            return 1;
+        }
+        let max_displacement = MAX_STEP_DISPLACEMENT * radius;
+        let steps = bodies
+            .iter()
+            .zip(accels)
+            .filter(|(body, _)| !body.is_frozen())
+            .map(|(body, accel)| {
+                // Solving `speed * t = max_displacement` and `accel * t^2 / 2 = max_displacement`
+                // for the longest step `t`
+                let by_speed = body.vel.magnitude() * dt / max_displacement;
+                let by_accel = dt * (accel.magnitude() / (2.0 * max_displacement)).sqrt();
+                by_speed.max(by_accel)
+            })
+            .max_by(Scalar::total_cmp)
+            .unwrap_or(1.0)
+            .ceil();
+        (steps as u32).clamp(1, MAX_SUBSTEPS)
     }
-    fn new_vel(&self) -> Vector3<f32> {
-        if self.pos.magnitude2() > SYSTEM_RADIUS.powi(2) && self.vel.dot(self.pos) > 0.0 {
+    fn new_vel(&self, config: &PhysicsConfig) -> Vector3<Scalar> {
+        let beyond = self.pos.magnitude2() > Scalar::from(config.boundary_radius).powi(2);
+        if config.boundary == Boundary::Damped && beyond && self.vel.dot(self.pos) > 0.0 {
             self.vel * 0.99
         } else {
             self.vel
         }
     }
-    fn step_using_vel_accel(&mut self, [vel, accel]: [Vector3<f32>; 2]) {
-        let dt = PHYSICS_DELTA_TIME.as_secs_f32();
+    fn step_using_vel_accel(&mut self, [vel, accel]: [Vector3<Scalar>; 2], dt: Scalar) {
         self.pos = self.pos + vel * dt + accel * dt * dt / 2.0;
         self.vel = vel + accel * dt;
     }
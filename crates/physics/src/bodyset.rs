@@ -0,0 +1,68 @@
+use crate::{Body, BODIES};
+use std::ops::{Deref, DerefMut};
+
+/// Up to [`BODIES`] bodies stored inline, so that the set is `Copy` without allocating and GPU
+/// buffers can be sized once for the maximum.
+#[derive(Clone, Copy, Debug)]
+pub struct BodySet {
+    bodies: [Body; BODIES],
+    len: usize,
+}
+
+impl BodySet {
+    pub fn new() -> Self {
+        Self {
+            bodies: [bytemuck::Zeroable::zeroed(); BODIES],
+            len: 0,
+        }
+    }
+    pub const fn capacity() -> usize {
+        BODIES
+    }
+    /// Returns the body back if the set is full.
+    pub fn push(&mut self, body: Body) -> Result<(), Body> {
+        if self.len == BODIES {
+            return Err(body);
+        }
+        self.bodies[self.len] = body;
+        self.len += 1;
+        Ok(())
+    }
+    /// Removes a body by moving the last body into its place.
+    pub fn swap_remove(&mut self, index: usize) -> Body {
+        assert!(index < self.len);
+        let removed = self.bodies[index];
+        self.bodies[index] = self.bodies[self.len - 1];
+        self.len -= 1;
+        removed
+    }
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+}
+impl Default for BodySet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Deref for BodySet {
+    type Target = [Body];
+    fn deref(&self) -> &[Body] {
+        &self.bodies[..self.len]
+    }
+}
+impl DerefMut for BodySet {
+    fn deref_mut(&mut self) -> &mut [Body] {
+        &mut self.bodies[..self.len]
+    }
+}
+impl FromIterator<Body> for BodySet {
+    /// Panics if given more than [`BODIES`] bodies.
+    fn from_iter<I: IntoIterator<Item = Body>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for body in iter {
+            set.push(body).expect("too many bodies");
+        }
+        set
+    }
+}
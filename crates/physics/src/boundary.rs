@@ -0,0 +1,72 @@
+//! What keeps bodies from leaving the system, within [`PhysicsConfig::boundary_radius`] of the
+//! origin.
+//!
+//! [`PhysicsConfig::boundary_radius`]: crate::PhysicsConfig::boundary_radius
+
+use crate::{Body, Scalar};
+use cgmath::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// Bodies moving away beyond the radius are slowed a little every step, so that most come
+    /// back eventually
+    #[default]
+    Damped,
+    /// Nothing keeps bodies from leaving
+    Unbounded,
+    /// Bodies bounce off the inside of a sphere of the radius without losing speed
+    Sphere,
+    /// Bodies leaving a cube whose faces are the radius from the origin enter it again through
+    /// the opposite face. They attract and collide only within the cube, not across its faces.
+    Periodic,
+}
+impl Boundary {
+    pub const ALL: [Self; 4] = [Self::Damped, Self::Unbounded, Self::Sphere, Self::Periodic];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Damped => "damped",
+            Self::Unbounded => "none",
+            Self::Sphere => "sphere",
+            Self::Periodic => "periodic",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|boundary| boundary.name() == name)
+    }
+    /// Moves bodies that crossed the boundary during a step back inside, after the step. Bodies
+    /// are slowed by [`Boundary::Damped`] before steps instead, see `Body::settle_velocities`.
+    pub(crate) fn contain(self, bodies: &mut [Body], radius: f32) {
+        let radius = Scalar::from(radius);
+        match self {
+            Self::Damped | Self::Unbounded => {}
+            Self::Sphere => {
+                for body in bodies.iter_mut().filter(|body| !body.is_frozen()) {
+                    let limit = (radius - body.radius).max(0.0);
+                    let distance = body.pos.magnitude();
+                    if distance <= limit || distance == 0.0 {
+                        continue;
+                    }
+                    let normal = body.pos / distance;
+                    body.pos = limit * normal;
+                    let outwards = body.vel.dot(normal);
+                    if outwards > 0.0 {
+                        body.vel -= 2.0 * outwards * normal;
+                    }
+                }
+            }
+            Self::Periodic => {
+                for body in bodies.iter_mut().filter(|body| !body.is_frozen()) {
+                    body.pos = body.pos.map(|x| {
+                        if x.abs() > radius {
+                            (x + radius).rem_euclid(2.0 * radius) - radius
+                        } else {
+                            x
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! The broad phase of collision detection: a uniform grid, hashed so that it takes no more room
+//! than the bodies, with cells as wide as the furthest bodies can be apart and still touch
+//! within a step. Collision forces are then only computed between bodies in neighboring cells,
+//! while gravity still acts between all pairs.
+
+use crate::{Body, PhysicsConfig, Scalar, BODIES};
+use cgmath::Vector3;
+
+pub(crate) struct ContactGrid {
+    cell_size: Scalar,
+    /// The hash of the cell of each body along with its index, sorted by hash
+    entries: [(u32, u32); BODIES],
+    len: usize,
+}
+impl ContactGrid {
+    /// Sorts the bodies into cells for a step of `dt` seconds.
+    pub fn new(bodies: &[Body], dt: Scalar, config: &PhysicsConfig) -> Self {
+        let cell_size = Body::contact_reach(bodies, dt, config).max(Scalar::MIN_POSITIVE);
+        let mut entries = [(0, 0); BODIES];
+        for (i, (entry, body)) in entries.iter_mut().zip(bodies).enumerate() {
+            *entry = (hash(cell_of(body.pos, cell_size)), i as u32);
+        }
+        entries[..bodies.len()].sort_unstable();
+        Self {
+            cell_size,
+            entries,
+            len: bodies.len(),
+        }
+    }
+    /// The indices of the bodies that may touch one at `pos` within the step, including itself
+    /// if it is one of them, each once. Others may be among them where cells share a hash.
+    pub fn near(&self, pos: Vector3<Scalar>) -> impl Iterator<Item = usize> + '_ {
+        let [x, y, z] = cell_of(pos, self.cell_size);
+        let mut hashes = [0; 27];
+        let neighbors = (-1..=1)
+            .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [dx, dy, dz])));
+        for (hash_of, [dx, dy, dz]) in hashes.iter_mut().zip(neighbors) {
+            *hash_of = hash([x.wrapping_add(dx), y.wrapping_add(dy), z.wrapping_add(dz)]);
+        }
+        // Neighboring cells sharing a hash would otherwise yield their bodies twice
+        hashes.sort_unstable();
+        let entries = &self.entries[..self.len];
+        (0..hashes.len())
+            .filter(move |&k| k == 0 || hashes[k] != hashes[k - 1])
+            .flat_map(move |k| {
+                let start = entries.partition_point(|&(hash, _)| hash < hashes[k]);
+                entries[start..]
+                    .iter()
+                    .take_while(move |&&(hash, _)| hash == hashes[k])
+                    .map(|&(_, i)| i as usize)
+            })
+    }
+}
+
+fn cell_of(pos: Vector3<Scalar>, cell_size: Scalar) -> [i32; 3] {
+    // Saturating, so that far away bodies share the outermost cells
+    [pos.x, pos.y, pos.z].map(|x| (x / cell_size).floor() as i32)
+}
+
+fn hash([x, y, z]: [i32; 3]) -> u32 {
+    (x as u32).wrapping_mul(73_856_093)
+        ^ (y as u32).wrapping_mul(19_349_663)
+        ^ (z as u32).wrapping_mul(83_492_791)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Physics;
+    use cgmath::prelude::*;
+
+    #[test]
+    fn grid_finds_every_collision() {
+        let config = PhysicsConfig::default();
+        let mut bodies = *Physics::seeded(3).bodies();
+        // Crowded and fast, so that many bodies touch and more are about to
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.pos *= 0.3;
+            body.vel = 2.0 * Vector3::new((i as Scalar).sin(), (i as Scalar).cos(), 0.5);
+        }
+        let dt = 0.005;
+        let grid = ContactGrid::new(&bodies, dt, &config);
+        let mut collisions = 0;
+        for body in bodies.iter() {
+            let everywhere = body.contact_accels(bodies.iter(), dt, &config);
+            let near = body.contact_accels(grid.near(body.pos).map(|j| &bodies[j]), dt, &config);
+            if everywhere.0 != Vector3::zero() {
+                collisions += 1;
+            }
+            assert!((everywhere.0 - near.0).magnitude() <= 1e-4 * everywhere.0.magnitude());
+            assert!((everywhere.1 - near.1).magnitude() <= 1e-4 * everywhere.1.magnitude());
+        }
+        assert!(collisions > 10, "{collisions}");
+    }
+}
@@ -0,0 +1,393 @@
+//! Explicit encoding of the simulation state as `u64` words, used to hand [`Physics`] to and from
+//! the wasm worker. Unlike a `Pod` cast, this does not depend on the memory layout of `Physics`
+//! or on how `Instant` is represented.
+
+use crate::{
+    Body, BodySet, Boundary, ForceField, Group, Integrator, Material, Physics, PhysicsConfig,
+    MAX_FIELDS, MAX_GROUPS,
+};
+use cgmath::Quaternion;
+use instant::Instant;
+use std::time::Duration;
+
+/// Bumped whenever the encoding changes. Bodies are encoded in the precision of the simulation,
+/// marked by the high half of the version, so that builds with and without the `f64` feature
+/// refuse each other's states.
+pub const ENCODING_VERSION: u64 = 14 | (DOUBLE_PRECISION as u64) << 32;
+const DOUBLE_PRECISION: bool = cfg!(feature = "f64");
+const HEADER_WORDS: usize = 11 + WORDS_PER_GROUP * MAX_GROUPS + WORDS_PER_FIELD * MAX_FIELDS;
+const WORDS_PER_GROUP: usize = 2;
+/// The kind of field and up to 8 parameters
+const WORDS_PER_FIELD: usize = 5;
+#[cfg(not(feature = "f64"))]
+const WORDS_PER_BODY: usize = 9;
+#[cfg(feature = "f64")]
+const WORDS_PER_BODY: usize = 14;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnsupportedVersion(u64),
+    Truncated,
+    TooManyBodies(u64),
+}
+
+impl Physics {
+    /// Encodes everything except the timestamp, which is only meaningful to the local clock.
+    pub fn encode(&self) -> Vec<u64> {
+        let mut words = Vec::with_capacity(HEADER_WORDS + WORDS_PER_BODY * self.bodies.len());
+        let integrator = Integrator::ALL
+            .iter()
+            .position(|&integrator| integrator == self.config.integrator)
+            .unwrap() as u32;
+        let boundary = Boundary::ALL
+            .iter()
+            .position(|&boundary| boundary == self.config.boundary)
+            .unwrap() as u32;
+        words.extend([
+            ENCODING_VERSION,
+            self.ticks,
+            self.delta_time.as_nanos() as u64,
+            self.simulated_time.as_nanos() as u64,
+            pack(
+                self.config.gravity.to_bits(),
+                self.config.stiffness.to_bits(),
+            ),
+            pack(self.config.damping.to_bits(), self.config.gap.to_bits()),
+            pack(self.config.softening.to_bits(), integrator),
+            self.bodies.len() as u64,
+            pack(boundary, self.config.boundary_radius.to_bits()),
+            u64::from(self.config.warm_up.to_bits()),
+            self.warm_up_start.as_nanos() as u64,
+        ]);
+        for group in &self.config.groups {
+            words.extend(encode_group(group));
+        }
+        for field in &self.config.fields {
+            words.extend(encode_field(field.as_ref()));
+        }
+        for body in self.bodies.iter() {
+            words.extend(encode_body(body));
+        }
+        words
+    }
+    pub fn decode(words: &[u64], timestamp: Instant) -> Result<Box<Self>, DecodeError> {
+        let (header, rest) = split(words, HEADER_WORDS)?;
+        let (header, field_words) = header.split_at(HEADER_WORDS - WORDS_PER_FIELD * MAX_FIELDS);
+        let (header, group_words) = header.split_at(header.len() - WORDS_PER_GROUP * MAX_GROUPS);
+        let (&warm_up_start, header) = header.split_last().unwrap();
+        let (&warm_up, header) = header.split_last().unwrap();
+        let (&containment, header) = header.split_last().unwrap();
+        let &[version, ticks, delta_time, simulated_time, constants, contact, integration, count] =
+            header
+        else {
+            unreachable!()
+        };
+        if version != ENCODING_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        if count > BodySet::capacity() as u64 {
+            return Err(DecodeError::TooManyBodies(count));
+        }
+        let (body_words, _) = split(rest, count as usize * WORDS_PER_BODY)?;
+        let bodies = body_words
+            .chunks_exact(WORDS_PER_BODY)
+            .map(decode_body)
+            .collect();
+        let mut groups = [Group::default(); MAX_GROUPS];
+        for (group, words) in groups
+            .iter_mut()
+            .zip(group_words.chunks_exact(WORDS_PER_GROUP))
+        {
+            *group = decode_group(words);
+        }
+        let mut fields = [None; MAX_FIELDS];
+        for (field, words) in fields
+            .iter_mut()
+            .zip(field_words.chunks_exact(WORDS_PER_FIELD))
+        {
+            *field = decode_field(words);
+        }
+        let f = f32::from_bits;
+        let ((gravity, stiffness), (damping, gap)) = (unpack(constants), unpack(contact));
+        let (softening, integrator) = unpack(integration);
+        let (boundary, boundary_radius) = unpack(containment);
+        Ok(Box::new(Self {
+            bodies,
+            config: PhysicsConfig {
+                gravity: f(gravity),
+                stiffness: f(stiffness),
+                damping: f(damping),
+                gap: f(gap),
+                softening: f(softening),
+                integrator: Integrator::ALL
+                    .get(integrator as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                groups,
+                fields,
+                boundary: Boundary::ALL
+                    .get(boundary as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                boundary_radius: f(boundary_radius),
+                warm_up: f(warm_up as u32),
+            },
+            ticks,
+            delta_time: Duration::from_nanos(delta_time),
+            simulated_time: Duration::from_nanos(simulated_time),
+            warm_up_start: Duration::from_nanos(warm_up_start),
+            timestamp,
+        }))
+    }
+}
+
+fn split(words: &[u64], at: usize) -> Result<(&[u64], &[u64]), DecodeError> {
+    if words.len() < at {
+        return Err(DecodeError::Truncated);
+    }
+    Ok(words.split_at(at))
+}
+fn pack(a: u32, b: u32) -> u64 {
+    (u64::from(a) << 32) | u64::from(b)
+}
+fn unpack(word: u64) -> (u32, u32) {
+    ((word >> 32) as u32, word as u32)
+}
+fn encode_group(group: &Group) -> [u64; WORDS_PER_GROUP] {
+    let material = Material::ALL
+        .iter()
+        .position(|&material| material == group.material)
+        .unwrap() as u32;
+    // Without a softening length of its own, NaN is stored in its place
+    let softening = group.softening.unwrap_or(f32::NAN);
+    [
+        pack(group.coupling.to_bits(), group.stiffness.to_bits()),
+        pack(material, softening.to_bits()),
+    ]
+}
+fn decode_group(words: &[u64]) -> Group {
+    let (coupling, stiffness) = unpack(words[0]);
+    let (material, softening) = unpack(words[1]);
+    Group {
+        coupling: f32::from_bits(coupling),
+        stiffness: f32::from_bits(stiffness),
+        softening: Some(f32::from_bits(softening)).filter(|softening| !softening.is_nan()),
+        material: Material::ALL
+            .get(material as usize)
+            .copied()
+            .unwrap_or(Material::Solid),
+    }
+}
+fn encode_field(field: Option<&ForceField>) -> [u64; WORDS_PER_FIELD] {
+    // 0 for no field, and the parameters unused by a kind left zero
+    let (kind, p) = match field {
+        None => (0, [0.0; 8]),
+        Some(&ForceField::Uniform { accel: a }) => (1, [a.x, a.y, a.z, 0.0, 0.0, 0.0, 0.0, 0.0]),
+        Some(&ForceField::Vortex {
+            center: c,
+            axis: a,
+            swirl,
+            pull,
+        }) => (2, [c.x, c.y, c.z, a.x, a.y, a.z, swirl, pull]),
+        Some(&ForceField::Point {
+            center: c,
+            strength,
+        }) => (3, [c.x, c.y, c.z, strength, 0.0, 0.0, 0.0, 0.0]),
+    };
+    let b = f32::to_bits;
+    [
+        pack(kind, b(p[0])),
+        pack(b(p[1]), b(p[2])),
+        pack(b(p[3]), b(p[4])),
+        pack(b(p[5]), b(p[6])),
+        pack(b(p[7]), 0),
+    ]
+}
+fn decode_field(words: &[u64]) -> Option<ForceField> {
+    let (kind, p0) = unpack(words[0]);
+    let (p1, p2) = unpack(words[1]);
+    let (p3, p4) = unpack(words[2]);
+    let (p5, p6) = unpack(words[3]);
+    let (p7, _) = unpack(words[4]);
+    let p = [p0, p1, p2, p3, p4, p5, p6, p7].map(f32::from_bits);
+    match kind {
+        1 => Some(ForceField::Uniform {
+            accel: [p[0], p[1], p[2]].into(),
+        }),
+        2 => Some(ForceField::Vortex {
+            center: [p[0], p[1], p[2]].into(),
+            axis: [p[3], p[4], p[5]].into(),
+            swirl: p[6],
+            pull: p[7],
+        }),
+        3 => Some(ForceField::Point {
+            center: [p[0], p[1], p[2]].into(),
+            strength: p[3],
+        }),
+        _ => None,
+    }
+}
+#[cfg(not(feature = "f64"))]
+fn encode_body(body: &Body) -> [u64; WORDS_PER_BODY] {
+    let Body {
+        pos,
+        vel,
+        radius,
+        density,
+        color,
+        flags,
+        spin,
+        orientation: Quaternion { v: axis, s },
+    } = *body;
+    [
+        pack(pos.x.to_bits(), pos.y.to_bits()),
+        pack(pos.z.to_bits(), vel.x.to_bits()),
+        pack(vel.y.to_bits(), vel.z.to_bits()),
+        pack(radius.to_bits(), color),
+        pack(flags, spin.x.to_bits()),
+        pack(spin.y.to_bits(), spin.z.to_bits()),
+        pack(axis.x.to_bits(), axis.y.to_bits()),
+        pack(axis.z.to_bits(), s.to_bits()),
+        pack(density.to_bits(), 0),
+    ]
+}
+#[cfg(not(feature = "f64"))]
+fn decode_body(words: &[u64]) -> Body {
+    let f = f32::from_bits;
+    let (px, py) = unpack(words[0]);
+    let (pz, vx) = unpack(words[1]);
+    let (vy, vz) = unpack(words[2]);
+    let (radius, color) = unpack(words[3]);
+    let (flags, sx) = unpack(words[4]);
+    let (sy, sz) = unpack(words[5]);
+    let (ox, oy) = unpack(words[6]);
+    let (oz, ow) = unpack(words[7]);
+    let (density, _) = unpack(words[8]);
+    Body {
+        pos: [f(px), f(py), f(pz)].into(),
+        vel: [f(vx), f(vy), f(vz)].into(),
+        radius: f(radius),
+        density: f(density),
+        color,
+        flags,
+        spin: [f(sx), f(sy), f(sz)].into(),
+        orientation: Quaternion::new(f(ow), f(ox), f(oy), f(oz)),
+    }
+}
+
+#[cfg(feature = "f64")]
+fn encode_body(body: &Body) -> [u64; WORDS_PER_BODY] {
+    let Body {
+        pos,
+        vel,
+        radius,
+        density,
+        color,
+        flags,
+        spin,
+        orientation: Quaternion { v: axis, s },
+    } = *body;
+    [
+        pos.x.to_bits(),
+        pos.y.to_bits(),
+        pos.z.to_bits(),
+        vel.x.to_bits(),
+        vel.y.to_bits(),
+        vel.z.to_bits(),
+        radius.to_bits(),
+        density.to_bits(),
+        spin.x.to_bits(),
+        spin.y.to_bits(),
+        spin.z.to_bits(),
+        pack(color, flags),
+        pack(axis.x.to_bits(), axis.y.to_bits()),
+        pack(axis.z.to_bits(), s.to_bits()),
+    ]
+}
+#[cfg(feature = "f64")]
+fn decode_body(words: &[u64]) -> Body {
+    let d = |index: usize| f64::from_bits(words[index]);
+    let f = f32::from_bits;
+    let (color, flags) = unpack(words[11]);
+    let (ox, oy) = unpack(words[12]);
+    let (oz, ow) = unpack(words[13]);
+    Body {
+        pos: [d(0), d(1), d(2)].into(),
+        vel: [d(3), d(4), d(5)].into(),
+        radius: d(6),
+        density: d(7),
+        color,
+        flags,
+        spin: [d(8), d(9), d(10)].into(),
+        orientation: Quaternion::new(f(ow), f(ox), f(oy), f(oz)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut physics = Physics::initial();
+        physics.ticks = 1234;
+        physics.set_delta_time(Duration::from_micros(500));
+        physics.simulated_time = Duration::from_millis(987);
+        physics.warm_up_start = Duration::from_millis(654);
+        physics.config.gravity = 12.5;
+        physics.config.softening = 0.01;
+        physics.config.integrator = Integrator::Yoshida4;
+        physics.config.boundary = Boundary::Periodic;
+        physics.config.boundary_radius = 2.5;
+        physics.config.warm_up = 3.0;
+        physics.config.groups[2] = Group {
+            coupling: 0.5,
+            stiffness: 3.0,
+            softening: Some(0.02),
+            material: Material::Glass,
+        };
+        physics.config.fields[1] = Some(ForceField::Vortex {
+            center: [0.0, 0.5, 0.0].into(),
+            axis: [0.0, 1.0, 0.0].into(),
+            swirl: 0.4,
+            pull: -0.1,
+        });
+        physics.config.fields[3] = Some(ForceField::Point {
+            center: [1.0, 2.0, 3.0].into(),
+            strength: 2.0,
+        });
+        physics.bodies_mut().swap_remove(7);
+        physics.bodies_mut()[3].density = 8.0;
+        let decoded = Physics::decode(&physics.encode(), Instant::now()).unwrap();
+        assert_eq!(decoded.ticks, physics.ticks);
+        assert_eq!(decoded.delta_time, physics.delta_time);
+        assert_eq!(decoded.simulated_time, physics.simulated_time);
+        assert_eq!(decoded.warm_up_start, physics.warm_up_start);
+        assert_eq!(decoded.config, physics.config);
+        assert_eq!(decoded.bodies().len(), physics.bodies().len());
+        for (a, b) in decoded.bodies().iter().zip(physics.bodies().iter()) {
+            assert_eq!(encode_body(a), encode_body(b));
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut words = Physics::initial().encode();
+        words[0] = ENCODING_VERSION + 1;
+        assert_eq!(
+            Physics::decode(&words, Instant::now()).unwrap_err(),
+            DecodeError::UnsupportedVersion(ENCODING_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let words = Physics::initial().encode();
+        for len in [0, HEADER_WORDS, words.len() - 1] {
+            assert_eq!(
+                Physics::decode(&words[..len], Instant::now()).unwrap_err(),
+                DecodeError::Truncated
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::Scalar;
+use cgmath::{prelude::*, Vector3};
+
+/// The number of force fields that may act at once, see [`PhysicsConfig::fields`]
+///
+/// [`PhysicsConfig::fields`]: crate::PhysicsConfig::fields
+pub const MAX_FIELDS: usize = 4;
+/// Within this distance of the center of a vortex or a point, fields stop growing, so that bodies
+/// passing through it are not flung away
+const CORE_RADIUS: Scalar = 0.05;
+/// How quickly bodies take up the flow of a vortex, the inverse of the time it takes in seconds
+const VORTEX_DRAG: Scalar = 2.0;
+
+/// A force from outside the system, accelerating every body regardless of its mass, in addition
+/// to gravity and collisions between the bodies. Fields are summed, so that several make up more
+/// interesting ones, such as a whirlpool draining downwards. Energy and momentum are not
+/// conserved while any act.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ForceField {
+    /// The same acceleration everywhere, like gravity near the surface of a planet
+    Uniform { accel: Vector3<f32> },
+    /// Drags bodies along a whirlpool around the line through `center` along `axis`, a unit
+    /// vector. The flow circles counter clockwise seen from where the axis points, at `swirl`
+    /// over the distance from the line, and flows in towards the line at `pull` over the
+    /// distance, or out when negative.
+    Vortex {
+        center: Vector3<f32>,
+        axis: Vector3<f32>,
+        swirl: f32,
+        pull: f32,
+    },
+    /// Attracts bodies to `center` when `strength` is positive and repels them when negative,
+    /// weakening with the square of the distance like gravity
+    Point { center: Vector3<f32>, strength: f32 },
+}
+impl ForceField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Uniform { .. } => "uniform",
+            Self::Vortex { .. } => "vortex",
+            Self::Point { .. } => "point",
+        }
+    }
+    /// The acceleration of a body at `pos` moving at `vel`.
+    pub fn accel(&self, pos: Vector3<Scalar>, vel: Vector3<Scalar>) -> Vector3<Scalar> {
+        let v = |v: Vector3<f32>| v.map(Scalar::from);
+        match *self {
+            Self::Uniform { accel } => v(accel),
+            Self::Vortex {
+                center,
+                axis,
+                swirl,
+                pull,
+            } => {
+                let axis = v(axis);
+                let rel_pos = pos - v(center);
+                let radial = rel_pos - rel_pos.dot(axis) * axis;
+                let flow = (Scalar::from(swirl) * axis.cross(radial) - Scalar::from(pull) * radial)
+                    / (radial.magnitude2() + CORE_RADIUS.powi(2));
+                VORTEX_DRAG * (flow - vel)
+            }
+            Self::Point { center, strength } => {
+                let rel_pos = v(center) - pos;
+                let softened_distance2 = rel_pos.magnitude2() + CORE_RADIUS.powi(2);
+                Scalar::from(strength) / (softened_distance2 * softened_distance2.sqrt()) * rel_pos
+            }
+        }
+    }
+}
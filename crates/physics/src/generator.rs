@@ -0,0 +1,415 @@
+//! The random generator of initial clusters, with configurable distributions of body radius and
+//! speed for systems of very different feel, laid out as one of several [`Preset`]s.
+//!
+//! Distributions are written as `<shape>:<min>..<max>`, where the shape is `half-normal`,
+//! `uniform`, `log-normal=<sigma>` or `power-law=<exponent>`, such as `power-law=2.5:0.005..0.1`.
+
+use crate::{
+    math,
+    random::{stream, Stream},
+    Body, BodySet, Material, PhysicsConfig, Scalar, BODIES,
+};
+use cgmath::{prelude::*, Quaternion, Vector3};
+use rand::Rng;
+use std::f32::consts::TAU;
+
+/// The radii of the disc and of the ring around the binary star, between which bodies orbit
+const DISC_RADII: (f32, f32) = (0.2, 1.5);
+const RING_RADII: (f32, f32) = (1.0, 1.8);
+/// The standard deviation of the height of bodies over the disc
+const DISC_THICKNESS: Scalar = 0.03;
+/// The scale of each of the colliding clusters, and how far from the origin they start
+const COLLIDING_SCALE: Scalar = 0.5;
+const COLLIDING_OFFSET: Scalar = 1.5;
+/// The distance between neighboring points of the lattice
+const LATTICE_SPACING: Scalar = 0.3;
+/// The standard deviation of how far bodies of the lattice and the shell are moved off of it, so
+/// that they do not collapse perfectly symmetrically
+const JITTER: Scalar = 0.01;
+const SHELL_RADIUS: Scalar = 1.5;
+/// The radius of each star of the binary star, and the distance between them
+const STAR_RADIUS: Scalar = 0.25;
+const STAR_SEPARATION: Scalar = 0.6;
+
+/// How the bodies of the initial cluster are laid out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Preset {
+    /// A normally distributed blob in equilibrium
+    #[default]
+    Cluster,
+    /// A thin disc facing the initial camera, with each body orbiting the mass inside of its
+    /// orbit
+    Disc,
+    /// Two clusters in equilibrium, headed for each other
+    Collision,
+    /// Bodies at rest on the points of a cubic lattice, which collapses
+    Lattice,
+    /// Bodies at rest on the surface of a sphere, which collapses
+    Shell,
+    /// Two large bodies orbiting each other, ringed by small ones
+    Binary,
+}
+impl Preset {
+    pub const ALL: [Self; 6] = [
+        Self::Cluster,
+        Self::Disc,
+        Self::Collision,
+        Self::Lattice,
+        Self::Shell,
+        Self::Binary,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cluster => "cluster",
+            Self::Disc => "disc",
+            Self::Collision => "collision",
+            Self::Lattice => "lattice",
+            Self::Shell => "shell",
+            Self::Binary => "binary",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// Most values near the minimum, thinning out towards the maximum three standard deviations
+    /// above it
+    HalfNormal,
+    Uniform,
+    /// Values whose logarithm is normally distributed around the geometric mean of the range
+    LogNormal {
+        sigma: f32,
+    },
+    /// A density proportional to `x^-exponent`, so mostly small values with a few large ones
+    PowerLaw {
+        exponent: f32,
+    },
+}
+
+/// A distribution of values within a range, which values outside of are clamped to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampling {
+    pub shape: Shape,
+    pub min: f32,
+    pub max: f32,
+}
+impl Sampling {
+    /// Parses the format described in the [module documentation](self).
+    pub fn parse(text: &str) -> Option<Self> {
+        let (shape, range) = text.trim().split_once(':')?;
+        let (min, max) = range.split_once("..")?;
+        let (min, max): (f32, f32) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+        let shape = match shape.split_once('=') {
+            None if shape == "half-normal" => Shape::HalfNormal,
+            None if shape == "uniform" => Shape::Uniform,
+            Some(("log-normal", sigma)) => Shape::LogNormal {
+                sigma: sigma.parse().ok()?,
+            },
+            Some(("power-law", exponent)) => Shape::PowerLaw {
+                exponent: exponent.parse().ok()?,
+            },
+            _ => return None,
+        };
+        // Logarithms and negative powers need a positive minimum
+        let positive = matches!(shape, Shape::HalfNormal | Shape::Uniform) || min > 0.0;
+        (min.is_finite() && max.is_finite() && 0.0 <= min && min <= max && positive)
+            .then_some(Self { shape, min, max })
+    }
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        let Self { shape, min, max } = *self;
+        let value = match shape {
+            Shape::HalfNormal => min + (max - min) / 3.0 * math::standard_normal(rng).abs(),
+            Shape::Uniform => rng.gen_range(min..=max),
+            Shape::LogNormal { sigma } => {
+                let mean = 0.5 * (math::ln(min) + math::ln(max));
+                if sigma >= 0.0 && sigma.is_finite() {
+                    math::exp(mean + sigma * math::standard_normal(rng))
+                } else {
+                    math::exp(mean)
+                }
+            }
+            Shape::PowerLaw { exponent } => {
+                // Inverting the cumulative distribution function
+                let u: f32 = rng.gen();
+                let k = 1.0 - exponent;
+                if k.abs() < 1e-6 {
+                    min * math::powf(max / min, u)
+                } else {
+                    let (min_k, max_k) = (math::powf(min, k), math::powf(max, k));
+                    math::powf(min_k + u * (max_k - min_k), 1.0 / k)
+                }
+            }
+        };
+        value.clamp(min, max)
+    }
+}
+
+/// How bodies of the initial cluster are drawn. Positions of [`Preset::Cluster`] are normally
+/// distributed around the origin and velocities go around it, while the other presets place
+/// bodies as they describe, with only their radius, color and material drawn like these.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Generator {
+    pub preset: Preset,
+    pub radius: Sampling,
+    /// Without a distribution, speeds grow with the distance from the origin and are then
+    /// rescaled into equilibrium, see [`Body::virialize`]. Only used by the presets of
+    /// clusters.
+    pub speed: Option<Sampling>,
+}
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            preset: Preset::Cluster,
+            radius: Sampling {
+                shape: Shape::HalfNormal,
+                min: 0.006,
+                max: 0.078,
+            },
+            speed: None,
+        }
+    }
+}
+impl Generator {
+    pub fn body(&self, rng: &mut impl Rng) -> Body {
+        let mut r = || math::standard_normal(&mut *rng);
+        let pos: Vector3<f32> = [r(), r(), r()].into();
+        let rand: Vector3<f32> = [r(), r(), r()].into();
+        let mut vel = 0.1 * pos.cross(rand);
+        if let Some(speed) = self.speed {
+            let direction = vel.normalize();
+            vel = if direction.is_finite() {
+                speed.sample(rng) * direction
+            } else {
+                Vector3::zero()
+            };
+        }
+        // Drawn in single precision either way, so that seeds make the same clusters
+        let mut body = Body {
+            pos: widen(pos),
+            vel: widen(vel),
+            radius: Scalar::from(self.radius.sample(rng)),
+            density: 1.0,
+            color: rng.gen(),
+            flags: 0,
+            spin: Vector3::zero(),
+            orientation: Quaternion::one(),
+        };
+        body.set_material(random_material(rng));
+        body
+    }
+    /// A full cluster drawn from the [`Stream::Initial`] stream of a master seed.
+    pub fn cluster(&self, seed: u64, config: &PhysicsConfig) -> BodySet {
+        let mut rng = stream(seed, Stream::Initial);
+        let rng = &mut rng;
+        let gaussian = |rng: &mut _| -> Vector3<Scalar> {
+            let mut r = || math::standard_normal(&mut *rng);
+            widen([r(), r(), r()].into())
+        };
+        let mut bodies: BodySet;
+        match self.preset {
+            Preset::Cluster => {
+                bodies = (0..BODIES).map(|_| self.body(rng)).collect();
+                if self.speed.is_none() {
+                    Body::virialize(&mut bodies, config);
+                }
+            }
+            Preset::Disc => {
+                bodies = (0..BODIES)
+                    .map(|_| {
+                        let mut body = self.body(rng);
+                        body.pos = DISC_THICKNESS * gaussian(rng);
+                        body.pos += planar_point(rng, DISC_RADII);
+                        body
+                    })
+                    .collect();
+                orbit_enclosed_mass(&mut bodies, config);
+            }
+            Preset::Collision => {
+                bodies = (0..BODIES)
+                    .map(|_| {
+                        let mut body = self.body(rng);
+                        body.pos *= COLLIDING_SCALE;
+                        body.vel *= COLLIDING_SCALE;
+                        body
+                    })
+                    .collect();
+                let (first, second) = bodies.split_at_mut(BODIES / 2);
+                if self.speed.is_none() {
+                    Body::virialize(first, config);
+                    Body::virialize(second, config);
+                }
+                // Approaching at about the speed of bodies within each cluster
+                let (kinetic, _) = Body::energies(&bodies, config);
+                let mass: Scalar = bodies.iter().map(Body::mass).sum();
+                let speed = (2.0 * kinetic / mass).sqrt();
+                for (index, body) in bodies.iter_mut().enumerate() {
+                    let side = if index < BODIES / 2 { -1.0 } else { 1.0 };
+                    body.pos.x += side * COLLIDING_OFFSET;
+                    body.vel.x -= side * speed;
+                }
+            }
+            Preset::Lattice => {
+                let side = (1..).take_while(|n| n * n * n <= BODIES).last().unwrap();
+                let middle = (side - 1) as Scalar / 2.0;
+                bodies = (0..side * side * side)
+                    .map(|index| {
+                        let point =
+                            Vector3::new(index % side, index / side % side, index / side / side)
+                                .map(|i| LATTICE_SPACING * (i as Scalar - middle));
+                        Body {
+                            pos: point + JITTER * gaussian(rng),
+                            vel: Vector3::zero(),
+                            ..self.body(rng)
+                        }
+                    })
+                    .collect();
+            }
+            Preset::Shell => {
+                bodies = (0..BODIES)
+                    .map(|_| Body {
+                        pos: SHELL_RADIUS * gaussian(rng).normalize() + JITTER * gaussian(rng),
+                        vel: Vector3::zero(),
+                        ..self.body(rng)
+                    })
+                    .collect();
+            }
+            Preset::Binary => {
+                bodies = (0..BODIES)
+                    .map(|index| {
+                        let mut body = self.body(rng);
+                        if index < 2 {
+                            let side = if index == 0 { -1.0 } else { 1.0 };
+                            body.pos = Vector3::new(0.0, side * STAR_SEPARATION / 2.0, 0.0);
+                            body.radius = STAR_RADIUS;
+                        } else {
+                            body.pos = DISC_THICKNESS * gaussian(rng);
+                            body.pos += planar_point(rng, RING_RADII);
+                        }
+                        body
+                    })
+                    .collect();
+                orbit_enclosed_mass(&mut bodies, config);
+                // The stars orbit each other instead, in the same direction as the ring, at the
+                // speed where gravity between them bends their paths into a circle
+                let mass = bodies[0].mass();
+                let speed = (Scalar::from(config.gravity) * mass / (2.0 * STAR_SEPARATION)).sqrt();
+                for star in &mut bodies[..2] {
+                    star.vel = speed * Vector3::unit_x().cross(star.pos.normalize());
+                }
+            }
+        }
+        bodies
+    }
+}
+
+/// A point in the plane through the origin facing the initial camera, evenly distributed over
+/// the area between two radii.
+fn planar_point(rng: &mut impl Rng, (inner, outer): (f32, f32)) -> Vector3<Scalar> {
+    let radius = rng.gen_range(inner.powi(2)..outer.powi(2)).sqrt();
+    let (sin, cos) = math::sin_cos(rng.gen_range(0.0..TAU));
+    widen(Vector3::new(0.0, radius * cos, radius * sin))
+}
+
+fn widen(vector: Vector3<f32>) -> Vector3<Scalar> {
+    vector.map(Scalar::from)
+}
+
+/// Sets the bodies going around the axis normal to the plane of [`planar_point`], each at the
+/// speed of a circular orbit around the mass closer to the axis, as if that mass were at the
+/// origin.
+fn orbit_enclosed_mass(bodies: &mut [Body], config: &PhysicsConfig) {
+    let planar = |pos: Vector3<Scalar>| Vector3::new(0.0, pos.y, pos.z);
+    let orbits: Vec<(Scalar, Scalar)> = bodies
+        .iter()
+        .map(|body| (planar(body.pos).magnitude(), body.mass()))
+        .collect();
+    for body in bodies.iter_mut() {
+        let radius = planar(body.pos).magnitude();
+        if radius == 0.0 {
+            continue;
+        }
+        let enclosed: Scalar = orbits
+            .iter()
+            .filter(|&&(other, _)| other < radius)
+            .map(|&(_, mass)| mass)
+            .sum();
+        let speed = (Scalar::from(config.gravity) * enclosed / radius).sqrt();
+        body.vel = speed * Vector3::unit_x().cross(planar(body.pos)) / radius;
+    }
+}
+
+/// Mostly bodies of the material of their group, with some bouncy and some dead ones among them
+fn random_material(rng: &mut impl Rng) -> Option<Material> {
+    match rng.gen_range(0..10) {
+        0 | 1 => Some(Material::Rubber),
+        2 | 3 => Some(Material::Metal),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::SYSTEM_RADIUS;
+
+    #[test]
+    fn parsed_distributions_stay_in_range() {
+        let parse = |text| Sampling::parse(text).unwrap();
+        assert_eq!(
+            parse("power-law=2.5:0.005..0.1"),
+            Sampling {
+                shape: Shape::PowerLaw { exponent: 2.5 },
+                min: 0.005,
+                max: 0.1,
+            }
+        );
+        assert_eq!(Sampling::parse("log-normal=1:0..1"), None);
+        assert_eq!(Sampling::parse("uniform:2..1"), None);
+        let mut rng = stream(0, Stream::Initial);
+        for text in [
+            "half-normal:0.01..0.05",
+            "uniform:0..0.5",
+            "log-normal=0.5:0.01..0.05",
+            "power-law=1:0.01..0.05",
+            "power-law=2.5:0.01..0.05",
+        ] {
+            let sampling = parse(text);
+            for _ in 0..1000 {
+                let value = sampling.sample(&mut rng);
+                assert!((sampling.min..=sampling.max).contains(&value), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn presets_are_reproducible_and_inside_the_system() {
+        let config = PhysicsConfig::default();
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+            let generator = Generator {
+                preset,
+                ..Generator::default()
+            };
+            let bodies = generator.cluster(5, &config);
+            assert!(bodies.len() > BODIES / 2, "{preset:?}");
+            assert_eq!(
+                bytemuck::cast_slice::<Body, u8>(&bodies),
+                bytemuck::cast_slice::<Body, u8>(&generator.cluster(5, &config)),
+                "{preset:?}"
+            );
+            for body in bodies.iter() {
+                assert!(
+                    body.pos.magnitude() + body.radius < SYSTEM_RADIUS,
+                    "{preset:?}"
+                );
+                assert!(body.vel.is_finite(), "{preset:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! The schemes advancing bodies by one step given their accelerations. Higher order schemes
+//! evaluate accelerations several times per step, and in exchange keep the energy of long
+//! running orbits from drifting.
+
+use crate::{Body, BodySet, PhysicsConfig, Scalar, BODIES};
+use cgmath::{prelude::*, Vector3};
+
+/// The Yoshida coefficients, composing three leapfrog steps of which the middle one runs
+/// backwards, such that the errors of the three cancel to fourth order
+#[allow(clippy::excessive_precision)]
+const YOSHIDA_FORWARD: Scalar = 1.351_207_191_959_657_6; // 1 / (2 - 2^(1/3))
+#[allow(clippy::excessive_precision)]
+const YOSHIDA_BACKWARD: Scalar = -1.702_414_383_919_315_3; // -2^(1/3) / (2 - 2^(1/3))
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// A second order Taylor step from the accelerations at the start of the step, which is the
+    /// cheapest but lets the energy of orbits drift
+    #[default]
+    Taylor,
+    /// Kick-drift-kick leapfrog, second order and symplectic, evaluating accelerations twice
+    Leapfrog,
+    /// The classical fourth order Runge-Kutta method, evaluating accelerations four times
+    RungeKutta4,
+    /// The fourth order symplectic method of Yoshida, evaluating accelerations three times
+    Yoshida4,
+}
+impl Integrator {
+    pub const ALL: [Self; 4] = [
+        Self::Taylor,
+        Self::Leapfrog,
+        Self::RungeKutta4,
+        Self::Yoshida4,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Taylor => "taylor",
+            Self::Leapfrog => "leapfrog",
+            Self::RungeKutta4 => "rk4",
+            Self::Yoshida4 => "yoshida",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|integrator| integrator.name() == name)
+    }
+    /// Advances the bodies by `dt` seconds. `accels` holds the accelerations of the bodies as
+    /// they are, and `accels_of` computes them for other states, into the given slice.
+    pub(crate) fn step(
+        self,
+        bodies: &mut [Body],
+        accels: &mut [Vector3<Scalar>],
+        dt: Scalar,
+        config: &PhysicsConfig,
+        mut accels_of: impl FnMut(&[Body], &mut [Vector3<Scalar>]),
+    ) {
+        if self != Self::Taylor {
+            Body::settle_velocities(bodies, config);
+        }
+        match self {
+            Self::Taylor => Body::perform_step(bodies, accels, dt, config),
+            Self::Leapfrog => {
+                kick(bodies, accels, dt / 2.0);
+                drift(bodies, dt);
+                accels_of(bodies, accels);
+                kick(bodies, accels, dt / 2.0);
+            }
+            Self::Yoshida4 => {
+                // In the drift-kick-drift form, so that the accelerations given are not used
+                let drifts = [
+                    YOSHIDA_FORWARD / 2.0,
+                    (YOSHIDA_FORWARD + YOSHIDA_BACKWARD) / 2.0,
+                    (YOSHIDA_FORWARD + YOSHIDA_BACKWARD) / 2.0,
+                    YOSHIDA_FORWARD / 2.0,
+                ];
+                let kicks = [YOSHIDA_FORWARD, YOSHIDA_BACKWARD, YOSHIDA_FORWARD];
+                for (drift_weight, kick_weight) in drifts.into_iter().zip(kicks) {
+                    drift(bodies, drift_weight * dt);
+                    accels_of(bodies, accels);
+                    kick(bodies, accels, kick_weight * dt);
+                }
+                drift(bodies, drifts[3] * dt);
+            }
+            Self::RungeKutta4 => runge_kutta_4(bodies, accels, dt, accels_of),
+        }
+    }
+}
+
+fn drift(bodies: &mut [Body], dt: Scalar) {
+    for body in bodies.iter_mut().filter(|body| !body.is_frozen()) {
+        body.pos += body.vel * dt;
+    }
+}
+fn kick(bodies: &mut [Body], accels: &[Vector3<Scalar>], dt: Scalar) {
+    for (body, accel) in bodies.iter_mut().zip(accels) {
+        if !body.is_frozen() {
+            body.vel += accel * dt;
+        }
+    }
+}
+
+fn runge_kutta_4(
+    bodies: &mut [Body],
+    accels: &mut [Vector3<Scalar>],
+    dt: Scalar,
+    mut accels_of: impl FnMut(&[Body], &mut [Vector3<Scalar>]),
+) {
+    let n = bodies.len();
+    let start: BodySet = bodies.iter().copied().collect();
+    // The weighted sums of the derivatives of position and velocity over the four stages
+    let mut pos_sum = [Vector3::zero(); BODIES];
+    let mut vel_sum = [Vector3::zero(); BODIES];
+    for (stage, weight) in [1.0, 2.0, 2.0, 1.0].into_iter().enumerate() {
+        if stage > 0 {
+            accels_of(bodies, accels);
+        }
+        for i in 0..n {
+            pos_sum[i] += weight * bodies[i].vel;
+            vel_sum[i] += weight * accels[i];
+        }
+        // The state the next stage is evaluated at, offset from the start by this stage
+        let offset = if stage < 2 { dt / 2.0 } else { dt };
+        if stage < 3 {
+            for i in (0..n).filter(|&i| !start[i].is_frozen()) {
+                bodies[i].pos = start[i].pos + offset * bodies[i].vel;
+                bodies[i].vel = start[i].vel + offset * accels[i];
+            }
+        }
+    }
+    for i in (0..n).filter(|&i| !start[i].is_frozen()) {
+        bodies[i].pos = start[i].pos + dt / 6.0 * pos_sum[i];
+        bodies[i].vel = start[i].vel + dt / 6.0 * vel_sum[i];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::body, Physics, PhysicsConfig};
+    use std::time::Duration;
+
+    #[test]
+    fn higher_order_integrators_keep_orbits_closer_to_their_energy() {
+        let energy_error = |integrator| {
+            let mut physics = Physics::initial();
+            // An eccentric orbit of two bodies with a period of a few hundred ticks, which
+            // never touch
+            *physics.bodies_mut() = [-1.0, 1.0]
+                .into_iter()
+                .map(|side| {
+                    body(
+                        Vector3::new(0.25 * side, 0.0, 0.0),
+                        Vector3::new(0.0, 0.18 * side, 0.0),
+                        0.1,
+                    )
+                })
+                .collect();
+            physics.set_config(PhysicsConfig {
+                integrator,
+                ..PhysicsConfig::default()
+            });
+            physics.set_delta_time(Duration::from_millis(16));
+            let energy = |physics: &Physics| {
+                let (kinetic, potential) = Body::energies(physics.bodies(), physics.config());
+                kinetic + potential
+            };
+            let initial = energy(&physics);
+            physics.advance_ticks(5000);
+            ((energy(&physics) - initial) / initial).abs()
+        };
+        let taylor = energy_error(Integrator::Taylor);
+        for integrator in [
+            Integrator::Leapfrog,
+            Integrator::RungeKutta4,
+            Integrator::Yoshida4,
+        ] {
+            let error = energy_error(integrator);
+            assert!(error < 0.01 * taylor, "{integrator:?} {error} {taylor}");
+        }
+    }
+}
@@ -1,56 +1,294 @@
+//! The simulation of marble-gravity, in [`Physics`]: bodies attracting each other and colliding.
+//!
+//! It needs neither a window nor a clock, so that other programs and tests can drive it
+//! headlessly, tick by tick:
+//!
+//! ```
+//! use physics::Physics;
+//!
+//! let mut physics = Physics::seeded(7);
+//! physics.step(100);
+//! println!("{:?} later: {:?}", physics.simulated_time(), physics.conserved());
+//! ```
+//!
+//! The app instead paces it by wall-clock time, with [`Physics::advance_to`].
+
 use instant::Instant;
 use std::time::Duration;
 
+/// The default duration of a tick, see [`Physics::set_delta_time`]
 pub const PHYSICS_DELTA_TIME: Duration = Duration::from_millis(1);
+/// The range of tick durations
+pub const MIN_DELTA_TIME: Duration = Duration::from_micros(125);
+pub const MAX_DELTA_TIME: Duration = Duration::from_millis(16);
 pub const PHYSICS_MAX_BEHIND_TIME: Duration = Duration::from_secs(1);
+/// The maximum number of bodies, which GPU buffers are sized for.
 pub const BODIES: usize = 256;
 
+/// The precision of the state of bodies and of the forces between them: `f32`, or `f64` with the
+/// `f64` feature, so that long or large simulations do not accumulate rounding errors. Constants
+/// such as [`PhysicsConfig`] stay `f32`, and renderers take the state as such, see [`ToF32`].
+#[cfg(not(feature = "f64"))]
+pub type Scalar = f32;
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
+
+/// Conversion of the state of the simulation to single precision, at the boundary to rendering.
+pub trait ToF32 {
+    type Output;
+    fn to_f32(self) -> Self::Output;
+}
+impl ToF32 for Scalar {
+    type Output = f32;
+    #[allow(clippy::unnecessary_cast)]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+impl ToF32 for cgmath::Vector3<Scalar> {
+    type Output = cgmath::Vector3<f32>;
+    fn to_f32(self) -> cgmath::Vector3<f32> {
+        self.map(ToF32::to_f32)
+    }
+}
+
+/// The duration in seconds, in the precision of the simulation.
+fn seconds(duration: Duration) -> Scalar {
+    duration.as_secs_f64() as Scalar
+}
+
 mod body;
-pub use body::Body;
+mod bodyset;
+mod boundary;
+#[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+mod broadphase;
+mod encoding;
+mod field;
+mod generator;
+mod integrator;
+mod math;
+mod random;
+mod scenario;
+#[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+mod threads;
+mod tracers;
+mod units;
+pub use body::{Body, Conserved, Group, Material, PhysicsConfig, MAX_GROUPS};
+pub use bodyset::BodySet;
+pub use boundary::Boundary;
+pub use encoding::{DecodeError, ENCODING_VERSION};
+pub use field::{ForceField, MAX_FIELDS};
+pub use generator::{Generator, Preset, Sampling, Shape};
+pub use integrator::Integrator;
+pub use random::{random_seed, stream, Stream};
+pub use scenario::{
+    parse_scenario, write_fields, write_scenario, write_settings, Scenario, ScenarioCamera,
+    ScenarioError, ScenarioGroup, ScenarioSettings,
+};
+#[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+pub use threads::{configure_thread_pool, raise_thread_priority};
+pub use tracers::{gravitational_field, Tracer, Tracers, MAX_TRACERS, TRACER_DELTA_TIME};
+pub use units::{Ephemeris, Scale, Units};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Physics {
-    bodies: [Body; BODIES],
-    #[allow(unused)]
+    bodies: BodySet,
+    config: PhysicsConfig,
+    ticks: u64,
+    delta_time: Duration,
+    /// The sum of the durations of all ticks, which may have differed
+    simulated_time: Duration,
+    /// The simulated time at which the bodies were last replaced by a fresh cluster or scenario,
+    /// from which [`PhysicsConfig::warm_up`] counts
+    warm_up_start: Duration,
     timestamp: Instant,
 }
-unsafe impl bytemuck::Zeroable for Physics {}
-unsafe impl bytemuck::Pod for Physics {}
 
 #[derive(Clone, Copy, Debug)]
 pub struct PhysicsResult {
     pub elapsed_real: Duration,
     pub elapsed_physics_ticks: u64,
+    /// The last checkpoint reached, if any
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// Ticks between checkpoints
+pub const CHECKPOINT_INTERVAL: u64 = 1000;
+
+/// The state hash after every [`CHECKPOINT_INTERVAL`] ticks, which is the same for runs that
+/// started from the same state and stayed in lockstep, see [`Physics::state_hash`]. Runs on
+/// different platforms only stay in lockstep with the `deterministic` feature, see `math`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub tick: u64,
+    pub hash: u64,
 }
 
 impl Physics {
     pub fn initial() -> Box<Self> {
+        Self::seeded(random_seed())
+    }
+    /// The initial cluster of the default [`Generator`] under a master seed.
+    pub fn seeded(seed: u64) -> Box<Self> {
+        Self::generated(&Generator::default(), PhysicsConfig::default(), seed)
+    }
+    /// The initial cluster of a [`Generator`] under a master seed, in equilibrium under `config`.
+    pub fn generated(generator: &Generator, config: PhysicsConfig, seed: u64) -> Box<Self> {
+        Self::new(generator.cluster(seed, &config), config)
+    }
+    /// A simulation of the given bodies, such as those of a [`Scenario`], at its first tick. Its
+    /// timestamp, which only pacing by [`Physics::advance_to`] uses, is now.
+    pub fn new(bodies: BodySet, config: PhysicsConfig) -> Box<Self> {
         Box::new(Self {
-            bodies: (0..BODIES)
-                .into_iter()
-                .map(|_| Body::initial())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
+            bodies,
+            config,
+            ticks: 0,
+            delta_time: PHYSICS_DELTA_TIME,
+            simulated_time: Duration::ZERO,
+            warm_up_start: Duration::ZERO,
             timestamp: Instant::now(),
         })
     }
-    pub fn bodies(&self) -> &[Body; BODIES] {
+    pub fn bodies(&self) -> &BodySet {
         &self.bodies
     }
+    pub fn bodies_mut(&mut self) -> &mut BodySet {
+        &mut self.bodies
+    }
+    /// Replaces the bodies with a fresh set, such as a newly generated cluster, restarting the
+    /// warm-up. Unlike edits through [`Physics::bodies_mut`], the new bodies need to settle.
+    pub fn replace_bodies(&mut self, bodies: BodySet) {
+        self.bodies = bodies;
+        self.warm_up_start = self.simulated_time;
+    }
+    pub fn config(&self) -> &PhysicsConfig {
+        &self.config
+    }
+    /// Changes the constants of the simulation, taking effect from the next tick.
+    pub fn set_config(&mut self, config: PhysicsConfig) {
+        self.config = config;
+    }
+    /// The mass-weighted mean position, which stays still since momentum is kept at zero.
+    pub fn center_of_mass(&self) -> cgmath::Vector3<Scalar> {
+        use cgmath::{prelude::*, Vector3};
+        let total_mass: Scalar = self.bodies.iter().map(Body::mass).sum();
+        if total_mass == 0.0 {
+            return Vector3::zero();
+        }
+        self.bodies
+            .iter()
+            .map(|b| b.mass() * b.pos)
+            .sum::<Vector3<Scalar>>()
+            / total_mass
+    }
+    /// The energies and momenta, whose drift shows integration error and collision losses.
+    pub fn conserved(&self) -> Conserved {
+        Body::conserved(&self.bodies, &self.config, self.center_of_mass())
+    }
+    /// The number of ticks simulated since the initial state.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+    /// The simulated time since the initial state. Unlike [`Physics::ticks`], this is unaffected
+    /// by changes to the tick duration.
+    pub fn simulated_time(&self) -> Duration {
+        self.simulated_time
+    }
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+    /// Sets the duration of each tick, clamped to [`MIN_DELTA_TIME`] and [`MAX_DELTA_TIME`].
+    /// Longer ticks are cheaper, but risk blowing up collisions, see
+    /// [`Physics::stable_delta_time`].
+    pub fn set_delta_time(&mut self, delta_time: Duration) {
+        self.delta_time = delta_time.clamp(MIN_DELTA_TIME, MAX_DELTA_TIME);
+    }
+    /// Replaces the bodies, groups and fields with those of a scenario, and takes its tick
+    /// duration if it has one. Like [`Physics::replace_bodies`], this restarts the warm-up.
+    pub fn load_scenario(&mut self, scenario: Scenario) {
+        self.config.groups = scenario.group_table();
+        self.config.fields = scenario.field_table();
+        self.replace_bodies(scenario.bodies);
+        if let Some(delta_time) = scenario.settings.delta_time {
+            self.set_delta_time(delta_time);
+        }
+    }
+    /// The pairs of bodies that touch, by their indices with the lower first, and the speed at
+    /// which each pair approaches.
+    pub fn contacts(&self) -> impl Iterator<Item = (usize, usize, Scalar)> + '_ {
+        use cgmath::prelude::*;
+
+        let bodies = &self.bodies;
+        let gap = Scalar::from(self.config.gap);
+        (0..bodies.len()).flat_map(move |i| {
+            (i + 1..bodies.len()).filter_map(move |j| {
+                let (a, b) = (&bodies[i], &bodies[j]);
+                let rel_pos = b.pos - a.pos;
+                let distance = rel_pos.magnitude();
+                (distance > 0.0 && distance < a.radius + gap + b.radius)
+                    .then(|| (i, j, (a.vel - b.vel).dot(rel_pos / distance)))
+            })
+        })
+    }
+    /// An estimate of the longest tick duration that keeps collisions between the current bodies
+    /// stable, or `None` if nothing can collide.
+    pub fn stable_delta_time(&self) -> Option<Duration> {
+        Body::stable_delta_time(&self.bodies, &self.config)
+            .map(|seconds| Duration::from_secs_f32(seconds.to_f32()))
+    }
+    /// A hash of the bodies, with positions, velocities and spins rounded to a millionth so that
+    /// differences in the last bits of a float do not matter. It is computed the same way on
+    /// every platform, unlike the standard hashers.
+    pub fn state_hash(&self) -> u64 {
+        const QUANTUM: Scalar = 1e-6;
+        // 64-bit FNV-1a
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut write = |word: u64| {
+            for byte in word.to_le_bytes() {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        write(self.bodies.len() as u64);
+        for body in self.bodies.iter() {
+            let vectors = [body.pos, body.vel, body.spin];
+            for value in vectors.iter().flat_map(|v| [v.x, v.y, v.z]) {
+                write(((value / QUANTUM).round() as i64) as u64);
+            }
+            write(u64::from(body.radius.to_f32().to_bits()));
+            write(u64::from(body.density.to_f32().to_bits()));
+            write(u64::from(body.color));
+            write(u64::from(body.flags));
+        }
+        hash
+    }
+    /// The instant up to which the simulation has been advanced.
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+    /// Moves the simulation to `target` without simulating the time in between. Used while
+    /// paused and when the process was suspended, where catching up is neither possible nor
+    /// desired.
+    pub fn skip_to(&mut self, target: Instant) {
+        if let Some(skipped) = target.checked_duration_since(self.timestamp) {
+            log::debug!("Skipping {}ms of physics", skipped.as_millis());
+            self.timestamp = target;
+        }
+    }
     #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
     pub fn advance_to(&mut self, target: Instant) -> PhysicsResult {
-        use cgmath::Vector3;
-        use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+        use cgmath::{prelude::*, Vector3};
 
         let before = Instant::now();
         let mut elapsed_physics_ticks = 0;
+        let mut checkpoint = None;
+        // Reused by every tick, to avoid allocating a thousand times per second
+        let mut accels = [Vector3::zero(); BODIES];
         loop {
             let lag = target.checked_duration_since(self.timestamp);
             match lag {
-                lag if lag < Some(PHYSICS_DELTA_TIME) => break,
+                lag if lag < Some(self.delta_time) => break,
                 lag if lag > Some(PHYSICS_MAX_BEHIND_TIME) => {
-                    let new_timestamp = target.checked_sub(PHYSICS_DELTA_TIME).unwrap();
+                    let new_timestamp = target.checked_sub(self.delta_time).unwrap();
                     log::error!(
                         "Physics computation far behind, dropping {}ms",
                         (new_timestamp - self.timestamp).as_millis()
@@ -59,18 +297,585 @@ impl Physics {
                 }
                 _ => {}
             }
-            let accels: Vec<Vector3<f32>> = self
-                .bodies
-                .par_iter()
-                .map(|b| b.accel_from(&self.bodies))
-                .collect();
-            Body::perform_step(&mut self.bodies, accels);
-            self.timestamp += PHYSICS_DELTA_TIME;
+            checkpoint = self.tick(&mut accels).or(checkpoint);
+            self.timestamp += self.delta_time;
             elapsed_physics_ticks += 1;
         }
         PhysicsResult {
             elapsed_real: Instant::now() - before,
             elapsed_physics_ticks,
+            checkpoint,
+        }
+    }
+    /// Simulates a number of ticks as fast as possible, regardless of the timestamp, which is
+    /// left as is, and measures how long that took.
+    #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+    pub fn advance_ticks(&mut self, ticks: u64) -> PhysicsResult {
+        let before = Instant::now();
+        let checkpoint = self.step(ticks);
+        PhysicsResult {
+            elapsed_real: Instant::now() - before,
+            elapsed_physics_ticks: ticks,
+            checkpoint,
         }
     }
+    /// Simulates a number of ticks, without regard for wall-clock time: the outcome depends only
+    /// on the state, and the timestamp is left as is. Returns the last checkpoint reached, if
+    /// any.
+    #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+    pub fn step(&mut self, ticks: u64) -> Option<Checkpoint> {
+        use cgmath::{prelude::*, Vector3};
+
+        let mut checkpoint = None;
+        let mut accels = [Vector3::zero(); BODIES];
+        for _ in 0..ticks {
+            checkpoint = self.tick(&mut accels).or(checkpoint);
+        }
+        checkpoint
+    }
+    #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+    fn tick(&mut self, accels: &mut [cgmath::Vector3<Scalar>; BODIES]) -> Option<Checkpoint> {
+        use rayon::prelude::{
+            IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+        };
+
+        use broadphase::ContactGrid;
+        use cgmath::{prelude::*, Vector3};
+
+        let tick = seconds(self.delta_time);
+        let len = self.bodies.len();
+        let accels = &mut accels[..len];
+        // Spin is advanced by the angular accelerations at the start of each step, while those
+        // of the integrator evaluating other states are discarded
+        let mut angular_accels = [Vector3::zero(); BODIES];
+        let angular_accels = &mut angular_accels[..len];
+        let mut discarded = [Vector3::zero(); BODIES];
+        let discarded = &mut discarded[..len];
+        // Faded in as of the end of the tick, so that even the first tick feels some gravity
+        let config = self
+            .config
+            .warmed_up(self.simulated_time + self.delta_time - self.warm_up_start);
+        let compute_accels = |bodies: &[Body],
+                              accels: &mut [Vector3<Scalar>],
+                              angular: &mut [Vector3<Scalar>],
+                              dt| {
+            let grid = ContactGrid::new(bodies, dt, &config);
+            accels
+                .par_iter_mut()
+                .zip(angular.par_iter_mut())
+                .enumerate()
+                .for_each(|(i, (accel, angular))| {
+                    let body = &bodies[i];
+                    let near = grid.near(body.pos).map(|j| &bodies[j]);
+                    (*accel, *angular) = body.contact_accels(near, dt, &config);
+                    *accel += body.gravity_accel(bodies, &config) + config.field_accel(body);
+                });
+        };
+        threads::install(|| {
+            // The accelerations over the whole tick decide how finely it is subdivided, and are
+            // reused as is when it is not
+            compute_accels(&self.bodies, accels, angular_accels, tick);
+            let substeps = Body::substeps(&self.bodies, accels, tick);
+            let dt = tick / substeps as Scalar;
+            for _ in 0..substeps {
+                if substeps > 1 {
+                    compute_accels(&self.bodies, accels, angular_accels, dt);
+                }
+                config
+                    .integrator
+                    .step(&mut self.bodies, accels, dt, &config, |bodies, accels| {
+                        compute_accels(bodies, accels, discarded, dt);
+                    });
+                Body::rotate(&mut self.bodies, angular_accels, dt);
+                config
+                    .boundary
+                    .contain(&mut self.bodies, config.boundary_radius);
+            }
+        });
+        self.end_tick()
+    }
+    /// Takes `bodies` as the outcome of the next tick instead of simulating it, so that those of
+    /// another simulation are checkpointed and recorded like simulated ones. The timestamp is
+    /// left as is.
+    pub fn take_tick(&mut self, bodies: BodySet) -> PhysicsResult {
+        self.bodies = bodies;
+        PhysicsResult {
+            elapsed_real: Duration::ZERO,
+            elapsed_physics_ticks: 1,
+            checkpoint: self.end_tick(),
+        }
+    }
+    fn end_tick(&mut self) -> Option<Checkpoint> {
+        self.ticks += 1;
+        self.simulated_time += self.delta_time;
+        (self.ticks % CHECKPOINT_INTERVAL == 0).then(|| Checkpoint {
+            tick: self.ticks,
+            hash: self.state_hash(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{prelude::*, Quaternion, Vector3};
+
+    /// A body of unit density, without spin, flags or color, shared by the tests of every module
+    pub(crate) fn body(pos: Vector3<Scalar>, vel: Vector3<Scalar>, radius: Scalar) -> Body {
+        Body {
+            pos,
+            vel,
+            radius,
+            density: 1.0,
+            color: 0,
+            flags: 0,
+            spin: Vector3::zero(),
+            orientation: Quaternion::one(),
+        }
+    }
+
+    #[test]
+    fn advance_ticks_keeps_timestamp() {
+        let mut physics = Physics::initial();
+        let timestamp = physics.timestamp();
+        let result = physics.advance_ticks(10);
+        assert_eq!(result.elapsed_physics_ticks, 10);
+        assert_eq!(physics.ticks(), 10);
+        assert_eq!(physics.timestamp(), timestamp);
+    }
+
+    #[test]
+    fn headless_steps_match_paced_ones() {
+        let mut headless = Physics::initial();
+        let mut paced = headless.clone();
+        let timestamp = headless.timestamp();
+        headless.step(100);
+        paced.advance_to(timestamp + 100 * paced.delta_time());
+        assert_eq!(headless.timestamp(), timestamp);
+        assert_eq!(headless.ticks(), paced.ticks());
+        assert_eq!(headless.state_hash(), paced.state_hash());
+    }
+
+    #[test]
+    fn simulated_time_sums_tick_durations() {
+        let mut physics = Physics::initial();
+        physics.advance_ticks(10);
+        physics.set_delta_time(Duration::from_millis(4));
+        physics.advance_ticks(5);
+        assert_eq!(physics.ticks(), 15);
+        assert_eq!(physics.simulated_time(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn fast_bodies_do_not_pass_through_others() {
+        let still = Body {
+            flags: Body::FROZEN,
+            ..body(Vector3::zero(), Vector3::zero(), 0.01)
+        };
+        let fast = body(
+            Vector3::new(-0.2, 0.0, 0.0),
+            Vector3::new(15.0, 0.0, 0.0),
+            0.01,
+        );
+        let mut physics = Physics::initial();
+        *physics.bodies_mut() = [still, fast].into_iter().collect();
+        // Each tick moves the fast body more than ten times the sum of the radii
+        physics.set_delta_time(MAX_DELTA_TIME);
+        physics.advance_ticks(4);
+        assert!(physics.bodies()[1].pos.x < 0.0, "{:?}", physics.bodies()[1]);
+    }
+
+    #[test]
+    fn strong_accelerations_subdivide_ticks() {
+        let body = body(Vector3::zero(), Vector3::zero(), 0.01);
+        let dt = seconds(PHYSICS_DELTA_TIME);
+        let calm = [Vector3::new(1.0, 0.0, 0.0)];
+        let violent = [Vector3::new(1e4, 0.0, 0.0)];
+        assert_eq!(Body::substeps(&[body], &calm, dt), 1);
+        assert!(Body::substeps(&[body], &violent, dt) > 1);
+    }
+
+    #[test]
+    fn checkpoints_match_between_identical_runs() {
+        let mut physics = Physics::initial();
+        let bodies: BodySet = physics.bodies().iter().take(16).copied().collect();
+        *physics.bodies_mut() = bodies;
+        let mut batched = physics.clone();
+        let whole = physics.advance_ticks(CHECKPOINT_INTERVAL + 10).checkpoint;
+        let mut last = None;
+        for _ in 0..(CHECKPOINT_INTERVAL + 10) / 30 {
+            last = batched.advance_ticks(30).checkpoint.or(last);
+        }
+        last = batched
+            .advance_ticks((CHECKPOINT_INTERVAL + 10) % 30)
+            .checkpoint
+            .or(last);
+        assert_eq!(whole.map(|c| c.tick), Some(CHECKPOINT_INTERVAL));
+        assert_eq!(whole, last);
+        assert_eq!(physics.state_hash(), batched.state_hash());
+        batched.bodies_mut()[3].vel.x += 1e-3;
+        assert_ne!(physics.state_hash(), batched.state_hash());
+    }
+
+    /// The checkpoint of bodies laid out by basic arithmetic alone, which must come out the same
+    /// on every platform with or without the `deterministic` feature, since stepping needs nothing
+    /// else
+    #[test]
+    fn stepping_matches_on_every_platform() {
+        let mut physics = Physics::seeded(7);
+        *physics.bodies_mut() = (0..64)
+            .map(|i: u16| {
+                let cell = Vector3::new(i % 4, i / 4 % 4, i / 16).map(|n| 0.3 * Scalar::from(n));
+                let drift = Vector3::new(i * 7 % 5, i * 3 % 5, i * 11 % 5)
+                    .map(|n| 0.05 * (Scalar::from(n) - 2.0));
+                body(cell, drift, 0.1)
+            })
+            .collect();
+        let checkpoint = physics.advance_ticks(CHECKPOINT_INTERVAL).checkpoint;
+        #[cfg(not(feature = "f64"))]
+        let hash = 0x894b_db27_9f51_e937;
+        #[cfg(feature = "f64")]
+        let hash = 0x6a1e_b7f2_d1d5_6525;
+        assert_eq!(checkpoint.map(|c| c.hash), Some(hash));
+    }
+
+    /// The checkpoint of a seeded run, which must come out the same on every platform. Drawing
+    /// the cluster takes the math library, so this only holds with the `deterministic` feature,
+    /// which `cargo test-deterministic` runs the tests with.
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn seeded_runs_match_on_every_platform() {
+        let mut physics = Physics::seeded(7);
+        let checkpoint = physics.advance_ticks(CHECKPOINT_INTERVAL).checkpoint;
+        #[cfg(not(feature = "f64"))]
+        let hash = 0xa2a9_eba6_ab7e_52c8;
+        #[cfg(feature = "f64")]
+        let hash = 0xe696_8522_f9e6_bf1d;
+        assert_eq!(checkpoint.map(|c| c.hash), Some(hash));
+    }
+
+    #[test]
+    fn initial_cluster_is_virialized() {
+        let physics = Physics::seeded(3);
+        let (kinetic, potential) = Body::energies(physics.bodies(), physics.config());
+        assert!(
+            (2.0 * kinetic + potential).abs() < 1e-3 * potential.abs(),
+            "{kinetic} {potential}"
+        );
+    }
+
+    #[test]
+    fn orbits_conserve_energy_and_momentum() {
+        let mut physics = Physics::initial();
+        // Two bodies on a tilted orbit about each other, never close enough to collide
+        *physics.bodies_mut() = [-1.0, 1.0]
+            .into_iter()
+            .map(|side| Body {
+                spin: Vector3::new(0.0, 0.0, side),
+                ..body(
+                    Vector3::new(0.5 * side, 0.0, 0.0),
+                    Vector3::new(0.0, 0.1 * side, -0.05 * side),
+                    0.1,
+                )
+            })
+            .collect();
+        let before = physics.conserved();
+        physics.advance_ticks(2000);
+        let after = physics.conserved();
+        assert!(before.kinetic > 0.0 && before.potential < 0.0, "{before:?}");
+        assert!(
+            (after.energy() - before.energy()).abs() < 1e-3 * before.potential.abs(),
+            "{before:?} {after:?}"
+        );
+        assert!(
+            (after.angular_momentum - before.angular_momentum).magnitude()
+                < 1e-3 * before.angular_momentum.magnitude(),
+            "{before:?} {after:?}"
+        );
+        assert!(after.momentum.magnitude() < 1e-6, "{after:?}");
+    }
+
+    #[test]
+    fn stronger_gravity_pulls_faster() {
+        let fallen = |gravity| {
+            let mut physics = Physics::initial();
+            *physics.bodies_mut() = [-0.25, 0.25]
+                .into_iter()
+                .map(|x| body(Vector3::new(x, 0.0, 0.0), Vector3::zero(), 0.1))
+                .collect();
+            physics.set_config(PhysicsConfig {
+                gravity,
+                ..PhysicsConfig::default()
+            });
+            physics.advance_ticks(200);
+            0.5 - (physics.bodies()[1].pos.x - physics.bodies()[0].pos.x)
+        };
+        let (default, doubled) = (fallen(40.0), fallen(80.0));
+        assert!(
+            default > 0.0 && doubled > 1.5 * default,
+            "{default} {doubled}"
+        );
+    }
+
+    #[test]
+    fn contacts_are_touching_pairs() {
+        let mut physics = Physics::initial();
+        *physics.bodies_mut() = [(0.0, 1.0), (0.15, -1.0), (1.0, 0.0)]
+            .into_iter()
+            .map(|(x, vel)| body(Vector3::new(x, 0.0, 0.0), Vector3::new(vel, 0.0, 0.0), 0.1))
+            .collect();
+        let contacts: Vec<_> = physics.contacts().collect();
+        assert_eq!(contacts, [(0, 1, 2.0)]);
+    }
+
+    #[test]
+    fn warm_up_fades_gravity_in() {
+        let config = PhysicsConfig {
+            warm_up: 1.0,
+            ..PhysicsConfig::default()
+        };
+        let first_tick = |config| {
+            let mut physics = Physics::initial();
+            *physics.bodies_mut() = [-0.25, 0.25]
+                .into_iter()
+                .map(|x| body(Vector3::new(x, 0.0, 0.0), Vector3::zero(), 0.1))
+                .collect();
+            physics.set_config(config);
+            physics.step(1);
+            physics.bodies()[0].vel.x
+        };
+        let (full, faded) = (first_tick(PhysicsConfig::default()), first_tick(config));
+        // A millisecond into a second of warm-up, gravity has a thousandth of its strength
+        assert!(
+            full > 0.0 && (faded - 1e-3 * full).abs() < 1e-5 * full,
+            "{full} {faded}"
+        );
+        assert_eq!(config.warmed_up(Duration::from_secs(1)), config);
+    }
+
+    #[test]
+    fn replacing_bodies_restarts_warm_up() {
+        let config = PhysicsConfig {
+            warm_up: 1.0,
+            ..PhysicsConfig::default()
+        };
+        let pair: BodySet = [-0.25, 0.25]
+            .into_iter()
+            .map(|x| body(Vector3::new(x, 0.0, 0.0), Vector3::zero(), 0.1))
+            .collect();
+        let mut physics = Physics::new(pair, config);
+        physics.step(1);
+        let first = physics.bodies()[0].vel.x;
+        physics.advance_ticks(2000);
+        physics.replace_bodies(pair);
+        physics.step(1);
+        assert_eq!(physics.bodies()[0].vel.x, first);
+    }
+
+    #[test]
+    fn softening_bounds_close_accelerations() {
+        let bodies: Vec<Body> = [0.0, 0.01]
+            .into_iter()
+            .map(|x| body(Vector3::new(x, 0.0, 0.0), Vector3::zero(), 0.001))
+            .collect();
+        let accel = |config: &PhysicsConfig| bodies[0].accel_from(&bodies, 0.01, config).x;
+        let mut config = PhysicsConfig::default();
+        let hard = accel(&config);
+        config.softening = 0.1;
+        let soft = accel(&config);
+        config.groups[0].softening = Some(0.0);
+        assert_eq!(accel(&config), hard);
+        assert!(0.0 < soft && soft < 0.01 * hard, "{hard} {soft}");
+    }
+
+    #[test]
+    fn density_weighs_bodies() {
+        let body = |x, radius, density| Body {
+            density,
+            ..body(Vector3::new(x, 0.0, 0.0), Vector3::zero(), radius)
+        };
+        let config = PhysicsConfig::default();
+        let probe = body(1.0, 0.01, 1.0);
+        let pull = |other: Body| probe.accel_from(&[probe, other], 0.01, &config).x;
+        // Half the radius at eight times the density weighs the same
+        let (dense, fluffy) = (body(0.0, 0.05, 8.0), body(0.0, 0.1, 1.0));
+        assert!((pull(dense) - pull(fluffy)).abs() <= 1e-4 * pull(fluffy).abs());
+
+        // Touching, the lighter body is pushed away faster
+        let config = PhysicsConfig {
+            gravity: 0.0,
+            ..config
+        };
+        let (heavy, light) = (body(0.0, 0.1, 8.0), body(0.19, 0.1, 1.0));
+        let heavy_accel = heavy.accel_from(&[heavy, light], 0.01, &config).x;
+        let light_accel = light.accel_from(&[heavy, light], 0.01, &config).x;
+        assert!(heavy_accel < 0.0, "{heavy_accel}");
+        assert!(
+            (light_accel + 8.0 * heavy_accel).abs() <= 1e-4 * light_accel,
+            "{light_accel}"
+        );
+    }
+
+    #[test]
+    fn fields_accelerate_bodies() {
+        let mut physics = Physics::initial();
+        *physics.bodies_mut() = [-1.0, 1.0]
+            .into_iter()
+            .map(|side| body(Vector3::new(side, 0.0, 0.0), Vector3::zero(), 0.01))
+            .collect();
+        let mut config = PhysicsConfig {
+            gravity: 0.0,
+            ..PhysicsConfig::default()
+        };
+        config.fields[0] = Some(ForceField::Uniform {
+            accel: Vector3::new(0.0, -1.0, 0.0),
+        });
+        config.fields[2] = Some(ForceField::Vortex {
+            center: Vector3::zero(),
+            axis: Vector3::unit_y(),
+            swirl: 1.0,
+            pull: 0.0,
+        });
+        physics.set_config(config);
+        physics.advance_ticks(100);
+        for body in physics.bodies().iter() {
+            // Falling, though dragged by the vortex, which the removal of momentum drift must not
+            // undo
+            assert!(body.vel.y < -0.08, "{:?}", body.vel);
+            // Swirling counter clockwise seen from above
+            assert!(body.vel.z * body.pos.x < -0.05, "{:?}", body.vel);
+        }
+    }
+
+    #[test]
+    fn boundaries_contain_bodies() {
+        let run = |boundary| {
+            let mut physics = Physics::initial();
+            *physics.bodies_mut() = [1.0, -1.0]
+                .into_iter()
+                .map(|side| {
+                    body(
+                        Vector3::new(0.5 * side, 0.0, 0.0),
+                        Vector3::new(3.0 * side, side, 0.0),
+                        0.05,
+                    )
+                })
+                .collect();
+            physics.set_config(PhysicsConfig {
+                gravity: 0.0,
+                boundary,
+                boundary_radius: 1.0,
+                ..PhysicsConfig::default()
+            });
+            physics.advance_ticks(2000);
+            physics.bodies()[0]
+        };
+        let escaped = run(Boundary::Unbounded);
+        assert!(escaped.pos.magnitude() > 6.0, "{:?}", escaped.pos);
+
+        let bounced = run(Boundary::Sphere);
+        assert!(bounced.pos.magnitude() <= 0.95 + 1e-4, "{:?}", bounced.pos);
+        assert!(
+            (bounced.vel.magnitude2() - 10.0).abs() < 1e-3,
+            "{:?}",
+            bounced.vel
+        );
+
+        let wrapped = run(Boundary::Periodic);
+        assert!(
+            wrapped.pos.x.abs() <= 1.0 && wrapped.pos.y.abs() <= 1.0,
+            "{:?}",
+            wrapped.pos
+        );
+        assert_eq!(wrapped.vel, Vector3::new(3.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rubber_bounces_more_than_metal() {
+        let (rubber, metal) = (
+            rebound(Material::Rubber, None),
+            rebound(Material::Metal, None),
+        );
+        assert!(
+            0.0 < metal && metal < rubber && rubber <= 1.0,
+            "{rubber} {metal}"
+        );
+    }
+
+    #[test]
+    fn body_materials_override_their_groups() {
+        let mixed = rebound(Material::Metal, Some(Material::Rubber));
+        assert_eq!(mixed, rebound(Material::Rubber, None));
+        assert!(mixed > rebound(Material::Metal, None));
+    }
+
+    /// The fraction of the speed two bodies of a group with the given material keep after
+    /// colliding head on, where the bodies may have a material of their own
+    fn rebound(group_material: Material, body_material: Option<Material>) -> Scalar {
+        let mut physics = Physics::initial();
+        *physics.bodies_mut() = [-1.0, 1.0]
+            .into_iter()
+            .map(|side| {
+                let mut body = Body {
+                    flags: 1 << Body::GROUP_SHIFT,
+                    ..body(
+                        Vector3::new(0.1 * side, 0.0, 0.0),
+                        Vector3::new(-0.5 * side, 0.0, 0.0),
+                        0.05,
+                    )
+                };
+                body.set_material(body_material);
+                body
+            })
+            .collect();
+        let mut config = PhysicsConfig {
+            gravity: 0.0,
+            ..PhysicsConfig::default()
+        };
+        config.groups[1].material = group_material;
+        physics.set_config(config);
+        physics.advance_ticks(1000);
+        physics.bodies()[1].vel.x / 0.5
+    }
+
+    #[test]
+    fn glancing_collisions_spin_bodies() {
+        let mut physics = Physics::initial();
+        *physics.bodies_mut() = [-1.0, 1.0]
+            .into_iter()
+            .map(|side| {
+                body(
+                    Vector3::new(0.15 * side, 0.03 * side, 0.0),
+                    Vector3::new(-0.5 * side, 0.0, 0.0),
+                    0.05,
+                )
+            })
+            .collect();
+        physics.set_config(PhysicsConfig {
+            gravity: 0.0,
+            ..PhysicsConfig::default()
+        });
+        let angular_momentum = |bodies: &[Body]| {
+            bodies
+                .iter()
+                .map(|b| b.mass() * (b.pos.cross(b.vel) + 0.4 * b.radius.powi(2) * b.spin))
+                .sum::<Vector3<Scalar>>()
+        };
+        let before = angular_momentum(physics.bodies());
+        physics.advance_ticks(1000);
+        let [a, b] = [physics.bodies()[0], physics.bodies()[1]];
+        assert!(a.spin.z != 0.0 && a.spin.z == b.spin.z, "{a:?} {b:?}");
+        assert!(a.orientation.s < 1.0 && a.orientation.magnitude() > 0.99);
+        let after = angular_momentum(physics.bodies());
+        assert!((after - before).magnitude() < 0.01 * before.magnitude());
+    }
+
+    #[test]
+    fn delta_time_is_clamped() {
+        let mut physics = Physics::initial();
+        physics.set_delta_time(Duration::ZERO);
+        assert_eq!(physics.delta_time(), MIN_DELTA_TIME);
+        physics.set_delta_time(Duration::from_secs(1));
+        assert_eq!(physics.delta_time(), MAX_DELTA_TIME);
+    }
 }
@@ -0,0 +1,208 @@
+//! The functions beyond basic arithmetic that the simulation draws on, such as for laying out
+//! initial clusters. Platforms round `+`, `-`, `*`, `/` and `sqrt` the same way, as IEEE 754
+//! requires, but their math libraries differ in the last bits of logarithms, exponentials and
+//! trigonometric functions, so that the same seed makes slightly different clusters natively
+//! and on the web, which then drift apart. With the `deterministic` feature, these functions are
+//! computed from basic arithmetic alone, making runs from the same seed identical on every
+//! platform, at the cost of different clusters than without the feature.
+//!
+//! Stepping needs nothing of this, as it only uses basic arithmetic, and each body sums the
+//! forces on it in the same order however many threads the bodies are spread over.
+//!
+//! This is portable floating point rather than fixed point or soft float, so it relies on every
+//! platform rounding basic arithmetic as IEEE 754 requires. That holds natively on 64-bit targets
+//! and on the web, where Rust neither fuses multiplications with additions nor keeps excess
+//! precision, but not for x87 floating point, as on 32-bit x86 without SSE2.
+
+use rand::Rng;
+
+/// The natural logarithm
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn ln(x: f32) -> f32 {
+    x.ln()
+}
+#[cfg(feature = "deterministic")]
+pub(crate) fn ln(x: f32) -> f32 {
+    portable::ln(f64::from(x)) as f32
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn exp(x: f32) -> f32 {
+    x.exp()
+}
+#[cfg(feature = "deterministic")]
+pub(crate) fn exp(x: f32) -> f32 {
+    portable::exp(f64::from(x)) as f32
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn powf(x: f32, y: f32) -> f32 {
+    x.powf(y)
+}
+#[cfg(feature = "deterministic")]
+pub(crate) fn powf(x: f32, y: f32) -> f32 {
+    if y == 0.0 {
+        return 1.0;
+    }
+    portable::exp(f64::from(y) * portable::ln(f64::from(x))) as f32
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn sin_cos(x: f32) -> (f32, f32) {
+    x.sin_cos()
+}
+#[cfg(feature = "deterministic")]
+pub(crate) fn sin_cos(x: f32) -> (f32, f32) {
+    let (sin, cos) = portable::sin_cos(f64::from(x));
+    (sin as f32, cos as f32)
+}
+
+/// A sample of the normal distribution with mean 0 and standard deviation 1
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn standard_normal(rng: &mut impl Rng) -> f32 {
+    rng.sample(rand_distr::StandardNormal)
+}
+/// A sample of the normal distribution with mean 0 and standard deviation 1, by the Box-Muller
+/// transform rather than the ziggurat method of `rand_distr`, which uses the math library
+#[cfg(feature = "deterministic")]
+pub(crate) fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // In (0, 1], so that the logarithm is finite
+    let uniform = 1.0 - rng.gen::<f64>();
+    let angle = std::f64::consts::TAU * rng.gen::<f64>();
+    let (_, cos) = portable::sin_cos(angle);
+    ((-2.0 * portable::ln(uniform)).sqrt() * cos) as f32
+}
+
+/// Double precision implementations using only basic arithmetic, accurate to a few units in the
+/// last place, which is plenty once rounded to single precision.
+#[cfg(any(test, feature = "deterministic"))]
+mod portable {
+    use std::f64::consts::{FRAC_2_PI, LOG2_E, SQRT_2};
+
+    /// The natural logarithm of 2 split in two, where the first has its low bits clear so that
+    /// multiplying it by small integers is exact
+    const LN_2_HI: f64 = 6.931_471_803_691_238e-1;
+    const LN_2_LO: f64 = 1.908_214_929_270_587_7e-10;
+    /// Half of pi split in three in the same way
+    const FRAC_PI_2_1: f64 = 1.570_796_326_734_125_6;
+    const FRAC_PI_2_2: f64 = 6.077_100_506_303_966e-11;
+    const FRAC_PI_2_3: f64 = 2.022_266_248_711_166_5e-21;
+
+    pub fn exp(x: f64) -> f64 {
+        if x.is_nan() {
+            return x;
+        }
+        if x > 709.8 {
+            return f64::INFINITY;
+        }
+        if x < -745.2 {
+            return 0.0;
+        }
+        // `x = k ln 2 + r` with `|r| <= ln 2 / 2`, for which 13 terms of the series suffice
+        let k = (x * LOG2_E).round();
+        let r = (x - k * LN_2_HI) - k * LN_2_LO;
+        let mut series = 1.0;
+        for n in (1..=13).rev() {
+            series = 1.0 + r * series / f64::from(n);
+        }
+        scale(series, k as i32)
+    }
+
+    /// `x` times two to the power of `k`, exactly unless the result is subnormal.
+    fn scale(mut x: f64, mut k: i32) -> f64 {
+        let power = |k: i32| f64::from_bits(((k + 1023) as u64) << 52);
+        while k > 1023 {
+            x *= power(1023);
+            k -= 1023;
+        }
+        while k < -1022 {
+            x *= power(-1022);
+            k += 1022;
+        }
+        x * power(k)
+    }
+
+    pub fn ln(x: f64) -> f64 {
+        if x.is_nan() || x < 0.0 {
+            return f64::NAN;
+        }
+        if x == 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        if x == f64::INFINITY {
+            return x;
+        }
+        // `x = m 2^e` with `m` within a factor of the square root of 2 from 1
+        let (mut x, mut e) = (x, 0);
+        if x < f64::MIN_POSITIVE {
+            x *= 2.0f64.powi(54);
+            e -= 54;
+        }
+        let bits = x.to_bits();
+        e += ((bits >> 52) & 0x7ff) as i32 - 1023;
+        let mut m = f64::from_bits((bits & !(0x7ff << 52)) | (1023 << 52));
+        if m > SQRT_2 {
+            m /= 2.0;
+            e += 1;
+        }
+        // `ln m = 2 atanh s` for the `s` below, whose odd powers up to the 23rd suffice
+        let s = (m - 1.0) / (m + 1.0);
+        let s2 = s * s;
+        let mut series = 0.0;
+        for n in (0..=11).rev() {
+            series = 1.0 / f64::from(2 * n + 1) + s2 * series;
+        }
+        let e = f64::from(e);
+        e * LN_2_HI + (2.0 * s * series + e * LN_2_LO)
+    }
+
+    /// The sine and cosine, accurate for arguments up to about a million in magnitude.
+    pub fn sin_cos(x: f64) -> (f64, f64) {
+        if !x.is_finite() {
+            return (f64::NAN, f64::NAN);
+        }
+        // `x = k pi / 2 + r` with `|r| <= pi / 4`, for which powers up to the 18th suffice
+        let k = (x * FRAC_2_PI).round();
+        let r = ((x - k * FRAC_PI_2_1) - k * FRAC_PI_2_2) - k * FRAC_PI_2_3;
+        let r2 = r * r;
+        let (mut sin, mut cos) = (1.0, 1.0);
+        for n in (1..=9).rev() {
+            sin = 1.0 - r2 * sin / f64::from((2 * n) * (2 * n + 1));
+            cos = 1.0 - r2 * cos / f64::from((2 * n - 1) * (2 * n));
+        }
+        let sin = r * sin;
+        match (k as i64).rem_euclid(4) {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::portable::*;
+
+    #[test]
+    fn portable_functions_match_the_math_library() {
+        let close = |portable: f64, library: f64, x: f64| {
+            let error = (portable - library).abs() / library.abs().max(1.0);
+            assert!(error < 1e-14, "{portable} != {library} at {x}");
+        };
+        for i in -2000..=2000 {
+            let x = f64::from(i) / 37.0;
+            close(exp(x / 10.0), (x / 10.0).exp(), x);
+            close(ln(x.abs() + 1e-3), (x.abs() + 1e-3).ln(), x);
+            let (sin, cos) = sin_cos(x);
+            close(sin, x.sin(), x);
+            close(cos, x.cos(), x);
+        }
+        assert_eq!(exp(0.0), 1.0);
+        assert_eq!(ln(1.0), 0.0);
+        close(ln(1e-310), 1e-310f64.ln(), 1e-310);
+        close(exp(700.0), 700.0f64.exp(), 700.0);
+        assert!(ln(-1.0).is_nan() && exp(f64::NAN).is_nan());
+        assert_eq!(exp(1000.0), f64::INFINITY);
+    }
+}
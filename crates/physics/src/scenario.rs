@@ -0,0 +1,582 @@
+//! A plain text format for sets of bodies, one body per line, meant to be read and edited by
+//! hand. Empty lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! # x y z vx vy vz radius color [frozen] [striped] [material] [density d]
+//! 0.5 0 1.2 0 0.1 0 0.03 ff8040 frozen striped rubber
+//! 0 0 0 0 0 0 0.01 c0c0c0 density 50
+//! ```
+//!
+//! Colors are hexadecimal `rrggbb` for opaque bodies, or `rrggbbaa` where `aa` is the
+//! transparency. Bodies have a density of 1 unless given another, see [`Body::density`].
+//!
+//! Lines starting with a name instead of a number recommend how to view the scenario:
+//!
+//! ```text
+//! # The camera position, and the point it looks at
+//! camera 0 -1 -3 0 0 0
+//! # The duration of a physics tick, in seconds
+//! delta-time 0.0005
+//! ray-splits 2
+//! ```
+//!
+//! Groups of bodies may attract and collide differently, and be drawn differently. Bodies join
+//! a group by naming it after their color, and take colors from its palette in turn with `-`:
+//!
+//! ```text
+//! # A group with any of these overrides, defined before its bodies
+//! group dust coupling 0.5 stiffness 2 softening 0.02 material glass palette 806040 a08060
+//! 0.5 0 1.2 0 0.1 0 0.01 - dust
+//! ```
+//!
+//! The materials are `solid`, `glass`, `metal` and `rubber`, which collide differently, see
+//! [`Material`]. That of a body replaces that of its group.
+//!
+//! Up to [`MAX_FIELDS`] force fields act on every body besides the bodies themselves, summed,
+//! see [`ForceField`]:
+//!
+//! ```text
+//! # An acceleration
+//! field uniform 0 -0.2 0
+//! # The center, the axis, how strongly bodies are swirled and how strongly pulled in
+//! field vortex 0 0 0 0 1 0 0.3 0.05
+//! # The center, and how strongly bodies are attracted, or repelled if negative
+//! field point 0 -1 0 0.5
+//! ```
+
+use crate::{Body, BodySet, ForceField, Group, Material, Scalar, MAX_FIELDS, MAX_GROUPS};
+use cgmath::{prelude::*, Quaternion, Vector3};
+use std::{
+    fmt::{self, Write},
+    time::Duration,
+};
+
+#[derive(Debug)]
+pub struct Scenario {
+    pub bodies: BodySet,
+    pub settings: ScenarioSettings,
+    /// The groups in order of their ids, starting from 1
+    pub groups: Vec<ScenarioGroup>,
+    pub fields: Vec<ForceField>,
+}
+impl Scenario {
+    /// The overrides of each group id, for [`PhysicsConfig::groups`](crate::PhysicsConfig).
+    pub fn group_table(&self) -> [Group; MAX_GROUPS] {
+        let mut table = [Group::default(); MAX_GROUPS];
+        for (entry, group) in table[1..].iter_mut().zip(&self.groups) {
+            *entry = group.group;
+        }
+        table
+    }
+    /// The fields, for [`PhysicsConfig::fields`](crate::PhysicsConfig).
+    pub fn field_table(&self) -> [Option<ForceField>; MAX_FIELDS] {
+        let mut table = [None; MAX_FIELDS];
+        for (entry, &field) in table.iter_mut().zip(&self.fields) {
+            *entry = Some(field);
+        }
+        table
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioGroup {
+    pub name: String,
+    pub group: Group,
+    /// The colors of bodies in the group whose color is `-`, in turn
+    pub palette: Vec<u32>,
+}
+
+/// How a scenario is meant to be viewed, where `None` leaves things as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScenarioSettings {
+    pub camera: Option<ScenarioCamera>,
+    pub delta_time: Option<Duration>,
+    pub ray_splits: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScenarioCamera {
+    pub position: Vector3<f32>,
+    /// The point the camera looks at
+    pub target: Vector3<f32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScenarioError {
+    /// A line, counted from 1, that is neither a body nor a setting
+    InvalidLine(usize),
+    TooManyBodies,
+    TooManyGroups,
+    TooManyFields,
+}
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLine(line) => write!(f, "line {line} is neither a body nor a setting"),
+            Self::TooManyBodies => write!(f, "more than {} bodies", BodySet::capacity()),
+            Self::TooManyGroups => write!(f, "more than {} groups", MAX_GROUPS - 1),
+            Self::TooManyFields => write!(f, "more than {MAX_FIELDS} fields"),
+        }
+    }
+}
+
+pub fn write_scenario<'a>(bodies: impl IntoIterator<Item = &'a Body>) -> String {
+    let mut text =
+        String::from("# x y z vx vy vz radius color [frozen] [striped] [material] [density d]\n");
+    for body in bodies {
+        let Body {
+            pos,
+            vel,
+            radius,
+            color,
+            flags,
+            ..
+        } = *body;
+        write!(
+            text,
+            "{} {} {} {} {} {} {} {:08x}",
+            pos.x, pos.y, pos.z, vel.x, vel.y, vel.z, radius, color
+        )
+        .unwrap();
+        if flags & Body::FROZEN != 0 {
+            text.push_str(" frozen");
+        }
+        if flags & Body::STRIPED != 0 {
+            text.push_str(" striped");
+        }
+        if let Some(material) = body.material() {
+            text.push(' ');
+            text.push_str(material.name());
+        }
+        if body.density != 1.0 {
+            write!(text, " density {}", body.density).unwrap();
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes the fields, one per line.
+pub fn write_fields<'a>(fields: impl IntoIterator<Item = &'a ForceField>) -> String {
+    let mut text = String::new();
+    for field in fields {
+        text.push_str("field ");
+        text.push_str(field.name());
+        let values: &[f32] = match field {
+            ForceField::Uniform { accel: a } => &[a.x, a.y, a.z],
+            ForceField::Vortex {
+                center: c,
+                axis: a,
+                swirl,
+                pull,
+            } => &[c.x, c.y, c.z, a.x, a.y, a.z, *swirl, *pull],
+            ForceField::Point {
+                center: c,
+                strength,
+            } => &[c.x, c.y, c.z, *strength],
+        };
+        for value in values {
+            write!(text, " {value}").unwrap();
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes the settings that are not `None`, one per line.
+pub fn write_settings(settings: &ScenarioSettings) -> String {
+    let mut text = String::new();
+    if let Some(ScenarioCamera {
+        position: p,
+        target: t,
+    }) = settings.camera
+    {
+        writeln!(
+            text,
+            "camera {} {} {} {} {} {}",
+            p.x, p.y, p.z, t.x, t.y, t.z
+        )
+        .unwrap();
+    }
+    if let Some(delta_time) = settings.delta_time {
+        writeln!(text, "delta-time {}", delta_time.as_secs_f32()).unwrap();
+    }
+    if let Some(ray_splits) = settings.ray_splits {
+        writeln!(text, "ray-splits {ray_splits}").unwrap();
+    }
+    text
+}
+
+pub fn parse_scenario(text: &str) -> Result<Scenario, ScenarioError> {
+    let mut bodies = BodySet::new();
+    let mut settings = ScenarioSettings::default();
+    let mut groups: Vec<ScenarioGroup> = Vec::new();
+    let mut fields = Vec::new();
+    // The number of bodies of each group colored from its palette so far
+    let mut palette_used = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = ScenarioError::InvalidLine(number + 1);
+        if let Some(definition) = line.strip_prefix("group ") {
+            let group = parse_group(definition)
+                .filter(|group| groups.iter().all(|other| other.name != group.name))
+                .ok_or(invalid)?;
+            if groups.len() == MAX_GROUPS - 1 {
+                return Err(ScenarioError::TooManyGroups);
+            }
+            groups.push(group);
+            palette_used.push(0);
+            continue;
+        }
+        if let Some(definition) = line.strip_prefix("field ") {
+            let field = parse_field(definition).ok_or(invalid)?;
+            if fields.len() == MAX_FIELDS {
+                return Err(ScenarioError::TooManyFields);
+            }
+            fields.push(field);
+            continue;
+        }
+        if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            parse_setting(line, &mut settings).ok_or(invalid)?;
+            continue;
+        }
+        let body = parse_body(line, &groups, &mut palette_used).ok_or(invalid)?;
+        bodies
+            .push(body)
+            .map_err(|_| ScenarioError::TooManyBodies)?;
+    }
+    Ok(Scenario {
+        bodies,
+        settings,
+        groups,
+        fields,
+    })
+}
+
+fn parse_group(definition: &str) -> Option<ScenarioGroup> {
+    let mut fields = definition.split_whitespace().peekable();
+    let name = fields.next().filter(|&name| {
+        name != "frozen" && name != "striped" && Material::from_name(name).is_none()
+    })?;
+    let mut group = Group::default();
+    let mut palette = Vec::new();
+    let number = |field: Option<&str>| field?.parse::<f32>().ok().filter(|n| n.is_finite());
+    while let Some(key) = fields.next() {
+        match key {
+            "coupling" => group.coupling = number(fields.next()).filter(|&n| n >= 0.0)?,
+            "stiffness" => group.stiffness = number(fields.next()).filter(|&n| n > 0.0)?,
+            "softening" => group.softening = Some(number(fields.next()).filter(|&n| n >= 0.0)?),
+            "material" => group.material = Material::from_name(fields.next()?)?,
+            "palette" => {
+                while let Some(color) = fields.peek().and_then(|field| parse_color(field)) {
+                    palette.push(color);
+                    fields.next();
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(ScenarioGroup {
+        name: name.to_owned(),
+        group,
+        palette,
+    })
+}
+
+fn parse_field(definition: &str) -> Option<ForceField> {
+    let mut fields = definition.split_whitespace();
+    let kind = fields.next()?;
+    let values: Vec<f32> = fields.map(str::parse).collect::<Result<_, _>>().ok()?;
+    if values.iter().any(|value| !value.is_finite()) {
+        return None;
+    }
+    match (kind, values.as_slice()) {
+        ("uniform", &[x, y, z]) => Some(ForceField::Uniform {
+            accel: [x, y, z].into(),
+        }),
+        ("vortex", &[x, y, z, ax, ay, az, swirl, pull]) => {
+            let axis = Vector3::new(ax, ay, az);
+            (axis.magnitude2() > 0.0).then(|| ForceField::Vortex {
+                center: [x, y, z].into(),
+                axis: axis.normalize(),
+                swirl,
+                pull,
+            })
+        }
+        ("point", &[x, y, z, strength]) => Some(ForceField::Point {
+            center: [x, y, z].into(),
+            strength,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_setting(line: &str, settings: &mut ScenarioSettings) -> Option<()> {
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?;
+    let values: Vec<f32> = fields.map(str::parse).collect::<Result<_, _>>().ok()?;
+    if values.iter().any(|value| !value.is_finite()) {
+        return None;
+    }
+    match (name, values.as_slice()) {
+        ("camera", &[x, y, z, tx, ty, tz]) => {
+            settings.camera = Some(ScenarioCamera {
+                position: [x, y, z].into(),
+                target: [tx, ty, tz].into(),
+            });
+        }
+        ("delta-time", &[seconds]) if seconds > 0.0 => {
+            settings.delta_time = Some(Duration::try_from_secs_f32(seconds).ok()?);
+        }
+        ("ray-splits", &[splits]) if splits >= 0.0 && splits.fract() == 0.0 => {
+            settings.ray_splits = Some(splits as u32);
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Opaque `rrggbb` or `rrggbbaa` where `aa` is the transparency.
+fn parse_color(color: &str) -> Option<u32> {
+    match color.len() {
+        6 => Some(u32::from_str_radix(color, 16).ok()? << 8),
+        8 => u32::from_str_radix(color, 16).ok(),
+        _ => None,
+    }
+}
+
+fn parse_body(line: &str, groups: &[ScenarioGroup], palette_used: &mut [usize]) -> Option<Body> {
+    let mut fields = line.split_whitespace();
+    let mut numbers: [Scalar; 7] = [0.0; 7];
+    for number in &mut numbers {
+        *number = fields.next()?.parse().ok()?;
+    }
+    let [x, y, z, vx, vy, vz, radius] = numbers;
+    let color = fields.next()?;
+    let mut flags = 0;
+    let mut group = None;
+    let mut material = None;
+    let mut density = None;
+    while let Some(field) = fields.next() {
+        match field {
+            "frozen" if flags & Body::FROZEN == 0 => flags |= Body::FROZEN,
+            "striped" if flags & Body::STRIPED == 0 => flags |= Body::STRIPED,
+            name if Material::from_name(name).is_some() => {
+                if material.is_some() {
+                    return None;
+                }
+                material = Material::from_name(name);
+            }
+            "density" if density.is_none() => density = Some(fields.next()?.parse().ok()?),
+            name if group.is_none() => {
+                group = Some(groups.iter().position(|group| group.name == name)?);
+            }
+            _ => return None,
+        }
+    }
+    let color = match (color, group) {
+        ("-", Some(index)) => {
+            let palette = &groups[index].palette;
+            let color = *palette.get(palette_used[index] % palette.len().max(1))?;
+            palette_used[index] += 1;
+            color
+        }
+        (color, _) => parse_color(color)?,
+    };
+    if let Some(index) = group {
+        flags |= (index as u32 + 1) << Body::GROUP_SHIFT;
+    }
+    let density: Scalar = density.unwrap_or(1.0);
+    if radius.is_nan() || radius <= 0.0 || density.is_nan() || density <= 0.0 {
+        return None;
+    }
+    let mut body = Body {
+        pos: [x, y, z].into(),
+        vel: [vx, vy, vz].into(),
+        radius,
+        density,
+        color,
+        flags,
+        spin: Vector3::zero(),
+        orientation: Quaternion::one(),
+    };
+    body.set_material(material);
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut bodies: Vec<Body> = (0..10).map(|_| Body::initial()).collect();
+        bodies[3].flags = Body::FROZEN | Body::STRIPED;
+        bodies[4].set_material(Some(Material::Glass));
+        bodies[5].density = 8.0;
+        let parsed = parse_scenario(&write_scenario(&bodies)).unwrap().bodies;
+        assert_eq!(parsed.len(), bodies.len());
+        for (a, b) in parsed.iter().zip(&bodies) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.vel, b.vel);
+            assert_eq!(a.radius, b.radius);
+            assert_eq!(a.density, b.density);
+            assert_eq!(a.color, b.color);
+            assert_eq!(a.flags, b.flags);
+        }
+    }
+
+    #[test]
+    fn parses_settings() {
+        let text =
+            "camera 0 -1 -3 0 0 0.5\ndelta-time 0.0005\nray-splits 2\n0 0 0 0 0 0 0.1 ffffff\n";
+        let scenario = parse_scenario(text).unwrap();
+        assert_eq!(scenario.bodies.len(), 1);
+        assert_eq!(
+            scenario.settings,
+            ScenarioSettings {
+                camera: Some(ScenarioCamera {
+                    position: [0.0, -1.0, -3.0].into(),
+                    target: [0.0, 0.0, 0.5].into(),
+                }),
+                delta_time: Some(Duration::from_micros(500)),
+                ray_splits: Some(2),
+            }
+        );
+        assert_eq!(
+            parse_scenario("ray-splits 1.5").unwrap_err(),
+            ScenarioError::InvalidLine(1)
+        );
+        assert_eq!(
+            parse_scenario("delta-time 1e30").unwrap_err(),
+            ScenarioError::InvalidLine(1)
+        );
+        assert_eq!(
+            parse_scenario("zoom 2").unwrap_err(),
+            ScenarioError::InvalidLine(1)
+        );
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = ScenarioSettings {
+            camera: Some(ScenarioCamera {
+                position: [0.1, -1.0, -3.25].into(),
+                target: [0.0, 0.0, 0.5].into(),
+            }),
+            delta_time: Some(Duration::from_millis(2)),
+            ray_splits: Some(3),
+        };
+        let parsed = parse_scenario(&write_settings(&settings)).unwrap();
+        assert!(parsed.bodies.is_empty());
+        assert_eq!(parsed.settings, settings);
+        assert_eq!(write_settings(&ScenarioSettings::default()), "");
+    }
+
+    #[test]
+    fn parses_groups() {
+        let text =
+            "group dust coupling 0.5 softening 0.02 material glass palette 806040 a08060ff\n\
+                    group heavies stiffness 2\n\
+                    0 0 0 0 0 0 0.1 - dust\n\
+                    0 0 0 0 0 0 0.1 - dust frozen\n\
+                    0 0 0 0 0 0 0.1 - dust\n\
+                    0 0 0 0 0 0 0.1 ffffff heavies\n\
+                    0 0 0 0 0 0 0.1 ffffff\n";
+        let scenario = parse_scenario(text).unwrap();
+        let colors: Vec<u32> = scenario.bodies.iter().map(|b| b.color).collect();
+        assert_eq!(
+            colors,
+            [
+                0x8060_4000,
+                0xa080_60ff,
+                0x8060_4000,
+                0xffff_ff00,
+                0xffff_ff00
+            ]
+        );
+        let groups: Vec<usize> = scenario.bodies.iter().map(Body::group).collect();
+        assert_eq!(groups, [1, 1, 1, 2, 0]);
+        assert!(scenario.bodies[1].is_frozen());
+        let table = scenario.group_table();
+        assert_eq!(
+            (table[1].coupling, table[1].material),
+            (0.5, Material::Glass)
+        );
+        assert_eq!((table[2].stiffness, table[0]), (2.0, Group::default()));
+        assert_eq!((table[1].softening, table[2].softening), (Some(0.02), None));
+
+        for invalid in [
+            "0 0 0 0 0 0 0.1 ffffff dust",
+            "group dust\n0 0 0 0 0 0 0.1 - dust",
+            "group dust material wood",
+            "group dust\ngroup dust",
+        ] {
+            assert!(parse_scenario(invalid).is_err(), "{invalid}");
+        }
+        let many: String = (0..MAX_GROUPS).map(|i| format!("group g{i}\n")).collect();
+        assert_eq!(
+            parse_scenario(&many).unwrap_err(),
+            ScenarioError::TooManyGroups
+        );
+    }
+
+    #[test]
+    fn fields_round_trip() {
+        let text = "field uniform 0 -0.2 0\n\
+                    field vortex 0 0 0 0 2 0 0.3 0.05\n\
+                    field point 0 -1 0 -0.5\n";
+        let scenario = parse_scenario(text).unwrap();
+        assert_eq!(
+            scenario.fields,
+            [
+                ForceField::Uniform {
+                    accel: [0.0, -0.2, 0.0].into()
+                },
+                ForceField::Vortex {
+                    center: Vector3::zero(),
+                    axis: Vector3::unit_y(),
+                    swirl: 0.3,
+                    pull: 0.05
+                },
+                ForceField::Point {
+                    center: [0.0, -1.0, 0.0].into(),
+                    strength: -0.5
+                },
+            ]
+        );
+        let table = scenario.field_table();
+        assert_eq!((table[2].is_some(), table[3]), (true, None));
+        let parsed = parse_scenario(&write_fields(&scenario.fields)).unwrap();
+        assert_eq!(parsed.fields, scenario.fields);
+
+        for invalid in [
+            "field uniform 0 1",
+            "field vortex 0 0 0 0 0 0 1 1",
+            "field sink 0 0 0",
+        ] {
+            assert!(parse_scenario(invalid).is_err(), "{invalid}");
+        }
+        let many = "field uniform 0 0 0\n".repeat(MAX_FIELDS + 1);
+        assert_eq!(
+            parse_scenario(&many).unwrap_err(),
+            ScenarioError::TooManyFields
+        );
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let text = "# comment\n\n0 0 0 0 0 0 0.1 ffffff\n0 0 0 0 0 0.1 ffffff\n";
+        assert_eq!(
+            parse_scenario(text).unwrap_err(),
+            ScenarioError::InvalidLine(4)
+        );
+        assert_eq!(
+            parse_scenario("0 0 0 0 0 0 0.1 ffffff thawed").unwrap_err(),
+            ScenarioError::InvalidLine(1)
+        );
+    }
+}
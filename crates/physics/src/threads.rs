@@ -0,0 +1,117 @@
+//! A dedicated thread pool for stepping bodies and tracers, so that physics need not share the
+//! global rayon pool with everything else. Without one, the global pool is used.
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::sync::OnceLock;
+
+static POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// Builds the pool used by all later physics work, with `threads` threads or one per core. When
+/// `pin` is set, the threads are pinned to distinct cores, skipping the first core, which is
+/// left for the render thread. Pinning is only supported on Linux, and ignored elsewhere. When
+/// `raise_priority` is set, the threads are scheduled ahead of ordinary ones, see
+/// [`raise_thread_priority`].
+///
+/// Has no effect once configured.
+pub fn configure_thread_pool(
+    threads: Option<usize>,
+    pin: bool,
+    raise_priority: bool,
+) -> Result<(), ThreadPoolBuildError> {
+    if POOL.get().is_some() {
+        return Ok(());
+    }
+    let mut builder = ThreadPoolBuilder::new().thread_name(|index| format!("physics-{index}"));
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads);
+    }
+    let cores = std::thread::available_parallelism().map_or(1, usize::from);
+    builder = builder.start_handler(move |index| {
+        if pin {
+            pin_to_core((index + 1) % cores);
+        }
+        if raise_priority {
+            raise_thread_priority();
+        }
+    });
+    let pool = builder.build()?;
+    log::info!("Physics runs on {} threads", pool.current_num_threads());
+    let _ = POOL.set(pool);
+    Ok(())
+}
+
+/// Runs `op` in the configured pool, or in the current one if none was configured.
+pub(crate) fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    match POOL.get() {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+/// Asks the OS to schedule the calling thread ahead of ordinary ones, so that ticks are not
+/// delayed by other busy programs: a nice value of -10 on Linux, which needs `CAP_SYS_NICE` or
+/// a raised `RLIMIT_NICE`, the user-interactive quality of service on macOS and the highest
+/// priority on Windows. Logs a warning if refused, and does nothing elsewhere.
+pub fn raise_thread_priority() {
+    if !set_high_priority() {
+        log::warn!(
+            "Failed to raise the priority of thread {:?}",
+            std::thread::current().name().unwrap_or("unnamed")
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_high_priority() -> bool {
+    /// `PRIO_PROCESS`, under which Linux takes thread ids as well
+    const PRIO_PROCESS: i32 = 0;
+    extern "C" {
+        fn gettid() -> i32;
+        fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+    }
+    unsafe { setpriority(PRIO_PROCESS, gettid() as u32, -10) == 0 }
+}
+
+#[cfg(target_os = "macos")]
+fn set_high_priority() -> bool {
+    const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+    extern "C" {
+        fn pthread_set_qos_class_self_np(class: u32, relative_priority: i32) -> i32;
+    }
+    unsafe { pthread_set_qos_class_self_np(QOS_CLASS_USER_INTERACTIVE, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn set_high_priority() -> bool {
+    const THREAD_PRIORITY_HIGHEST: i32 = 2;
+    extern "system" {
+        fn GetCurrentThread() -> *mut std::ffi::c_void;
+        fn SetThreadPriority(thread: *mut std::ffi::c_void, priority: i32) -> i32;
+    }
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_HIGHEST) != 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn set_high_priority() -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_core(core: usize) {
+    /// The `cpu_set_t` of glibc and musl, a bitmask of 1024 cores
+    type CpuSet = [u64; 16];
+    extern "C" {
+        fn sched_setaffinity(pid: i32, size: usize, mask: *const CpuSet) -> i32;
+    }
+    let mut mask: CpuSet = [0; 16];
+    mask[core / 64 % 16] |= 1 << (core % 64);
+    // Pid 0 is the calling thread
+    if unsafe { sched_setaffinity(0, std::mem::size_of::<CpuSet>(), &mask) } != 0 {
+        log::warn!("Failed to pin a physics thread to core {core}");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_core(core: usize) {
+    let _ = core;
+}
@@ -0,0 +1,206 @@
+//! Massless tracer particles, which are pulled by the gravity of bodies without pulling back or
+//! colliding with anything, to show how the cluster moves space around it. Tracers that fall
+//! into a body or leave the system are respawned elsewhere.
+
+use crate::{
+    body::SYSTEM_RADIUS,
+    math,
+    random::{stream, Stream},
+    Body, Physics, PhysicsConfig, Scalar, ToF32, PHYSICS_MAX_BEHIND_TIME,
+};
+use cgmath::{prelude::*, Vector3};
+use rand::{rngs::StdRng, Rng};
+use std::time::Duration;
+
+/// Tracers are stepped this much coarser than bodies, since they are many and only for show
+pub const TRACER_DELTA_TIME: Duration = Duration::from_millis(10);
+/// The most tracers there may be, which GPU buffers are sized for.
+pub const MAX_TRACERS: usize = 16384;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Tracer {
+    pub pos: Vector3<f32>,
+    pub vel: Vector3<f32>,
+}
+unsafe impl bytemuck::Zeroable for Tracer {}
+unsafe impl bytemuck::Pod for Tracer {}
+
+pub struct Tracers {
+    tracers: Vec<Tracer>,
+    /// The simulated time the tracers have been advanced to
+    simulated_time: Duration,
+    /// The [`Stream::Respawn`] stream
+    rng: StdRng,
+}
+impl Tracers {
+    /// A cloud spread and moving like the initial bodies, clamped to [`MAX_TRACERS`]. Where
+    /// tracers are placed and respawned is drawn from the master seed.
+    pub fn new(count: usize, simulated_time: Duration, seed: u64) -> Self {
+        let mut rng = stream(seed, Stream::Respawn);
+        let tracers = (0..count.min(MAX_TRACERS))
+            .map(|_| spawn(&mut rng))
+            .collect();
+        Self {
+            tracers,
+            simulated_time,
+            rng,
+        }
+    }
+    pub fn as_slice(&self) -> &[Tracer] {
+        &self.tracers
+    }
+    /// Steps the tracers through the bodies of `physics` until they have caught up with it,
+    /// skipping ahead when too far behind, as after loading a save.
+    pub fn advance_to(&mut self, physics: &Physics) {
+        let target = physics.simulated_time();
+        match target.checked_sub(self.simulated_time) {
+            Some(behind) if behind <= PHYSICS_MAX_BEHIND_TIME => {}
+            _ => self.simulated_time = target,
+        }
+        while self.simulated_time + TRACER_DELTA_TIME <= target {
+            self.step(physics);
+            self.simulated_time += TRACER_DELTA_TIME;
+        }
+    }
+    fn step(&mut self, physics: &Physics) {
+        let dt = TRACER_DELTA_TIME.as_secs_f32();
+        let bodies = physics.bodies();
+        let center = physics.center_of_mass().to_f32();
+        let system_radius = SYSTEM_RADIUS.to_f32();
+        let config = physics.config();
+        let step = |tracer: &mut Tracer| match gravitational_field(bodies, tracer.pos, config) {
+            Some(accel) => {
+                tracer.vel += dt * accel;
+                tracer.pos += dt * tracer.vel;
+            }
+            // Swallowed, so moved out of the system to be respawned below
+            None => tracer.pos = center + system_radius * Vector3::unit_x(),
+        };
+        #[cfg(any(feature = "rayon", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::{IntoParallelRefMutIterator, ParallelIterator};
+            crate::threads::install(|| self.tracers.par_iter_mut().for_each(step));
+        }
+        #[cfg(not(any(feature = "rayon", not(target_arch = "wasm32"))))]
+        self.tracers.iter_mut().for_each(step);
+
+        for tracer in &mut self.tracers {
+            if tracer.pos.distance2(center) >= system_radius.powi(2) {
+                let Tracer { pos, vel } = spawn(&mut self.rng);
+                *tracer = Tracer {
+                    pos: center + pos,
+                    vel,
+                };
+            }
+        }
+    }
+}
+
+/// The acceleration of a massless test particle at `pos`, or `None` inside a body.
+pub fn gravitational_field(
+    bodies: &[Body],
+    pos: Vector3<f32>,
+    config: &PhysicsConfig,
+) -> Option<Vector3<f32>> {
+    let pos = pos.map(Scalar::from);
+    let mut accel = Vector3::zero();
+    for body in bodies {
+        let rel_pos = body.pos - pos;
+        let distance = rel_pos.magnitude();
+        if distance < body.radius {
+            return None;
+        }
+        let softened_distance2 = distance.powi(2) + Scalar::from(config.softening_squared(body));
+        accel += Scalar::from(config.gravity * config.group(body).coupling) * body.mass()
+            / (distance * softened_distance2)
+            * rel_pos;
+    }
+    Some(accel.to_f32())
+}
+
+/// A tracer placed and moving like [`Body::initial`]
+fn spawn(rng: &mut impl Rng) -> Tracer {
+    let mut r = || math::standard_normal(rng);
+    let pos: Vector3<f32> = [r(), r(), r()].into();
+    let rand: Vector3<f32> = [r(), r(), r()].into();
+    Tracer {
+        pos,
+        vel: 0.1 * pos.cross(rand),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Body;
+    use instant::Instant;
+
+    fn resting_at(pos: Vector3<f32>) -> Tracers {
+        Tracers {
+            tracers: vec![Tracer {
+                pos,
+                vel: Vector3::zero(),
+            }],
+            simulated_time: Duration::ZERO,
+            rng: stream(0, Stream::Respawn),
+        }
+    }
+    fn heavy_body() -> Body {
+        Body {
+            radius: 0.1,
+            ..Body::at(Vector3::zero(), Vector3::zero())
+        }
+    }
+    fn one_body_at(body: Body, simulated_millis: u64) -> Physics {
+        Physics {
+            bodies: std::iter::once(body).collect(),
+            config: PhysicsConfig::default(),
+            ticks: 0,
+            delta_time: crate::PHYSICS_DELTA_TIME,
+            simulated_time: Duration::from_millis(simulated_millis),
+            warm_up_start: Duration::ZERO,
+            timestamp: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn tracers_fall_toward_bodies() {
+        let body = heavy_body();
+        let mut tracers = resting_at(Vector3::new(1.0, 0.0, 0.0));
+        tracers.advance_to(&one_body_at(body, 100));
+        let pos = tracers.as_slice()[0].pos;
+        assert!(pos.x < 1.0, "{pos:?}");
+        assert_eq!((pos.y, pos.z), (0.0, 0.0));
+    }
+
+    #[test]
+    fn field_points_toward_bodies_and_is_undefined_inside() {
+        let body = heavy_body();
+        let config = PhysicsConfig::default();
+        let field = gravitational_field(&[body], Vector3::new(0.0, 2.0, 0.0), &config).unwrap();
+        assert!(
+            field.y < 0.0 && field.x == 0.0 && field.z == 0.0,
+            "{field:?}"
+        );
+        assert!(gravitational_field(&[body], Vector3::new(0.05, 0.0, 0.0), &config).is_none());
+    }
+
+    #[test]
+    fn advance_to_steps_whole_tracer_steps() {
+        let body = heavy_body();
+        let mut tracers = resting_at(Vector3::new(1.0, 0.0, 0.0));
+        tracers.advance_to(&one_body_at(body, 5));
+        assert_eq!(tracers.as_slice()[0].pos, Vector3::new(1.0, 0.0, 0.0));
+        tracers.advance_to(&one_body_at(body, 15));
+        assert_eq!(tracers.simulated_time, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn swallowed_tracers_are_respawned() {
+        let body = heavy_body();
+        let mut tracers = resting_at(Vector3::new(0.5 * body.radius.to_f32(), 0.0, 0.0));
+        tracers.advance_to(&one_body_at(body, 10));
+        assert!(tracers.as_slice()[0].pos.magnitude() > body.radius.to_f32());
+    }
+}
@@ -0,0 +1,194 @@
+//! Real units, so that real systems such as the Sun and the planets can be simulated with their
+//! actual distances and orbital periods. [`Units`] map astronomical units onto simulated
+//! distances and days onto simulated seconds, and choose simulated masses so that orbits take as
+//! long as they really do under the gravitational constant of the simulation, see
+//! [`PhysicsConfig::gravity`](crate::PhysicsConfig::gravity).
+//!
+//! Radii cannot be to scale, since planets would be far too small to see next to their orbits.
+//! They are exaggerated instead, and compressed by a square root so that the Sun does not
+//! swallow the innermost planets.
+
+use crate::{Body, BodySet, Scalar, Scenario, ScenarioCamera, ScenarioSettings};
+use cgmath::{prelude::*, Quaternion, Vector3};
+use std::time::Duration;
+
+/// The gravitational constant times the mass of the Sun, in cubic astronomical units per
+/// squared day
+pub(crate) const SUN_GM: f64 = 2.959_122_082_855_911e-4;
+/// In kilograms
+pub(crate) const SUN_MASS: f64 = 1.988_47e30;
+/// In kilometers
+pub(crate) const EARTH_RADIUS: f64 = 6371.0;
+
+/// How real quantities are scaled into the simulation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Units {
+    /// The simulated length of an astronomical unit
+    pub au: f64,
+    /// The days passing in each simulated second
+    pub days: f64,
+    /// The simulated radius of a body as large as the Earth
+    pub earth_radius: f64,
+}
+impl Units {
+    /// Of a distance in astronomical units.
+    pub fn length(&self, au: f64) -> Scalar {
+        (au * self.au) as Scalar
+    }
+    /// Of a speed in astronomical units per day.
+    pub fn speed(&self, au_per_day: f64) -> Scalar {
+        (au_per_day * self.au * self.days) as Scalar
+    }
+    /// Of a mass in kilograms, such that it attracts like the real mass under the gravitational
+    /// constant `gravity`, see [`Body::mass`].
+    pub fn mass(&self, kg: f64, gravity: f32) -> Scalar {
+        let gm = SUN_GM * kg / SUN_MASS;
+        (gm * self.au.powi(3) * self.days.powi(2) / f64::from(gravity)) as Scalar
+    }
+    /// Of a radius in kilometers, exaggerated as described in the [module documentation](self).
+    pub fn radius(&self, km: f64) -> Scalar {
+        (self.earth_radius * (km / EARTH_RADIUS).sqrt()) as Scalar
+    }
+}
+
+/// A body of a real system at some instant, in real units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ephemeris {
+    pub name: String,
+    /// In kilograms
+    pub mass: f64,
+    /// In kilometers
+    pub radius: f64,
+    /// As `rrggbb`
+    pub color: u32,
+    /// In astronomical units, in a frame whose xy plane is the ecliptic, with north along z
+    pub position: [f64; 3],
+    /// In astronomical units per day
+    pub velocity: [f64; 3],
+}
+
+/// The ways to fit the solar system into the simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// Out to Mars, where a second is ten days
+    Inner,
+    /// Out to Neptune, where a second is a year
+    Whole,
+}
+impl Scale {
+    pub const ALL: [Self; 2] = [Self::Inner, Self::Whole];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Inner => "inner",
+            Self::Whole => "whole",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scale| scale.name() == name)
+    }
+    pub fn units(self) -> Units {
+        match self {
+            Self::Inner => Units {
+                au: 2.0,
+                days: 10.0,
+                earth_radius: 0.02,
+            },
+            Self::Whole => Units {
+                au: 0.15,
+                days: 365.25,
+                earth_radius: 0.003,
+            },
+        }
+    }
+    /// The bodies of `ephemerides` that are within [`Scale::reach`] of the origin, as a
+    /// scenario under the gravitational constant `gravity`, seen from north of the ecliptic.
+    /// North is towards negative y, as up is in scenarios.
+    pub fn scenario(self, ephemerides: &[Ephemeris], gravity: f32) -> Scenario {
+        let units = self.units();
+        let north_up = |[x, y, z]: [f64; 3]| Vector3::new(x, -z, y);
+        let bodies = ephemerides
+            .iter()
+            .filter(|ephemeris| north_up(ephemeris.position).magnitude() <= self.reach())
+            .take(BodySet::capacity())
+            .map(|ephemeris| {
+                let radius = units.radius(ephemeris.radius);
+                Body {
+                    pos: north_up(ephemeris.position).map(|au| units.length(au)),
+                    vel: north_up(ephemeris.velocity).map(|speed| units.speed(speed)),
+                    radius,
+                    density: units.mass(ephemeris.mass, gravity) / (radius * radius * radius),
+                    color: ephemeris.color << 8,
+                    flags: 0,
+                    spin: Vector3::zero(),
+                    orientation: Quaternion::one(),
+                }
+            })
+            .collect();
+        let (camera, delta_time) = match self {
+            Self::Inner => (Vector3::new(0.0, -3.5, -4.5), None),
+            // Mercury goes around four times a second
+            Self::Whole => (
+                Vector3::new(0.0, -5.0, -7.0),
+                Some(Duration::from_micros(250)),
+            ),
+        };
+        Scenario {
+            bodies,
+            settings: ScenarioSettings {
+                camera: Some(ScenarioCamera {
+                    position: camera,
+                    target: Vector3::zero(),
+                }),
+                delta_time,
+                ray_splits: None,
+            },
+            groups: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+    /// How far from the Sun bodies may be, in astronomical units, to be included.
+    pub fn reach(self) -> f64 {
+        match self {
+            Self::Inner => 2.0,
+            Self::Whole => 32.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Physics, PhysicsConfig};
+
+    #[test]
+    fn orbits_take_their_real_time() {
+        let earth = Ephemeris {
+            name: "Earth".to_owned(),
+            mass: 5.9722e24,
+            radius: EARTH_RADIUS,
+            color: 0x3a7bd5,
+            position: [1.0, 0.0, 0.0],
+            // A circular orbit, of a year
+            velocity: [0.0, SUN_GM.sqrt(), 0.0],
+        };
+        let sun = Ephemeris {
+            name: "Sun".to_owned(),
+            mass: SUN_MASS,
+            radius: 695_700.0,
+            color: 0xfdb813,
+            position: [0.0; 3],
+            velocity: [0.0; 3],
+        };
+        let config = PhysicsConfig::default();
+        let scenario = Scale::Inner.scenario(&[sun, earth], config.gravity);
+        let mut physics = Physics::seeded(0);
+        physics.load_scenario(scenario);
+        // A quarter of a year later, the Earth is a quarter of the way around
+        let year = 2.0 * std::f64::consts::PI / SUN_GM.sqrt() / Scale::Inner.units().days;
+        let ticks = year / 4.0 / physics.delta_time().as_secs_f64();
+        physics.advance_ticks(ticks.round() as u64);
+        let earth = physics.bodies()[1].pos - physics.bodies()[0].pos;
+        let expected = Vector3::new(0.0, 0.0, 2.0);
+        assert!(earth.distance(expected) < 0.02, "{earth:?}");
+    }
+}
@@ -0,0 +1,278 @@
+//! The bounding volume hierarchy the GPU ray traces bodies through: a [`SphereTree`] of the
+//! bodies of a `physics` simulation as seen from a camera, built by [`SphereTreeBuilder`].
+
+use camera::Pose;
+use cgmath::{prelude::*, Quaternion, Vector3};
+use physics::{Body, Group, Material, Scalar, ToF32, BODIES, MAX_GROUPS};
+use std::mem;
+
+/// The least transparency of bodies of glass, out of 255
+const GLASS_TRANSPARENCY: u32 = 0xA0;
+
+/// The rotation from camera space to the frame of a striped body, as the `[x, y, z, w]` of a
+/// quaternion, or zero for bodies drawn without a pattern
+pub type Orientation = [f32; 4];
+
+/// A sphere tree as uploaded to the GPU, and the orientations of the bodies it was built from,
+/// indexed like them and always spanning `BODIES`.
+#[derive(Clone, Copy)]
+pub struct SphereTree<'a> {
+    pub spheres: &'a [Sphere],
+    pub orientations: &'a [Orientation],
+}
+impl SphereTree<'_> {
+    /// The center in camera space and the radius of the root, bounding every body, if there are
+    /// any bodies.
+    pub fn bounds(&self) -> Option<(Vector3<f32>, f32)> {
+        let root = self.spheres.last()?;
+        (root.radius > 0.0).then_some((root.pos, root.radius))
+    }
+}
+
+/// Builds the sphere tree uploaded to the GPU, reusing its buffers across frames so that the
+/// steady-state frame loop does not allocate.
+pub struct SphereTreeBuilder {
+    spheres: Vec<Option<Sphere>>,
+    tree: Vec<Sphere>,
+    chain: Vec<usize>,
+    orientations: Vec<Orientation>,
+    radius_scale: f32,
+}
+impl SphereTreeBuilder {
+    pub fn new() -> Self {
+        Self {
+            spheres: Vec::with_capacity(2 * BODIES - 1),
+            tree: Vec::with_capacity(2 * BODIES - 1),
+            chain: Vec::with_capacity(BODIES),
+            orientations: Vec::with_capacity(BODIES),
+            radius_scale: 1.0,
+        }
+    }
+    /// Draws bodies this many times larger than they are from the next build on, so that small
+    /// bodies stay visible from afar.
+    pub fn set_radius_scale(&mut self, radius_scale: f32) {
+        self.radius_scale = radius_scale;
+    }
+    /// The tree always spans `2 * BODIES - 1` nodes with the root last, regardless of how many
+    /// bodies are active.
+    /// The materials of bodies, or otherwise of their `groups`, are applied to their colors.
+    /// Bodies are placed relative to the camera at `pose` in the precision of the simulation
+    /// before being rounded to single precision, so that bodies far from the origin do not
+    /// shimmer as the camera moves among them.
+    pub fn build(
+        &mut self,
+        bodies: &[Body],
+        groups: &[Group; MAX_GROUPS],
+        pose: &Pose,
+    ) -> SphereTree<'_> {
+        let Self {
+            spheres,
+            tree,
+            chain,
+            orientations,
+            radius_scale,
+        } = self;
+        let world_to_camera_rotation = pose.rotation.conjugate();
+        orientations.clear();
+        orientations.extend(bodies.iter().map(|body| {
+            if body.flags & Body::STRIPED == 0 {
+                return [0.0; 4];
+            }
+            let camera_to_body = (world_to_camera_rotation * body.orientation).conjugate();
+            let Quaternion { v, s } = camera_to_body;
+            [v.x, v.y, v.z, s]
+        }));
+        orientations.resize(BODIES, [0.0; 4]);
+        spheres.clear();
+        spheres.extend(
+            bodies
+                .iter()
+                .enumerate()
+                .map(|(index, body)| Sphere::leaf(index, body, groups, *radius_scale, pose))
+                .map(Option::from),
+        );
+
+        let tot_nodes = 2 * BODIES - 1;
+        let mut num_spheres = spheres.len();
+        tree.clear();
+        tree.resize(tot_nodes, Sphere::placeholder());
+        chain.clear();
+        while num_spheres > 1 {
+            let current = loop {
+                if chain.is_empty() {
+                    // Put arbitrary sphere on empty stack
+                    chain.push(
+                        spheres
+                            .iter()
+                            .enumerate()
+                            .rev()
+                            .find(|(_, s)| Option::is_some(*s))
+                            .unwrap()
+                            .0,
+                    );
+                }
+                let current = chain[chain.len() - 1];
+                if spheres[current].is_some() {
+                    break current;
+                }
+                chain.pop();
+            };
+            // Find closest neighbor
+            let (_cost, nearest_neighbor) = spheres
+                .iter()
+                .enumerate()
+                .filter(|(i, neighbor)| *i != current && neighbor.is_some())
+                .map(|(i, neighbor)| (measure(&spheres[current].unwrap(), &neighbor.unwrap()), i))
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap();
+
+            if chain.len() >= 2 && nearest_neighbor == chain[chain.len() - 2] {
+                // Join a pair of mutually closest neighbors
+                let last = chain[chain.len() - 2];
+                spheres.push(Some(Sphere::branch(current, last, spheres)));
+                tree[current] = spheres[current].take().unwrap();
+                tree[last] = spheres[last].take().unwrap();
+                num_spheres -= 1;
+                chain.pop();
+                chain.pop();
+            } else {
+                // Found closer pair, pushing to stack
+                chain.push(nearest_neighbor);
+            }
+        }
+        // Push root
+        tree[tot_nodes - 1] = match spheres.last() {
+            Some(root) => root.unwrap(),
+            None => Sphere::empty(),
+        };
+        SphereTree {
+            spheres: tree,
+            orientations,
+        }
+    }
+}
+impl Default for SphereTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// This is not strictly a measure, but it works as a cost in a nearest-neighbor chain algorithm
+fn measure(a: &Sphere, b: &Sphere) -> f32 {
+    let joined_radius = ((a.pos - b.pos).magnitude() + a.radius + b.radius) / 2.0;
+    joined_radius.powi(3) - a.radius.powi(3) - b.radius.powi(3)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Sphere {
+    pos: Vector3<f32>,
+    radius: f32,
+    left: i32,
+    right: i32,
+    color: u32,
+    /// The index of the body a leaf was made from, which also pads this to 32 bytes
+    body: u32,
+}
+impl Sphere {
+    /// The layout of a sphere when used as instance data, at shader locations 1 through 5.
+    pub const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 5] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: mem::offset_of!(Sphere, pos) as u64,
+            shader_location: 1,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32,
+            offset: mem::offset_of!(Sphere, radius) as u64,
+            shader_location: 2,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Sint32,
+            offset: mem::offset_of!(Sphere, left) as u64,
+            shader_location: 3,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Uint32,
+            offset: mem::offset_of!(Sphere, color) as u64,
+            shader_location: 4,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Uint32,
+            offset: mem::offset_of!(Sphere, body) as u64,
+            shader_location: 5,
+        },
+    ];
+    pub(self) fn leaf(
+        index: usize,
+        body: &Body,
+        groups: &[Group; MAX_GROUPS],
+        radius_scale: f32,
+        pose: &Pose,
+    ) -> Self {
+        // Subtracting before rotating is exact for bodies near the camera, whereas transforming
+        // both by a matrix cancels the large terms and keeps their rounding errors
+        let origin = pose.position.map(Scalar::from);
+        let pos = pose
+            .rotation
+            .conjugate()
+            .rotate_vector((body.pos - origin).to_f32());
+        // The lowest byte of a color is its transparency
+        let color = match body.material().unwrap_or(groups[body.group()].material) {
+            Material::Solid | Material::Metal | Material::Rubber => body.color,
+            Material::Glass => (body.color & !0xFF) | (body.color & 0xFF).max(GLASS_TRANSPARENCY),
+        };
+        Self {
+            pos,
+            radius: radius_scale * body.radius.to_f32(),
+            left: -1,
+            right: -1,
+            color,
+            body: index as u32,
+        }
+    }
+    pub(self) fn branch(a_index: usize, b_index: usize, spheres: &[Option<Sphere>]) -> Self {
+        let a = spheres[a_index].unwrap();
+        let b = spheres[b_index].unwrap();
+        let rel_pos_norm = (b.pos - a.pos).normalize();
+        let distance = (b.pos - a.pos).magnitude();
+        let joined_midpoint =
+            ((a.pos - rel_pos_norm * a.radius) + (b.pos + rel_pos_norm * b.radius)) / 2.0;
+        let joined_radius = (distance + a.radius + b.radius) / 2.0;
+        Self {
+            pos: joined_midpoint,
+            radius: joined_radius,
+            left: a_index as i32,
+            right: b_index as i32,
+            color: 0,
+            body: 0,
+        }
+    }
+    /// The position and radius of the largest, and so heaviest, body in a tree
+    pub fn heaviest(tree: &[Sphere]) -> Option<(Vector3<f32>, f32)> {
+        tree.iter()
+            .filter(|sphere| sphere.left == -1 && sphere.radius > 0.0)
+            .max_by(|a, b| a.radius.total_cmp(&b.radius))
+            .map(|sphere| (sphere.pos, sphere.radius))
+    }
+    /// A leaf that no ray can hit
+    pub(self) fn empty() -> Self {
+        Self {
+            left: -1,
+            right: -1,
+            ..Self::placeholder()
+        }
+    }
+    pub(self) fn placeholder() -> Self {
+        Self {
+            pos: Vector3::zero(),
+            radius: 0.0,
+            left: 0,
+            right: 0,
+            color: 0,
+            body: 0,
+        }
+    }
+}
+unsafe impl bytemuck::Pod for Sphere {}
+unsafe impl bytemuck::Zeroable for Sphere {}
@@ -4,7 +4,15 @@
 compile_error!("This crate's only purpose is supporting multithreading through web workers");
 
 use instant::Instant;
-use physics::{Physics, PhysicsResult};
+use physics::{Checkpoint, Physics, PhysicsResult};
+use std::time::Duration;
+
+// The main thread and the worker do not share a clock origin, so durations are sent relative to
+// the physics timestamp rather than as instants.
+//
+// Input words: [target - timestamp (nanos), ..Physics::encode]
+// Output words: [target - timestamp (nanos), elapsed_real (nanos), elapsed_physics_ticks,
+//                checkpoint tick (0 without one), checkpoint hash, ..Physics::encode]
 
 #[cfg(feature = "inner")]
 pub mod inner {
@@ -22,18 +30,27 @@ pub mod inner {
     /// Allowed to block
     #[wasm_bindgen::prelude::wasm_bindgen(js_name = "workerInner")]
     pub fn worker_inner(input: &[u64]) -> Box<[u64]> {
-        let WorkerInput {
-            physics,
-            target_instant,
-        } = if let &[data] = bytemuck::cast_slice(input) {
-            data
-        } else {
+        let (&[lag_nanos], physics) = input.split_at(1) else {
             unreachable!();
         };
-        let mut physics = physics.clone();
-        let result = physics.advance_to(target_instant);
-        let output: Vec<WorkerOutput> = vec![WorkerOutput { physics, result }];
-        bytemuck::cast_vec(output).into_boxed_slice()
+        let mut physics = Physics::decode(physics, Instant::now()).unwrap();
+        let target = physics.timestamp() + Duration::from_nanos(lag_nanos);
+        let PhysicsResult {
+            elapsed_real,
+            elapsed_physics_ticks,
+            checkpoint,
+        } = physics.advance_to(target);
+        let Checkpoint { tick, hash } = checkpoint.unwrap_or(Checkpoint { tick: 0, hash: 0 });
+
+        let mut output = vec![
+            nanos(target - physics.timestamp()),
+            nanos(elapsed_real),
+            elapsed_physics_ticks,
+            tick,
+            hash,
+        ];
+        output.extend(physics.encode());
+        output.into_boxed_slice()
     }
 }
 
@@ -56,19 +73,25 @@ pub mod outer {
                 return Err(());
             }
 
-            let input: WorkerInput = WorkerInput {
-                physics: *physics,
-                target_instant: target,
-            };
-            let promise = worker_outer(bytemuck::cast_slice(&[input]));
+            let mut input = vec![nanos(target.saturating_duration_since(physics.timestamp()))];
+            input.extend(physics.encode());
+            let promise = worker_outer(&input);
             wasm_bindgen_futures::spawn_local(async move {
-                let output_data: Vec<u64> =
+                let output: Vec<u64> =
                     BigUint64Array::from(JsFuture::from(promise).await.unwrap()).to_vec();
-                if let &[WorkerOutput { physics, result }] = bytemuck::cast_slice(&output_data) {
-                    proxy.send_event((Box::new(physics), result)).unwrap();
-                } else {
+                let (&[lag_nanos, elapsed_real_nanos, elapsed_physics_ticks, tick, hash], physics) =
+                    output.split_at(5)
+                else {
                     unreachable!();
-                }
+                };
+                let timestamp = target.checked_sub(Duration::from_nanos(lag_nanos)).unwrap();
+                let physics = Physics::decode(physics, timestamp).unwrap();
+                let result = PhysicsResult {
+                    elapsed_real: Duration::from_nanos(elapsed_real_nanos),
+                    elapsed_physics_ticks,
+                    checkpoint: (tick != 0).then_some(Checkpoint { tick, hash }),
+                };
+                proxy.send_event((physics, result)).unwrap();
             });
             Ok(())
         }
@@ -87,22 +110,6 @@ pub mod outer {
     }
 }
 
-#[derive(Clone, Copy)]
-struct WorkerInput {
-    #[allow(dead_code)]
-    physics: Physics,
-    #[allow(dead_code)]
-    target_instant: Instant,
-}
-unsafe impl bytemuck::Zeroable for WorkerInput {}
-unsafe impl bytemuck::Pod for WorkerInput {}
-
-#[derive(Clone, Copy)]
-struct WorkerOutput {
-    #[allow(dead_code)]
-    physics: Physics,
-    #[allow(dead_code)]
-    result: PhysicsResult,
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap()
 }
-unsafe impl bytemuck::Zeroable for WorkerOutput {}
-unsafe impl bytemuck::Pod for WorkerOutput {}